@@ -0,0 +1,48 @@
+pub(crate) fn read_u16(buf: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(buf[offset..offset + 2].try_into().unwrap())
+}
+
+pub(crate) fn read_u32(buf: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(buf[offset..offset + 4].try_into().unwrap())
+}
+
+pub(crate) fn read_u64(buf: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(buf[offset..offset + 8].try_into().unwrap())
+}
+
+pub(crate) fn write_u16(buf: &mut [u8], offset: usize, value: u16) {
+    buf[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+}
+
+pub(crate) fn write_u32(buf: &mut [u8], offset: usize, value: u32) {
+    buf[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+pub(crate) fn write_u64(buf: &mut [u8], offset: usize, value: u64) {
+    buf[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+}
+
+/// NUL-terminated UTF-8 string starting at `offset` of a string table.
+pub(crate) fn read_cstr(buf: &[u8], offset: usize) -> Option<&str> {
+    let tail = buf.get(offset..)?;
+    let len = tail.iter().position(|&b| b == 0)?;
+    core::str::from_utf8(&tail[..len]).ok()
+}
+
+/// The `count` entries of `entry_size` bytes at `offset` of `data`. A
+/// non-empty table must start at or after `min_offset` and end at or before
+/// `max_end`; `None` if it does not or runs past the end of `data`.
+pub(crate) fn table(
+    data: &[u8],
+    offset: u64,
+    count: u64,
+    entry_size: usize,
+    min_offset: u64,
+    max_end: u64,
+) -> Option<&[u8]> {
+    let end = offset.checked_add(count.checked_mul(entry_size as u64)?)?;
+    if count > 0 && (offset < min_offset || end > max_end) {
+        return None;
+    }
+    data.get(usize::try_from(offset).ok()?..usize::try_from(end).ok()?)
+}
@@ -0,0 +1,149 @@
+use alloc::format;
+use alloc::string::String;
+use core::fmt;
+
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use sha2::{Digest, Sha256};
+
+pub const ALG_ED25519: &str = "ed25519";
+pub const HASH_SHA256: &str = "sha256";
+
+const MESSAGE_TAG: &[u8; 8] = b"PLUMSIG1";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DetachedError {
+    Malformed,
+    UnknownKey,
+    BadSignature,
+    Corrupted,
+}
+
+impl fmt::Display for DetachedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DetachedError::Malformed => write!(f, "signature file is malformed"),
+            DetachedError::UnknownKey => write!(f, "signed by an unknown key"),
+            DetachedError::BadSignature => write!(f, "bad signature"),
+            DetachedError::Corrupted => write!(f, "file does not match the signed hash"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DetachedError {}
+
+/// Short identifier of a public key: the first 8 bytes of its SHA-256.
+pub fn key_id(public_key: &[u8; 32]) -> [u8; 8] {
+    Sha256::digest(public_key)[..8].try_into().unwrap()
+}
+
+/// Contents of a detached `.sig` file. The Ed25519 signature covers the file
+/// hash, size and timestamp, so none of them can be edited independently.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DetachedSignature {
+    pub key_id: [u8; 8],
+    pub file_hash: [u8; 32],
+    pub file_size: u64,
+    pub timestamp: u64,
+    pub signature: [u8; 64],
+}
+
+impl DetachedSignature {
+    pub fn sign(data: &[u8], key: &SigningKey, timestamp: u64) -> Self {
+        let mut sig = DetachedSignature {
+            key_id: key_id(&key.verifying_key().to_bytes()),
+            file_hash: Sha256::digest(data).into(),
+            file_size: data.len() as u64,
+            timestamp,
+            signature: [0u8; 64],
+        };
+        sig.signature = key.sign(&sig.message()).to_bytes();
+        sig
+    }
+
+    fn message(&self) -> [u8; 56] {
+        let mut message = [0u8; 56];
+        message[0..8].copy_from_slice(MESSAGE_TAG);
+        message[8..40].copy_from_slice(&self.file_hash);
+        message[40..48].copy_from_slice(&self.file_size.to_le_bytes());
+        message[48..56].copy_from_slice(&self.timestamp.to_le_bytes());
+        message
+    }
+
+    /// Verifies the signature with whichever of `trusted_keys` matches
+    /// `key_id`, then checks `data` against the signed hash. Returns the key
+    /// that signed it.
+    pub fn verify(&self, data: &[u8], trusted_keys: &[[u8; 32]]) -> Result<[u8; 32], DetachedError> {
+        let public_key = trusted_keys
+            .iter()
+            .find(|k| key_id(k) == self.key_id)
+            .ok_or(DetachedError::UnknownKey)?;
+
+        let key = VerifyingKey::from_bytes(public_key).map_err(|_| DetachedError::UnknownKey)?;
+        key.verify_strict(&self.message(), &Signature::from_bytes(&self.signature))
+            .map_err(|_| DetachedError::BadSignature)?;
+
+        let file_hash: [u8; 32] = Sha256::digest(data).into();
+        if data.len() as u64 != self.file_size || file_hash != self.file_hash {
+            return Err(DetachedError::Corrupted);
+        }
+
+        Ok(*public_key)
+    }
+
+    pub fn to_text(&self) -> String {
+        format!(
+            "algorithm = {}\nhash = {}\nkey_id = {}\nfile_hash = {}\nfile_size = {}\ntimestamp = {}\nsignature = {}\n",
+            ALG_ED25519,
+            HASH_SHA256,
+            hex::encode(self.key_id),
+            hex::encode(self.file_hash),
+            self.file_size,
+            self.timestamp,
+            hex::encode(self.signature),
+        )
+    }
+
+    pub fn parse(text: &str) -> Result<Self, DetachedError> {
+        let mut algorithm = None;
+        let mut hash = None;
+        let mut key_id = None;
+        let mut file_hash = None;
+        let mut file_size = None;
+        let mut timestamp = None;
+        let mut signature = None;
+
+        for line in text.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#')) {
+            let (name, value) = line.split_once('=').ok_or(DetachedError::Malformed)?;
+            let value = value.trim();
+            match name.trim() {
+                "algorithm" => algorithm = Some(value),
+                "hash" => hash = Some(value),
+                "key_id" => key_id = Some(decode_hex::<8>(value)?),
+                "file_hash" => file_hash = Some(decode_hex::<32>(value)?),
+                "file_size" => file_size = Some(value.parse().map_err(|_| DetachedError::Malformed)?),
+                "timestamp" => timestamp = Some(value.parse().map_err(|_| DetachedError::Malformed)?),
+                "signature" => signature = Some(decode_hex::<64>(value)?),
+                _ => return Err(DetachedError::Malformed),
+            }
+        }
+
+        if algorithm != Some(ALG_ED25519) || hash != Some(HASH_SHA256) {
+            return Err(DetachedError::Malformed);
+        }
+
+        Ok(DetachedSignature {
+            key_id: key_id.ok_or(DetachedError::Malformed)?,
+            file_hash: file_hash.ok_or(DetachedError::Malformed)?,
+            file_size: file_size.ok_or(DetachedError::Malformed)?,
+            timestamp: timestamp.ok_or(DetachedError::Malformed)?,
+            signature: signature.ok_or(DetachedError::Malformed)?,
+        })
+    }
+}
+
+fn decode_hex<const N: usize>(value: &str) -> Result<[u8; N], DetachedError> {
+    let mut out = [0u8; N];
+    hex::decode_to_slice(value, &mut out).map_err(|_| DetachedError::Malformed)?;
+    Ok(out)
+}
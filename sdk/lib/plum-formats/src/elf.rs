@@ -0,0 +1,408 @@
+use core::fmt;
+
+use crate::bytes::{read_cstr, read_u16, read_u32, read_u64};
+
+pub const ELF_MAGIC: [u8; 4] = *b"\x7fELF";
+
+pub const ET_REL: u16 = 1;
+pub const ET_EXEC: u16 = 2;
+pub const ET_DYN: u16 = 3;
+
+pub const EM_X86_64: u16 = 62;
+pub const EM_AARCH64: u16 = 183;
+pub const EM_RISCV: u16 = 243;
+
+pub const PT_LOAD: u32 = 1;
+pub const PT_DYNAMIC: u32 = 2;
+
+pub const DT_NULL: u64 = 0;
+pub const DT_RELA: u64 = 7;
+pub const DT_RELASZ: u64 = 8;
+pub const DT_RELAENT: u64 = 9;
+pub const DT_REL: u64 = 17;
+pub const DT_RELR: u64 = 36;
+
+pub const PF_X: u32 = 1 << 0;
+pub const PF_W: u32 = 1 << 1;
+pub const PF_R: u32 = 1 << 2;
+
+pub const SHT_PROGBITS: u32 = 1;
+pub const SHT_SYMTAB: u32 = 2;
+pub const SHT_STRTAB: u32 = 3;
+pub const SHT_RELA: u32 = 4;
+pub const SHT_NOBITS: u32 = 8;
+pub const SHT_REL: u32 = 9;
+pub const SHT_DYNSYM: u32 = 11;
+
+pub const SHF_WRITE: u64 = 1 << 0;
+pub const SHF_ALLOC: u64 = 1 << 1;
+pub const SHF_EXECINSTR: u64 = 1 << 2;
+
+pub const SHN_UNDEF: u16 = 0;
+pub const SHN_ABS: u16 = 0xFFF1;
+pub const SHN_COMMON: u16 = 0xFFF2;
+
+pub const STB_LOCAL: u8 = 0;
+pub const STB_GLOBAL: u8 = 1;
+pub const STB_WEAK: u8 = 2;
+
+pub const STT_NOTYPE: u8 = 0;
+pub const STT_OBJECT: u8 = 1;
+pub const STT_FUNC: u8 = 2;
+pub const STT_SECTION: u8 = 3;
+
+pub const R_X86_64_64: u32 = 1;
+pub const R_X86_64_GLOB_DAT: u32 = 6;
+pub const R_X86_64_JUMP_SLOT: u32 = 7;
+pub const R_X86_64_RELATIVE: u32 = 8;
+
+pub const R_AARCH64_ABS64: u32 = 257;
+pub const R_AARCH64_GLOB_DAT: u32 = 1025;
+pub const R_AARCH64_JUMP_SLOT: u32 = 1026;
+pub const R_AARCH64_RELATIVE: u32 = 1027;
+
+pub const R_RISCV_64: u32 = 2;
+pub const R_RISCV_RELATIVE: u32 = 3;
+pub const R_RISCV_JUMP_SLOT: u32 = 5;
+
+/// How a dynamic relocation of an executable or shared object is resolved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DynamicReloc {
+    None,
+    /// Load base + addend.
+    Relative,
+    /// Symbol address + addend, written as a 64-bit value.
+    Symbol,
+}
+
+pub fn dynamic_reloc(e_machine: u16, r_type: u32) -> Option<DynamicReloc> {
+    match (e_machine, r_type) {
+        (_, 0) => Some(DynamicReloc::None),
+        (EM_X86_64, R_X86_64_RELATIVE)
+        | (EM_AARCH64, R_AARCH64_RELATIVE)
+        | (EM_RISCV, R_RISCV_RELATIVE) => Some(DynamicReloc::Relative),
+        (EM_X86_64, R_X86_64_64 | R_X86_64_GLOB_DAT | R_X86_64_JUMP_SLOT)
+        | (EM_AARCH64, R_AARCH64_ABS64 | R_AARCH64_GLOB_DAT | R_AARCH64_JUMP_SLOT)
+        | (EM_RISCV, R_RISCV_64 | R_RISCV_JUMP_SLOT) => Some(DynamicReloc::Symbol),
+        _ => None,
+    }
+}
+
+const EHDR_SIZE: usize = 0x40;
+const PHDR_SIZE: usize = 0x38;
+const SHDR_SIZE: usize = 0x40;
+const SYM_SIZE: usize = 24;
+const RELA_SIZE: usize = 24;
+const DYN_SIZE: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ElfError {
+    NotElf,
+    Unsupported(&'static str),
+    OutOfBounds(&'static str),
+}
+
+impl fmt::Display for ElfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ElfError::NotElf => write!(f, "not an ELF file"),
+            ElfError::Unsupported(what) => write!(f, "unsupported ELF: {}", what),
+            ElfError::OutOfBounds(what) => write!(f, "{} out of bounds", what),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ElfError {}
+
+/// Read-only view of a little-endian ELF64 file.
+#[derive(Debug, Clone, Copy)]
+pub struct Elf<'a> {
+    pub data: &'a [u8],
+    pub e_type: u16,
+    pub e_machine: u16,
+    pub e_entry: u64,
+    e_phoff: usize,
+    e_phentsize: usize,
+    e_phnum: usize,
+    e_shoff: usize,
+    e_shentsize: usize,
+    e_shnum: usize,
+    e_shstrndx: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProgramHeader {
+    pub p_type: u32,
+    pub p_flags: u32,
+    pub p_offset: u64,
+    pub p_vaddr: u64,
+    pub p_filesz: u64,
+    pub p_memsz: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SectionHeader {
+    pub sh_name: u32,
+    pub sh_type: u32,
+    pub sh_flags: u64,
+    pub sh_addr: u64,
+    pub sh_offset: u64,
+    pub sh_size: u64,
+    pub sh_link: u32,
+    pub sh_info: u32,
+    pub sh_addralign: u64,
+    pub sh_entsize: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Symbol<'a> {
+    pub name: &'a str,
+    pub st_info: u8,
+    pub st_other: u8,
+    pub st_shndx: u16,
+    pub st_value: u64,
+    pub st_size: u64,
+}
+
+impl Symbol<'_> {
+    pub fn bind(&self) -> u8 {
+        self.st_info >> 4
+    }
+
+    pub fn sym_type(&self) -> u8 {
+        self.st_info & 0xF
+    }
+
+    pub fn is_defined(&self) -> bool {
+        self.st_shndx != SHN_UNDEF
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rela {
+    pub r_offset: u64,
+    pub r_sym: u32,
+    pub r_type: u32,
+    pub r_addend: i64,
+}
+
+impl<'a> Elf<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self, ElfError> {
+        if data.len() < EHDR_SIZE || data[0..4] != ELF_MAGIC {
+            return Err(ElfError::NotElf);
+        }
+        if data[4] != 2 || data[5] != 1 {
+            return Err(ElfError::Unsupported("only little-endian ELF64 is supported"));
+        }
+
+        let elf = Elf {
+            data,
+            e_type: read_u16(data, 0x10),
+            e_machine: read_u16(data, 0x12),
+            e_entry: read_u64(data, 0x18),
+            e_phoff: read_u64(data, 0x20) as usize,
+            e_phentsize: read_u16(data, 0x36) as usize,
+            e_phnum: read_u16(data, 0x38) as usize,
+            e_shoff: read_u64(data, 0x28) as usize,
+            e_shentsize: read_u16(data, 0x3A) as usize,
+            e_shnum: read_u16(data, 0x3C) as usize,
+            e_shstrndx: read_u16(data, 0x3E) as usize,
+        };
+
+        if elf.e_phnum > 0
+            && (elf.e_phentsize < PHDR_SIZE || table_end(elf.e_phoff, elf.e_phnum, elf.e_phentsize) > data.len())
+        {
+            return Err(ElfError::OutOfBounds("program header table"));
+        }
+        if elf.e_shnum > 0
+            && (elf.e_shentsize < SHDR_SIZE || table_end(elf.e_shoff, elf.e_shnum, elf.e_shentsize) > data.len())
+        {
+            return Err(ElfError::OutOfBounds("section header table"));
+        }
+
+        Ok(elf)
+    }
+
+    pub fn program_headers(&self) -> impl Iterator<Item = ProgramHeader> + 'a {
+        let (data, offset, size) = (self.data, self.e_phoff, self.e_phentsize);
+        (0..self.e_phnum).map(move |i| {
+            let ph = &data[offset + i * size..][..PHDR_SIZE];
+            ProgramHeader {
+                p_type: read_u32(ph, 0x00),
+                p_flags: read_u32(ph, 0x04),
+                p_offset: read_u64(ph, 0x08),
+                p_vaddr: read_u64(ph, 0x10),
+                p_filesz: read_u64(ph, 0x20),
+                p_memsz: read_u64(ph, 0x28),
+            }
+        })
+    }
+
+    /// File contents of a segment, `p_filesz` bytes from `p_offset`.
+    pub fn segment_data(&self, ph: &ProgramHeader) -> Result<&'a [u8], ElfError> {
+        let start = usize::try_from(ph.p_offset).map_err(|_| ElfError::OutOfBounds("segment data"))?;
+        let size = usize::try_from(ph.p_filesz).map_err(|_| ElfError::OutOfBounds("segment data"))?;
+        start
+            .checked_add(size)
+            .and_then(|end| self.data.get(start..end))
+            .ok_or(ElfError::OutOfBounds("segment data"))
+    }
+
+    /// File offset of a virtual address inside the file-backed part of a
+    /// PT_LOAD segment.
+    pub fn file_offset(&self, vaddr: u64) -> Option<usize> {
+        self.program_headers()
+            .filter(|ph| ph.p_type == PT_LOAD)
+            .find(|ph| vaddr >= ph.p_vaddr && vaddr - ph.p_vaddr < ph.p_filesz)
+            .and_then(|ph| ph.p_offset.checked_add(vaddr - ph.p_vaddr))
+            .and_then(|offset| usize::try_from(offset).ok())
+    }
+
+    pub fn section_count(&self) -> usize {
+        self.e_shnum
+    }
+
+    pub fn section_header(&self, index: usize) -> Option<SectionHeader> {
+        if index >= self.e_shnum {
+            return None;
+        }
+        let sh = &self.data[self.e_shoff + index * self.e_shentsize..][..SHDR_SIZE];
+        Some(SectionHeader {
+            sh_name: read_u32(sh, 0x00),
+            sh_type: read_u32(sh, 0x04),
+            sh_flags: read_u64(sh, 0x08),
+            sh_addr: read_u64(sh, 0x10),
+            sh_offset: read_u64(sh, 0x18),
+            sh_size: read_u64(sh, 0x20),
+            sh_link: read_u32(sh, 0x28),
+            sh_info: read_u32(sh, 0x2C),
+            sh_addralign: read_u64(sh, 0x30),
+            sh_entsize: read_u64(sh, 0x38),
+        })
+    }
+
+    pub fn section_headers(&self) -> impl Iterator<Item = SectionHeader> + '_ {
+        (0..self.e_shnum).filter_map(|i| self.section_header(i))
+    }
+
+    /// File contents of a section; empty for SHT_NOBITS.
+    pub fn section_data(&self, sh: &SectionHeader) -> Result<&'a [u8], ElfError> {
+        if sh.sh_type == SHT_NOBITS {
+            return Ok(&[]);
+        }
+        let start = sh.sh_offset as usize;
+        self.data
+            .get(start..start.saturating_add(sh.sh_size as usize))
+            .ok_or(ElfError::OutOfBounds("section data"))
+    }
+
+    pub fn section_name(&self, sh: &SectionHeader) -> &'a str {
+        self.section_header(self.e_shstrndx)
+            .and_then(|strtab| self.section_data(&strtab).ok())
+            .and_then(|strings| read_cstr(strings, sh.sh_name as usize))
+            .unwrap_or("")
+    }
+
+    /// Symbols of a SHT_SYMTAB or SHT_DYNSYM section, index 0 included so
+    /// relocation symbol indices can be used directly.
+    pub fn symbols(&self, symtab: &SectionHeader) -> Result<impl Iterator<Item = Symbol<'a>> + 'a, ElfError> {
+        let symbols = self.section_data(symtab)?;
+        let strings = self
+            .section_header(symtab.sh_link as usize)
+            .ok_or(ElfError::OutOfBounds("symbol string table"))
+            .and_then(|sh| self.section_data(&sh))?;
+        Ok(symbols.chunks_exact(SYM_SIZE).map(move |sym| Symbol {
+            name: read_cstr(strings, read_u32(sym, 0x00) as usize).unwrap_or(""),
+            st_info: sym[4],
+            st_other: sym[5],
+            st_shndx: read_u16(sym, 0x06),
+            st_value: read_u64(sym, 0x08),
+            st_size: read_u64(sym, 0x10),
+        }))
+    }
+
+    pub fn symbol(&self, symtab: &SectionHeader, index: u32) -> Result<Symbol<'a>, ElfError> {
+        self.symbols(symtab)?
+            .nth(index as usize)
+            .ok_or(ElfError::OutOfBounds("symbol index"))
+    }
+
+    /// Looks `name` up among the defined symbols of .symtab, then .dynsym.
+    pub fn find_symbol(&self, name: &str) -> Option<Symbol<'a>> {
+        [SHT_SYMTAB, SHT_DYNSYM].into_iter().find_map(|wanted| {
+            self.section_headers()
+                .filter(|sh| sh.sh_type == wanted)
+                .filter_map(|sh| self.symbols(&sh).ok())
+                .flatten()
+                .find(|sym| sym.is_defined() && sym.name == name)
+        })
+    }
+
+    /// Entries of a SHT_RELA section.
+    pub fn relas(&self, sh: &SectionHeader) -> Result<impl Iterator<Item = Rela> + 'a, ElfError> {
+        if sh.sh_type != SHT_RELA {
+            return Err(ElfError::Unsupported("only RELA relocations are supported"));
+        }
+        Ok(rela_entries(self.section_data(sh)?))
+    }
+
+    /// Entries of the DT_RELA table named by the PT_DYNAMIC segment, found
+    /// without section headers so stripped files work too. Empty when the
+    /// file has no dynamic relocations.
+    pub fn dynamic_relas(&self) -> Result<impl Iterator<Item = Rela> + 'a, ElfError> {
+        let Some(dynamic) = self.program_headers().find(|ph| ph.p_type == PT_DYNAMIC) else {
+            return Ok(rela_entries(&[]));
+        };
+
+        let mut rela = None;
+        let mut rela_size = 0u64;
+        let mut rela_entry_size = RELA_SIZE as u64;
+        for entry in self.segment_data(&dynamic)?.chunks_exact(DYN_SIZE) {
+            let value = read_u64(entry, 8);
+            match read_u64(entry, 0) {
+                DT_NULL => break,
+                DT_RELA => rela = Some(value),
+                DT_RELASZ => rela_size = value,
+                DT_RELAENT => rela_entry_size = value,
+                DT_REL | DT_RELR => return Err(ElfError::Unsupported("only RELA relocations are supported")),
+                _ => {}
+            }
+        }
+
+        let Some(rela_vaddr) = rela else {
+            return Ok(rela_entries(&[]));
+        };
+        if rela_entry_size != RELA_SIZE as u64 {
+            return Err(ElfError::Unsupported("unexpected DT_RELAENT"));
+        }
+        let start = self
+            .file_offset(rela_vaddr)
+            .ok_or(ElfError::OutOfBounds("DT_RELA"))?;
+        let table = usize::try_from(rela_size)
+            .ok()
+            .and_then(|size| start.checked_add(size))
+            .and_then(|end| self.data.get(start..end))
+            .ok_or(ElfError::OutOfBounds("relocation table"))?;
+        Ok(rela_entries(table))
+    }
+}
+
+fn rela_entries(data: &[u8]) -> impl Iterator<Item = Rela> + '_ {
+    data.chunks_exact(RELA_SIZE).map(|r| {
+        let info = read_u64(r, 0x08);
+        Rela {
+            r_offset: read_u64(r, 0x00),
+            r_sym: (info >> 32) as u32,
+            r_type: info as u32,
+            r_addend: read_u64(r, 0x10) as i64,
+        }
+    })
+}
+
+fn table_end(offset: usize, count: usize, entry_size: usize) -> usize {
+    count
+        .checked_mul(entry_size)
+        .and_then(|size| offset.checked_add(size))
+        .unwrap_or(usize::MAX)
+}
@@ -0,0 +1,493 @@
+use core::fmt;
+use core::mem::{offset_of, size_of};
+
+#[cfg(feature = "alloc")]
+use alloc::{vec, vec::Vec};
+
+use crate::bytes::{self, read_u16, read_u32, read_u64, write_u16, write_u32, write_u64};
+#[cfg(feature = "alloc")]
+use crate::plam::align_up;
+use crate::plam::{PlamHeader, CPU_AARCH64, CPU_PRUM64, CPU_RISCV64, CPU_X86_64, PAGE_SIZE, PLAM_MAGIC};
+use crate::plm::{PlmHeader, PLM_MAGIC};
+
+pub const FAT_MAGIC: [u8; 4] = *b"PFAT";
+pub const FAT_VERSION_MAJOR: u16 = 1;
+pub const FAT_VERSION: u16 = FAT_VERSION_MAJOR << 8;
+
+/// Slices start on a page boundary so that each one can be mapped as if it
+/// were a file of its own.
+pub const SLICE_ALIGN: u64 = PAGE_SIZE;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FatError {
+    TooShort,
+    BadMagic([u8; 4]),
+    UnsupportedVersion(u16),
+    SizeMismatch { header: u64, actual: u64 },
+    OutOfBounds(&'static str),
+    /// Slices must all be `.plam` or all be `.plm` images.
+    UnsupportedFormat([u8; 4]),
+    /// The slice is not an image of the container's format, or its header
+    /// names another architecture than its directory entry.
+    BadSlice(u32),
+    DuplicateArch(u16),
+    /// Neither a slice nor the thin image is built for this cpu_id.
+    NoSlice(u16),
+    /// The architecture has no PlumOS cpu_id.
+    UnknownArch,
+}
+
+impl fmt::Display for FatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FatError::TooShort => write!(f, "buffer is shorter than the PFAT header"),
+            FatError::BadMagic(m) => write!(f, "bad magic {:02x?}, expected \"PFAT\"", m),
+            FatError::UnsupportedVersion(v) => {
+                write!(f, "unsupported version {}.{} (expected {}.x)", v >> 8, v & 0xFF, FAT_VERSION_MAJOR)
+            }
+            FatError::SizeMismatch { header, actual } => {
+                write!(f, "header describes {} bytes but the file is {} bytes", header, actual)
+            }
+            FatError::OutOfBounds(what) => write!(f, "{} out of bounds", what),
+            FatError::UnsupportedFormat(m) => write!(f, "slices must be PLAM or PLM images, not {:02x?}", m),
+            FatError::BadSlice(index) => write!(f, "slice {} does not match its directory entry", index),
+            FatError::DuplicateArch(cpu_id) => write!(f, "more than one slice for cpu_id 0x{:04X}", cpu_id),
+            FatError::NoSlice(cpu_id) => write!(f, "no slice for cpu_id 0x{:04X}", cpu_id),
+            FatError::UnknownArch => write!(f, "architecture has no PlumOS cpu_id"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for FatError {}
+
+/// Header of a universal container: the same `.plam` or `.plm` image built
+/// for several architectures, with a directory of one slice per cpu_id.
+/// The file keeps the extension of the images it holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct FatHeader {
+    pub magic: [u8; 4],
+    pub version: u16,
+    pub reserved: u16,
+    /// Magic of every slice, [`PLAM_MAGIC`] or [`PLM_MAGIC`].
+    pub format: [u8; 4],
+    pub slice_count: u32,
+    pub slice_table_offset: u64,
+    pub file_size: u64,
+}
+
+pub const FAT_HEADER_SIZE: usize = size_of::<FatHeader>();
+
+const _: () = assert!(FAT_HEADER_SIZE == 0x20);
+
+impl FatHeader {
+    pub fn parse(buf: &[u8]) -> Result<Self, FatError> {
+        if buf.len() < FAT_HEADER_SIZE {
+            return Err(FatError::TooShort);
+        }
+
+        let magic: [u8; 4] = buf[0x00..0x04].try_into().unwrap();
+        if magic != FAT_MAGIC {
+            return Err(FatError::BadMagic(magic));
+        }
+
+        let header = FatHeader {
+            magic,
+            version: read_u16(buf, offset_of!(FatHeader, version)),
+            reserved: read_u16(buf, offset_of!(FatHeader, reserved)),
+            format: buf[offset_of!(FatHeader, format)..][..4].try_into().unwrap(),
+            slice_count: read_u32(buf, offset_of!(FatHeader, slice_count)),
+            slice_table_offset: read_u64(buf, offset_of!(FatHeader, slice_table_offset)),
+            file_size: read_u64(buf, offset_of!(FatHeader, file_size)),
+        };
+
+        if header.version >> 8 != FAT_VERSION_MAJOR {
+            return Err(FatError::UnsupportedVersion(header.version));
+        }
+        if header.format != PLAM_MAGIC && header.format != PLM_MAGIC {
+            return Err(FatError::UnsupportedFormat(header.format));
+        }
+
+        Ok(header)
+    }
+
+    pub fn write(&self, buf: &mut [u8]) -> Result<(), FatError> {
+        if buf.len() < FAT_HEADER_SIZE {
+            return Err(FatError::TooShort);
+        }
+
+        buf[0x00..0x04].copy_from_slice(&self.magic);
+        write_u16(buf, offset_of!(FatHeader, version), self.version);
+        write_u16(buf, offset_of!(FatHeader, reserved), self.reserved);
+        buf[offset_of!(FatHeader, format)..][..4].copy_from_slice(&self.format);
+        write_u32(buf, offset_of!(FatHeader, slice_count), self.slice_count);
+        write_u64(buf, offset_of!(FatHeader, slice_table_offset), self.slice_table_offset);
+        write_u64(buf, offset_of!(FatHeader, file_size), self.file_size);
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct FatSliceEntry {
+    pub cpu_id: u16,
+    pub reserved: u16,
+    pub reserved2: u32,
+    pub offset: u64,
+    pub size: u64,
+}
+
+pub const SLICE_ENTRY_SIZE: usize = size_of::<FatSliceEntry>();
+
+const _: () = assert!(SLICE_ENTRY_SIZE == 24);
+
+impl FatSliceEntry {
+    pub fn parse(entry: &[u8]) -> Self {
+        FatSliceEntry {
+            cpu_id: read_u16(entry, offset_of!(FatSliceEntry, cpu_id)),
+            reserved: read_u16(entry, offset_of!(FatSliceEntry, reserved)),
+            reserved2: read_u32(entry, offset_of!(FatSliceEntry, reserved2)),
+            offset: read_u64(entry, offset_of!(FatSliceEntry, offset)),
+            size: read_u64(entry, offset_of!(FatSliceEntry, size)),
+        }
+    }
+
+    pub fn write(&self, entry: &mut [u8]) {
+        write_u16(entry, offset_of!(FatSliceEntry, cpu_id), self.cpu_id);
+        write_u16(entry, offset_of!(FatSliceEntry, reserved), self.reserved);
+        write_u32(entry, offset_of!(FatSliceEntry, reserved2), self.reserved2);
+        write_u64(entry, offset_of!(FatSliceEntry, offset), self.offset);
+        write_u64(entry, offset_of!(FatSliceEntry, size), self.size);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Slice<'a> {
+    pub cpu_id: u16,
+    pub data: &'a [u8],
+}
+
+/// A parsed universal container. [`FatFile::parse`] checks that every slice
+/// lies in the file, starts with the header of an image of the container's
+/// format for the cpu_id of its entry, and that no cpu_id appears twice. The
+/// slices themselves are parsed by whoever loads them.
+#[derive(Debug, Clone, Copy)]
+pub struct FatFile<'a> {
+    pub header: FatHeader,
+    data: &'a [u8],
+}
+
+impl<'a> FatFile<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self, FatError> {
+        let header = FatHeader::parse(data)?;
+        if header.file_size != data.len() as u64 {
+            return Err(FatError::SizeMismatch {
+                header: header.file_size,
+                actual: data.len() as u64,
+            });
+        }
+
+        let file = FatFile { header, data };
+        file.table(header.slice_table_offset, header.slice_count as u64, SLICE_ENTRY_SIZE, "slice table")?;
+
+        for (index, entry) in file.slice_entries().enumerate() {
+            let slice = file.table(entry.offset, entry.size, 1, "slice data")?;
+            if !entry.offset.is_multiple_of(SLICE_ALIGN) || image_cpu_id(header.format, slice) != Some(entry.cpu_id) {
+                return Err(FatError::BadSlice(index as u32));
+            }
+            if file.slice_entries().take(index).any(|other| other.cpu_id == entry.cpu_id) {
+                return Err(FatError::DuplicateArch(entry.cpu_id));
+            }
+        }
+
+        Ok(file)
+    }
+
+    fn table(&self, offset: u64, count: u64, entry_size: usize, what: &'static str) -> Result<&'a [u8], FatError> {
+        bytes::table(self.data, offset, count, entry_size, FAT_HEADER_SIZE as u64, u64::MAX)
+            .ok_or(FatError::OutOfBounds(what))
+    }
+
+    pub fn slice_entries(&self) -> impl Iterator<Item = FatSliceEntry> + 'a {
+        let header = self.header;
+        self.table(header.slice_table_offset, header.slice_count as u64, SLICE_ENTRY_SIZE, "slice table")
+            .unwrap_or_default()
+            .chunks_exact(SLICE_ENTRY_SIZE)
+            .map(FatSliceEntry::parse)
+    }
+
+    pub fn slices(&self) -> impl Iterator<Item = Slice<'a>> + 'a {
+        let file = *self;
+        self.slice_entries().map(move |entry| Slice {
+            cpu_id: entry.cpu_id,
+            data: file.table(entry.offset, entry.size, 1, "slice data").unwrap_or_default(),
+        })
+    }
+
+    /// The image built for `cpu_id`, if the container has one.
+    pub fn slice(&self, cpu_id: u16) -> Option<&'a [u8]> {
+        self.slices().find(|slice| slice.cpu_id == cpu_id).map(|slice| slice.data)
+    }
+}
+
+/// cpu_id in the header of a `.plam` or `.plm` image of the given format.
+fn image_cpu_id(format: [u8; 4], image: &[u8]) -> Option<u16> {
+    match format {
+        PLAM_MAGIC => PlamHeader::parse(image).ok().map(|header| header.cpu_id),
+        PLM_MAGIC => PlmHeader::parse(image).ok().map(|header| header.cpu_id),
+        _ => None,
+    }
+}
+
+pub fn is_fat(data: &[u8]) -> bool {
+    data.starts_with(&FAT_MAGIC)
+}
+
+/// What a loader runs for `cpu_id`: the matching slice of a universal
+/// container, or `data` itself when it is a single-architecture image built
+/// for `cpu_id`. Data that is not a PLAM or PLM image at all is returned
+/// unchanged so that the image parser reports what is wrong with it.
+pub fn select(data: &[u8], cpu_id: u16) -> Result<&[u8], FatError> {
+    if is_fat(data) {
+        return FatFile::parse(data)?.slice(cpu_id).ok_or(FatError::NoSlice(cpu_id));
+    }
+    let format = data.get(..4).and_then(|magic| <[u8; 4]>::try_from(magic).ok());
+    match format.and_then(|format| image_cpu_id(format, data)) {
+        Some(image_cpu) if image_cpu != cpu_id => Err(FatError::NoSlice(cpu_id)),
+        _ => Ok(data),
+    }
+}
+
+/// [`select`] for the architecture this code runs on.
+pub fn select_current(data: &[u8]) -> Result<&[u8], FatError> {
+    let cpu_id = current_cpu_id().ok_or(FatError::UnknownArch)?;
+    select(data, cpu_id)
+}
+
+/// [`select`] by architecture name as package metadata spells it.
+pub fn select_arch<'a>(data: &'a [u8], arch: &str) -> Result<&'a [u8], FatError> {
+    select(data, cpu_from_name(arch).ok_or(FatError::UnknownArch)?)
+}
+
+/// cpu_id of the architecture this code was compiled for.
+pub const fn current_cpu_id() -> Option<u16> {
+    if cfg!(target_arch = "x86_64") {
+        Some(CPU_X86_64)
+    } else if cfg!(target_arch = "aarch64") {
+        Some(CPU_AARCH64)
+    } else if cfg!(target_arch = "riscv64") {
+        Some(CPU_RISCV64)
+    } else {
+        None
+    }
+}
+
+/// cpu_id for an architecture name as package metadata and tool options
+/// spell it.
+pub fn cpu_from_name(name: &str) -> Option<u16> {
+    match name {
+        "x86_64" | "amd64" => Some(CPU_X86_64),
+        "aarch64" | "arm64" => Some(CPU_AARCH64),
+        "riscv64" => Some(CPU_RISCV64),
+        "prum64" => Some(CPU_PRUM64),
+        _ => None,
+    }
+}
+
+/// Assembles a universal container from single-architecture images of one
+/// format, one per cpu_id. Slices keep the order they were added in.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, Default)]
+pub struct FatBuilder {
+    format: Option<[u8; 4]>,
+    slices: Vec<(u16, Vec<u8>)>,
+}
+
+#[cfg(feature = "alloc")]
+impl FatBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an image, taking its cpu_id from its header, and returns that
+    /// cpu_id.
+    pub fn add_slice(&mut self, image: Vec<u8>) -> Result<u16, FatError> {
+        let magic: [u8; 4] = image.get(..4).ok_or(FatError::TooShort)?.try_into().unwrap();
+        if magic != PLAM_MAGIC && magic != PLM_MAGIC || self.format.is_some_and(|format| format != magic) {
+            return Err(FatError::UnsupportedFormat(magic));
+        }
+        let cpu_id = image_cpu_id(magic, &image).ok_or(FatError::BadSlice(self.slices.len() as u32))?;
+        if self.slices.iter().any(|(other, _)| *other == cpu_id) {
+            return Err(FatError::DuplicateArch(cpu_id));
+        }
+        self.format = Some(magic);
+        self.slices.push((cpu_id, image));
+        Ok(cpu_id)
+    }
+
+    pub fn slice_count(&self) -> usize {
+        self.slices.len()
+    }
+
+    pub fn build(&self) -> Vec<u8> {
+        let slice_table_offset = FAT_HEADER_SIZE as u64;
+        let mut next = slice_table_offset + (self.slices.len() * SLICE_ENTRY_SIZE) as u64;
+        let entries: Vec<FatSliceEntry> = self
+            .slices
+            .iter()
+            .map(|(cpu_id, image)| {
+                let offset = align_up(next, SLICE_ALIGN);
+                next = offset + image.len() as u64;
+                FatSliceEntry {
+                    cpu_id: *cpu_id,
+                    reserved: 0,
+                    reserved2: 0,
+                    offset,
+                    size: image.len() as u64,
+                }
+            })
+            .collect();
+
+        let header = FatHeader {
+            magic: FAT_MAGIC,
+            version: FAT_VERSION,
+            reserved: 0,
+            format: self.format.unwrap_or(PLAM_MAGIC),
+            slice_count: self.slices.len() as u32,
+            slice_table_offset,
+            file_size: next,
+        };
+
+        let mut out = vec![0u8; next as usize];
+        header.write(&mut out).unwrap();
+        for (i, (entry, (_, image))) in entries.iter().zip(&self.slices).enumerate() {
+            entry.write(&mut out[slice_table_offset as usize + i * SLICE_ENTRY_SIZE..]);
+            out[entry.offset as usize..][..image.len()].copy_from_slice(image);
+        }
+        out
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use crate::plam::{PlamBuilder, PERM_R, PERM_X};
+    use crate::plm::PlmBuilder;
+
+    fn plm(cpu_id: u16) -> Vec<u8> {
+        let mut builder = PlmBuilder::new(cpu_id, 0x40_0000, 0);
+        builder.add_segment(PERM_R | PERM_X, 0x40_0000, vec![cpu_id as u8; 0x20], 0x20);
+        builder.build()
+    }
+
+    fn sample() -> (Vec<u8>, [Vec<u8>; 3]) {
+        let images = [plm(CPU_X86_64), plm(CPU_AARCH64), plm(CPU_RISCV64)];
+        let mut builder = FatBuilder::new();
+        for image in &images {
+            builder.add_slice(image.clone()).unwrap();
+        }
+        (builder.build(), images)
+    }
+
+    fn entry_offset(index: usize) -> usize {
+        FAT_HEADER_SIZE + index * SLICE_ENTRY_SIZE
+    }
+
+    #[test]
+    fn build_parse_round_trip() {
+        let (data, images) = sample();
+        let file = FatFile::parse(&data).unwrap();
+        assert_eq!(file.header.format, PLM_MAGIC);
+        assert_eq!(file.header.file_size, data.len() as u64);
+
+        let slices: Vec<_> = file.slices().collect();
+        assert_eq!(
+            slices,
+            [
+                Slice { cpu_id: CPU_X86_64, data: &images[0] },
+                Slice { cpu_id: CPU_AARCH64, data: &images[1] },
+                Slice { cpu_id: CPU_RISCV64, data: &images[2] },
+            ]
+        );
+        assert!(file.slice_entries().all(|entry| entry.offset.is_multiple_of(SLICE_ALIGN)));
+        assert_eq!(file.slice(CPU_RISCV64), Some(&images[2][..]));
+        assert_eq!(file.slice(CPU_PRUM64), None);
+
+        assert_eq!(select(&data, CPU_AARCH64), Ok(&images[1][..]));
+        assert_eq!(select(&data, CPU_PRUM64), Err(FatError::NoSlice(CPU_PRUM64)));
+        assert_eq!(select_arch(&data, "amd64"), Ok(&images[0][..]));
+        assert_eq!(select_arch(&data, "mips"), Err(FatError::UnknownArch));
+    }
+
+    #[test]
+    fn builder_rejects_duplicates_and_mixed_formats() {
+        let mut builder = FatBuilder::new();
+        assert_eq!(builder.add_slice(plm(CPU_X86_64)), Ok(CPU_X86_64));
+        assert_eq!(builder.add_slice(plm(CPU_X86_64)), Err(FatError::DuplicateArch(CPU_X86_64)));
+        let plam = PlamBuilder::new(CPU_AARCH64, 0x4008_0000, 0).build(None, None).unwrap();
+        assert_eq!(builder.add_slice(plam), Err(FatError::UnsupportedFormat(PLAM_MAGIC)));
+        assert_eq!(builder.add_slice(b"PLM".to_vec()), Err(FatError::TooShort));
+        assert_eq!(builder.slice_count(), 1);
+    }
+
+    #[test]
+    fn parse_rejects_duplicate_arch() {
+        let (mut data, _) = sample();
+        // Point the second entry at the first slice.
+        let first = FatSliceEntry::parse(&data[entry_offset(0)..]);
+        first.write(&mut data[entry_offset(1)..]);
+        assert_eq!(FatFile::parse(&data).map(|_| ()), Err(FatError::DuplicateArch(CPU_X86_64)));
+    }
+
+    #[test]
+    fn parse_rejects_misaligned_slice() {
+        let (mut data, images) = sample();
+        // Move the last slice 8 bytes further, off its page boundary.
+        let mut entry = FatSliceEntry::parse(&data[entry_offset(2)..]);
+        entry.offset += 8;
+        data.resize(data.len() + 8, 0);
+        data[entry.offset as usize..].copy_from_slice(&images[2]);
+        entry.write(&mut data[entry_offset(2)..]);
+        let mut header = FatHeader::parse(&data).unwrap();
+        header.file_size = data.len() as u64;
+        header.write(&mut data).unwrap();
+        assert_eq!(FatFile::parse(&data).map(|_| ()), Err(FatError::BadSlice(2)));
+    }
+
+    #[test]
+    fn parse_rejects_mismatched_cpu_id() {
+        let (mut data, _) = sample();
+        let mut entry = FatSliceEntry::parse(&data[entry_offset(2)..]);
+        entry.cpu_id = CPU_PRUM64;
+        entry.write(&mut data[entry_offset(2)..]);
+        assert_eq!(FatFile::parse(&data).map(|_| ()), Err(FatError::BadSlice(2)));
+    }
+
+    #[test]
+    fn parse_rejects_truncated() {
+        let (data, _) = sample();
+        let short = &data[..data.len() - 1];
+        assert_eq!(
+            FatFile::parse(short).map(|_| ()),
+            Err(FatError::SizeMismatch { header: data.len() as u64, actual: short.len() as u64 })
+        );
+        assert_eq!(FatFile::parse(&data[..FAT_HEADER_SIZE - 1]).map(|_| ()), Err(FatError::TooShort));
+    }
+
+    #[test]
+    fn select_thin_images() {
+        let thin = plm(CPU_X86_64);
+        assert!(!is_fat(&thin));
+        assert_eq!(select(&thin, CPU_X86_64), Ok(&thin[..]));
+        assert_eq!(select(&thin, CPU_RISCV64), Err(FatError::NoSlice(CPU_RISCV64)));
+        assert_eq!(select_arch(&thin, "x86_64"), Ok(&thin[..]));
+
+        let plam = PlamBuilder::new(CPU_AARCH64, 0x4008_0000, 0).build(None, None).unwrap();
+        assert_eq!(select(&plam, CPU_AARCH64), Ok(&plam[..]));
+        assert_eq!(select(&plam, CPU_X86_64), Err(FatError::NoSlice(CPU_X86_64)));
+
+        // Anything else is left for the image parser to reject.
+        assert_eq!(select(b"junk", CPU_X86_64), Ok(&b"junk"[..]));
+    }
+}
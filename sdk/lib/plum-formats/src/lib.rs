@@ -0,0 +1,26 @@
+#![no_std]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "std")]
+extern crate std;
+
+mod bytes;
+
+pub mod crc32;
+#[cfg(feature = "alloc")]
+pub mod detached;
+pub mod elf;
+pub mod fat;
+pub mod lz4;
+pub mod plam;
+#[cfg(feature = "alloc")]
+pub mod plconf;
+pub mod plib;
+pub mod plkmod;
+pub mod plm;
+pub mod pom;
+pub mod plres;
+pub mod plstat;
+pub mod version;
@@ -0,0 +1,63 @@
+/// Compresses a whole buffer into one LZ4 block, e.g. `lz4_flex::block::compress`.
+/// The crate only decompresses; builders take the compressor from the caller.
+#[cfg(feature = "alloc")]
+pub type Compressor = fn(&[u8]) -> alloc::vec::Vec<u8>;
+
+/// Most bytes a block of `compressed_size` bytes can decompress to. A length
+/// grows by at most 255 per input byte, so nothing expands further.
+pub const fn max_decompressed_size(compressed_size: u64) -> u64 {
+    compressed_size.saturating_mul(255)
+}
+
+/// Decompresses a raw LZ4 block (no frame header) into `output` and returns
+/// the number of bytes written. Returns `None` on malformed input or if
+/// `output` is too small.
+pub fn decompress_into(input: &[u8], output: &mut [u8]) -> Option<usize> {
+    let mut ip = 0;
+    let mut op = 0;
+
+    loop {
+        let token = *input.get(ip)?;
+        ip += 1;
+
+        let literal_len = read_length(input, &mut ip, (token >> 4) as usize)?;
+        let literals = input.get(ip..ip.checked_add(literal_len)?)?;
+        output.get_mut(op..op + literal_len)?.copy_from_slice(literals);
+        ip += literal_len;
+        op += literal_len;
+
+        if ip == input.len() {
+            return Some(op);
+        }
+
+        let offset = u16::from_le_bytes([*input.get(ip)?, *input.get(ip + 1)?]) as usize;
+        ip += 2;
+        if offset == 0 || offset > op {
+            return None;
+        }
+
+        let match_len = read_length(input, &mut ip, (token & 0x0F) as usize)? + 4;
+        if op + match_len > output.len() {
+            return None;
+        }
+        for i in op..op + match_len {
+            output[i] = output[i - offset];
+        }
+        op += match_len;
+    }
+}
+
+fn read_length(input: &[u8], ip: &mut usize, nibble: usize) -> Option<usize> {
+    let mut len = nibble;
+    if nibble == 15 {
+        loop {
+            let b = *input.get(*ip)?;
+            *ip += 1;
+            len = len.checked_add(b as usize)?;
+            if b != 255 {
+                break;
+            }
+        }
+    }
+    Some(len)
+}
@@ -0,0 +1,960 @@
+use core::fmt;
+use core::mem::{offset_of, size_of};
+
+#[cfg(feature = "alloc")]
+use alloc::{vec, vec::Vec};
+#[cfg(feature = "alloc")]
+use ed25519_dalek::{Signer, SigningKey};
+use ed25519_dalek::{Signature, VerifyingKey};
+use sha2::{Digest, Sha256};
+
+use crate::bytes::{read_u16, read_u32, read_u64, write_u16, write_u32, write_u64};
+use crate::crc32::{crc32, Crc32};
+use crate::lz4;
+#[cfg(feature = "alloc")]
+use crate::lz4::Compressor;
+
+pub const PLAM_MAGIC: [u8; 4] = *b"PLAM";
+pub const PLAM_VERSION_MAJOR: u16 = 3;
+pub const PLAM_VERSION: u16 = PLAM_VERSION_MAJOR << 8;
+
+/// Size of the header page; section payloads start at or after this offset.
+pub const HEADER_SIZE: usize = 4096;
+pub const PAGE_SIZE: u64 = 4096;
+
+pub const SECTION_TABLE_OFFSET: usize = 0x100;
+pub const MAX_SECTIONS: usize = (HEADER_SIZE - SECTION_TABLE_OFFSET) / size_of::<PlamSection>();
+
+pub const CPU_X86_64: u16 = 0x8664;
+pub const CPU_AARCH64: u16 = 0xAA64;
+pub const CPU_RISCV64: u16 = 0x00F3;
+pub const CPU_PRUM64: u16 = 0x7072;
+
+pub const PERM_R: u32 = 1 << 0;
+pub const PERM_W: u32 = 1 << 1;
+pub const PERM_X: u32 = 1 << 2;
+
+pub const FLAG_SIGNED: u64 = 1 << 0;
+/// Payload is stored as a single LZ4 block right after the header page.
+pub const FLAG_LZ4: u64 = 1 << 1;
+/// Image carries a relocation table and may be loaded at any page-aligned
+/// base, see [`PlamHeader::apply_relocations`].
+pub const FLAG_RELOCATABLE: u64 = 1 << 2;
+/// Loader should pick a randomized base. Only meaningful with
+/// [`FLAG_RELOCATABLE`].
+pub const FLAG_ASLR: u64 = 1 << 3;
+
+/// Names of the `flags` accepted in the `[plam]` section of `plum.config`.
+pub const CONFIG_FLAGS: &[(&str, u64)] = &[("pie", FLAG_RELOCATABLE), ("aslr", FLAG_ASLR)];
+
+/// A relocation entry is the offset from `image_base` of a 64-bit absolute
+/// address that moves with the image.
+pub const RELOC_ENTRY_SIZE: usize = 8;
+
+pub const SIGNATURE_MAGIC: [u8; 4] = *b"PSIG";
+pub const SIG_ALG_ED25519: u16 = 1;
+
+pub fn arch_name(cpu_id: u16) -> Option<&'static str> {
+    match cpu_id {
+        CPU_AARCH64 => Some("AArch64"),
+        CPU_X86_64 => Some("x86_64"),
+        CPU_RISCV64 => Some("RISC-V 64"),
+        CPU_PRUM64 => Some("prum64"),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum SectionKind {
+    Text = 1,
+    Rodata = 2,
+    Data = 3,
+    Bss = 4,
+}
+
+impl SectionKind {
+    pub fn from_u32(value: u32) -> Option<Self> {
+        match value {
+            1 => Some(SectionKind::Text),
+            2 => Some(SectionKind::Rodata),
+            3 => Some(SectionKind::Data),
+            4 => Some(SectionKind::Bss),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SectionKind::Text => ".text",
+            SectionKind::Rodata => ".rodata",
+            SectionKind::Data => ".data",
+            SectionKind::Bss => ".bss",
+        }
+    }
+
+    pub fn default_perms(self) -> u32 {
+        match self {
+            SectionKind::Text => PERM_R | PERM_X,
+            SectionKind::Rodata => PERM_R,
+            SectionKind::Data | SectionKind::Bss => PERM_R | PERM_W,
+        }
+    }
+}
+
+/// What the image is for, as named by `subsystem` in the `[plam]` section of
+/// `plum.config`. Images from before the field existed read as kernels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u16)]
+pub enum Subsystem {
+    NativeKernel = 0,
+    Driver = 1,
+    ConsoleApp = 2,
+    GuiApp = 3,
+    Wasm = 4,
+    Firmware = 5,
+}
+
+impl Subsystem {
+    pub const ALL: [Subsystem; 6] = [
+        Subsystem::NativeKernel,
+        Subsystem::Driver,
+        Subsystem::ConsoleApp,
+        Subsystem::GuiApp,
+        Subsystem::Wasm,
+        Subsystem::Firmware,
+    ];
+
+    pub fn from_u16(value: u16) -> Option<Self> {
+        Self::ALL.into_iter().find(|s| *s as u16 == value)
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|s| s.name() == name)
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Subsystem::NativeKernel => "native_kernel",
+            Subsystem::Driver => "driver",
+            Subsystem::ConsoleApp => "console_app",
+            Subsystem::GuiApp => "gui_app",
+            Subsystem::Wasm => "wasm",
+            Subsystem::Firmware => "firmware",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlamError {
+    TooShort,
+    BadMagic([u8; 4]),
+    UnsupportedVersion(u16),
+    BadSectionTable,
+    NotSigned,
+    BadSignatureBlock,
+    UntrustedKey,
+    BadSignature,
+    BadPayload,
+    PayloadChecksum,
+    SizeMismatch { header: u64, actual: u64 },
+    HeaderChecksum,
+    PayloadHash,
+    NotRelocatable,
+    BadRelocation,
+}
+
+impl fmt::Display for PlamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlamError::TooShort => write!(f, "buffer is shorter than the PLAM header"),
+            PlamError::BadMagic(m) => write!(f, "bad magic {:02x?}, expected \"PLAM\"", m),
+            PlamError::UnsupportedVersion(v) => {
+                write!(f, "unsupported version {}.{} (expected {}.x)", v >> 8, v & 0xFF, PLAM_VERSION_MAJOR)
+            }
+            PlamError::BadSectionTable => write!(f, "section table does not fit in the header"),
+            PlamError::NotSigned => write!(f, "image is not signed"),
+            PlamError::BadSignatureBlock => write!(f, "signature block is missing or malformed"),
+            PlamError::UntrustedKey => write!(f, "image is signed by a key that is not trusted"),
+            PlamError::BadSignature => write!(f, "signature does not match the image"),
+            PlamError::BadPayload => write!(f, "payload is truncated or cannot be decompressed"),
+            PlamError::PayloadChecksum => write!(f, "payload CRC32 does not match the header"),
+            PlamError::SizeMismatch { header, actual } => {
+                write!(f, "header describes {} bytes but the image is {} bytes", header, actual)
+            }
+            PlamError::HeaderChecksum => write!(f, "header CRC32 mismatch"),
+            PlamError::PayloadHash => write!(f, "payload SHA-256 does not match the header"),
+            PlamError::NotRelocatable => write!(f, "image is not relocatable"),
+            PlamError::BadRelocation => write!(f, "relocation table or target is out of bounds"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for PlamError {}
+
+/// Fixed part of the PLAM header at offset 0 of the image. All fields are
+/// little-endian on disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct PlamHeader {
+    pub magic: [u8; 4],
+    pub version: u16,
+    pub subsystem: u16,
+    pub flags: u64,
+    pub file_size: u64,
+    pub cpu_id: u16,
+    pub section_count: u16,
+    pub section_table_offset: u32,
+    pub image_base: u64,
+    pub entry_offset: u64,
+    pub signature_offset: u64,
+    pub payload_size: u64,
+    pub compressed_size: u64,
+    pub payload_crc32: u32,
+    pub header_crc32: u32,
+    pub payload_sha256: [u8; 32],
+    pub reloc_offset: u64,
+    pub reloc_count: u64,
+}
+
+const _: () = assert!(size_of::<PlamHeader>() == 0x80);
+const _: () = assert!(offset_of!(PlamHeader, subsystem) == 0x06);
+const _: () = assert!(offset_of!(PlamHeader, flags) == 0x08);
+const _: () = assert!(offset_of!(PlamHeader, file_size) == 0x10);
+const _: () = assert!(offset_of!(PlamHeader, cpu_id) == 0x18);
+const _: () = assert!(offset_of!(PlamHeader, image_base) == 0x20);
+const _: () = assert!(offset_of!(PlamHeader, entry_offset) == 0x28);
+const _: () = assert!(offset_of!(PlamHeader, signature_offset) == 0x30);
+const _: () = assert!(offset_of!(PlamHeader, payload_size) == 0x38);
+const _: () = assert!(offset_of!(PlamHeader, payload_crc32) == 0x48);
+const _: () = assert!(offset_of!(PlamHeader, header_crc32) == 0x4C);
+const _: () = assert!(offset_of!(PlamHeader, payload_sha256) == 0x50);
+const _: () = assert!(offset_of!(PlamHeader, reloc_offset) == 0x70);
+const _: () = assert!(offset_of!(PlamHeader, reloc_count) == 0x78);
+
+impl PlamHeader {
+    pub fn new(cpu_id: u16, image_base: u64, entry_offset: u64) -> Self {
+        PlamHeader {
+            magic: PLAM_MAGIC,
+            version: PLAM_VERSION,
+            subsystem: Subsystem::NativeKernel as u16,
+            flags: 0,
+            file_size: 0,
+            cpu_id,
+            section_count: 0,
+            section_table_offset: SECTION_TABLE_OFFSET as u32,
+            image_base,
+            entry_offset,
+            signature_offset: 0,
+            payload_size: 0,
+            compressed_size: 0,
+            payload_crc32: 0,
+            header_crc32: 0,
+            payload_sha256: [0u8; 32],
+            reloc_offset: 0,
+            reloc_count: 0,
+        }
+    }
+
+    pub fn parse(buf: &[u8]) -> Result<Self, PlamError> {
+        if buf.len() < size_of::<PlamHeader>() {
+            return Err(PlamError::TooShort);
+        }
+
+        let magic: [u8; 4] = buf[0x00..0x04].try_into().unwrap();
+        if magic != PLAM_MAGIC {
+            return Err(PlamError::BadMagic(magic));
+        }
+
+        let header = PlamHeader {
+            magic,
+            version: read_u16(buf, offset_of!(PlamHeader, version)),
+            subsystem: read_u16(buf, offset_of!(PlamHeader, subsystem)),
+            flags: read_u64(buf, offset_of!(PlamHeader, flags)),
+            file_size: read_u64(buf, offset_of!(PlamHeader, file_size)),
+            cpu_id: read_u16(buf, offset_of!(PlamHeader, cpu_id)),
+            section_count: read_u16(buf, offset_of!(PlamHeader, section_count)),
+            section_table_offset: read_u32(buf, offset_of!(PlamHeader, section_table_offset)),
+            image_base: read_u64(buf, offset_of!(PlamHeader, image_base)),
+            entry_offset: read_u64(buf, offset_of!(PlamHeader, entry_offset)),
+            signature_offset: read_u64(buf, offset_of!(PlamHeader, signature_offset)),
+            payload_size: read_u64(buf, offset_of!(PlamHeader, payload_size)),
+            compressed_size: read_u64(buf, offset_of!(PlamHeader, compressed_size)),
+            payload_crc32: read_u32(buf, offset_of!(PlamHeader, payload_crc32)),
+            header_crc32: read_u32(buf, offset_of!(PlamHeader, header_crc32)),
+            payload_sha256: buf[offset_of!(PlamHeader, payload_sha256)..][..32].try_into().unwrap(),
+            reloc_offset: read_u64(buf, offset_of!(PlamHeader, reloc_offset)),
+            reloc_count: read_u64(buf, offset_of!(PlamHeader, reloc_count)),
+        };
+
+        if header.version >> 8 != PLAM_VERSION_MAJOR {
+            return Err(PlamError::UnsupportedVersion(header.version));
+        }
+
+        Ok(header)
+    }
+
+    pub fn write(&self, buf: &mut [u8]) -> Result<(), PlamError> {
+        if buf.len() < size_of::<PlamHeader>() {
+            return Err(PlamError::TooShort);
+        }
+
+        buf[0x00..0x04].copy_from_slice(&self.magic);
+        write_u16(buf, offset_of!(PlamHeader, version), self.version);
+        write_u16(buf, offset_of!(PlamHeader, subsystem), self.subsystem);
+        write_u64(buf, offset_of!(PlamHeader, flags), self.flags);
+        write_u64(buf, offset_of!(PlamHeader, file_size), self.file_size);
+        write_u16(buf, offset_of!(PlamHeader, cpu_id), self.cpu_id);
+        write_u16(buf, offset_of!(PlamHeader, section_count), self.section_count);
+        write_u32(buf, offset_of!(PlamHeader, section_table_offset), self.section_table_offset);
+        write_u64(buf, offset_of!(PlamHeader, image_base), self.image_base);
+        write_u64(buf, offset_of!(PlamHeader, entry_offset), self.entry_offset);
+        write_u64(buf, offset_of!(PlamHeader, signature_offset), self.signature_offset);
+        write_u64(buf, offset_of!(PlamHeader, payload_size), self.payload_size);
+        write_u64(buf, offset_of!(PlamHeader, compressed_size), self.compressed_size);
+        write_u32(buf, offset_of!(PlamHeader, payload_crc32), self.payload_crc32);
+        write_u32(buf, offset_of!(PlamHeader, header_crc32), self.header_crc32);
+        buf[offset_of!(PlamHeader, payload_sha256)..][..32].copy_from_slice(&self.payload_sha256);
+        write_u64(buf, offset_of!(PlamHeader, reloc_offset), self.reloc_offset);
+        write_u64(buf, offset_of!(PlamHeader, reloc_count), self.reloc_count);
+        Ok(())
+    }
+
+    fn section_table_range(&self) -> Result<core::ops::Range<usize>, PlamError> {
+        let start = self.section_table_offset as usize;
+        let end = start + self.section_count as usize * size_of::<PlamSection>();
+        if start < size_of::<PlamHeader>() || end > HEADER_SIZE {
+            return Err(PlamError::BadSectionTable);
+        }
+        Ok(start..end)
+    }
+
+    /// Iterates the section table of `image`, which must start with this header.
+    pub fn sections<'a>(&self, image: &'a [u8]) -> Result<impl Iterator<Item = PlamSection> + 'a, PlamError> {
+        let range = self.section_table_range()?;
+        if image.len() < range.end {
+            return Err(PlamError::TooShort);
+        }
+        Ok(image[range]
+            .chunks_exact(size_of::<PlamSection>())
+            .map(PlamSection::parse))
+    }
+
+    /// Writes the section table at `section_table_offset` into `buf` and updates
+    /// `section_count` to match.
+    pub fn write_sections(&mut self, buf: &mut [u8], sections: &[PlamSection]) -> Result<(), PlamError> {
+        if sections.len() > MAX_SECTIONS {
+            return Err(PlamError::BadSectionTable);
+        }
+        self.section_count = sections.len() as u16;
+        let range = self.section_table_range()?;
+        if buf.len() < range.end {
+            return Err(PlamError::TooShort);
+        }
+        for (entry, section) in buf[range].chunks_exact_mut(size_of::<PlamSection>()).zip(sections) {
+            section.write(entry);
+        }
+        Ok(())
+    }
+
+    /// Bytes of payload stored after the header page, compressed or not.
+    pub fn stored_payload_size(&self) -> u64 {
+        if self.is_compressed() {
+            self.compressed_size
+        } else {
+            self.payload_size
+        }
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        self.flags & FLAG_LZ4 != 0
+    }
+
+    /// Size of the image once the payload is inflated, not counting the
+    /// signature block. Section file offsets are relative to this layout.
+    pub fn image_size(&self) -> u64 {
+        HEADER_SIZE as u64 + self.payload_size
+    }
+
+    /// Copies or inflates the payload of `image` into `out` and checks it
+    /// against `payload_crc32`. `out` must hold at least `payload_size` bytes
+    /// and becomes everything past the header page of the inflated image.
+    pub fn load_payload(&self, image: &[u8], out: &mut [u8]) -> Result<(), PlamError> {
+        let payload_size = usize::try_from(self.payload_size).map_err(|_| PlamError::BadPayload)?;
+        let stored = usize::try_from(self.stored_payload_size())
+            .ok()
+            .and_then(|stored_size| HEADER_SIZE.checked_add(stored_size))
+            .and_then(|end| image.get(HEADER_SIZE..end))
+            .ok_or(PlamError::BadPayload)?;
+        let out = out.get_mut(..payload_size).ok_or(PlamError::BadPayload)?;
+
+        if self.is_compressed() {
+            if lz4::decompress_into(stored, out) != Some(payload_size) {
+                return Err(PlamError::BadPayload);
+            }
+        } else {
+            out.copy_from_slice(stored);
+        }
+
+        if crc32(out) != self.payload_crc32 {
+            return Err(PlamError::PayloadChecksum);
+        }
+        Ok(())
+    }
+
+    pub fn is_relocatable(&self) -> bool {
+        self.flags & FLAG_RELOCATABLE != 0
+    }
+
+    /// Iterates the relocation table of an inflated image (header page
+    /// followed by the output of [`PlamHeader::load_payload`]).
+    pub fn relocations<'a>(&self, inflated: &'a [u8]) -> Result<impl Iterator<Item = u64> + 'a, PlamError> {
+        if !self.is_relocatable() {
+            return Err(PlamError::NotRelocatable);
+        }
+        let start = self.reloc_offset as usize;
+        let len = (self.reloc_count as usize)
+            .checked_mul(RELOC_ENTRY_SIZE)
+            .ok_or(PlamError::BadRelocation)?;
+        if start < HEADER_SIZE || start.checked_add(len).is_none_or(|end| end > inflated.len()) {
+            return Err(PlamError::BadRelocation);
+        }
+        Ok(inflated[start..start + len]
+            .chunks_exact(RELOC_ENTRY_SIZE)
+            .map(|entry| read_u64(entry, 0)))
+    }
+
+    /// Rebases `memory`, the image as mapped at `new_base` (each section at
+    /// `vaddr - image_base`), by adding `new_base - image_base` to every
+    /// relocated address. `inflated` is the image it was loaded from.
+    pub fn apply_relocations(&self, inflated: &[u8], memory: &mut [u8], new_base: u64) -> Result<(), PlamError> {
+        if !new_base.is_multiple_of(PAGE_SIZE) {
+            return Err(PlamError::BadRelocation);
+        }
+        let delta = new_base.wrapping_sub(self.image_base);
+        for offset in self.relocations(inflated)? {
+            let offset = usize::try_from(offset).map_err(|_| PlamError::BadRelocation)?;
+            if offset.checked_add(8).is_none_or(|end| end > memory.len()) {
+                return Err(PlamError::BadRelocation);
+            }
+            write_u64(memory, offset, read_u64(memory, offset).wrapping_add(delta));
+        }
+        Ok(())
+    }
+
+    pub fn is_signed(&self) -> bool {
+        self.flags & FLAG_SIGNED != 0
+    }
+
+    /// Locates the signature block of a signed `image`.
+    pub fn signature(&self, image: &[u8]) -> Result<PlamSignature, PlamError> {
+        if !self.is_signed() {
+            return Err(PlamError::NotSigned);
+        }
+        let start = usize::try_from(self.signature_offset).map_err(|_| PlamError::BadSignatureBlock)?;
+        if start < HEADER_SIZE {
+            return Err(PlamError::BadSignatureBlock);
+        }
+        let block = start
+            .checked_add(size_of::<PlamSignature>())
+            .and_then(|end| image.get(start..end))
+            .ok_or(PlamError::BadSignatureBlock)?;
+        PlamSignature::parse(block)
+    }
+}
+
+/// CRC32 of a full header page with the `header_crc32` field treated as zero.
+pub fn header_crc32(header_page: &[u8]) -> u32 {
+    let field = offset_of!(PlamHeader, header_crc32);
+    let mut crc = Crc32::new();
+    crc.update(&header_page[..field]);
+    crc.update(&[0u8; 4]);
+    crc.update(&header_page[field + 4..HEADER_SIZE]);
+    crc.finish()
+}
+
+/// Checks that `image` is a complete, uncorrupted PLAM file: version, sizes,
+/// header CRC32, section table bounds and the SHA-256 of the stored payload.
+/// Does not decompress the payload or check signatures, see
+/// [`PlamHeader::load_payload`] and [`verify_signature`].
+pub fn validate(image: &[u8]) -> Result<PlamHeader, PlamError> {
+    let header = PlamHeader::parse(image)?;
+
+    if image.len() < HEADER_SIZE || header.file_size != image.len() as u64 {
+        return Err(PlamError::SizeMismatch {
+            header: header.file_size,
+            actual: image.len() as u64,
+        });
+    }
+
+    if header_crc32(image) != header.header_crc32 {
+        return Err(PlamError::HeaderChecksum);
+    }
+
+    let payload_end = (HEADER_SIZE as u64)
+        .checked_add(header.stored_payload_size())
+        .ok_or(PlamError::BadPayload)?;
+    let expected_size = if header.is_signed() {
+        if header.signature_offset != payload_end {
+            return Err(PlamError::BadSignatureBlock);
+        }
+        payload_end
+            .checked_add(SIGNATURE_BLOCK_SIZE as u64)
+            .ok_or(PlamError::BadSignatureBlock)?
+    } else {
+        payload_end
+    };
+    if expected_size != header.file_size {
+        return Err(PlamError::SizeMismatch {
+            header: expected_size,
+            actual: image.len() as u64,
+        });
+    }
+
+    header.section_table_range()?;
+
+    let payload = usize::try_from(payload_end)
+        .ok()
+        .filter(|&end| end >= HEADER_SIZE)
+        .and_then(|end| image.get(HEADER_SIZE..end))
+        .ok_or(PlamError::BadPayload)?;
+    let payload_hash: [u8; 32] = Sha256::digest(payload).into();
+    if payload_hash != header.payload_sha256 {
+        return Err(PlamError::PayloadHash);
+    }
+
+    Ok(header)
+}
+
+/// One entry of the section table. `file_size` may be smaller than
+/// `mem_size`, the loader zero-fills the rest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct PlamSection {
+    pub name: [u8; 8],
+    pub kind: u32,
+    pub perms: u32,
+    pub vaddr: u64,
+    pub file_offset: u64,
+    pub file_size: u64,
+    pub mem_size: u64,
+}
+
+const _: () = assert!(size_of::<PlamSection>() == 48);
+
+impl PlamSection {
+    pub fn new(kind: SectionKind, perms: u32, vaddr: u64, file_size: u64, mem_size: u64) -> Self {
+        let mut name = [0u8; 8];
+        let kind_name = kind.name().as_bytes();
+        name[..kind_name.len()].copy_from_slice(kind_name);
+        PlamSection {
+            name,
+            kind: kind as u32,
+            perms,
+            vaddr,
+            file_offset: 0,
+            file_size,
+            mem_size,
+        }
+    }
+
+    pub fn parse(entry: &[u8]) -> Self {
+        PlamSection {
+            name: entry[0x00..0x08].try_into().unwrap(),
+            kind: read_u32(entry, offset_of!(PlamSection, kind)),
+            perms: read_u32(entry, offset_of!(PlamSection, perms)),
+            vaddr: read_u64(entry, offset_of!(PlamSection, vaddr)),
+            file_offset: read_u64(entry, offset_of!(PlamSection, file_offset)),
+            file_size: read_u64(entry, offset_of!(PlamSection, file_size)),
+            mem_size: read_u64(entry, offset_of!(PlamSection, mem_size)),
+        }
+    }
+
+    pub fn write(&self, entry: &mut [u8]) {
+        entry[0x00..0x08].copy_from_slice(&self.name);
+        write_u32(entry, offset_of!(PlamSection, kind), self.kind);
+        write_u32(entry, offset_of!(PlamSection, perms), self.perms);
+        write_u64(entry, offset_of!(PlamSection, vaddr), self.vaddr);
+        write_u64(entry, offset_of!(PlamSection, file_offset), self.file_offset);
+        write_u64(entry, offset_of!(PlamSection, file_size), self.file_size);
+        write_u64(entry, offset_of!(PlamSection, mem_size), self.mem_size);
+    }
+
+    pub fn name(&self) -> &str {
+        let len = self.name.iter().position(|&b| b == 0).unwrap_or(self.name.len());
+        core::str::from_utf8(&self.name[..len]).unwrap_or("?")
+    }
+
+    pub fn section_kind(&self) -> Option<SectionKind> {
+        SectionKind::from_u32(self.kind)
+    }
+}
+
+pub fn align_up(value: u64, align: u64) -> u64 {
+    (value + align - 1) & !(align - 1)
+}
+
+/// Assembles a PLAM image. Sections keep the order they were added in and
+/// get file offsets congruent to their load addresses, so the loader can map
+/// each one page-by-page straight from the image; the relocation table
+/// follows the last section.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone)]
+pub struct PlamBuilder {
+    pub cpu_id: u16,
+    pub image_base: u64,
+    pub entry_offset: u64,
+    pub subsystem: Subsystem,
+    /// Extra header flags such as [`FLAG_ASLR`]. The relocatable, LZ4 and
+    /// signed flags follow from the other fields and `build` arguments.
+    pub flags: u64,
+    /// Offsets from `image_base` of the 64-bit addresses to rebase. `Some`
+    /// marks the image relocatable even when there is nothing to patch.
+    pub relocations: Option<Vec<u64>>,
+    sections: Vec<(PlamSection, Vec<u8>)>,
+}
+
+#[cfg(feature = "alloc")]
+impl PlamBuilder {
+    pub fn new(cpu_id: u16, image_base: u64, entry_offset: u64) -> Self {
+        PlamBuilder {
+            cpu_id,
+            image_base,
+            entry_offset,
+            subsystem: Subsystem::NativeKernel,
+            flags: 0,
+            relocations: None,
+            sections: Vec::new(),
+        }
+    }
+
+    /// Adds a section loaded at `vaddr`. `data` may be shorter than
+    /// `mem_size`; the rest is zero-filled.
+    pub fn add_section(&mut self, kind: SectionKind, perms: u32, vaddr: u64, data: Vec<u8>, mem_size: u64) {
+        let section = PlamSection::new(kind, perms, vaddr, data.len() as u64, mem_size);
+        self.sections.push((section, data));
+    }
+
+    /// Records a 64-bit address at `offset` from the image base that the
+    /// loader rebases, and marks the image relocatable.
+    pub fn add_relocation(&mut self, offset: u64) {
+        self.relocations.get_or_insert_with(Vec::new).push(offset);
+    }
+
+    pub fn section_count(&self) -> usize {
+        self.sections.len()
+    }
+
+    /// Lays out the image. `compress` stores the payload as one LZ4 block
+    /// and `signing_key` appends a signature block covering everything
+    /// before it.
+    pub fn build(&self, compress: Option<Compressor>, signing_key: Option<&SigningKey>) -> Result<Vec<u8>, PlamError> {
+        let mut table = Vec::with_capacity(self.sections.len());
+        let mut next_offset = HEADER_SIZE as u64;
+        for (section, data) in &self.sections {
+            let mut section = *section;
+            if !data.is_empty() {
+                section.file_offset = align_up(next_offset, PAGE_SIZE) + section.vaddr % PAGE_SIZE;
+                next_offset = section.file_offset + data.len() as u64;
+            }
+            table.push(section);
+        }
+
+        let reloc_offset = align_up(next_offset, RELOC_ENTRY_SIZE as u64);
+        if let Some(relocations) = &self.relocations {
+            next_offset = reloc_offset + (relocations.len() * RELOC_ENTRY_SIZE) as u64;
+        }
+
+        let mut payload = vec![0u8; next_offset as usize - HEADER_SIZE];
+        for (section, (_, data)) in table.iter().zip(&self.sections) {
+            let start = section.file_offset as usize;
+            if !data.is_empty() {
+                payload[start - HEADER_SIZE..][..data.len()].copy_from_slice(data);
+            }
+        }
+
+        let mut header = PlamHeader::new(self.cpu_id, self.image_base, self.entry_offset);
+        header.subsystem = self.subsystem as u16;
+        header.flags = self.flags;
+        if let Some(relocations) = &self.relocations {
+            let entries = &mut payload[reloc_offset as usize - HEADER_SIZE..];
+            for (entry, offset) in entries.chunks_exact_mut(RELOC_ENTRY_SIZE).zip(relocations) {
+                write_u64(entry, 0, *offset);
+            }
+            header.flags |= FLAG_RELOCATABLE;
+            header.reloc_offset = reloc_offset;
+            header.reloc_count = relocations.len() as u64;
+        }
+        header.payload_size = payload.len() as u64;
+        header.payload_crc32 = crc32(&payload);
+
+        let body = match compress {
+            Some(compress) => {
+                let compressed = compress(&payload);
+                header.flags |= FLAG_LZ4;
+                header.compressed_size = compressed.len() as u64;
+                compressed
+            }
+            None => payload,
+        };
+        header.payload_sha256 = Sha256::digest(&body).into();
+
+        header.file_size = (HEADER_SIZE + body.len()) as u64;
+        if signing_key.is_some() {
+            header.flags |= FLAG_SIGNED;
+            header.signature_offset = header.file_size;
+            header.file_size += SIGNATURE_BLOCK_SIZE as u64;
+        }
+
+        let mut image = vec![0u8; HEADER_SIZE];
+        header.write_sections(&mut image, &table)?;
+        header.write(&mut image)?;
+        header.header_crc32 = header_crc32(&image);
+        header.write(&mut image)?;
+
+        image.extend_from_slice(&body);
+        image.resize(header.file_size as usize, 0);
+
+        if let Some(key) = signing_key {
+            let start = header.signature_offset as usize;
+            let signature = key.sign(&image[..start]);
+            PlamSignature::new(key.verifying_key().to_bytes(), signature.to_bytes()).write(&mut image[start..]);
+        }
+        Ok(image)
+    }
+}
+
+/// Signature block appended after the last section. The signature covers
+/// every byte of the image before the block, header included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct PlamSignature {
+    pub magic: [u8; 4],
+    pub algorithm: u16,
+    pub reserved: u16,
+    pub public_key: [u8; 32],
+    pub signature: [u8; 64],
+}
+
+pub const SIGNATURE_BLOCK_SIZE: usize = size_of::<PlamSignature>();
+
+const _: () = assert!(SIGNATURE_BLOCK_SIZE == 104);
+
+impl PlamSignature {
+    pub fn new(public_key: [u8; 32], signature: [u8; 64]) -> Self {
+        PlamSignature {
+            magic: SIGNATURE_MAGIC,
+            algorithm: SIG_ALG_ED25519,
+            reserved: 0,
+            public_key,
+            signature,
+        }
+    }
+
+    pub fn parse(buf: &[u8]) -> Result<Self, PlamError> {
+        if buf.len() < SIGNATURE_BLOCK_SIZE {
+            return Err(PlamError::BadSignatureBlock);
+        }
+        let block = PlamSignature {
+            magic: buf[0x00..0x04].try_into().unwrap(),
+            algorithm: read_u16(buf, offset_of!(PlamSignature, algorithm)),
+            reserved: read_u16(buf, offset_of!(PlamSignature, reserved)),
+            public_key: buf[offset_of!(PlamSignature, public_key)..][..32].try_into().unwrap(),
+            signature: buf[offset_of!(PlamSignature, signature)..][..64].try_into().unwrap(),
+        };
+        if block.magic != SIGNATURE_MAGIC || block.algorithm != SIG_ALG_ED25519 {
+            return Err(PlamError::BadSignatureBlock);
+        }
+        Ok(block)
+    }
+
+    pub fn write(&self, buf: &mut [u8]) {
+        buf[0x00..0x04].copy_from_slice(&self.magic);
+        write_u16(buf, offset_of!(PlamSignature, algorithm), self.algorithm);
+        write_u16(buf, offset_of!(PlamSignature, reserved), self.reserved);
+        buf[offset_of!(PlamSignature, public_key)..][..32].copy_from_slice(&self.public_key);
+        buf[offset_of!(PlamSignature, signature)..][..64].copy_from_slice(&self.signature);
+    }
+}
+
+/// Checks the embedded signature of `image` against `trusted_keys` and
+/// returns the key that signed it.
+pub fn verify_signature(image: &[u8], trusted_keys: &[[u8; 32]]) -> Result<[u8; 32], PlamError> {
+    let header = PlamHeader::parse(image)?;
+    let block = header.signature(image)?;
+
+    if !trusted_keys.contains(&block.public_key) {
+        return Err(PlamError::UntrustedKey);
+    }
+
+    let key = VerifyingKey::from_bytes(&block.public_key).map_err(|_| PlamError::BadSignatureBlock)?;
+    let signature = Signature::from_bytes(&block.signature);
+    key.verify_strict(&image[..header.signature_offset as usize], &signature)
+        .map_err(|_| PlamError::BadSignature)?;
+
+    Ok(block.public_key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_header() -> PlamHeader {
+        let mut header = PlamHeader::new(CPU_AARCH64, 0xFFFF_8000_0010_0000, 0x1234);
+        header.subsystem = Subsystem::Driver as u16;
+        header.flags = FLAG_SIGNED | FLAG_LZ4 | FLAG_RELOCATABLE;
+        header.file_size = 0x0001_2345;
+        header.section_count = 3;
+        header.signature_offset = 0x0001_2000;
+        header.payload_size = 0x0002_0000;
+        header.compressed_size = 0xE000;
+        header.payload_crc32 = 0xDEAD_BEEF;
+        header.header_crc32 = 0x0BAD_F00D;
+        header.payload_sha256 = core::array::from_fn(|i| i as u8);
+        header.reloc_offset = 0x3000;
+        header.reloc_count = 17;
+        header
+    }
+
+    #[test]
+    fn header_round_trip() {
+        let header = sample_header();
+        let mut buf = [0u8; HEADER_SIZE];
+        header.write(&mut buf).unwrap();
+        assert_eq!(PlamHeader::parse(&buf), Ok(header));
+    }
+
+    #[test]
+    fn header_field_offsets() {
+        let header = sample_header();
+        let mut buf = [0u8; size_of::<PlamHeader>()];
+        header.write(&mut buf).unwrap();
+        assert_eq!(&buf[0x00..0x04], b"PLAM");
+        assert_eq!(read_u16(&buf, 0x04), PLAM_VERSION);
+        assert_eq!(read_u16(&buf, 0x18), CPU_AARCH64);
+        assert_eq!(read_u64(&buf, 0x20), 0xFFFF_8000_0010_0000);
+        assert_eq!(read_u32(&buf, 0x48), 0xDEAD_BEEF);
+        assert_eq!(buf[0x50], 0);
+        assert_eq!(buf[0x6F], 31);
+        assert_eq!(read_u64(&buf, 0x78), 17);
+    }
+
+    #[test]
+    fn header_short_buffer() {
+        let header = sample_header();
+        let mut buf = [0u8; size_of::<PlamHeader>() - 1];
+        assert_eq!(header.write(&mut buf), Err(PlamError::TooShort));
+        assert_eq!(PlamHeader::parse(&buf), Err(PlamError::TooShort));
+    }
+
+    #[test]
+    fn header_bad_magic() {
+        let mut buf = [0u8; HEADER_SIZE];
+        sample_header().write(&mut buf).unwrap();
+        buf[0] = b'X';
+        assert_eq!(PlamHeader::parse(&buf), Err(PlamError::BadMagic(*b"XLAM")));
+    }
+
+    #[test]
+    fn header_minor_version_accepted() {
+        let mut header = sample_header();
+        header.version = PLAM_VERSION | 7;
+        let mut buf = [0u8; HEADER_SIZE];
+        header.write(&mut buf).unwrap();
+        assert_eq!(PlamHeader::parse(&buf), Ok(header));
+    }
+
+    #[test]
+    fn header_unsupported_version() {
+        let mut header = sample_header();
+        header.version = (PLAM_VERSION_MAJOR + 1) << 8;
+        let mut buf = [0u8; HEADER_SIZE];
+        header.write(&mut buf).unwrap();
+        assert_eq!(PlamHeader::parse(&buf), Err(PlamError::UnsupportedVersion(header.version)));
+    }
+
+    #[test]
+    fn sections_round_trip() {
+        let sections = [
+            PlamSection::new(SectionKind::Text, PERM_R | PERM_X, 0x1000, 0x2000, 0x2000),
+            PlamSection::new(SectionKind::Rodata, PERM_R, 0x3000, 0x800, 0x1000),
+            PlamSection::new(SectionKind::Bss, PERM_R | PERM_W, 0x4000, 0, 0x4000),
+        ];
+        let mut header = PlamHeader::new(CPU_X86_64, 0x40_0000, 0);
+        let mut buf = [0u8; HEADER_SIZE];
+        header.write_sections(&mut buf, &sections).unwrap();
+        header.write(&mut buf).unwrap();
+
+        let parsed = PlamHeader::parse(&buf).unwrap();
+        assert_eq!(parsed.section_count, 3);
+        let mut read = parsed.sections(&buf).unwrap();
+        for section in &sections {
+            assert_eq!(read.next().as_ref(), Some(section));
+        }
+        assert!(read.next().is_none());
+        assert_eq!(sections[2].name(), ".bss");
+    }
+
+    #[test]
+    fn signature_offset_out_of_range() {
+        let mut header = PlamHeader::new(CPU_X86_64, 0, 0);
+        header.flags = FLAG_SIGNED;
+        let image = [0u8; HEADER_SIZE + SIGNATURE_BLOCK_SIZE];
+        for offset in [0, HEADER_SIZE as u64 + 1, u64::MAX - 8, u64::MAX] {
+            header.signature_offset = offset;
+            assert_eq!(header.signature(&image), Err(PlamError::BadSignatureBlock));
+        }
+    }
+
+    fn sealed_image(header: &PlamHeader) -> [u8; HEADER_SIZE] {
+        let mut image = [0u8; HEADER_SIZE];
+        header.write(&mut image).unwrap();
+        let crc = header_crc32(&image);
+        write_u32(&mut image, offset_of!(PlamHeader, header_crc32), crc);
+        image
+    }
+
+    #[test]
+    fn validate_payload_size_overflow() {
+        let mut header = PlamHeader::new(CPU_X86_64, 0, 0);
+        header.file_size = HEADER_SIZE as u64;
+        header.payload_size = u64::MAX;
+        assert_eq!(validate(&sealed_image(&header)), Err(PlamError::BadPayload));
+
+        header.payload_size = u64::MAX - HEADER_SIZE as u64;
+        header.flags = FLAG_SIGNED;
+        header.signature_offset = u64::MAX;
+        assert_eq!(validate(&sealed_image(&header)), Err(PlamError::BadSignatureBlock));
+    }
+
+    #[test]
+    fn validate_empty_payload() {
+        let mut header = PlamHeader::new(CPU_X86_64, 0, 0);
+        header.file_size = HEADER_SIZE as u64;
+        header.payload_sha256 = Sha256::digest([]).into();
+        assert_eq!(validate(&sealed_image(&header)).map(|h| h.payload_size), Ok(0));
+    }
+
+    #[test]
+    fn load_payload_out_of_range() {
+        let mut header = PlamHeader::new(CPU_X86_64, 0, 0);
+        let image = [0u8; HEADER_SIZE + 16];
+        let mut out = [0u8; 16];
+        for size in [17, u64::MAX - 8, u64::MAX] {
+            header.payload_size = size;
+            assert_eq!(header.load_payload(&image, &mut out), Err(PlamError::BadPayload));
+        }
+        header.payload_size = 16;
+        header.payload_crc32 = crc32(&[0u8; 16]);
+        assert_eq!(header.load_payload(&image, &mut out), Ok(()));
+    }
+
+    #[test]
+    fn too_many_sections() {
+        let sections = [PlamSection::new(SectionKind::Data, PERM_R, 0, 0, 0); MAX_SECTIONS + 1];
+        let mut header = PlamHeader::new(CPU_X86_64, 0, 0);
+        let mut buf = [0u8; HEADER_SIZE];
+        assert_eq!(header.write_sections(&mut buf, &sections), Err(PlamError::BadSectionTable));
+    }
+}
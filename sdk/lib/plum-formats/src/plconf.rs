@@ -0,0 +1,1312 @@
+use alloc::borrow::ToOwned;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::plam::Subsystem;
+use crate::version::Version;
+
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+/// File name used in errors for values given with [`Config::apply_override`].
+pub const OVERRIDE_FILE: &str = "<override>";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location {
+    pub file: usize,
+    pub line: u32,
+    pub column: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    UnexpectedChar(char),
+    UnexpectedEnd,
+    UnterminatedString,
+    BadEscape(char),
+    BadNumber,
+    ExpectedKey,
+    ExpectedEquals,
+    ExpectedValue,
+    MixedList,
+    DuplicateKey(String),
+    DuplicateSection(String),
+    SectionKindMismatch(String),
+    IncludeAfterSection,
+    IncludeNotFound(String),
+    IncludeCycle(String),
+    IncludeTooDeep,
+    BadOverride,
+    UnknownSection(String),
+    UnknownKey(String),
+    MissingSection(String),
+    MissingKey(String),
+    TypeMismatch { key: String, expected: &'static str },
+    NotAllowed { key: String, value: String },
+    OutOfRange { key: String, min: i64, max: i64 },
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorKind::UnexpectedChar(c) => write!(f, "unexpected character {:?}", c),
+            ErrorKind::UnexpectedEnd => write!(f, "unexpected end of file"),
+            ErrorKind::UnterminatedString => write!(f, "unterminated string"),
+            ErrorKind::BadEscape(c) => write!(f, "unknown escape \\{}", c),
+            ErrorKind::BadNumber => write!(f, "invalid number"),
+            ErrorKind::ExpectedKey => write!(f, "expected a key"),
+            ErrorKind::ExpectedEquals => write!(f, "expected '='"),
+            ErrorKind::ExpectedValue => write!(f, "expected a value"),
+            ErrorKind::MixedList => write!(f, "list elements must all have the same type"),
+            ErrorKind::DuplicateKey(key) => write!(f, "{} is already set in this file", key),
+            ErrorKind::DuplicateSection(name) => write!(f, "section [{}] is already defined in this file", name),
+            ErrorKind::SectionKindMismatch(name) => write!(f, "{} is used both as [{0}] and [[{0}]]", name),
+            ErrorKind::IncludeAfterSection => write!(f, "include must come before the first section"),
+            ErrorKind::IncludeNotFound(path) => write!(f, "cannot read included file {}", path),
+            ErrorKind::IncludeCycle(path) => write!(f, "{} includes itself", path),
+            ErrorKind::IncludeTooDeep => write!(f, "includes nested more than {} deep", MAX_INCLUDE_DEPTH),
+            ErrorKind::BadOverride => write!(f, "override must look like section.key=value"),
+            ErrorKind::UnknownSection(name) => write!(f, "unknown section [{}]", name),
+            ErrorKind::UnknownKey(key) => write!(f, "unknown key {}", key),
+            ErrorKind::MissingSection(name) => write!(f, "missing section [{}]", name),
+            ErrorKind::MissingKey(key) => write!(f, "missing required key {}", key),
+            ErrorKind::TypeMismatch { key, expected } => write!(f, "{} must be {}", key, expected),
+            ErrorKind::NotAllowed { key, value } => write!(f, "{} is not a valid value for {}", value, key),
+            ErrorKind::OutOfRange { key, min, max } => write!(f, "{} must be between {} and {}", key, min, max),
+        }
+    }
+}
+
+/// An error with the file, line and column it refers to (1-based).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigError {
+    pub file: String,
+    pub line: u32,
+    pub column: u32,
+    pub kind: ErrorKind,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}: {}", self.file, self.line, self.column, self.kind)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ConfigError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Bool(bool),
+    Int(i64),
+    Str(String),
+    List(Vec<Value>),
+}
+
+impl Value {
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Value::Int(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[Value]> {
+        match self {
+            Value::List(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    /// A byte count given as an integer or a string such as `"16K"`, `"1G"`.
+    pub fn as_size(&self) -> Option<u64> {
+        match self {
+            Value::Int(i) => u64::try_from(*i).ok(),
+            Value::Str(s) => parse_size(s),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Int(i) => write!(f, "{}", i),
+            Value::Str(s) => write!(f, "{:?}", s),
+            Value::List(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+fn parse_size(s: &str) -> Option<u64> {
+    let (digits, shift) = match s.as_bytes().last()? {
+        b'K' | b'k' => (&s[..s.len() - 1], 10),
+        b'M' | b'm' => (&s[..s.len() - 1], 20),
+        b'G' | b'g' => (&s[..s.len() - 1], 30),
+        _ => (s, 0),
+    };
+    let value: u64 = digits.parse().ok()?;
+    value.checked_mul(1 << shift)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub key: String,
+    pub value: Value,
+    pub location: Location,
+}
+
+/// A `[name]` or one `[[name]]` section. Keys before the first header live
+/// in a section with an empty name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    pub name: String,
+    pub repeated: bool,
+    pub location: Location,
+    pub entries: Vec<Entry>,
+}
+
+impl Section {
+    pub fn entry(&self, key: &str) -> Option<&Entry> {
+        self.entries.iter().find(|e| e.key == key)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.entry(key).map(|e| &e.value)
+    }
+
+    fn set(&mut self, entry: Entry) -> Result<(), ErrorKind> {
+        match self.entries.iter_mut().find(|e| e.key == entry.key) {
+            Some(existing) if existing.location.file == entry.location.file => {
+                Err(ErrorKind::DuplicateKey(entry.key))
+            }
+            Some(existing) => {
+                *existing = entry;
+                Ok(())
+            }
+            None => {
+                self.entries.push(entry);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Reads included files. Implemented for closures returning the file text.
+pub trait Loader {
+    fn load(&mut self, path: &str) -> Option<String>;
+}
+
+impl<F: FnMut(&str) -> Option<String>> Loader for F {
+    fn load(&mut self, path: &str) -> Option<String> {
+        self(path)
+    }
+}
+
+/// A loaded `.plconf` configuration: TOML-like `key = value` lines under
+/// `[section]` and `[[repeated]]` headers, with `include "file"` lines before
+/// the first section. Included files are loaded first and the including file
+/// overrides their keys; setting a key twice in the same file is an error.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Config {
+    /// Every file loaded, indexed by [`Location::file`].
+    pub files: Vec<String>,
+    pub sections: Vec<Section>,
+}
+
+impl Config {
+    /// Parses a single file; `include` directives are rejected.
+    pub fn parse(name: &str, text: &str) -> Result<Self, ConfigError> {
+        Self::load(name, text, &mut |_: &str| None)
+    }
+
+    /// Parses `text` as the file `name`, reading includes through `loader`.
+    /// Include paths are relative to the directory of the including file.
+    pub fn load(name: &str, text: &str, loader: &mut dyn Loader) -> Result<Self, ConfigError> {
+        let mut config = Config::default();
+        let mut stack = Vec::new();
+        config.load_file(name, text, loader, &mut stack)?;
+        Ok(config)
+    }
+
+    fn load_file(
+        &mut self,
+        name: &str,
+        text: &str,
+        loader: &mut dyn Loader,
+        stack: &mut Vec<String>,
+    ) -> Result<(), ConfigError> {
+        let file = self.files.len();
+        self.files.push(name.to_owned());
+        stack.push(name.to_owned());
+
+        let mut parser = Parser::new(text, file);
+        let mut current: Option<usize> = None;
+        let mut seen_sections: Vec<usize> = Vec::new();
+
+        loop {
+            parser.skip_blank_lines();
+            let Some(c) = parser.peek() else {
+                break;
+            };
+            let location = parser.location();
+
+            if c == '[' {
+                let (section_name, repeated) = parser.section_header().map_err(|e| self.error(e))?;
+                let index = self
+                    .open_section(&section_name, repeated, location, &seen_sections)
+                    .map_err(|kind| self.error((kind, location)))?;
+                seen_sections.push(index);
+                current = Some(index);
+            } else if parser.at_keyword("include") {
+                if current.is_some() {
+                    return Err(self.error((ErrorKind::IncludeAfterSection, location)));
+                }
+                parser.advance_by("include".len());
+                parser.skip_spaces();
+                let path_location = parser.location();
+                let path = match parser.value().map_err(|e| self.error(e))? {
+                    Value::Str(path) => path,
+                    _ => return Err(self.error((ErrorKind::ExpectedValue, path_location))),
+                };
+                parser.end_of_line().map_err(|e| self.error(e))?;
+
+                let path = resolve_path(name, &path);
+                if stack.contains(&path) {
+                    return Err(self.error((ErrorKind::IncludeCycle(path), path_location)));
+                }
+                if stack.len() >= MAX_INCLUDE_DEPTH {
+                    return Err(self.error((ErrorKind::IncludeTooDeep, path_location)));
+                }
+                let included = loader
+                    .load(&path)
+                    .ok_or_else(|| self.error((ErrorKind::IncludeNotFound(path.clone()), path_location)))?;
+                self.load_file(&path, &included, loader, stack)?;
+            } else {
+                let (key, value) = parser.assignment().map_err(|e| self.error(e))?;
+                let index = match current {
+                    Some(index) => index,
+                    None => {
+                        let index = self.open_section("", false, location, &seen_sections).unwrap();
+                        if !seen_sections.contains(&index) {
+                            seen_sections.push(index);
+                        }
+                        index
+                    }
+                };
+                self.sections[index]
+                    .set(Entry { key, value, location })
+                    .map_err(|kind| self.error((kind, location)))?;
+            }
+        }
+
+        stack.pop();
+        Ok(())
+    }
+
+    fn open_section(
+        &mut self,
+        name: &str,
+        repeated: bool,
+        location: Location,
+        seen_in_file: &[usize],
+    ) -> Result<usize, ErrorKind> {
+        let existing = self.sections.iter().position(|s| s.name == name);
+        if let Some(index) = existing {
+            if self.sections[index].repeated != repeated {
+                return Err(ErrorKind::SectionKindMismatch(name.to_owned()));
+            }
+            if !repeated {
+                if !name.is_empty() && seen_in_file.contains(&index) {
+                    return Err(ErrorKind::DuplicateSection(name.to_owned()));
+                }
+                return Ok(index);
+            }
+        }
+        self.sections.push(Section {
+            name: name.to_owned(),
+            repeated,
+            location,
+            entries: Vec::new(),
+        });
+        Ok(self.sections.len() - 1)
+    }
+
+    fn error(&self, (kind, location): (ErrorKind, Location)) -> ConfigError {
+        ConfigError {
+            file: self.files.get(location.file).cloned().unwrap_or_default(),
+            line: location.line,
+            column: location.column,
+            kind,
+        }
+    }
+
+    /// Sets `section.key=value` (or `key=value` for the root section) on top
+    /// of the loaded files, e.g. from a command line. A value that does not
+    /// start like a number, list or string is taken as a bare string.
+    /// Overriding a repeated section is not supported.
+    pub fn apply_override(&mut self, text: &str) -> Result<(), ConfigError> {
+        let file = self
+            .files
+            .iter()
+            .position(|f| f == OVERRIDE_FILE)
+            .unwrap_or_else(|| {
+                self.files.push(OVERRIDE_FILE.to_owned());
+                self.files.len() - 1
+            });
+        let location = Location { file, line: 1, column: 1 };
+
+        let (path, value_text) = text
+            .split_once('=')
+            .ok_or_else(|| self.error((ErrorKind::BadOverride, location)))?;
+        let path = path.trim();
+        let (section_name, key) = path.rsplit_once('.').unwrap_or(("", path));
+        if key.is_empty() || !key.chars().all(is_key_char) {
+            return Err(self.error((ErrorKind::BadOverride, location)));
+        }
+
+        let value_text = value_text.trim();
+        let value = match value_text.chars().next() {
+            Some('"' | '[' | '-' | '+' | '0'..='9') | None => {
+                let mut parser = Parser::new(value_text, file);
+                let value = parser.value().map_err(|e| self.error(e))?;
+                if parser.peek().is_some() {
+                    return Err(self.error((ErrorKind::BadOverride, location)));
+                }
+                value
+            }
+            _ => match value_text {
+                "true" => Value::Bool(true),
+                "false" => Value::Bool(false),
+                _ => Value::Str(value_text.to_owned()),
+            },
+        };
+
+        let index = self
+            .open_section(section_name, false, location, &[])
+            .map_err(|kind| self.error((kind, location)))?;
+        let section = &mut self.sections[index];
+        section.entries.retain(|e| e.key != key);
+        section.entries.push(Entry {
+            key: key.to_owned(),
+            value,
+            location,
+        });
+        Ok(())
+    }
+
+    pub fn section(&self, name: &str) -> Option<&Section> {
+        self.sections.iter().find(|s| s.name == name)
+    }
+
+    /// All `[[name]]` sections in order.
+    pub fn sections<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Section> + 'a {
+        self.sections.iter().filter(move |s| s.name == name)
+    }
+
+    pub fn get(&self, section: &str, key: &str) -> Option<&Value> {
+        self.section(section)?.get(key)
+    }
+
+    pub fn file_name(&self, location: Location) -> &str {
+        self.files.get(location.file).map_or("", String::as_str)
+    }
+}
+
+fn resolve_path(including: &str, path: &str) -> String {
+    if path.starts_with('/') {
+        return path.to_owned();
+    }
+    match including.rfind('/') {
+        Some(slash) => {
+            let mut resolved = including[..=slash].to_owned();
+            resolved.push_str(path);
+            resolved
+        }
+        None => path.to_owned(),
+    }
+}
+
+fn is_key_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-'
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+    file: usize,
+    line: u32,
+    column: u32,
+}
+
+type ParseResult<T> = Result<T, (ErrorKind, Location)>;
+
+impl<'a> Parser<'a> {
+    fn new(text: &'a str, file: usize) -> Self {
+        Parser {
+            text,
+            pos: 0,
+            file,
+            line: 1,
+            column: 1,
+        }
+    }
+
+    fn location(&self) -> Location {
+        Location {
+            file: self.file,
+            line: self.line,
+            column: self.column,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn advance_by(&mut self, len: usize) {
+        let end = self.pos + len;
+        while self.pos < end {
+            self.bump();
+        }
+    }
+
+    fn unexpected(&self) -> (ErrorKind, Location) {
+        match self.peek() {
+            Some(c) => (ErrorKind::UnexpectedChar(c), self.location()),
+            None => (ErrorKind::UnexpectedEnd, self.location()),
+        }
+    }
+
+    fn expect(&mut self, wanted: char) -> ParseResult<()> {
+        if self.peek() != Some(wanted) {
+            return Err(self.unexpected());
+        }
+        self.bump();
+        Ok(())
+    }
+
+    fn skip_spaces(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t')) {
+            self.bump();
+        }
+    }
+
+    fn skip_comment(&mut self) {
+        if self.peek() == Some('#') {
+            while !matches!(self.peek(), None | Some('\n')) {
+                self.bump();
+            }
+        }
+    }
+
+    /// Skips whitespace, comments and newlines.
+    fn skip_blank_lines(&mut self) {
+        loop {
+            self.skip_spaces();
+            self.skip_comment();
+            match self.peek() {
+                Some('\n') => {
+                    self.bump();
+                }
+                Some('\r') if self.text[self.pos..].starts_with("\r\n") => {
+                    self.bump();
+                }
+                _ => return,
+            }
+        }
+    }
+
+    fn end_of_line(&mut self) -> ParseResult<()> {
+        self.skip_spaces();
+        self.skip_comment();
+        if self.peek() == Some('\r') {
+            self.bump();
+        }
+        match self.peek() {
+            None => Ok(()),
+            Some('\n') => {
+                self.bump();
+                Ok(())
+            }
+            Some(_) => Err(self.unexpected()),
+        }
+    }
+
+    fn at_keyword(&self, keyword: &str) -> bool {
+        let rest = &self.text[self.pos..];
+        rest.starts_with(keyword) && matches!(rest[keyword.len()..].chars().next(), Some(' ' | '\t' | '"'))
+    }
+
+    fn key(&mut self) -> ParseResult<String> {
+        let start = self.pos;
+        while self.peek().is_some_and(is_key_char) {
+            self.bump();
+        }
+        if self.pos == start {
+            return Err((ErrorKind::ExpectedKey, self.location()));
+        }
+        Ok(self.text[start..self.pos].to_owned())
+    }
+
+    /// `[name]` or `[[name]]`, where the name is dotted keys.
+    fn section_header(&mut self) -> ParseResult<(String, bool)> {
+        self.expect('[')?;
+        let repeated = self.peek() == Some('[');
+        if repeated {
+            self.bump();
+        }
+        self.skip_spaces();
+        let mut name = self.key()?;
+        while self.peek() == Some('.') {
+            self.bump();
+            name.push('.');
+            name.push_str(&self.key()?);
+        }
+        self.skip_spaces();
+        self.expect(']')?;
+        if repeated {
+            self.expect(']')?;
+        }
+        self.end_of_line()?;
+        Ok((name, repeated))
+    }
+
+    fn assignment(&mut self) -> ParseResult<(String, Value)> {
+        let key = self.key()?;
+        self.skip_spaces();
+        if self.peek() != Some('=') {
+            return Err((ErrorKind::ExpectedEquals, self.location()));
+        }
+        self.bump();
+        self.skip_spaces();
+        let value = self.value()?;
+        self.end_of_line()?;
+        Ok((key, value))
+    }
+
+    fn value(&mut self) -> ParseResult<Value> {
+        match self.peek() {
+            Some('"') => self.string().map(Value::Str),
+            Some('[') => self.list(),
+            Some('t' | 'f') => {
+                let location = self.location();
+                let word = self.key()?;
+                match word.as_str() {
+                    "true" => Ok(Value::Bool(true)),
+                    "false" => Ok(Value::Bool(false)),
+                    _ => Err((ErrorKind::ExpectedValue, location)),
+                }
+            }
+            Some(c) if c.is_ascii_digit() || c == '-' || c == '+' => self.number(),
+            _ => Err((ErrorKind::ExpectedValue, self.location())),
+        }
+    }
+
+    fn string(&mut self) -> ParseResult<String> {
+        let start = self.location();
+        self.expect('"')?;
+        let mut out = String::new();
+        loop {
+            match self.bump() {
+                None | Some('\n') => return Err((ErrorKind::UnterminatedString, start)),
+                Some('"') => return Ok(out),
+                Some('\\') => {
+                    let location = self.location();
+                    match self.bump() {
+                        Some('\\') => out.push('\\'),
+                        Some('"') => out.push('"'),
+                        Some('n') => out.push('\n'),
+                        Some('t') => out.push('\t'),
+                        Some('r') => out.push('\r'),
+                        Some(c) => return Err((ErrorKind::BadEscape(c), location)),
+                        None => return Err((ErrorKind::UnterminatedString, start)),
+                    }
+                }
+                Some(c) => out.push(c),
+            }
+        }
+    }
+
+    fn number(&mut self) -> ParseResult<Value> {
+        let location = self.location();
+        let start = self.pos;
+        if matches!(self.peek(), Some('-' | '+')) {
+            self.bump();
+        }
+        while self.peek().is_some_and(|c| c.is_ascii_alphanumeric() || c == '_') {
+            self.bump();
+        }
+        let text: String = self.text[start..self.pos].chars().filter(|&c| c != '_').collect();
+        let (negative, digits) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text.strip_prefix('+').unwrap_or(&text)),
+        };
+        let magnitude = match digits.strip_prefix("0x") {
+            Some(hex) => i64::from_str_radix(hex, 16),
+            None => digits.parse(),
+        }
+        .map_err(|_| (ErrorKind::BadNumber, location))?;
+        Ok(Value::Int(if negative { -magnitude } else { magnitude }))
+    }
+
+    /// `[a, b, ...]`, possibly over several lines with comments and a
+    /// trailing comma.
+    fn list(&mut self) -> ParseResult<Value> {
+        self.expect('[')?;
+        let mut items: Vec<Value> = Vec::new();
+        loop {
+            self.skip_blank_lines();
+            if self.peek() == Some(']') {
+                self.bump();
+                return Ok(Value::List(items));
+            }
+            let location = self.location();
+            let item = self.value()?;
+            if items
+                .first()
+                .is_some_and(|first| core::mem::discriminant(first) != core::mem::discriminant(&item))
+            {
+                return Err((ErrorKind::MixedList, location));
+            }
+            items.push(item);
+            self.skip_blank_lines();
+            match self.peek() {
+                Some(',') => {
+                    self.bump();
+                }
+                Some(']') => {}
+                _ => return Err(self.unexpected()),
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Type {
+    Bool,
+    Int { min: i64, max: i64 },
+    Str,
+    /// Byte count, see [`Value::as_size`].
+    Size,
+    /// `major[.minor[.patch]]` string, see [`Version::parse`].
+    Version,
+    OneOf(&'static [&'static str]),
+    List(&'static Type),
+}
+
+impl Type {
+    fn name(&self) -> &'static str {
+        match self {
+            Type::Bool => "a boolean",
+            Type::Int { .. } => "an integer",
+            Type::Str | Type::OneOf(_) => "a string",
+            Type::Size => "a size such as 4096 or \"16K\"",
+            Type::Version => "a version string such as \"1.2.0\"",
+            Type::List(_) => "a list",
+        }
+    }
+
+    fn check(&self, key: &str, value: &Value) -> Result<(), ErrorKind> {
+        let mismatch = || ErrorKind::TypeMismatch {
+            key: key.to_owned(),
+            expected: self.name(),
+        };
+        match (self, value) {
+            (Type::Bool, Value::Bool(_)) | (Type::Str, Value::Str(_)) => Ok(()),
+            (Type::Int { min, max }, Value::Int(i)) => {
+                if i < min || i > max {
+                    return Err(ErrorKind::OutOfRange {
+                        key: key.to_owned(),
+                        min: *min,
+                        max: *max,
+                    });
+                }
+                Ok(())
+            }
+            (Type::Size, value) => value.as_size().map(|_| ()).ok_or_else(mismatch),
+            (Type::Version, Value::Str(s)) => Version::parse(s).map(|_| ()).ok_or_else(mismatch),
+            (Type::OneOf(allowed), Value::Str(s)) => {
+                if !allowed.contains(&s.as_str()) {
+                    return Err(ErrorKind::NotAllowed {
+                        key: key.to_owned(),
+                        value: s.clone(),
+                    });
+                }
+                Ok(())
+            }
+            (Type::List(item), Value::List(items)) => items.iter().try_for_each(|v| item.check(key, v)),
+            _ => Err(mismatch()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Field {
+    pub name: &'static str,
+    pub ty: Type,
+    pub required: bool,
+}
+
+impl Field {
+    pub const fn new(name: &'static str, ty: Type) -> Self {
+        Field { name, ty, required: false }
+    }
+
+    pub const fn required(name: &'static str, ty: Type) -> Self {
+        Field { name, ty, required: true }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SectionSchema {
+    pub name: &'static str,
+    pub repeated: bool,
+    pub required: bool,
+    pub fields: &'static [Field],
+}
+
+/// Declares which sections and keys a configuration may contain and their
+/// types. Unknown sections and keys are errors, so typos are caught.
+#[derive(Debug, Clone, Copy)]
+pub struct Schema {
+    pub sections: &'static [SectionSchema],
+}
+
+impl Schema {
+    /// Checks `config` and returns every problem found, ordered by the file
+    /// (in load order, see [`Config::files`]), line and column it refers to.
+    pub fn validate(&self, config: &Config) -> Result<(), Vec<ConfigError>> {
+        let mut errors = Vec::new();
+        let start = Location { file: 0, line: 1, column: 1 };
+
+        for section in &config.sections {
+            let Some(schema) = self.sections.iter().find(|s| s.name == section.name) else {
+                if section.name.is_empty() {
+                    for entry in &section.entries {
+                        errors.push((ErrorKind::UnknownKey(entry.key.clone()), entry.location));
+                    }
+                } else {
+                    errors.push((ErrorKind::UnknownSection(section.name.clone()), section.location));
+                }
+                continue;
+            };
+            if schema.repeated != section.repeated {
+                errors.push((ErrorKind::SectionKindMismatch(section.name.clone()), section.location));
+            }
+
+            for entry in &section.entries {
+                let key = qualified(&section.name, &entry.key);
+                let result = match schema.fields.iter().find(|f| f.name == entry.key) {
+                    Some(field) => field.ty.check(&key, &entry.value),
+                    None => Err(ErrorKind::UnknownKey(key)),
+                };
+                if let Err(kind) = result {
+                    errors.push((kind, entry.location));
+                }
+            }
+            for field in schema.fields.iter().filter(|f| f.required) {
+                if section.entry(field.name).is_none() {
+                    let key = qualified(&section.name, field.name);
+                    errors.push((ErrorKind::MissingKey(key), section.location));
+                }
+            }
+        }
+
+        for schema in self.sections.iter().filter(|s| s.required) {
+            if config.section(schema.name).is_none() {
+                errors.push((ErrorKind::MissingSection(schema.name.to_owned()), start));
+            }
+        }
+
+        errors.sort_by_key(|(_, location)| (location.file, location.line, location.column));
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.into_iter().map(|e| config.error(e)).collect())
+        }
+    }
+}
+
+fn qualified(section: &str, key: &str) -> String {
+    if section.is_empty() {
+        return key.to_owned();
+    }
+    let mut out = section.to_string();
+    out.push('.');
+    out.push_str(key);
+    out
+}
+
+const ARCHITECTURES: &[&str] = &["prum64", "aarch64", "x86_64", "riscv64"];
+const CHANNELS: &[&str] = &["stable", "testing", "unstable", "dev"];
+const SUBSYSTEMS: &[&str] = &{
+    let mut names = [""; Subsystem::ALL.len()];
+    let mut i = 0;
+    while i < names.len() {
+        names[i] = Subsystem::ALL[i].name();
+        i += 1;
+    }
+    names
+};
+
+/// `plum.config`: the kernel build configuration edited by `plum-config`.
+pub const PLUM_CONFIG_SCHEMA: Schema = Schema {
+    sections: &[
+        SectionSchema {
+            name: "system",
+            repeated: false,
+            required: true,
+            fields: &[
+                Field::required("arch", Type::OneOf(ARCHITECTURES)),
+                Field::new("page_size", Type::OneOf(&["4K", "8K", "16K", "64K"])),
+                Field::new("smp_cores", Type::Int { min: 1, max: 4096 }),
+                Field::new("secure_boot", Type::Bool),
+                Field::new("memory_size", Type::Size),
+                Field::new("boot_delay", Type::Int { min: 0, max: 60 }),
+            ],
+        },
+        SectionSchema {
+            name: "drivers",
+            repeated: false,
+            required: false,
+            fields: &[
+                Field::new("usb", Type::Bool),
+                Field::new("wifi", Type::Bool),
+                Field::new("gpu", Type::Bool),
+                Field::new("nvme", Type::Bool),
+                Field::new("audio", Type::Bool),
+                Field::new("bluetooth", Type::Bool),
+            ],
+        },
+        SectionSchema {
+            name: "compatibility",
+            repeated: false,
+            required: false,
+            fields: &[
+                Field::new("posix", Type::Bool),
+                Field::new("win32", Type::Bool),
+                Field::new("darwin", Type::Bool),
+                Field::new("android", Type::Bool),
+                Field::new("linux", Type::Bool),
+            ],
+        },
+        SectionSchema {
+            name: "build",
+            repeated: false,
+            required: false,
+            fields: &[
+                Field::new("mode", Type::OneOf(&["debug", "release", "profile"])),
+                Field::new("optimization", Type::OneOf(&["none", "speed", "size", "balanced"])),
+                Field::new("debug_info", Type::Bool),
+                Field::new("lto", Type::Bool),
+            ],
+        },
+        SectionSchema {
+            name: "plam",
+            repeated: false,
+            required: false,
+            fields: &[
+                Field::new("subsystem", Type::OneOf(SUBSYSTEMS)),
+                Field::new("flags", Type::List(&Type::OneOf(&["pie", "aslr"]))),
+                Field::new("version", Type::Version),
+            ],
+        },
+    ],
+};
+
+/// The ppm package manager configuration.
+pub const PPM_CONFIG_SCHEMA: Schema = Schema {
+    sections: &[
+        SectionSchema {
+            name: "system",
+            repeated: false,
+            required: true,
+            fields: &[
+                Field::new("default_channel", Type::OneOf(CHANNELS)),
+                Field::required("architecture", Type::OneOf(ARCHITECTURES)),
+                Field::new("system_root", Type::Str),
+            ],
+        },
+        SectionSchema {
+            name: "repositories",
+            repeated: true,
+            required: false,
+            fields: &[
+                Field::required("name", Type::Str),
+                Field::required("channel", Type::OneOf(CHANNELS)),
+                Field::new("url", Type::Str),
+                Field::new("path", Type::Str),
+            ],
+        },
+        SectionSchema {
+            name: "security",
+            repeated: false,
+            required: false,
+            fields: &[
+                Field::new("kernel_verification", Type::Bool),
+                Field::new("secure_boot", Type::Bool),
+                Field::new("trusted_keys", Type::List(&Type::Str)),
+            ],
+        },
+    ],
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::collections::BTreeMap;
+
+    /// An in-memory file system, standing in for the disk on targets without std.
+    struct Files(BTreeMap<&'static str, &'static str>);
+
+    impl Loader for Files {
+        fn load(&mut self, path: &str) -> Option<String> {
+            self.0.get(path).map(|text| text.to_string())
+        }
+    }
+
+    fn load(files: &[(&'static str, &'static str)]) -> Result<Config, ConfigError> {
+        let mut loader = Files(files.iter().copied().collect());
+        let (name, text) = files[0];
+        Config::load(name, text, &mut loader)
+    }
+
+    fn error_at(result: Result<Config, ConfigError>) -> (String, u32, u32, ErrorKind) {
+        let e = result.unwrap_err();
+        (e.file, e.line, e.column, e.kind)
+    }
+
+    const TEST_SCHEMA: Schema = Schema {
+        sections: &[
+            SectionSchema {
+                name: "system",
+                repeated: false,
+                required: true,
+                fields: &[
+                    Field::required("arch", Type::OneOf(ARCHITECTURES)),
+                    Field::new("cores", Type::Int { min: 1, max: 64 }),
+                    Field::new("memory", Type::Size),
+                    Field::new("secure", Type::Bool),
+                ],
+            },
+            SectionSchema {
+                name: "repo",
+                repeated: true,
+                required: false,
+                fields: &[Field::required("name", Type::Str), Field::new("version", Type::Version)],
+            },
+        ],
+    };
+
+    #[test]
+    fn parses_each_value_type() {
+        let config = Config::parse(
+            "test.plconf",
+            "top = 1\n\
+             [values]\n\
+             yes = true\n\
+             no = false # comment\n\
+             decimal = -1_000\n\
+             plus = +7\n\
+             hex = 0x1F\n\
+             text = \"a \\\"b\\\"\\t\\\\\"\n\
+             size = \"16K\"\n\
+             empty = []\n\
+             list = [\n    \"x\", # first\n    \"y\",\n]\n",
+        )
+        .unwrap();
+
+        assert_eq!(config.get("", "top"), Some(&Value::Int(1)));
+        assert_eq!(config.get("values", "yes").and_then(Value::as_bool), Some(true));
+        assert_eq!(config.get("values", "no").and_then(Value::as_bool), Some(false));
+        assert_eq!(config.get("values", "decimal").and_then(Value::as_int), Some(-1000));
+        assert_eq!(config.get("values", "plus").and_then(Value::as_int), Some(7));
+        assert_eq!(config.get("values", "hex").and_then(Value::as_int), Some(0x1f));
+        assert_eq!(config.get("values", "text").and_then(Value::as_str), Some("a \"b\"\t\\"));
+        assert_eq!(config.get("values", "size").and_then(Value::as_size), Some(16 * 1024));
+        assert_eq!(config.get("values", "hex").and_then(Value::as_size), Some(0x1f));
+        assert_eq!(config.get("values", "empty").and_then(Value::as_list), Some(&[][..]));
+        let list = config.get("values", "list").unwrap();
+        assert_eq!(list.as_list(), Some(&[Value::Str("x".into()), Value::Str("y".into())][..]));
+        assert_eq!(list.to_string(), "[\"x\", \"y\"]");
+
+        assert_eq!(Value::Int(-1).as_size(), None);
+        assert_eq!(Value::Str("2G".into()).as_size(), Some(2 << 30));
+        assert_eq!(Value::Str("lots".into()).as_size(), None);
+        assert_eq!(Value::Bool(true).as_str(), None);
+    }
+
+    #[test]
+    fn schema_checks_types() {
+        let check = |ty: Type, value: Value| ty.check("k", &value);
+        assert_eq!(check(Type::Bool, Value::Bool(true)), Ok(()));
+        assert_eq!(
+            check(Type::Bool, Value::Int(1)),
+            Err(ErrorKind::TypeMismatch { key: "k".into(), expected: "a boolean" })
+        );
+        assert_eq!(check(Type::Int { min: 0, max: 9 }, Value::Int(9)), Ok(()));
+        assert_eq!(
+            check(Type::Int { min: 0, max: 9 }, Value::Int(10)),
+            Err(ErrorKind::OutOfRange { key: "k".into(), min: 0, max: 9 })
+        );
+        assert_eq!(check(Type::Str, Value::Str("s".into())), Ok(()));
+        assert_eq!(check(Type::Size, Value::Str("512M".into())), Ok(()));
+        assert_eq!(check(Type::Size, Value::Int(4096)), Ok(()));
+        assert!(check(Type::Size, Value::Str("big".into())).is_err());
+        assert_eq!(check(Type::Version, Value::Str("1.2.3".into())), Ok(()));
+        assert!(check(Type::Version, Value::Str("1.x".into())).is_err());
+        assert_eq!(check(Type::OneOf(&["a", "b"]), Value::Str("b".into())), Ok(()));
+        assert_eq!(
+            check(Type::OneOf(&["a", "b"]), Value::Str("c".into())),
+            Err(ErrorKind::NotAllowed { key: "k".into(), value: "c".into() })
+        );
+        let flags = Type::List(&Type::OneOf(&["pie", "aslr"]));
+        assert_eq!(check(flags, Value::List(alloc::vec![Value::Str("pie".into())])), Ok(()));
+        assert!(check(flags, Value::List(alloc::vec![Value::Str("nx".into())])).is_err());
+        assert!(check(flags, Value::Str("pie".into())).is_err());
+    }
+
+    #[test]
+    fn includes_load_first_and_are_overridden() {
+        let config = load(&[
+            (
+                "conf/main.plconf",
+                "include \"base.plconf\"\n[system]\narch = \"riscv64\"\n[[repo]]\nname = \"main\"\n",
+            ),
+            (
+                "conf/base.plconf",
+                "include \"/shared/defaults.plconf\"\n\
+                 [system]\narch = \"x86_64\"\ncores = 4\n[[repo]]\nname = \"base\"\n",
+            ),
+            ("/shared/defaults.plconf", "[system]\ncores = 1\nsecure = true\n"),
+        ])
+        .unwrap();
+
+        assert_eq!(config.files, ["conf/main.plconf", "conf/base.plconf", "/shared/defaults.plconf"]);
+        assert_eq!(config.get("system", "arch").and_then(Value::as_str), Some("riscv64"));
+        assert_eq!(config.get("system", "cores").and_then(Value::as_int), Some(4));
+        assert_eq!(config.get("system", "secure").and_then(Value::as_bool), Some(true));
+        let arch = config.section("system").unwrap().entry("arch").unwrap();
+        assert_eq!(config.file_name(arch.location), "conf/main.plconf");
+        let repos: Vec<_> = config.sections("repo").filter_map(|s| s.get("name")?.as_str()).collect();
+        assert_eq!(repos, ["base", "main"]);
+
+        let mut config = config;
+        config.apply_override("system.cores=8").unwrap();
+        config.apply_override("system.arch = aarch64").unwrap();
+        config.apply_override("debug=true").unwrap();
+        assert_eq!(config.get("system", "cores").and_then(Value::as_int), Some(8));
+        assert_eq!(config.get("system", "arch").and_then(Value::as_str), Some("aarch64"));
+        assert_eq!(config.get("", "debug").and_then(Value::as_bool), Some(true));
+        assert_eq!(config.files.last().map(String::as_str), Some(OVERRIDE_FILE));
+        assert_eq!(config.apply_override("no equals").unwrap_err().kind, ErrorKind::BadOverride);
+        assert_eq!(config.apply_override("system.=1").unwrap_err().kind, ErrorKind::BadOverride);
+        assert_eq!(
+            config.apply_override("repo.name=x").unwrap_err().kind,
+            ErrorKind::SectionKindMismatch("repo".into())
+        );
+    }
+
+    #[test]
+    fn include_errors() {
+        assert_eq!(
+            error_at(load(&[("a.plconf", "include \"missing.plconf\"\n")])),
+            ("a.plconf".into(), 1, 9, ErrorKind::IncludeNotFound("missing.plconf".into()))
+        );
+        assert_eq!(
+            error_at(load(&[("a.plconf", "include \"b.plconf\"\n"), ("b.plconf", "include \"a.plconf\"\n")])),
+            ("b.plconf".into(), 1, 9, ErrorKind::IncludeCycle("a.plconf".into()))
+        );
+        assert_eq!(
+            error_at(load(&[("a.plconf", "[s]\ninclude \"b.plconf\"\n"), ("b.plconf", "")])),
+            ("a.plconf".into(), 2, 1, ErrorKind::IncludeAfterSection)
+        );
+        assert_eq!(
+            error_at(Config::parse("a.plconf", "include \"b.plconf\"\n")).3,
+            ErrorKind::IncludeNotFound("b.plconf".into())
+        );
+        assert_eq!(
+            error_at(load(&[("a.plconf", "include \"a.plconf\"\n")])).3,
+            ErrorKind::IncludeCycle("a.plconf".into())
+        );
+    }
+
+    #[test]
+    fn rejects_duplicates_within_a_file() {
+        assert_eq!(
+            error_at(Config::parse("a.plconf", "[s]\nk = 1\nk = 2\n")),
+            ("a.plconf".into(), 3, 1, ErrorKind::DuplicateKey("k".into()))
+        );
+        assert_eq!(
+            error_at(Config::parse("a.plconf", "[s]\n[t]\n[s]\n")),
+            ("a.plconf".into(), 3, 1, ErrorKind::DuplicateSection("s".into()))
+        );
+        assert_eq!(
+            error_at(Config::parse("a.plconf", "[s]\n[[s]]\n")),
+            ("a.plconf".into(), 2, 1, ErrorKind::SectionKindMismatch("s".into()))
+        );
+        assert_eq!(
+            error_at(Config::parse("a.plconf", "[[r]]\nk = 1\nk = 1\n")).3,
+            ErrorKind::DuplicateKey("k".into())
+        );
+
+        // The same key or section in different files is an override, and
+        // repeated sections may appear any number of times.
+        let config = load(&[("a.plconf", "include \"b.plconf\"\n[s]\nk = 2\n"), ("b.plconf", "[s]\nk = 1\n")]);
+        assert_eq!(config.unwrap().get("s", "k"), Some(&Value::Int(2)));
+        let config = Config::parse("a.plconf", "[[r]]\nk = 1\n[[r]]\nk = 2\n").unwrap();
+        assert_eq!(config.sections("r").count(), 2);
+    }
+
+    #[test]
+    fn reports_line_and_column() {
+        let parse = |text: &str| error_at(Config::parse("a.plconf", text));
+        assert_eq!(parse("[s]\nkey = @\n"), ("a.plconf".into(), 2, 7, ErrorKind::ExpectedValue));
+        assert_eq!(parse("[s]\n  key 1\n"), ("a.plconf".into(), 2, 7, ErrorKind::ExpectedEquals));
+        assert_eq!(parse("k = \"open\n"), ("a.plconf".into(), 1, 5, ErrorKind::UnterminatedString));
+        assert_eq!(parse("k = \"a\\q\"\n"), ("a.plconf".into(), 1, 8, ErrorKind::BadEscape('q')));
+        assert_eq!(parse("k = 12ab\n"), ("a.plconf".into(), 1, 5, ErrorKind::BadNumber));
+        assert_eq!(parse("k = [1, \"x\"]\n"), ("a.plconf".into(), 1, 9, ErrorKind::MixedList));
+        assert_eq!(parse("k = [1\n"), ("a.plconf".into(), 2, 1, ErrorKind::UnexpectedEnd));
+        assert_eq!(parse("k = 1 2\n"), ("a.plconf".into(), 1, 7, ErrorKind::UnexpectedChar('2')));
+        assert_eq!(parse("[s\n"), ("a.plconf".into(), 1, 3, ErrorKind::UnexpectedChar('\n')));
+        assert_eq!(parse("= 1\n"), ("a.plconf".into(), 1, 1, ErrorKind::ExpectedKey));
+        assert_eq!(parse("\r\n# c\r\nk = 1\r\n]\r\n"), ("a.plconf".into(), 4, 1, ErrorKind::ExpectedKey));
+
+        let e = Config::parse("a.plconf", "\n\nk = tru\n").unwrap_err();
+        assert_eq!(e.to_string(), "a.plconf:3:5: expected a value");
+    }
+
+    #[test]
+    fn schema_reports_unknown_and_missing() {
+        let config = Config::parse(
+            "a.plconf",
+            "stray = 1\n[system]\ncores = 100\nspeed = 1\n[network]\n[[repo]]\nversion = \"1\"\n",
+        )
+        .unwrap();
+        let errors: Vec<_> = TEST_SCHEMA
+            .validate(&config)
+            .unwrap_err()
+            .into_iter()
+            .map(|e| (e.line, e.column, e.kind))
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                (1, 1, ErrorKind::UnknownKey("stray".into())),
+                (2, 1, ErrorKind::MissingKey("system.arch".into())),
+                (3, 1, ErrorKind::OutOfRange { key: "system.cores".into(), min: 1, max: 64 }),
+                (4, 1, ErrorKind::UnknownKey("system.speed".into())),
+                (5, 1, ErrorKind::UnknownSection("network".into())),
+                (6, 1, ErrorKind::MissingKey("repo.name".into())),
+            ]
+        );
+
+        let config = Config::parse("a.plconf", "[repo]\nname = \"x\"\n").unwrap();
+        let kinds: Vec<_> = TEST_SCHEMA.validate(&config).unwrap_err().into_iter().map(|e| e.kind).collect();
+        assert_eq!(
+            kinds,
+            [ErrorKind::SectionKindMismatch("repo".into()), ErrorKind::MissingSection("system".into())]
+        );
+    }
+
+    #[test]
+    fn schema_errors_follow_load_order() {
+        // Sorting by file name would put b.plconf before z.plconf.
+        let config = load(&[
+            ("z.plconf", "include \"b.plconf\"\n[system]\narch = \"vax\"\n"),
+            ("b.plconf", "[system]\narch = \"x86_64\"\ncores = 0\n"),
+        ])
+        .unwrap();
+        let errors: Vec<_> = TEST_SCHEMA
+            .validate(&config)
+            .unwrap_err()
+            .into_iter()
+            .map(|e| (e.file, e.line))
+            .collect();
+        assert_eq!(errors, [("z.plconf".into(), 3), ("b.plconf".into(), 3)]);
+    }
+
+    #[test]
+    fn shipped_configs_pass_their_schemas() {
+        let ppm = include_str!("../../../../user/utils/ppm/assets/default-config.toml");
+        let config = Config::parse("default-config.toml", ppm).unwrap();
+        assert_eq!(PPM_CONFIG_SCHEMA.validate(&config), Ok(()));
+        let names: Vec<_> = config.sections("repositories").filter_map(|s| s.get("name")?.as_str()).collect();
+        assert_eq!(names, ["Plum", "Blossom", "Seed", "dev"]);
+
+        // What plum-config writes for a new configuration.
+        let plum = "[system]\n\
+                    arch = \"prum64\"\n\
+                    page_size = \"16K\"\n\
+                    smp_cores = 8\n\
+                    secure_boot = true\n\
+                    memory_size = \"1G\"\n\
+                    boot_delay = 3\n\
+                    \n\
+                    [drivers]\n\
+                    usb = true\n\
+                    wifi = true\n\
+                    gpu = false\n\
+                    nvme = true\n\
+                    audio = false\n\
+                    bluetooth = false\n\
+                    \n\
+                    [compatibility]\n\
+                    posix = true\n\
+                    win32 = true\n\
+                    darwin = true\n\
+                    android = false\n\
+                    linux = true\n\
+                    \n\
+                    [build]\n\
+                    mode = \"release\"\n\
+                    optimization = \"speed\"\n\
+                    debug_info = false\n\
+                    lto = true\n\
+                    \n\
+                    [plam]\n\
+                    subsystem = \"native_kernel\"\n\
+                    flags = [\n    \"pie\",\n    \"aslr\",\n]\n\
+                    version = \"1.0.0\"\n";
+        let config = Config::parse("plum.config", plum).unwrap();
+        assert_eq!(PLUM_CONFIG_SCHEMA.validate(&config), Ok(()));
+    }
+}
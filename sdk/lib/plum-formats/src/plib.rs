@@ -0,0 +1,597 @@
+use core::fmt;
+use core::mem::{offset_of, size_of};
+
+#[cfg(feature = "alloc")]
+use alloc::{string::String, vec::Vec};
+
+use crate::bytes::{self, read_cstr, read_u16, read_u32, read_u64, write_u16, write_u32, write_u64};
+use crate::plam::PAGE_SIZE;
+#[cfg(feature = "alloc")]
+use crate::plam::align_up;
+#[cfg(feature = "alloc")]
+use crate::plm::PlmBuilder;
+use crate::plm::{Import, PlmError, PlmFile};
+use crate::version::Version;
+
+pub const PLIB_MAGIC: [u8; 4] = *b"PLIB";
+pub const PLIB_VERSION_MAJOR: u16 = 1;
+pub const PLIB_VERSION: u16 = PLIB_VERSION_MAJOR << 8;
+
+pub const EXPORT_FUNC: u32 = 1;
+pub const EXPORT_OBJECT: u32 = 2;
+
+const NO_EXPORT: u32 = u32::MAX;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlibError {
+    TooShort,
+    BadMagic([u8; 4]),
+    UnsupportedVersion(u16),
+    SizeMismatch { header: u64, actual: u64 },
+    OutOfBounds(&'static str),
+    BadString,
+    Image(PlmError),
+}
+
+impl fmt::Display for PlibError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlibError::TooShort => write!(f, "buffer is shorter than the PLIB header"),
+            PlibError::BadMagic(m) => write!(f, "bad magic {:02x?}, expected \"PLIB\"", m),
+            PlibError::UnsupportedVersion(v) => {
+                write!(f, "unsupported version {}.{} (expected {}.x)", v >> 8, v & 0xFF, PLIB_VERSION_MAJOR)
+            }
+            PlibError::SizeMismatch { header, actual } => {
+                write!(f, "header describes {} bytes but the file is {} bytes", header, actual)
+            }
+            PlibError::OutOfBounds(what) => write!(f, "{} out of bounds", what),
+            PlibError::BadString => write!(f, "string table entry is not NUL-terminated UTF-8"),
+            PlibError::Image(e) => write!(f, "library image: {}", e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for PlibError {}
+
+/// Header of a `.plib` shared library. The code, data, relocations and the
+/// library's own imports live in an embedded `.plm` image at the
+/// page-aligned `image_offset`; the header adds the name, version and the
+/// exported symbols.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct PlibHeader {
+    pub magic: [u8; 4],
+    pub version: u16,
+    pub reserved: u16,
+    pub name: u32,
+    pub lib_version: u32,
+    pub export_count: u32,
+    pub bucket_count: u32,
+    pub export_table_offset: u64,
+    pub hash_table_offset: u64,
+    pub string_table_offset: u64,
+    pub string_table_size: u64,
+    pub image_offset: u64,
+    pub image_size: u64,
+}
+
+pub const PLIB_HEADER_SIZE: usize = size_of::<PlibHeader>();
+
+const _: () = assert!(PLIB_HEADER_SIZE == 0x48);
+
+impl PlibHeader {
+    pub fn parse(buf: &[u8]) -> Result<Self, PlibError> {
+        if buf.len() < PLIB_HEADER_SIZE {
+            return Err(PlibError::TooShort);
+        }
+
+        let magic: [u8; 4] = buf[0x00..0x04].try_into().unwrap();
+        if magic != PLIB_MAGIC {
+            return Err(PlibError::BadMagic(magic));
+        }
+
+        let header = PlibHeader {
+            magic,
+            version: read_u16(buf, offset_of!(PlibHeader, version)),
+            reserved: read_u16(buf, offset_of!(PlibHeader, reserved)),
+            name: read_u32(buf, offset_of!(PlibHeader, name)),
+            lib_version: read_u32(buf, offset_of!(PlibHeader, lib_version)),
+            export_count: read_u32(buf, offset_of!(PlibHeader, export_count)),
+            bucket_count: read_u32(buf, offset_of!(PlibHeader, bucket_count)),
+            export_table_offset: read_u64(buf, offset_of!(PlibHeader, export_table_offset)),
+            hash_table_offset: read_u64(buf, offset_of!(PlibHeader, hash_table_offset)),
+            string_table_offset: read_u64(buf, offset_of!(PlibHeader, string_table_offset)),
+            string_table_size: read_u64(buf, offset_of!(PlibHeader, string_table_size)),
+            image_offset: read_u64(buf, offset_of!(PlibHeader, image_offset)),
+            image_size: read_u64(buf, offset_of!(PlibHeader, image_size)),
+        };
+
+        if header.version >> 8 != PLIB_VERSION_MAJOR {
+            return Err(PlibError::UnsupportedVersion(header.version));
+        }
+
+        Ok(header)
+    }
+
+    pub fn write(&self, buf: &mut [u8]) -> Result<(), PlibError> {
+        if buf.len() < PLIB_HEADER_SIZE {
+            return Err(PlibError::TooShort);
+        }
+
+        buf[0x00..0x04].copy_from_slice(&self.magic);
+        write_u16(buf, offset_of!(PlibHeader, version), self.version);
+        write_u16(buf, offset_of!(PlibHeader, reserved), self.reserved);
+        write_u32(buf, offset_of!(PlibHeader, name), self.name);
+        write_u32(buf, offset_of!(PlibHeader, lib_version), self.lib_version);
+        write_u32(buf, offset_of!(PlibHeader, export_count), self.export_count);
+        write_u32(buf, offset_of!(PlibHeader, bucket_count), self.bucket_count);
+        write_u64(buf, offset_of!(PlibHeader, export_table_offset), self.export_table_offset);
+        write_u64(buf, offset_of!(PlibHeader, hash_table_offset), self.hash_table_offset);
+        write_u64(buf, offset_of!(PlibHeader, string_table_offset), self.string_table_offset);
+        write_u64(buf, offset_of!(PlibHeader, string_table_size), self.string_table_size);
+        write_u64(buf, offset_of!(PlibHeader, image_offset), self.image_offset);
+        write_u64(buf, offset_of!(PlibHeader, image_size), self.image_size);
+        Ok(())
+    }
+}
+
+/// One exported symbol. `value` is an offset from the library's image base.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct PlibExportEntry {
+    pub name: u32,
+    pub kind: u32,
+    pub hash: u32,
+    pub reserved: u32,
+    pub value: u64,
+}
+
+pub const EXPORT_ENTRY_SIZE: usize = size_of::<PlibExportEntry>();
+
+const _: () = assert!(EXPORT_ENTRY_SIZE == 24);
+
+impl PlibExportEntry {
+    pub fn parse(entry: &[u8]) -> Self {
+        PlibExportEntry {
+            name: read_u32(entry, offset_of!(PlibExportEntry, name)),
+            kind: read_u32(entry, offset_of!(PlibExportEntry, kind)),
+            hash: read_u32(entry, offset_of!(PlibExportEntry, hash)),
+            reserved: read_u32(entry, offset_of!(PlibExportEntry, reserved)),
+            value: read_u64(entry, offset_of!(PlibExportEntry, value)),
+        }
+    }
+
+    pub fn write(&self, entry: &mut [u8]) {
+        write_u32(entry, offset_of!(PlibExportEntry, name), self.name);
+        write_u32(entry, offset_of!(PlibExportEntry, kind), self.kind);
+        write_u32(entry, offset_of!(PlibExportEntry, hash), self.hash);
+        write_u32(entry, offset_of!(PlibExportEntry, reserved), self.reserved);
+        write_u64(entry, offset_of!(PlibExportEntry, value), self.value);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Export<'a> {
+    pub name: &'a str,
+    pub kind: u32,
+    pub value: u64,
+}
+
+/// Hash of a symbol name used by the export hash table (djb2, as in the GNU
+/// hash section).
+pub fn symbol_hash(name: &str) -> u32 {
+    name.bytes().fold(5381u32, |h, b| h.wrapping_mul(33).wrapping_add(b as u32))
+}
+
+/// A parsed `.plib` file. The hash table is `bucket_count` u32 heads
+/// followed by one u32 chain link per export, `u32::MAX` ending a chain.
+#[derive(Debug, Clone, Copy)]
+pub struct PlibFile<'a> {
+    pub header: PlibHeader,
+    pub image: PlmFile<'a>,
+    data: &'a [u8],
+}
+
+impl<'a> PlibFile<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self, PlibError> {
+        let header = PlibHeader::parse(data)?;
+        let expected_size = header.image_offset.saturating_add(header.image_size);
+        if expected_size != data.len() as u64 {
+            return Err(PlibError::SizeMismatch {
+                header: expected_size,
+                actual: data.len() as u64,
+            });
+        }
+        if header.image_offset < PLIB_HEADER_SIZE as u64 || !header.image_offset.is_multiple_of(PAGE_SIZE) {
+            return Err(PlibError::OutOfBounds("library image"));
+        }
+        let image = PlmFile::parse(&data[header.image_offset as usize..]).map_err(PlibError::Image)?;
+
+        let file = PlibFile { header, image, data };
+        file.table(header.export_table_offset, header.export_count as u64, EXPORT_ENTRY_SIZE, "export table")?;
+        let hash_entries = header.bucket_count as u64 + header.export_count as u64;
+        file.table(header.hash_table_offset, hash_entries, 4, "hash table")?;
+        file.table(header.string_table_offset, header.string_table_size, 1, "string table")?;
+        if header.export_count > 0 && header.bucket_count == 0 {
+            return Err(PlibError::OutOfBounds("hash table"));
+        }
+
+        file.name()?;
+        for entry in file.export_entries() {
+            file.string(entry.name)?;
+        }
+
+        Ok(file)
+    }
+
+    fn table(&self, offset: u64, count: u64, entry_size: usize, what: &'static str) -> Result<&'a [u8], PlibError> {
+        bytes::table(self.data, offset, count, entry_size, PLIB_HEADER_SIZE as u64, self.header.image_offset)
+            .ok_or(PlibError::OutOfBounds(what))
+    }
+
+    pub fn string(&self, offset: u32) -> Result<&'a str, PlibError> {
+        let strings = self.table(self.header.string_table_offset, self.header.string_table_size, 1, "string table")?;
+        read_cstr(strings, offset as usize).ok_or(PlibError::BadString)
+    }
+
+    pub fn name(&self) -> Result<&'a str, PlibError> {
+        self.string(self.header.name)
+    }
+
+    pub fn lib_version(&self) -> Version {
+        Version::from_u32(self.header.lib_version)
+    }
+
+    pub fn export_entries(&self) -> impl Iterator<Item = PlibExportEntry> + 'a {
+        let header = self.header;
+        self.table(header.export_table_offset, header.export_count as u64, EXPORT_ENTRY_SIZE, "export table")
+            .unwrap_or_default()
+            .chunks_exact(EXPORT_ENTRY_SIZE)
+            .map(PlibExportEntry::parse)
+    }
+
+    pub fn exports(&self) -> impl Iterator<Item = Export<'a>> + 'a {
+        let file = *self;
+        self.export_entries().map(move |entry| Export {
+            name: file.string(entry.name).unwrap_or_default(),
+            kind: entry.kind,
+            value: entry.value,
+        })
+    }
+
+    fn export_at(&self, index: u32) -> Option<PlibExportEntry> {
+        let offset = self.header.export_table_offset as usize + index as usize * EXPORT_ENTRY_SIZE;
+        (index < self.header.export_count).then(|| PlibExportEntry::parse(&self.data[offset..]))
+    }
+
+    /// Finds an export by name through the hash table.
+    pub fn lookup(&self, name: &str) -> Option<Export<'a>> {
+        if self.header.bucket_count == 0 {
+            return None;
+        }
+        let hash_entries = self.header.bucket_count as u64 + self.header.export_count as u64;
+        let table = self.table(self.header.hash_table_offset, hash_entries, 4, "hash table").ok()?;
+        let link = |slot: usize| table.get(slot.checked_mul(4)?..)?.get(..4).map(|entry| read_u32(entry, 0));
+        let hash = symbol_hash(name);
+        let bucket = hash % self.header.bucket_count;
+        let mut index = link(bucket as usize)?;
+
+        // A chain can visit each export at most once; anything longer is a loop.
+        for _ in 0..self.header.export_count {
+            if index == NO_EXPORT {
+                return None;
+            }
+            let entry = self.export_at(index)?;
+            let entry_name = self.string(entry.name).ok()?;
+            if entry.hash == hash && entry_name == name {
+                return Some(Export {
+                    name: entry_name,
+                    kind: entry.kind,
+                    value: entry.value,
+                });
+            }
+            index = link((self.header.bucket_count as usize).checked_add(index as usize)?)?;
+        }
+        None
+    }
+}
+
+/// A library mapped at `base` for import resolution.
+#[derive(Debug, Clone, Copy)]
+pub struct LoadedLibrary<'a> {
+    pub file: PlibFile<'a>,
+    pub base: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResolveError<'a> {
+    MissingLibrary(&'a str),
+    VersionTooOld { library: &'a str, have: Version, need: Version },
+    MissingSymbol { library: &'a str, symbol: &'a str },
+    BadSlot(u64),
+}
+
+impl fmt::Display for ResolveError<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResolveError::MissingLibrary(name) => write!(f, "library {} is not loaded", name),
+            ResolveError::VersionTooOld { library, have, need } => {
+                write!(f, "{} {} does not satisfy the required {}", library, have, need)
+            }
+            ResolveError::MissingSymbol { library: "", symbol } => {
+                write!(f, "no loaded library exports {}", symbol)
+            }
+            ResolveError::MissingSymbol { library, symbol } => write!(f, "{} does not export {}", library, symbol),
+            ResolveError::BadSlot(slot) => write!(f, "import slot +0x{:x} is outside the image", slot),
+        }
+    }
+}
+
+/// Finds the address `import` binds to among `libraries`, honouring its
+/// library name and minimum version. An empty library name searches every
+/// library in order.
+pub fn resolve_import<'a>(import: &Import<'a>, libraries: &[LoadedLibrary<'a>]) -> Result<u64, ResolveError<'a>> {
+    let any_version = import.min_version == Version::default();
+    let mut found_library = false;
+
+    for library in libraries {
+        let name = library.file.name().unwrap_or_default();
+        if !import.library.is_empty() && name != import.library {
+            continue;
+        }
+        found_library = true;
+
+        let have = library.file.lib_version();
+        if !any_version && !have.satisfies(import.min_version) {
+            if import.library.is_empty() {
+                continue;
+            }
+            return Err(ResolveError::VersionTooOld {
+                library: import.library,
+                have,
+                need: import.min_version,
+            });
+        }
+
+        if let Some(export) = library.file.lookup(import.symbol) {
+            return Ok(library
+                .base
+                .wrapping_add(export.value)
+                .wrapping_add(import.addend as u64));
+        }
+    }
+
+    if !found_library {
+        return Err(ResolveError::MissingLibrary(import.library));
+    }
+    Err(ResolveError::MissingSymbol {
+        library: import.library,
+        symbol: import.symbol,
+    })
+}
+
+/// Fills every import slot of `image`, already loaded into `memory` (see
+/// [`PlmFile::load`]), with the address it resolves to.
+pub fn bind_imports<'a>(
+    image: &PlmFile<'a>,
+    memory: &mut [u8],
+    libraries: &[LoadedLibrary<'a>],
+) -> Result<(), ResolveError<'a>> {
+    for import in image.imports() {
+        let address = resolve_import(&import, libraries)?;
+        let slot = import.slot as usize;
+        if slot.checked_add(8).is_none_or(|end| end > memory.len()) {
+            return Err(ResolveError::BadSlot(import.slot));
+        }
+        write_u64(memory, slot, address);
+    }
+    Ok(())
+}
+
+/// Assembles a `.plib` around an image built with [`PlmBuilder`].
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone)]
+pub struct PlibBuilder {
+    pub name: String,
+    pub version: Version,
+    pub image: PlmBuilder,
+    exports: Vec<(String, u32, u64)>,
+}
+
+#[cfg(feature = "alloc")]
+impl PlibBuilder {
+    pub fn new(name: &str, version: Version, image: PlmBuilder) -> Self {
+        PlibBuilder {
+            name: String::from(name),
+            version,
+            image,
+            exports: Vec::new(),
+        }
+    }
+
+    pub fn add_export(&mut self, name: &str, kind: u32, value: u64) {
+        self.exports.push((String::from(name), kind, value));
+    }
+
+    pub fn build(&self) -> Vec<u8> {
+        let mut strings = Vec::from([0u8]);
+        let mut intern = |s: &str| {
+            let offset = strings.len() as u32;
+            strings.extend_from_slice(s.as_bytes());
+            strings.push(0);
+            offset
+        };
+
+        let name = intern(&self.name);
+        let exports: Vec<PlibExportEntry> = self
+            .exports
+            .iter()
+            .map(|(export_name, kind, value)| PlibExportEntry {
+                name: intern(export_name),
+                kind: *kind,
+                hash: symbol_hash(export_name),
+                reserved: 0,
+                value: *value,
+            })
+            .collect();
+
+        let bucket_count = (exports.len() as u32 / 2).max(1);
+        let mut buckets = alloc::vec![NO_EXPORT; bucket_count as usize];
+        let mut chains = alloc::vec![NO_EXPORT; exports.len()];
+        for (i, export) in exports.iter().enumerate().rev() {
+            let bucket = (export.hash % bucket_count) as usize;
+            chains[i] = buckets[bucket];
+            buckets[bucket] = i as u32;
+        }
+
+        let export_table_offset = PLIB_HEADER_SIZE as u64;
+        let hash_table_offset = export_table_offset + (exports.len() * EXPORT_ENTRY_SIZE) as u64;
+        let string_table_offset = hash_table_offset + ((buckets.len() + chains.len()) * 4) as u64;
+        let image_offset = align_up(string_table_offset + strings.len() as u64, PAGE_SIZE);
+        let image = self.image.build();
+
+        let header = PlibHeader {
+            magic: PLIB_MAGIC,
+            version: PLIB_VERSION,
+            reserved: 0,
+            name,
+            lib_version: self.version.to_u32(),
+            export_count: exports.len() as u32,
+            bucket_count,
+            export_table_offset,
+            hash_table_offset,
+            string_table_offset,
+            string_table_size: strings.len() as u64,
+            image_offset,
+            image_size: image.len() as u64,
+        };
+
+        let mut out = alloc::vec![0u8; image_offset as usize];
+        header.write(&mut out).unwrap();
+        for (i, export) in exports.iter().enumerate() {
+            export.write(&mut out[export_table_offset as usize + i * EXPORT_ENTRY_SIZE..]);
+        }
+        for (i, link) in buckets.iter().chain(&chains).enumerate() {
+            write_u32(&mut out, hash_table_offset as usize + i * 4, *link);
+        }
+        out[string_table_offset as usize..][..strings.len()].copy_from_slice(&strings);
+        out.extend_from_slice(&image);
+        out
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use crate::plam::{CPU_X86_64, PERM_R, PERM_X};
+
+    const LIB_BASE: u64 = 0x7000_0000;
+
+    fn library(name: &str, version: Version, exports: &[(&str, u64)]) -> Vec<u8> {
+        let mut image = PlmBuilder::new(CPU_X86_64, 0, 0);
+        image.add_segment(PERM_R | PERM_X, 0, alloc::vec![0xC3; 16], 16);
+        let mut builder = PlibBuilder::new(name, version, image);
+        for (export, value) in exports {
+            builder.add_export(export, EXPORT_FUNC, *value);
+        }
+        builder.build()
+    }
+
+    fn import<'a>(library: &'a str, symbol: &'a str, min_version: Version) -> Import<'a> {
+        Import {
+            library,
+            symbol,
+            min_version,
+            slot: 0,
+            addend: 0,
+        }
+    }
+
+    #[test]
+    fn lookup_finds_every_export() {
+        let names = ["open", "close", "read", "write", "seek", "stat", "mmap"];
+        let exports: Vec<_> = names.iter().enumerate().map(|(i, n)| (*n, 0x100 * i as u64)).collect();
+        let data = library("libc", Version::new(1, 0, 0), &exports);
+        let file = PlibFile::parse(&data).unwrap();
+
+        for (name, value) in &exports {
+            let export = file.lookup(name).unwrap();
+            assert_eq!((export.name, export.kind, export.value), (*name, EXPORT_FUNC, *value));
+        }
+        assert_eq!(file.lookup("exit"), None);
+        assert_eq!(file.lookup(""), None);
+    }
+
+    #[test]
+    fn lookup_without_exports() {
+        let data = library("libempty", Version::new(1, 0, 0), &[]);
+        let file = PlibFile::parse(&data).unwrap();
+        assert_eq!(file.lookup("anything"), None);
+    }
+
+    #[test]
+    fn lookup_stops_on_chain_loop() {
+        let mut data = library("libc", Version::new(1, 0, 0), &[("open", 0x10), ("close", 0x20)]);
+        let header = PlibFile::parse(&data).unwrap().header;
+        let bucket_count = header.bucket_count as usize;
+        let table = header.hash_table_offset as usize;
+        // Point every bucket and chain link at export 0.
+        for slot in 0..bucket_count + header.export_count as usize {
+            write_u32(&mut data, table + slot * 4, 0);
+        }
+        let file = PlibFile::parse(&data).unwrap();
+        let missing = if file.lookup("open").is_some() { "close" } else { "open" };
+        assert_eq!(file.lookup(missing), None);
+    }
+
+    #[test]
+    fn resolve_import_by_library_and_version() {
+        let libc = library("libc", Version::new(1, 2, 0), &[("open", 0x40)]);
+        let libm = library("libm", Version::new(2, 0, 0), &[("sqrt", 0x80), ("open", 0x90)]);
+        let libraries = [
+            LoadedLibrary { file: PlibFile::parse(&libc).unwrap(), base: LIB_BASE },
+            LoadedLibrary { file: PlibFile::parse(&libm).unwrap(), base: LIB_BASE * 2 },
+        ];
+
+        let mut open = import("libc", "open", Version::new(1, 1, 0));
+        open.addend = 8;
+        assert_eq!(resolve_import(&open, &libraries), Ok(LIB_BASE + 0x48));
+        assert_eq!(resolve_import(&import("libm", "open", Version::default()), &libraries), Ok(LIB_BASE * 2 + 0x90));
+        assert_eq!(resolve_import(&import("", "sqrt", Version::default()), &libraries), Ok(LIB_BASE * 2 + 0x80));
+
+        assert_eq!(
+            resolve_import(&import("libc", "open", Version::new(1, 3, 0)), &libraries),
+            Err(ResolveError::VersionTooOld {
+                library: "libc",
+                have: Version::new(1, 2, 0),
+                need: Version::new(1, 3, 0),
+            })
+        );
+        assert_eq!(
+            resolve_import(&import("libc", "open", Version::new(2, 0, 0)), &libraries),
+            Err(ResolveError::VersionTooOld {
+                library: "libc",
+                have: Version::new(1, 2, 0),
+                need: Version::new(2, 0, 0),
+            })
+        );
+        assert_eq!(
+            resolve_import(&import("libc", "sqrt", Version::default()), &libraries),
+            Err(ResolveError::MissingSymbol { library: "libc", symbol: "sqrt" })
+        );
+        assert_eq!(
+            resolve_import(&import("libz", "inflate", Version::default()), &libraries),
+            Err(ResolveError::MissingLibrary("libz"))
+        );
+        // Without a library name, versions that do not match are skipped.
+        assert_eq!(
+            resolve_import(&import("", "open", Version::new(2, 0, 0)), &libraries),
+            Ok(LIB_BASE * 2 + 0x90)
+        );
+        assert_eq!(
+            resolve_import(&import("", "exit", Version::default()), &libraries),
+            Err(ResolveError::MissingSymbol { library: "", symbol: "exit" })
+        );
+    }
+}
@@ -0,0 +1,630 @@
+use core::fmt;
+use core::mem::{offset_of, size_of};
+
+#[cfg(feature = "alloc")]
+use alloc::{string::String, vec::Vec};
+#[cfg(feature = "alloc")]
+use ed25519_dalek::{Signer, SigningKey};
+use ed25519_dalek::{Signature, VerifyingKey};
+
+use crate::bytes::{self, read_cstr, read_u16, read_u32, read_u64, write_u16, write_u32, write_u64};
+#[cfg(feature = "alloc")]
+use crate::plam::align_up;
+use crate::plam::{PlamSignature, PAGE_SIZE, SIGNATURE_BLOCK_SIZE};
+#[cfg(feature = "alloc")]
+use crate::plm::PlmBuilder;
+use crate::plm::{PlmError, PlmFile};
+use crate::version::Version;
+
+pub const PLKMOD_MAGIC: [u8; 4] = *b"PLKM";
+pub const PLKMOD_VERSION_MAJOR: u16 = 1;
+pub const PLKMOD_VERSION: u16 = PLKMOD_VERSION_MAJOR << 8;
+
+/// Kernel module ABI implemented by this tree. A module loads when its
+/// `kernel_abi` is satisfied by the running kernel's ABI.
+pub const KERNEL_ABI: Version = Version::new(1, 0, 0);
+
+pub const FLAG_SIGNED: u64 = 1 << 0;
+
+/// `exit_offset` of a module that cannot be unloaded.
+pub const NO_EXIT: u64 = u64::MAX;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u16)]
+pub enum ModuleKind {
+    Module = 0,
+    Driver = 1,
+    Service = 2,
+}
+
+impl ModuleKind {
+    pub const ALL: [ModuleKind; 3] = [ModuleKind::Module, ModuleKind::Driver, ModuleKind::Service];
+
+    pub fn from_u16(value: u16) -> Option<Self> {
+        Self::ALL.into_iter().find(|k| *k as u16 == value)
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|k| k.name() == name)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ModuleKind::Module => "module",
+            ModuleKind::Driver => "driver",
+            ModuleKind::Service => "service",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlkmodError {
+    TooShort,
+    BadMagic([u8; 4]),
+    UnsupportedVersion(u16),
+    SizeMismatch { header: u64, actual: u64 },
+    OutOfBounds(&'static str),
+    BadString,
+    BadKind(u16),
+    BadEntry(&'static str),
+    Image(PlmError),
+    NotSigned,
+    BadSignatureBlock,
+    UntrustedKey,
+    BadSignature,
+    KernelAbi { required: Version, kernel: Version },
+}
+
+impl fmt::Display for PlkmodError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlkmodError::TooShort => write!(f, "buffer is shorter than the PLKMOD header"),
+            PlkmodError::BadMagic(m) => write!(f, "bad magic {:02x?}, expected \"PLKM\"", m),
+            PlkmodError::UnsupportedVersion(v) => {
+                write!(f, "unsupported version {}.{} (expected {}.x)", v >> 8, v & 0xFF, PLKMOD_VERSION_MAJOR)
+            }
+            PlkmodError::SizeMismatch { header, actual } => {
+                write!(f, "header describes {} bytes but the file is {} bytes", header, actual)
+            }
+            PlkmodError::OutOfBounds(what) => write!(f, "{} out of bounds", what),
+            PlkmodError::BadString => write!(f, "string table entry is not NUL-terminated UTF-8"),
+            PlkmodError::BadKind(kind) => write!(f, "unknown module kind {}", kind),
+            PlkmodError::BadEntry(which) => write!(f, "{} entry point is outside the module image", which),
+            PlkmodError::Image(e) => write!(f, "module image: {}", e),
+            PlkmodError::NotSigned => write!(f, "module is not signed"),
+            PlkmodError::BadSignatureBlock => write!(f, "signature block is missing or malformed"),
+            PlkmodError::UntrustedKey => write!(f, "module is signed by a key that is not trusted"),
+            PlkmodError::BadSignature => write!(f, "signature does not match the module"),
+            PlkmodError::KernelAbi { required, kernel } => {
+                write!(f, "module needs kernel ABI {} but the kernel provides {}", required, kernel)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for PlkmodError {}
+
+/// Header of a `.plkmod` kernel module (`.pkd` drivers use the same format
+/// with kind `driver`). The code lives in an embedded `.plm` image whose
+/// imports are bound against kernel and dependency exports; `init_offset`
+/// and `exit_offset` are offsets from its image base. A signature block,
+/// when present, covers every byte before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct PlkmodHeader {
+    pub magic: [u8; 4],
+    pub version: u16,
+    pub kind: u16,
+    pub name: u32,
+    pub description: u32,
+    pub class: u32,
+    pub mod_version: u32,
+    pub kernel_abi: u32,
+    pub dep_count: u32,
+    pub flags: u64,
+    pub init_offset: u64,
+    pub exit_offset: u64,
+    pub dep_table_offset: u64,
+    pub string_table_offset: u64,
+    pub string_table_size: u64,
+    pub image_offset: u64,
+    pub image_size: u64,
+    pub signature_offset: u64,
+}
+
+pub const PLKMOD_HEADER_SIZE: usize = size_of::<PlkmodHeader>();
+
+const _: () = assert!(PLKMOD_HEADER_SIZE == 0x68);
+
+impl PlkmodHeader {
+    pub fn parse(buf: &[u8]) -> Result<Self, PlkmodError> {
+        if buf.len() < PLKMOD_HEADER_SIZE {
+            return Err(PlkmodError::TooShort);
+        }
+
+        let magic: [u8; 4] = buf[0x00..0x04].try_into().unwrap();
+        if magic != PLKMOD_MAGIC {
+            return Err(PlkmodError::BadMagic(magic));
+        }
+
+        let header = PlkmodHeader {
+            magic,
+            version: read_u16(buf, offset_of!(PlkmodHeader, version)),
+            kind: read_u16(buf, offset_of!(PlkmodHeader, kind)),
+            name: read_u32(buf, offset_of!(PlkmodHeader, name)),
+            description: read_u32(buf, offset_of!(PlkmodHeader, description)),
+            class: read_u32(buf, offset_of!(PlkmodHeader, class)),
+            mod_version: read_u32(buf, offset_of!(PlkmodHeader, mod_version)),
+            kernel_abi: read_u32(buf, offset_of!(PlkmodHeader, kernel_abi)),
+            dep_count: read_u32(buf, offset_of!(PlkmodHeader, dep_count)),
+            flags: read_u64(buf, offset_of!(PlkmodHeader, flags)),
+            init_offset: read_u64(buf, offset_of!(PlkmodHeader, init_offset)),
+            exit_offset: read_u64(buf, offset_of!(PlkmodHeader, exit_offset)),
+            dep_table_offset: read_u64(buf, offset_of!(PlkmodHeader, dep_table_offset)),
+            string_table_offset: read_u64(buf, offset_of!(PlkmodHeader, string_table_offset)),
+            string_table_size: read_u64(buf, offset_of!(PlkmodHeader, string_table_size)),
+            image_offset: read_u64(buf, offset_of!(PlkmodHeader, image_offset)),
+            image_size: read_u64(buf, offset_of!(PlkmodHeader, image_size)),
+            signature_offset: read_u64(buf, offset_of!(PlkmodHeader, signature_offset)),
+        };
+
+        if header.version >> 8 != PLKMOD_VERSION_MAJOR {
+            return Err(PlkmodError::UnsupportedVersion(header.version));
+        }
+        if ModuleKind::from_u16(header.kind).is_none() {
+            return Err(PlkmodError::BadKind(header.kind));
+        }
+
+        Ok(header)
+    }
+
+    pub fn write(&self, buf: &mut [u8]) -> Result<(), PlkmodError> {
+        if buf.len() < PLKMOD_HEADER_SIZE {
+            return Err(PlkmodError::TooShort);
+        }
+
+        buf[0x00..0x04].copy_from_slice(&self.magic);
+        write_u16(buf, offset_of!(PlkmodHeader, version), self.version);
+        write_u16(buf, offset_of!(PlkmodHeader, kind), self.kind);
+        write_u32(buf, offset_of!(PlkmodHeader, name), self.name);
+        write_u32(buf, offset_of!(PlkmodHeader, description), self.description);
+        write_u32(buf, offset_of!(PlkmodHeader, class), self.class);
+        write_u32(buf, offset_of!(PlkmodHeader, mod_version), self.mod_version);
+        write_u32(buf, offset_of!(PlkmodHeader, kernel_abi), self.kernel_abi);
+        write_u32(buf, offset_of!(PlkmodHeader, dep_count), self.dep_count);
+        write_u64(buf, offset_of!(PlkmodHeader, flags), self.flags);
+        write_u64(buf, offset_of!(PlkmodHeader, init_offset), self.init_offset);
+        write_u64(buf, offset_of!(PlkmodHeader, exit_offset), self.exit_offset);
+        write_u64(buf, offset_of!(PlkmodHeader, dep_table_offset), self.dep_table_offset);
+        write_u64(buf, offset_of!(PlkmodHeader, string_table_offset), self.string_table_offset);
+        write_u64(buf, offset_of!(PlkmodHeader, string_table_size), self.string_table_size);
+        write_u64(buf, offset_of!(PlkmodHeader, image_offset), self.image_offset);
+        write_u64(buf, offset_of!(PlkmodHeader, image_size), self.image_size);
+        write_u64(buf, offset_of!(PlkmodHeader, signature_offset), self.signature_offset);
+        Ok(())
+    }
+
+    pub fn is_signed(&self) -> bool {
+        self.flags & FLAG_SIGNED != 0
+    }
+
+    pub fn module_kind(&self) -> ModuleKind {
+        ModuleKind::from_u16(self.kind).unwrap_or(ModuleKind::Module)
+    }
+}
+
+/// A module this one needs loaded first, at `min_version` or newer within
+/// the same major version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct PlkmodDependency {
+    pub name: u32,
+    pub min_version: u32,
+}
+
+pub const DEPENDENCY_ENTRY_SIZE: usize = size_of::<PlkmodDependency>();
+
+const _: () = assert!(DEPENDENCY_ENTRY_SIZE == 8);
+
+impl PlkmodDependency {
+    pub fn parse(entry: &[u8]) -> Self {
+        PlkmodDependency {
+            name: read_u32(entry, offset_of!(PlkmodDependency, name)),
+            min_version: read_u32(entry, offset_of!(PlkmodDependency, min_version)),
+        }
+    }
+
+    pub fn write(&self, entry: &mut [u8]) {
+        write_u32(entry, offset_of!(PlkmodDependency, name), self.name);
+        write_u32(entry, offset_of!(PlkmodDependency, min_version), self.min_version);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dependency<'a> {
+    pub name: &'a str,
+    pub min_version: Version,
+}
+
+/// A parsed `.plkmod` file. Parsing checks the layout, strings, entry points
+/// and embedded image but not the signature, see [`PlkmodFile::verify_signature`].
+#[derive(Debug, Clone, Copy)]
+pub struct PlkmodFile<'a> {
+    pub header: PlkmodHeader,
+    pub image: PlmFile<'a>,
+    data: &'a [u8],
+}
+
+impl<'a> PlkmodFile<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self, PlkmodError> {
+        let header = PlkmodHeader::parse(data)?;
+        let image_end = header.image_offset.saturating_add(header.image_size);
+        let expected_size = if header.is_signed() {
+            if header.signature_offset != image_end {
+                return Err(PlkmodError::BadSignatureBlock);
+            }
+            image_end.saturating_add(SIGNATURE_BLOCK_SIZE as u64)
+        } else {
+            image_end
+        };
+        if expected_size != data.len() as u64 {
+            return Err(PlkmodError::SizeMismatch {
+                header: expected_size,
+                actual: data.len() as u64,
+            });
+        }
+        if header.image_offset < PLKMOD_HEADER_SIZE as u64 || !header.image_offset.is_multiple_of(PAGE_SIZE) {
+            return Err(PlkmodError::OutOfBounds("module image"));
+        }
+        let image = PlmFile::parse(&data[header.image_offset as usize..image_end as usize])
+            .map_err(PlkmodError::Image)?;
+
+        let file = PlkmodFile { header, image, data };
+        file.table(header.dep_table_offset, header.dep_count as u64, DEPENDENCY_ENTRY_SIZE, "dependency table")?;
+        file.table(header.string_table_offset, header.string_table_size, 1, "string table")?;
+
+        file.string(header.name)?;
+        file.string(header.description)?;
+        file.string(header.class)?;
+        for entry in file.dependency_entries() {
+            file.string(entry.name)?;
+        }
+
+        let image_size = image.image_size();
+        if header.init_offset >= image_size {
+            return Err(PlkmodError::BadEntry("init"));
+        }
+        if header.exit_offset != NO_EXIT && header.exit_offset >= image_size {
+            return Err(PlkmodError::BadEntry("exit"));
+        }
+
+        Ok(file)
+    }
+
+    fn table(&self, offset: u64, count: u64, entry_size: usize, what: &'static str) -> Result<&'a [u8], PlkmodError> {
+        bytes::table(self.data, offset, count, entry_size, PLKMOD_HEADER_SIZE as u64, self.header.image_offset)
+            .ok_or(PlkmodError::OutOfBounds(what))
+    }
+
+    pub fn string(&self, offset: u32) -> Result<&'a str, PlkmodError> {
+        let strings = self.table(self.header.string_table_offset, self.header.string_table_size, 1, "string table")?;
+        read_cstr(strings, offset as usize).ok_or(PlkmodError::BadString)
+    }
+
+    pub fn name(&self) -> &'a str {
+        self.string(self.header.name).unwrap_or_default()
+    }
+
+    pub fn description(&self) -> &'a str {
+        self.string(self.header.description).unwrap_or_default()
+    }
+
+    /// Driver class matching a `[drivers]` switch of `plum.config` (`usb`,
+    /// `nvme`, ...), empty for modules that are not drivers.
+    pub fn class(&self) -> &'a str {
+        self.string(self.header.class).unwrap_or_default()
+    }
+
+    pub fn mod_version(&self) -> Version {
+        Version::from_u32(self.header.mod_version)
+    }
+
+    pub fn kernel_abi(&self) -> Version {
+        Version::from_u32(self.header.kernel_abi)
+    }
+
+    pub fn exit_offset(&self) -> Option<u64> {
+        (self.header.exit_offset != NO_EXIT).then_some(self.header.exit_offset)
+    }
+
+    pub fn dependency_entries(&self) -> impl Iterator<Item = PlkmodDependency> + 'a {
+        let header = self.header;
+        self.table(header.dep_table_offset, header.dep_count as u64, DEPENDENCY_ENTRY_SIZE, "dependency table")
+            .unwrap_or_default()
+            .chunks_exact(DEPENDENCY_ENTRY_SIZE)
+            .map(PlkmodDependency::parse)
+    }
+
+    pub fn dependencies(&self) -> impl Iterator<Item = Dependency<'a>> + 'a {
+        let file = *self;
+        self.dependency_entries().map(move |entry| Dependency {
+            name: file.string(entry.name).unwrap_or_default(),
+            min_version: Version::from_u32(entry.min_version),
+        })
+    }
+
+    /// Fails unless a kernel providing `kernel_abi` can load the module.
+    pub fn check_kernel_abi(&self, kernel_abi: Version) -> Result<(), PlkmodError> {
+        if !kernel_abi.satisfies(self.kernel_abi()) {
+            return Err(PlkmodError::KernelAbi {
+                required: self.kernel_abi(),
+                kernel: kernel_abi,
+            });
+        }
+        Ok(())
+    }
+
+    pub fn signature(&self) -> Result<PlamSignature, PlkmodError> {
+        if !self.header.is_signed() {
+            return Err(PlkmodError::NotSigned);
+        }
+        PlamSignature::parse(&self.data[self.header.signature_offset as usize..])
+            .map_err(|_| PlkmodError::BadSignatureBlock)
+    }
+
+    /// Checks the embedded signature against `trusted_keys` and returns the
+    /// key that signed the module.
+    pub fn verify_signature(&self, trusted_keys: &[[u8; 32]]) -> Result<[u8; 32], PlkmodError> {
+        let block = self.signature()?;
+        if !trusted_keys.contains(&block.public_key) {
+            return Err(PlkmodError::UntrustedKey);
+        }
+
+        let key = VerifyingKey::from_bytes(&block.public_key).map_err(|_| PlkmodError::BadSignatureBlock)?;
+        key.verify_strict(
+            &self.data[..self.header.signature_offset as usize],
+            &Signature::from_bytes(&block.signature),
+        )
+        .map_err(|_| PlkmodError::BadSignature)?;
+
+        Ok(block.public_key)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DependencyError<'a> {
+    Missing { module: &'a str, dependency: &'a str },
+    TooOld { module: &'a str, dependency: Dependency<'a>, found: Version },
+    Cycle(&'a str),
+}
+
+impl fmt::Display for DependencyError<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DependencyError::Missing { module, dependency } => write!(f, "{} depends on missing {}", module, dependency),
+            DependencyError::TooOld { module, dependency, found } => write!(
+                f,
+                "{} needs {} {} but {} is available",
+                module, dependency.name, dependency.min_version, found
+            ),
+            DependencyError::Cycle(module) => write!(f, "{} is part of a dependency cycle", module),
+        }
+    }
+}
+
+/// Orders `modules` so that every module comes after its dependencies.
+/// Returns indices into `modules`.
+#[cfg(feature = "alloc")]
+pub fn load_order<'a>(modules: &[PlkmodFile<'a>]) -> Result<Vec<usize>, DependencyError<'a>> {
+    // 0 = not visited, 1 = on the current path, 2 = ordered.
+    fn visit<'a>(
+        index: usize,
+        modules: &[PlkmodFile<'a>],
+        state: &mut [u8],
+        order: &mut Vec<usize>,
+    ) -> Result<(), DependencyError<'a>> {
+        match state[index] {
+            1 => return Err(DependencyError::Cycle(modules[index].name())),
+            2 => return Ok(()),
+            _ => {}
+        }
+        state[index] = 1;
+        for dependency in modules[index].dependencies() {
+            let found = modules
+                .iter()
+                .position(|m| m.name() == dependency.name)
+                .ok_or(DependencyError::Missing {
+                    module: modules[index].name(),
+                    dependency: dependency.name,
+                })?;
+            // A dependency recorded without a version accepts any, as for
+            // library imports.
+            let version = modules[found].mod_version();
+            if dependency.min_version != Version::default() && !version.satisfies(dependency.min_version) {
+                return Err(DependencyError::TooOld {
+                    module: modules[index].name(),
+                    dependency,
+                    found: version,
+                });
+            }
+            visit(found, modules, state, order)?;
+        }
+        state[index] = 2;
+        order.push(index);
+        Ok(())
+    }
+
+    let mut state = alloc::vec![0u8; modules.len()];
+    let mut order = Vec::with_capacity(modules.len());
+    for index in 0..modules.len() {
+        visit(index, modules, &mut state, &mut order)?;
+    }
+    Ok(order)
+}
+
+/// Assembles a `.plkmod` around an image built with [`PlmBuilder`].
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone)]
+pub struct PlkmodBuilder {
+    pub name: String,
+    pub description: String,
+    pub class: String,
+    pub kind: ModuleKind,
+    pub version: Version,
+    pub kernel_abi: Version,
+    pub init_offset: u64,
+    pub exit_offset: Option<u64>,
+    pub image: PlmBuilder,
+    dependencies: Vec<(String, Version)>,
+}
+
+#[cfg(feature = "alloc")]
+impl PlkmodBuilder {
+    pub fn new(name: &str, version: Version, image: PlmBuilder, init_offset: u64) -> Self {
+        PlkmodBuilder {
+            name: String::from(name),
+            description: String::new(),
+            class: String::new(),
+            kind: ModuleKind::Module,
+            version,
+            kernel_abi: KERNEL_ABI,
+            init_offset,
+            exit_offset: None,
+            image,
+            dependencies: Vec::new(),
+        }
+    }
+
+    pub fn add_dependency(&mut self, name: &str, min_version: Version) {
+        self.dependencies.push((String::from(name), min_version));
+    }
+
+    /// Lays out the module and, with `signing_key`, appends a signature over
+    /// everything before it.
+    pub fn build(&self, signing_key: Option<&SigningKey>) -> Vec<u8> {
+        let mut strings = Vec::from([0u8]);
+        let mut intern = |s: &str| {
+            if s.is_empty() {
+                return 0;
+            }
+            let offset = strings.len() as u32;
+            strings.extend_from_slice(s.as_bytes());
+            strings.push(0);
+            offset
+        };
+
+        let name = intern(&self.name);
+        let description = intern(&self.description);
+        let class = intern(&self.class);
+        let dependencies: Vec<PlkmodDependency> = self
+            .dependencies
+            .iter()
+            .map(|(dep, min_version)| PlkmodDependency {
+                name: intern(dep),
+                min_version: min_version.to_u32(),
+            })
+            .collect();
+
+        let dep_table_offset = PLKMOD_HEADER_SIZE as u64;
+        let string_table_offset = dep_table_offset + (dependencies.len() * DEPENDENCY_ENTRY_SIZE) as u64;
+        let image_offset = align_up(string_table_offset + strings.len() as u64, PAGE_SIZE);
+        let image = self.image.build();
+        let image_end = image_offset + image.len() as u64;
+
+        let header = PlkmodHeader {
+            magic: PLKMOD_MAGIC,
+            version: PLKMOD_VERSION,
+            kind: self.kind as u16,
+            name,
+            description,
+            class,
+            mod_version: self.version.to_u32(),
+            kernel_abi: self.kernel_abi.to_u32(),
+            dep_count: dependencies.len() as u32,
+            flags: if signing_key.is_some() { FLAG_SIGNED } else { 0 },
+            init_offset: self.init_offset,
+            exit_offset: self.exit_offset.unwrap_or(NO_EXIT),
+            dep_table_offset,
+            string_table_offset,
+            string_table_size: strings.len() as u64,
+            image_offset,
+            image_size: image.len() as u64,
+            signature_offset: if signing_key.is_some() { image_end } else { 0 },
+        };
+
+        let mut out = alloc::vec![0u8; image_offset as usize];
+        header.write(&mut out).unwrap();
+        for (i, dependency) in dependencies.iter().enumerate() {
+            dependency.write(&mut out[dep_table_offset as usize + i * DEPENDENCY_ENTRY_SIZE..]);
+        }
+        out[string_table_offset as usize..][..strings.len()].copy_from_slice(&strings);
+        out.extend_from_slice(&image);
+
+        if let Some(key) = signing_key {
+            let signature = key.sign(&out);
+            let mut block = [0u8; SIGNATURE_BLOCK_SIZE];
+            PlamSignature::new(key.verifying_key().to_bytes(), signature.to_bytes()).write(&mut block);
+            out.extend_from_slice(&block);
+        }
+        out
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use crate::plam::{CPU_X86_64, PERM_R, PERM_X};
+
+    fn module(name: &str, version: Version, dependencies: &[(&str, Version)]) -> Vec<u8> {
+        let mut image = PlmBuilder::new(CPU_X86_64, 0, 0);
+        image.add_segment(PERM_R | PERM_X, 0, alloc::vec![0xC3; 16], 16);
+        let mut builder = PlkmodBuilder::new(name, version, image, 0);
+        for (dependency, min_version) in dependencies {
+            builder.add_dependency(dependency, *min_version);
+        }
+        builder.build(None)
+    }
+
+    fn order(images: &[Vec<u8>]) -> Result<Vec<usize>, String> {
+        let modules: Vec<_> = images.iter().map(|data| PlkmodFile::parse(data).unwrap()).collect();
+        load_order(&modules).map_err(|e| alloc::format!("{}", e))
+    }
+
+    #[test]
+    fn load_order_puts_dependencies_first() {
+        let images = [
+            module("usb-storage", Version::new(1, 0, 0), &[("usb", Version::new(2, 1, 0))]),
+            module("usb", Version::new(2, 3, 0), &[("pci", Version::new(1, 0, 0))]),
+            module("pci", Version::new(1, 0, 4), &[]),
+        ];
+        assert_eq!(order(&images), Ok(alloc::vec![2, 1, 0]));
+    }
+
+    #[test]
+    fn load_order_any_version() {
+        let images = [
+            module("usb", Version::new(2, 3, 0), &[("pci", Version::default())]),
+            module("pci", Version::new(1, 0, 4), &[]),
+        ];
+        assert_eq!(order(&images), Ok(alloc::vec![1, 0]));
+    }
+
+    #[test]
+    fn load_order_errors() {
+        let too_old = [
+            module("usb", Version::new(2, 3, 0), &[("pci", Version::new(1, 1, 0))]),
+            module("pci", Version::new(1, 0, 4), &[]),
+        ];
+        assert_eq!(order(&too_old), Err("usb needs pci 1.1.0 but 1.0.4 is available".into()));
+
+        let missing = [module("usb", Version::new(2, 3, 0), &[("pci", Version::default())])];
+        assert_eq!(order(&missing), Err("usb depends on missing pci".into()));
+
+        let cycle = [
+            module("a", Version::new(1, 0, 0), &[("b", Version::default())]),
+            module("b", Version::new(1, 0, 0), &[("a", Version::default())]),
+        ];
+        assert_eq!(order(&cycle), Err("a is part of a dependency cycle".into()));
+    }
+}
@@ -0,0 +1,680 @@
+use core::fmt;
+use core::mem::{offset_of, size_of};
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use crate::bytes::{self, read_cstr, read_u16, read_u32, read_u64, write_u16, write_u32, write_u64};
+use crate::plam::PAGE_SIZE;
+#[cfg(feature = "alloc")]
+use crate::plam::align_up;
+use crate::version::Version;
+
+pub const PLM_MAGIC: [u8; 4] = *b"PLM\0";
+pub const PLM_VERSION_MAJOR: u16 = 1;
+pub const PLM_VERSION: u16 = PLM_VERSION_MAJOR << 8;
+
+pub const DEFAULT_IMAGE_BASE: u64 = 0x40_0000;
+pub const DEFAULT_STACK_SIZE: u64 = 1024 * 1024;
+
+/// Image may be loaded at any page-aligned base by applying its relocation
+/// table, see [`PlmFile::relocate`].
+pub const FLAG_PIE: u64 = 1 << 0;
+
+/// Personality the program expects from the kernel, named after the
+/// `[compatibility]` switches in `plum.config`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u16)]
+pub enum Abi {
+    Native = 0,
+    Posix = 1,
+    Win32 = 2,
+    Darwin = 3,
+    Android = 4,
+    Linux = 5,
+}
+
+impl Abi {
+    pub const ALL: [Abi; 6] = [Abi::Native, Abi::Posix, Abi::Win32, Abi::Darwin, Abi::Android, Abi::Linux];
+
+    pub fn from_u16(value: u16) -> Option<Self> {
+        Self::ALL.into_iter().find(|a| *a as u16 == value)
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|a| a.name() == name)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Abi::Native => "native",
+            Abi::Posix => "posix",
+            Abi::Win32 => "win32",
+            Abi::Darwin => "darwin",
+            Abi::Android => "android",
+            Abi::Linux => "linux",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlmError {
+    TooShort,
+    BadMagic([u8; 4]),
+    UnsupportedVersion(u16),
+    SizeMismatch { header: u64, actual: u64 },
+    OutOfBounds(&'static str),
+    BadString,
+    NotRelocatable,
+    /// Segments must be sorted by address and must not overlap.
+    SegmentOverlap,
+}
+
+impl fmt::Display for PlmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlmError::TooShort => write!(f, "buffer is shorter than the PLM header"),
+            PlmError::BadMagic(m) => write!(f, "bad magic {:02x?}, expected \"PLM\\0\"", m),
+            PlmError::UnsupportedVersion(v) => {
+                write!(f, "unsupported version {}.{} (expected {}.x)", v >> 8, v & 0xFF, PLM_VERSION_MAJOR)
+            }
+            PlmError::SizeMismatch { header, actual } => {
+                write!(f, "header describes {} bytes but the file is {} bytes", header, actual)
+            }
+            PlmError::OutOfBounds(what) => write!(f, "{} out of bounds", what),
+            PlmError::BadString => write!(f, "string table entry is not NUL-terminated UTF-8"),
+            PlmError::NotRelocatable => write!(f, "executable is not position-independent"),
+            PlmError::SegmentOverlap => write!(f, "segments overlap or are not in address order"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for PlmError {}
+
+/// Header at offset 0 of a `.plm` executable. All offsets are file offsets,
+/// all fields little-endian.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct PlmHeader {
+    pub magic: [u8; 4],
+    pub version: u16,
+    pub cpu_id: u16,
+    pub abi: u16,
+    pub segment_count: u16,
+    pub import_count: u32,
+    pub flags: u64,
+    pub image_base: u64,
+    pub entry_offset: u64,
+    pub stack_size: u64,
+    pub segment_table_offset: u64,
+    pub import_table_offset: u64,
+    pub reloc_table_offset: u64,
+    pub reloc_count: u64,
+    pub string_table_offset: u64,
+    pub string_table_size: u64,
+    pub file_size: u64,
+}
+
+pub const PLM_HEADER_SIZE: usize = size_of::<PlmHeader>();
+
+const _: () = assert!(PLM_HEADER_SIZE == 0x68);
+const _: () = assert!(offset_of!(PlmHeader, flags) == 0x10);
+const _: () = assert!(offset_of!(PlmHeader, file_size) == 0x60);
+
+impl PlmHeader {
+    pub fn parse(buf: &[u8]) -> Result<Self, PlmError> {
+        if buf.len() < PLM_HEADER_SIZE {
+            return Err(PlmError::TooShort);
+        }
+
+        let magic: [u8; 4] = buf[0x00..0x04].try_into().unwrap();
+        if magic != PLM_MAGIC {
+            return Err(PlmError::BadMagic(magic));
+        }
+
+        let header = PlmHeader {
+            magic,
+            version: read_u16(buf, offset_of!(PlmHeader, version)),
+            cpu_id: read_u16(buf, offset_of!(PlmHeader, cpu_id)),
+            abi: read_u16(buf, offset_of!(PlmHeader, abi)),
+            segment_count: read_u16(buf, offset_of!(PlmHeader, segment_count)),
+            import_count: read_u32(buf, offset_of!(PlmHeader, import_count)),
+            flags: read_u64(buf, offset_of!(PlmHeader, flags)),
+            image_base: read_u64(buf, offset_of!(PlmHeader, image_base)),
+            entry_offset: read_u64(buf, offset_of!(PlmHeader, entry_offset)),
+            stack_size: read_u64(buf, offset_of!(PlmHeader, stack_size)),
+            segment_table_offset: read_u64(buf, offset_of!(PlmHeader, segment_table_offset)),
+            import_table_offset: read_u64(buf, offset_of!(PlmHeader, import_table_offset)),
+            reloc_table_offset: read_u64(buf, offset_of!(PlmHeader, reloc_table_offset)),
+            reloc_count: read_u64(buf, offset_of!(PlmHeader, reloc_count)),
+            string_table_offset: read_u64(buf, offset_of!(PlmHeader, string_table_offset)),
+            string_table_size: read_u64(buf, offset_of!(PlmHeader, string_table_size)),
+            file_size: read_u64(buf, offset_of!(PlmHeader, file_size)),
+        };
+
+        if header.version >> 8 != PLM_VERSION_MAJOR {
+            return Err(PlmError::UnsupportedVersion(header.version));
+        }
+
+        Ok(header)
+    }
+
+    pub fn write(&self, buf: &mut [u8]) -> Result<(), PlmError> {
+        if buf.len() < PLM_HEADER_SIZE {
+            return Err(PlmError::TooShort);
+        }
+
+        buf[0x00..0x04].copy_from_slice(&self.magic);
+        write_u16(buf, offset_of!(PlmHeader, version), self.version);
+        write_u16(buf, offset_of!(PlmHeader, cpu_id), self.cpu_id);
+        write_u16(buf, offset_of!(PlmHeader, abi), self.abi);
+        write_u16(buf, offset_of!(PlmHeader, segment_count), self.segment_count);
+        write_u32(buf, offset_of!(PlmHeader, import_count), self.import_count);
+        write_u64(buf, offset_of!(PlmHeader, flags), self.flags);
+        write_u64(buf, offset_of!(PlmHeader, image_base), self.image_base);
+        write_u64(buf, offset_of!(PlmHeader, entry_offset), self.entry_offset);
+        write_u64(buf, offset_of!(PlmHeader, stack_size), self.stack_size);
+        write_u64(buf, offset_of!(PlmHeader, segment_table_offset), self.segment_table_offset);
+        write_u64(buf, offset_of!(PlmHeader, import_table_offset), self.import_table_offset);
+        write_u64(buf, offset_of!(PlmHeader, reloc_table_offset), self.reloc_table_offset);
+        write_u64(buf, offset_of!(PlmHeader, reloc_count), self.reloc_count);
+        write_u64(buf, offset_of!(PlmHeader, string_table_offset), self.string_table_offset);
+        write_u64(buf, offset_of!(PlmHeader, string_table_size), self.string_table_size);
+        write_u64(buf, offset_of!(PlmHeader, file_size), self.file_size);
+        Ok(())
+    }
+
+    pub fn is_pie(&self) -> bool {
+        self.flags & FLAG_PIE != 0
+    }
+}
+
+/// One loadable segment. `file_offset` is congruent to `vaddr` modulo the
+/// page size so segments can be mapped straight from the file. The segment
+/// table is sorted by `vaddr` and segments do not overlap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct PlmSegment {
+    pub perms: u32,
+    pub reserved: u32,
+    pub vaddr: u64,
+    pub file_offset: u64,
+    pub file_size: u64,
+    pub mem_size: u64,
+}
+
+pub const SEGMENT_ENTRY_SIZE: usize = size_of::<PlmSegment>();
+
+const _: () = assert!(SEGMENT_ENTRY_SIZE == 40);
+
+impl PlmSegment {
+    pub fn parse(entry: &[u8]) -> Self {
+        PlmSegment {
+            perms: read_u32(entry, offset_of!(PlmSegment, perms)),
+            reserved: read_u32(entry, offset_of!(PlmSegment, reserved)),
+            vaddr: read_u64(entry, offset_of!(PlmSegment, vaddr)),
+            file_offset: read_u64(entry, offset_of!(PlmSegment, file_offset)),
+            file_size: read_u64(entry, offset_of!(PlmSegment, file_size)),
+            mem_size: read_u64(entry, offset_of!(PlmSegment, mem_size)),
+        }
+    }
+
+    pub fn write(&self, entry: &mut [u8]) {
+        write_u32(entry, offset_of!(PlmSegment, perms), self.perms);
+        write_u32(entry, offset_of!(PlmSegment, reserved), self.reserved);
+        write_u64(entry, offset_of!(PlmSegment, vaddr), self.vaddr);
+        write_u64(entry, offset_of!(PlmSegment, file_offset), self.file_offset);
+        write_u64(entry, offset_of!(PlmSegment, file_size), self.file_size);
+        write_u64(entry, offset_of!(PlmSegment, mem_size), self.mem_size);
+    }
+}
+
+/// A 64-bit slot, at `slot` bytes from `image_base`, that the loader fills
+/// with the address of `symbol` from `library` plus `addend`. An empty
+/// library name matches any loaded library.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct PlmImportEntry {
+    pub library: u32,
+    pub symbol: u32,
+    pub min_version: u32,
+    pub reserved: u32,
+    pub slot: u64,
+    pub addend: i64,
+}
+
+pub const IMPORT_ENTRY_SIZE: usize = size_of::<PlmImportEntry>();
+
+const _: () = assert!(IMPORT_ENTRY_SIZE == 32);
+
+impl PlmImportEntry {
+    pub fn parse(entry: &[u8]) -> Self {
+        PlmImportEntry {
+            library: read_u32(entry, offset_of!(PlmImportEntry, library)),
+            symbol: read_u32(entry, offset_of!(PlmImportEntry, symbol)),
+            min_version: read_u32(entry, offset_of!(PlmImportEntry, min_version)),
+            reserved: read_u32(entry, offset_of!(PlmImportEntry, reserved)),
+            slot: read_u64(entry, offset_of!(PlmImportEntry, slot)),
+            addend: read_u64(entry, offset_of!(PlmImportEntry, addend)) as i64,
+        }
+    }
+
+    pub fn write(&self, entry: &mut [u8]) {
+        write_u32(entry, offset_of!(PlmImportEntry, library), self.library);
+        write_u32(entry, offset_of!(PlmImportEntry, symbol), self.symbol);
+        write_u32(entry, offset_of!(PlmImportEntry, min_version), self.min_version);
+        write_u32(entry, offset_of!(PlmImportEntry, reserved), self.reserved);
+        write_u64(entry, offset_of!(PlmImportEntry, slot), self.slot);
+        write_u64(entry, offset_of!(PlmImportEntry, addend), self.addend as u64);
+    }
+}
+
+/// An import with its names looked up in the string table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Import<'a> {
+    pub library: &'a str,
+    pub symbol: &'a str,
+    pub min_version: Version,
+    pub slot: u64,
+    pub addend: i64,
+}
+
+/// A parsed `.plm` file. Every table is bounds-checked by [`PlmFile::parse`],
+/// so the accessors below cannot fail.
+#[derive(Debug, Clone, Copy)]
+pub struct PlmFile<'a> {
+    pub header: PlmHeader,
+    data: &'a [u8],
+}
+
+impl<'a> PlmFile<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self, PlmError> {
+        let header = PlmHeader::parse(data)?;
+        if header.file_size != data.len() as u64 {
+            return Err(PlmError::SizeMismatch {
+                header: header.file_size,
+                actual: data.len() as u64,
+            });
+        }
+
+        let file = PlmFile { header, data };
+        file.table(header.segment_table_offset, header.segment_count as u64, SEGMENT_ENTRY_SIZE, "segment table")?;
+        file.table(header.import_table_offset, header.import_count as u64, IMPORT_ENTRY_SIZE, "import table")?;
+        file.table(header.reloc_table_offset, header.reloc_count, 8, "relocation table")?;
+        file.table(header.string_table_offset, header.string_table_size, 1, "string table")?;
+
+        let mut previous_end = 0;
+        for segment in file.segments() {
+            if segment.file_size > segment.mem_size {
+                return Err(PlmError::OutOfBounds("segment file size"));
+            }
+            if segment.vaddr < previous_end {
+                return Err(PlmError::SegmentOverlap);
+            }
+            previous_end = segment.vaddr.checked_add(segment.mem_size).ok_or(PlmError::OutOfBounds("segment"))?;
+            file.table(segment.file_offset, segment.file_size, 1, "segment data")?;
+        }
+        for entry in file.import_entries() {
+            file.string(entry.library)?;
+            file.string(entry.symbol)?;
+        }
+
+        Ok(file)
+    }
+
+    fn table(&self, offset: u64, count: u64, entry_size: usize, what: &'static str) -> Result<&'a [u8], PlmError> {
+        bytes::table(self.data, offset, count, entry_size, PLM_HEADER_SIZE as u64, u64::MAX)
+            .ok_or(PlmError::OutOfBounds(what))
+    }
+
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    pub fn string(&self, offset: u32) -> Result<&'a str, PlmError> {
+        let strings = self.table(self.header.string_table_offset, self.header.string_table_size, 1, "string table")?;
+        read_cstr(strings, offset as usize).ok_or(PlmError::BadString)
+    }
+
+    pub fn segments(&self) -> impl Iterator<Item = PlmSegment> + 'a {
+        let header = self.header;
+        self.table(header.segment_table_offset, header.segment_count as u64, SEGMENT_ENTRY_SIZE, "segment table")
+            .unwrap_or_default()
+            .chunks_exact(SEGMENT_ENTRY_SIZE)
+            .map(PlmSegment::parse)
+    }
+
+    pub fn segment_data(&self, segment: &PlmSegment) -> &'a [u8] {
+        self.table(segment.file_offset, segment.file_size, 1, "segment data")
+            .unwrap_or_default()
+    }
+
+    pub fn import_entries(&self) -> impl Iterator<Item = PlmImportEntry> + 'a {
+        let header = self.header;
+        self.table(header.import_table_offset, header.import_count as u64, IMPORT_ENTRY_SIZE, "import table")
+            .unwrap_or_default()
+            .chunks_exact(IMPORT_ENTRY_SIZE)
+            .map(PlmImportEntry::parse)
+    }
+
+    pub fn imports(&self) -> impl Iterator<Item = Import<'a>> + 'a {
+        let file = *self;
+        self.import_entries().map(move |entry| Import {
+            library: file.string(entry.library).unwrap_or_default(),
+            symbol: file.string(entry.symbol).unwrap_or_default(),
+            min_version: Version::from_u32(entry.min_version),
+            slot: entry.slot,
+            addend: entry.addend,
+        })
+    }
+
+    /// Offsets from `image_base` of the 64-bit absolute addresses to rebase.
+    pub fn relocations(&self) -> impl Iterator<Item = u64> + 'a {
+        let header = self.header;
+        self.table(header.reloc_table_offset, header.reloc_count, 8, "relocation table")
+            .unwrap_or_default()
+            .chunks_exact(8)
+            .map(|entry| read_u64(entry, 0))
+    }
+
+    /// Bytes of address space from `image_base` to the end of the last segment.
+    pub fn image_size(&self) -> u64 {
+        self.segments()
+            .map(|s| s.vaddr.saturating_add(s.mem_size).saturating_sub(self.header.image_base))
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Copies every segment into `memory`, which stands for the address
+    /// range starting at `image_base`, and zero-fills the rest of each one.
+    pub fn load(&self, memory: &mut [u8]) -> Result<(), PlmError> {
+        for segment in self.segments() {
+            let start = segment.vaddr.checked_sub(self.header.image_base).ok_or(PlmError::OutOfBounds("segment"))?;
+            let end = start.checked_add(segment.mem_size).ok_or(PlmError::OutOfBounds("segment"))?;
+            let target = usize::try_from(start)
+                .ok()
+                .zip(usize::try_from(end).ok())
+                .and_then(|(start, end)| memory.get_mut(start..end))
+                .ok_or(PlmError::OutOfBounds("segment"))?;
+            let data = self.segment_data(&segment);
+            target[..data.len()].copy_from_slice(data);
+            target[data.len()..].fill(0);
+        }
+        Ok(())
+    }
+
+    /// Rebases `memory`, filled by [`PlmFile::load`], to run at `new_base`.
+    pub fn relocate(&self, memory: &mut [u8], new_base: u64) -> Result<(), PlmError> {
+        if !self.header.is_pie() {
+            return Err(PlmError::NotRelocatable);
+        }
+        if !new_base.is_multiple_of(PAGE_SIZE) {
+            return Err(PlmError::OutOfBounds("load base"));
+        }
+        let delta = new_base.wrapping_sub(self.header.image_base);
+        for offset in self.relocations() {
+            let offset = offset as usize;
+            if offset.checked_add(8).is_none_or(|end| end > memory.len()) {
+                return Err(PlmError::OutOfBounds("relocation"));
+            }
+            write_u64(memory, offset, read_u64(memory, offset).wrapping_add(delta));
+        }
+        Ok(())
+    }
+}
+
+/// Assembles a `.plm` file. Segments are laid out in the order they are
+/// added, after the header and tables.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone)]
+pub struct PlmBuilder {
+    pub cpu_id: u16,
+    pub abi: Abi,
+    pub flags: u64,
+    pub image_base: u64,
+    pub entry_offset: u64,
+    pub stack_size: u64,
+    segments: Vec<(PlmSegment, Vec<u8>)>,
+    imports: Vec<PlmImportEntry>,
+    relocations: Vec<u64>,
+    strings: Vec<u8>,
+}
+
+#[cfg(feature = "alloc")]
+impl PlmBuilder {
+    pub fn new(cpu_id: u16, image_base: u64, entry_offset: u64) -> Self {
+        PlmBuilder {
+            cpu_id,
+            abi: Abi::Native,
+            flags: 0,
+            image_base,
+            entry_offset,
+            stack_size: DEFAULT_STACK_SIZE,
+            segments: Vec::new(),
+            imports: Vec::new(),
+            relocations: Vec::new(),
+            strings: Vec::from([0u8]),
+        }
+    }
+
+    pub fn add_segment(&mut self, perms: u32, vaddr: u64, data: Vec<u8>, mem_size: u64) {
+        let segment = PlmSegment {
+            perms,
+            reserved: 0,
+            vaddr,
+            file_offset: 0,
+            file_size: data.len() as u64,
+            mem_size: mem_size.max(data.len() as u64),
+        };
+        self.segments.push((segment, data));
+    }
+
+    pub fn add_import(&mut self, library: &str, symbol: &str, min_version: Version, slot: u64, addend: i64) {
+        let entry = PlmImportEntry {
+            library: self.intern(library),
+            symbol: self.intern(symbol),
+            min_version: min_version.to_u32(),
+            reserved: 0,
+            slot,
+            addend,
+        };
+        self.imports.push(entry);
+    }
+
+    /// Records a 64-bit absolute address at `offset` from `image_base` and
+    /// marks the executable position-independent.
+    pub fn add_relocation(&mut self, offset: u64) {
+        self.flags |= FLAG_PIE;
+        self.relocations.push(offset);
+    }
+
+    fn intern(&mut self, s: &str) -> u32 {
+        if s.is_empty() {
+            return 0;
+        }
+        let offset = self.strings.len() as u32;
+        self.strings.extend_from_slice(s.as_bytes());
+        self.strings.push(0);
+        offset
+    }
+
+    pub fn build(&self) -> Vec<u8> {
+        let segment_table_offset = PLM_HEADER_SIZE as u64;
+        let import_table_offset = segment_table_offset + (self.segments.len() * SEGMENT_ENTRY_SIZE) as u64;
+        let reloc_table_offset = import_table_offset + (self.imports.len() * IMPORT_ENTRY_SIZE) as u64;
+        let string_table_offset = reloc_table_offset + (self.relocations.len() * 8) as u64;
+        let mut next_offset = string_table_offset + self.strings.len() as u64;
+
+        let mut segments = Vec::with_capacity(self.segments.len());
+        for (segment, data) in &self.segments {
+            let mut segment = *segment;
+            if !data.is_empty() {
+                segment.file_offset = align_up(next_offset, PAGE_SIZE) + segment.vaddr % PAGE_SIZE;
+                next_offset = segment.file_offset + data.len() as u64;
+            }
+            segments.push(segment);
+        }
+
+        let header = PlmHeader {
+            magic: PLM_MAGIC,
+            version: PLM_VERSION,
+            cpu_id: self.cpu_id,
+            abi: self.abi as u16,
+            segment_count: segments.len() as u16,
+            import_count: self.imports.len() as u32,
+            flags: self.flags,
+            image_base: self.image_base,
+            entry_offset: self.entry_offset,
+            stack_size: self.stack_size,
+            segment_table_offset,
+            import_table_offset,
+            reloc_table_offset,
+            reloc_count: self.relocations.len() as u64,
+            string_table_offset,
+            string_table_size: self.strings.len() as u64,
+            file_size: next_offset,
+        };
+
+        let mut out = alloc::vec![0u8; next_offset as usize];
+        header.write(&mut out).unwrap();
+        for (i, segment) in segments.iter().enumerate() {
+            segment.write(&mut out[segment_table_offset as usize + i * SEGMENT_ENTRY_SIZE..]);
+        }
+        for (i, import) in self.imports.iter().enumerate() {
+            import.write(&mut out[import_table_offset as usize + i * IMPORT_ENTRY_SIZE..]);
+        }
+        for (i, offset) in self.relocations.iter().enumerate() {
+            write_u64(&mut out, reloc_table_offset as usize + i * 8, *offset);
+        }
+        out[string_table_offset as usize..][..self.strings.len()].copy_from_slice(&self.strings);
+        for (segment, (_, data)) in segments.iter().zip(&self.segments) {
+            out[segment.file_offset as usize..][..data.len()].copy_from_slice(data);
+        }
+        out
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use crate::plam::{CPU_X86_64, PERM_R, PERM_W, PERM_X};
+
+    const BASE: u64 = DEFAULT_IMAGE_BASE;
+
+    /// A PIE with a text segment, a data segment with trailing bss, one
+    /// import and one relocation.
+    fn sample() -> PlmBuilder {
+        let mut builder = PlmBuilder::new(CPU_X86_64, BASE, 0x10);
+        builder.abi = Abi::Posix;
+        builder.stack_size = 0x8000;
+        builder.add_segment(PERM_R | PERM_X, BASE, alloc::vec![0x90; 0x20], 0x20);
+        let mut data = alloc::vec![0u8; 16];
+        data[..8].copy_from_slice(&(BASE + 0x10).to_le_bytes());
+        builder.add_segment(PERM_R | PERM_W, BASE + 0x1008, data, 0x100);
+        builder.add_import("libc", "exit", Version::new(1, 0, 0), 0x1010, 4);
+        builder.add_relocation(0x1008);
+        builder
+    }
+
+    #[test]
+    fn build_parse_load_round_trip() {
+        let data = sample().build();
+        let file = PlmFile::parse(&data).unwrap();
+
+        let header = file.header;
+        assert_eq!((header.cpu_id, header.abi, header.image_base), (CPU_X86_64, Abi::Posix as u16, BASE));
+        assert_eq!((header.entry_offset, header.stack_size, header.file_size), (0x10, 0x8000, data.len() as u64));
+        assert!(header.is_pie());
+
+        let segments: Vec<_> = file.segments().collect();
+        assert_eq!(segments.len(), 2);
+        for segment in &segments {
+            assert_eq!(segment.file_offset % PAGE_SIZE, segment.vaddr % PAGE_SIZE);
+        }
+        assert_eq!((segments[1].vaddr, segments[1].file_size, segments[1].mem_size), (BASE + 0x1008, 16, 0x100));
+        assert_eq!(file.segment_data(&segments[0]), [0x90; 0x20]);
+
+        let imports: Vec<_> = file.imports().collect();
+        assert_eq!(
+            imports,
+            [Import { library: "libc", symbol: "exit", min_version: Version::new(1, 0, 0), slot: 0x1010, addend: 4 }]
+        );
+        assert_eq!(file.relocations().collect::<Vec<_>>(), [0x1008]);
+
+        assert_eq!(file.image_size(), 0x1108);
+        let mut memory = alloc::vec![0xAAu8; file.image_size() as usize];
+        file.load(&mut memory).unwrap();
+        assert_eq!(memory[..0x20], [0x90; 0x20]);
+        assert_eq!(read_u64(&memory, 0x1008), BASE + 0x10);
+        assert!(memory[0x1010..].iter().all(|b| *b == 0));
+
+        file.relocate(&mut memory, 0x7000_0000).unwrap();
+        assert_eq!(read_u64(&memory, 0x1008), 0x7000_0010);
+        assert_eq!(file.relocate(&mut memory, 0x7000_0001), Err(PlmError::OutOfBounds("load base")));
+    }
+
+    #[test]
+    fn load_rejects_short_memory() {
+        let data = sample().build();
+        let file = PlmFile::parse(&data).unwrap();
+        let mut memory = alloc::vec![0u8; 0x1100];
+        assert_eq!(file.load(&mut memory), Err(PlmError::OutOfBounds("segment")));
+    }
+
+    #[test]
+    fn relocate_needs_pie() {
+        let mut builder = PlmBuilder::new(CPU_X86_64, BASE, 0);
+        builder.add_segment(PERM_R | PERM_X, BASE, alloc::vec![0xC3], 1);
+        let data = builder.build();
+        let file = PlmFile::parse(&data).unwrap();
+        assert_eq!(file.relocate(&mut [0u8; 1], 0), Err(PlmError::NotRelocatable));
+    }
+
+    #[test]
+    fn parse_rejects_truncated() {
+        let data = sample().build();
+        assert_eq!(PlmFile::parse(&data[..PLM_HEADER_SIZE - 1]).map(|_| ()), Err(PlmError::TooShort));
+        assert_eq!(
+            PlmFile::parse(&data[..data.len() - 1]).map(|_| ()),
+            Err(PlmError::SizeMismatch { header: data.len() as u64, actual: data.len() as u64 - 1 })
+        );
+
+        // The header agrees with the length, but the last segment's data
+        // runs past the end.
+        let mut truncated = data[..data.len() - 1].to_vec();
+        write_u64(&mut truncated, offset_of!(PlmHeader, file_size), data.len() as u64 - 1);
+        assert_eq!(PlmFile::parse(&truncated).map(|_| ()), Err(PlmError::OutOfBounds("segment data")));
+
+        let mut bad_table = data.clone();
+        write_u64(&mut bad_table, offset_of!(PlmHeader, segment_table_offset), data.len() as u64);
+        assert_eq!(PlmFile::parse(&bad_table).map(|_| ()), Err(PlmError::OutOfBounds("segment table")));
+    }
+
+    #[test]
+    fn parse_rejects_overlapping_segments() {
+        let mut builder = PlmBuilder::new(CPU_X86_64, BASE, 0);
+        builder.add_segment(PERM_R | PERM_X, BASE, alloc::vec![0xC3; 0x20], 0x20);
+        builder.add_segment(PERM_R | PERM_W, BASE + 0x10, alloc::vec![0; 8], 8);
+        assert_eq!(PlmFile::parse(&builder.build()).map(|_| ()), Err(PlmError::SegmentOverlap));
+
+        let mut builder = PlmBuilder::new(CPU_X86_64, BASE, 0);
+        builder.add_segment(PERM_R | PERM_W, BASE + 0x1000, alloc::vec![0; 8], 8);
+        builder.add_segment(PERM_R | PERM_X, BASE, alloc::vec![0xC3; 0x20], 0x20);
+        assert_eq!(PlmFile::parse(&builder.build()).map(|_| ()), Err(PlmError::SegmentOverlap));
+
+        // Adjacent segments are fine.
+        let mut builder = PlmBuilder::new(CPU_X86_64, BASE, 0);
+        builder.add_segment(PERM_R | PERM_X, BASE, alloc::vec![0xC3; 0x20], 0x20);
+        builder.add_segment(PERM_R | PERM_W, BASE + 0x20, alloc::vec![0; 8], 8);
+        assert!(PlmFile::parse(&builder.build()).is_ok());
+    }
+
+    #[test]
+    fn parse_rejects_segment_past_address_space() {
+        let top = u64::MAX - PAGE_SIZE + 1;
+        let mut builder = PlmBuilder::new(CPU_X86_64, top, 0);
+        builder.add_segment(PERM_R | PERM_W, top, alloc::vec![0; 8], PAGE_SIZE);
+        assert_eq!(PlmFile::parse(&builder.build()).map(|_| ()), Err(PlmError::OutOfBounds("segment")));
+    }
+}
@@ -0,0 +1,697 @@
+use core::cmp::Ordering;
+use core::fmt;
+use core::mem::{offset_of, size_of};
+
+#[cfg(feature = "alloc")]
+use alloc::{string::String, vec::Vec};
+
+use crate::bytes::{self, read_cstr, read_u16, read_u32, read_u64, write_u16, write_u32, write_u64};
+use crate::crc32::crc32;
+use crate::lz4;
+#[cfg(feature = "alloc")]
+use crate::lz4::Compressor;
+#[cfg(feature = "alloc")]
+use crate::plam::align_up;
+
+pub const PLRES_MAGIC: [u8; 4] = *b"PLRS";
+pub const PLRES_VERSION_MAJOR: u16 = 1;
+pub const PLRES_VERSION: u16 = PLRES_VERSION_MAJOR << 8;
+
+/// Resource data is stored as an LZ4 block, see [`Resource::read_into`].
+pub const FLAG_LZ4: u32 = 1 << 0;
+
+pub const FORMAT_RAW: u16 = 0;
+pub const FORMAT_BMP: u16 = 1;
+pub const FORMAT_PNG: u16 = 2;
+pub const FORMAT_TTF: u16 = 3;
+pub const FORMAT_OTF: u16 = 4;
+pub const FORMAT_STRINGS: u16 = 5;
+
+/// Resource data is aligned to this many bytes inside the bundle.
+pub const RESOURCE_ALIGN: u64 = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u16)]
+pub enum ResourceKind {
+    Blob = 0,
+    Image = 1,
+    Font = 2,
+    Strings = 3,
+}
+
+impl ResourceKind {
+    pub const ALL: [ResourceKind; 4] = [
+        ResourceKind::Blob,
+        ResourceKind::Image,
+        ResourceKind::Font,
+        ResourceKind::Strings,
+    ];
+
+    pub fn from_u16(value: u16) -> Option<Self> {
+        Self::ALL.into_iter().find(|k| *k as u16 == value)
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|k| k.name() == name)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ResourceKind::Blob => "blob",
+            ResourceKind::Image => "image",
+            ResourceKind::Font => "font",
+            ResourceKind::Strings => "strings",
+        }
+    }
+}
+
+pub fn format_name(format: u16) -> Option<&'static str> {
+    match format {
+        FORMAT_RAW => Some("raw"),
+        FORMAT_BMP => Some("bmp"),
+        FORMAT_PNG => Some("png"),
+        FORMAT_TTF => Some("ttf"),
+        FORMAT_OTF => Some("otf"),
+        FORMAT_STRINGS => Some("strings"),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlresError {
+    TooShort,
+    BadMagic([u8; 4]),
+    UnsupportedVersion(u16),
+    SizeMismatch { header: u64, actual: u64 },
+    OutOfBounds(&'static str),
+    BadString,
+    BadKind(u16),
+    BadIndex,
+    BadData,
+    Checksum,
+    BufferTooSmall { needed: u64 },
+    TooLarge(u64),
+}
+
+impl fmt::Display for PlresError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlresError::TooShort => write!(f, "buffer is shorter than the PLRES header"),
+            PlresError::BadMagic(m) => write!(f, "bad magic {:02x?}, expected \"PLRS\"", m),
+            PlresError::UnsupportedVersion(v) => {
+                write!(f, "unsupported version {}.{} (expected {}.x)", v >> 8, v & 0xFF, PLRES_VERSION_MAJOR)
+            }
+            PlresError::SizeMismatch { header, actual } => {
+                write!(f, "header describes {} bytes but the file is {} bytes", header, actual)
+            }
+            PlresError::OutOfBounds(what) => write!(f, "{} out of bounds", what),
+            PlresError::BadString => write!(f, "string table entry is not NUL-terminated UTF-8"),
+            PlresError::BadKind(kind) => write!(f, "unknown resource kind {}", kind),
+            PlresError::BadIndex => write!(f, "resource index is unsorted or has duplicates"),
+            PlresError::BadData => write!(f, "resource data is truncated or cannot be decompressed"),
+            PlresError::Checksum => write!(f, "resource CRC32 mismatch"),
+            PlresError::BufferTooSmall { needed } => write!(f, "buffer too small, {} bytes needed", needed),
+            PlresError::TooLarge(size) => write!(f, "resource of {} bytes does not fit in memory", size),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for PlresError {}
+
+/// Header of a `.plres` resource bundle: images, fonts and localized string
+/// tables looked up by name through an index sorted by `(id, locale)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct PlresHeader {
+    pub magic: [u8; 4],
+    pub version: u16,
+    pub reserved: u16,
+    pub entry_count: u32,
+    pub reserved2: u32,
+    pub entry_table_offset: u64,
+    pub string_table_offset: u64,
+    pub string_table_size: u64,
+    pub file_size: u64,
+}
+
+pub const PLRES_HEADER_SIZE: usize = size_of::<PlresHeader>();
+
+const _: () = assert!(PLRES_HEADER_SIZE == 0x30);
+
+impl PlresHeader {
+    pub fn parse(buf: &[u8]) -> Result<Self, PlresError> {
+        if buf.len() < PLRES_HEADER_SIZE {
+            return Err(PlresError::TooShort);
+        }
+
+        let magic: [u8; 4] = buf[0x00..0x04].try_into().unwrap();
+        if magic != PLRES_MAGIC {
+            return Err(PlresError::BadMagic(magic));
+        }
+
+        let header = PlresHeader {
+            magic,
+            version: read_u16(buf, offset_of!(PlresHeader, version)),
+            reserved: read_u16(buf, offset_of!(PlresHeader, reserved)),
+            entry_count: read_u32(buf, offset_of!(PlresHeader, entry_count)),
+            reserved2: read_u32(buf, offset_of!(PlresHeader, reserved2)),
+            entry_table_offset: read_u64(buf, offset_of!(PlresHeader, entry_table_offset)),
+            string_table_offset: read_u64(buf, offset_of!(PlresHeader, string_table_offset)),
+            string_table_size: read_u64(buf, offset_of!(PlresHeader, string_table_size)),
+            file_size: read_u64(buf, offset_of!(PlresHeader, file_size)),
+        };
+
+        if header.version >> 8 != PLRES_VERSION_MAJOR {
+            return Err(PlresError::UnsupportedVersion(header.version));
+        }
+
+        Ok(header)
+    }
+
+    pub fn write(&self, buf: &mut [u8]) -> Result<(), PlresError> {
+        if buf.len() < PLRES_HEADER_SIZE {
+            return Err(PlresError::TooShort);
+        }
+
+        buf[0x00..0x04].copy_from_slice(&self.magic);
+        write_u16(buf, offset_of!(PlresHeader, version), self.version);
+        write_u16(buf, offset_of!(PlresHeader, reserved), self.reserved);
+        write_u32(buf, offset_of!(PlresHeader, entry_count), self.entry_count);
+        write_u32(buf, offset_of!(PlresHeader, reserved2), self.reserved2);
+        write_u64(buf, offset_of!(PlresHeader, entry_table_offset), self.entry_table_offset);
+        write_u64(buf, offset_of!(PlresHeader, string_table_offset), self.string_table_offset);
+        write_u64(buf, offset_of!(PlresHeader, string_table_size), self.string_table_size);
+        write_u64(buf, offset_of!(PlresHeader, file_size), self.file_size);
+        Ok(())
+    }
+}
+
+/// Index entry. `width` and `height` are in pixels for images and zero
+/// otherwise; `crc32` covers the uncompressed data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct PlresEntry {
+    pub id: u32,
+    pub locale: u32,
+    pub kind: u16,
+    pub format: u16,
+    pub flags: u32,
+    pub width: u32,
+    pub height: u32,
+    pub crc32: u32,
+    pub reserved: u32,
+    pub offset: u64,
+    pub stored_size: u64,
+    pub size: u64,
+}
+
+pub const RESOURCE_ENTRY_SIZE: usize = size_of::<PlresEntry>();
+
+const _: () = assert!(RESOURCE_ENTRY_SIZE == 56);
+
+impl PlresEntry {
+    pub fn parse(entry: &[u8]) -> Self {
+        PlresEntry {
+            id: read_u32(entry, offset_of!(PlresEntry, id)),
+            locale: read_u32(entry, offset_of!(PlresEntry, locale)),
+            kind: read_u16(entry, offset_of!(PlresEntry, kind)),
+            format: read_u16(entry, offset_of!(PlresEntry, format)),
+            flags: read_u32(entry, offset_of!(PlresEntry, flags)),
+            width: read_u32(entry, offset_of!(PlresEntry, width)),
+            height: read_u32(entry, offset_of!(PlresEntry, height)),
+            crc32: read_u32(entry, offset_of!(PlresEntry, crc32)),
+            reserved: read_u32(entry, offset_of!(PlresEntry, reserved)),
+            offset: read_u64(entry, offset_of!(PlresEntry, offset)),
+            stored_size: read_u64(entry, offset_of!(PlresEntry, stored_size)),
+            size: read_u64(entry, offset_of!(PlresEntry, size)),
+        }
+    }
+
+    pub fn write(&self, entry: &mut [u8]) {
+        write_u32(entry, offset_of!(PlresEntry, id), self.id);
+        write_u32(entry, offset_of!(PlresEntry, locale), self.locale);
+        write_u16(entry, offset_of!(PlresEntry, kind), self.kind);
+        write_u16(entry, offset_of!(PlresEntry, format), self.format);
+        write_u32(entry, offset_of!(PlresEntry, flags), self.flags);
+        write_u32(entry, offset_of!(PlresEntry, width), self.width);
+        write_u32(entry, offset_of!(PlresEntry, height), self.height);
+        write_u32(entry, offset_of!(PlresEntry, crc32), self.crc32);
+        write_u32(entry, offset_of!(PlresEntry, reserved), self.reserved);
+        write_u64(entry, offset_of!(PlresEntry, offset), self.offset);
+        write_u64(entry, offset_of!(PlresEntry, stored_size), self.stored_size);
+        write_u64(entry, offset_of!(PlresEntry, size), self.size);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Resource<'a> {
+    pub id: &'a str,
+    /// Empty for resources that are not localized.
+    pub locale: &'a str,
+    pub kind: ResourceKind,
+    pub format: u16,
+    pub flags: u32,
+    pub width: u32,
+    pub height: u32,
+    pub crc32: u32,
+    pub size: u64,
+    pub stored: &'a [u8],
+}
+
+impl<'a> Resource<'a> {
+    pub fn is_compressed(&self) -> bool {
+        self.flags & FLAG_LZ4 != 0
+    }
+
+    /// The data in place, for uncompressed resources. Not checksummed.
+    pub fn data(&self) -> Option<&'a [u8]> {
+        (!self.is_compressed()).then_some(self.stored)
+    }
+
+    /// Decompresses if needed and checks the CRC32. Returns the size.
+    pub fn read_into(&self, out: &mut [u8]) -> Result<usize, PlresError> {
+        let size = usize::try_from(self.size).map_err(|_| PlresError::BufferTooSmall { needed: self.size })?;
+        if out.len() < size {
+            return Err(PlresError::BufferTooSmall { needed: self.size });
+        }
+        if self.is_compressed() {
+            if lz4::decompress_into(self.stored, &mut out[..size]) != Some(size) {
+                return Err(PlresError::BadData);
+            }
+        } else {
+            out[..size].copy_from_slice(self.stored);
+        }
+        if crc32(&out[..size]) != self.crc32 {
+            return Err(PlresError::Checksum);
+        }
+        Ok(size)
+    }
+
+    /// Reads the resource into a new buffer, failing rather than aborting
+    /// if `size` cannot be allocated.
+    #[cfg(feature = "alloc")]
+    pub fn to_vec(&self) -> Result<Vec<u8>, PlresError> {
+        let size = usize::try_from(self.size).map_err(|_| PlresError::TooLarge(self.size))?;
+        let mut out = Vec::new();
+        out.try_reserve_exact(size).map_err(|_| PlresError::TooLarge(self.size))?;
+        out.resize(size, 0);
+        self.read_into(&mut out)?;
+        Ok(out)
+    }
+}
+
+/// A parsed `.plres` file. The index, strings and data ranges are checked by
+/// [`PlresFile::parse`]; resource contents are checked when read.
+#[derive(Debug, Clone, Copy)]
+pub struct PlresFile<'a> {
+    pub header: PlresHeader,
+    data: &'a [u8],
+}
+
+impl<'a> PlresFile<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self, PlresError> {
+        let header = PlresHeader::parse(data)?;
+        if header.file_size != data.len() as u64 {
+            return Err(PlresError::SizeMismatch {
+                header: header.file_size,
+                actual: data.len() as u64,
+            });
+        }
+
+        let file = PlresFile { header, data };
+        file.table(header.entry_table_offset, header.entry_count as u64, RESOURCE_ENTRY_SIZE, "resource index")?;
+        file.table(header.string_table_offset, header.string_table_size, 1, "string table")?;
+
+        let mut previous: Option<(&str, &str)> = None;
+        for entry in file.entries() {
+            let key = (file.string(entry.id)?, file.string(entry.locale)?);
+            if previous.is_some_and(|p| p >= key) {
+                return Err(PlresError::BadIndex);
+            }
+            previous = Some(key);
+            let kind = ResourceKind::from_u16(entry.kind).ok_or(PlresError::BadKind(entry.kind))?;
+            let stored = file.table(entry.offset, entry.stored_size, 1, "resource data")?;
+            if entry.flags & FLAG_LZ4 == 0 && entry.stored_size != entry.size {
+                return Err(PlresError::OutOfBounds("resource data"));
+            }
+            if entry.flags & FLAG_LZ4 != 0 && entry.size > lz4::max_decompressed_size(entry.stored_size) {
+                return Err(PlresError::BadData);
+            }
+            if kind == ResourceKind::Strings {
+                if entry.flags & FLAG_LZ4 != 0 {
+                    return Err(PlresError::BadData);
+                }
+                StringTable::parse(stored)?;
+            }
+        }
+
+        Ok(file)
+    }
+
+    fn table(&self, offset: u64, count: u64, entry_size: usize, what: &'static str) -> Result<&'a [u8], PlresError> {
+        bytes::table(self.data, offset, count, entry_size, PLRES_HEADER_SIZE as u64, u64::MAX)
+            .ok_or(PlresError::OutOfBounds(what))
+    }
+
+    pub fn string(&self, offset: u32) -> Result<&'a str, PlresError> {
+        let strings = self.table(self.header.string_table_offset, self.header.string_table_size, 1, "string table")?;
+        read_cstr(strings, offset as usize).ok_or(PlresError::BadString)
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = PlresEntry> + 'a {
+        let header = self.header;
+        self.table(header.entry_table_offset, header.entry_count as u64, RESOURCE_ENTRY_SIZE, "resource index")
+            .unwrap_or_default()
+            .chunks_exact(RESOURCE_ENTRY_SIZE)
+            .map(PlresEntry::parse)
+    }
+
+    fn to_resource(self, entry: PlresEntry) -> Resource<'a> {
+        Resource {
+            id: self.string(entry.id).unwrap_or_default(),
+            locale: self.string(entry.locale).unwrap_or_default(),
+            kind: ResourceKind::from_u16(entry.kind).unwrap_or(ResourceKind::Blob),
+            format: entry.format,
+            flags: entry.flags,
+            width: entry.width,
+            height: entry.height,
+            crc32: entry.crc32,
+            size: entry.size,
+            stored: self
+                .table(entry.offset, entry.stored_size, 1, "resource data")
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn resources(&self) -> impl Iterator<Item = Resource<'a>> + 'a {
+        let file = *self;
+        self.entries().map(move |entry| file.to_resource(entry))
+    }
+
+    /// Finds `id` in `locale` (empty for resources that are not localized)
+    /// by binary search of the index.
+    pub fn get(&self, id: &str, locale: &str) -> Option<Resource<'a>> {
+        let (mut low, mut high) = (0usize, self.header.entry_count as usize);
+        while low < high {
+            let mid = (low + high) / 2;
+            let offset = self.header.entry_table_offset as usize + mid * RESOURCE_ENTRY_SIZE;
+            let entry = PlresEntry::parse(&self.data[offset..]);
+            let key = (self.string(entry.id).ok()?, self.string(entry.locale).ok()?);
+            match key.cmp(&(id, locale)) {
+                Ordering::Less => low = mid + 1,
+                Ordering::Greater => high = mid,
+                Ordering::Equal => return Some(self.to_resource(entry)),
+            }
+        }
+        None
+    }
+
+    /// The string table `id` for `locale`.
+    pub fn strings(&self, id: &str, locale: &str) -> Option<StringTable<'a>> {
+        let resource = self.get(id, locale).filter(|r| r.kind == ResourceKind::Strings)?;
+        StringTable::parse(resource.stored).ok()
+    }
+
+    /// Looks `key` up in string table `id`, trying each of `locales` in
+    /// order and then the table without a locale.
+    pub fn localized(&self, id: &str, key: &str, locales: &[&str]) -> Option<&'a str> {
+        locales
+            .iter()
+            .chain(&[""])
+            .find_map(|locale| self.strings(id, locale)?.get(key))
+    }
+}
+
+/// Data of a `strings` resource: a count, `count` pairs of u32 offsets
+/// `(key, text)` sorted by key, then the NUL-terminated strings they point
+/// into (offsets are from the start of the table).
+#[derive(Debug, Clone, Copy)]
+pub struct StringTable<'a> {
+    data: &'a [u8],
+    count: usize,
+}
+
+const STRING_TABLE_HEADER_SIZE: usize = 8;
+
+impl<'a> StringTable<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self, PlresError> {
+        if data.len() < STRING_TABLE_HEADER_SIZE {
+            return Err(PlresError::BadData);
+        }
+        let count = read_u32(data, 0) as usize;
+        let pairs_end = count
+            .checked_mul(8)
+            .and_then(|len| len.checked_add(STRING_TABLE_HEADER_SIZE))
+            .filter(|&end| end <= data.len())
+            .ok_or(PlresError::BadData)?;
+
+        let table = StringTable { data, count };
+        let mut previous = None;
+        for i in 0..count {
+            let (key, _) = table.pair(i).ok_or(PlresError::BadString)?;
+            if previous.is_some_and(|p| p >= key) {
+                return Err(PlresError::BadIndex);
+            }
+            previous = Some(key);
+        }
+        debug_assert!(pairs_end <= data.len());
+        Ok(table)
+    }
+
+    fn pair(&self, index: usize) -> Option<(&'a str, &'a str)> {
+        let at = STRING_TABLE_HEADER_SIZE + index * 8;
+        let key = read_cstr(self.data, read_u32(self.data, at) as usize)?;
+        let text = read_cstr(self.data, read_u32(self.data, at + 4) as usize)?;
+        Some((key, text))
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&'a str, &'a str)> + 'a {
+        let table = *self;
+        (0..self.count).filter_map(move |i| table.pair(i))
+    }
+
+    pub fn get(&self, key: &str) -> Option<&'a str> {
+        let (mut low, mut high) = (0usize, self.count);
+        while low < high {
+            let mid = (low + high) / 2;
+            let (candidate, text) = self.pair(mid)?;
+            match candidate.cmp(key) {
+                Ordering::Less => low = mid + 1,
+                Ordering::Greater => high = mid,
+                Ordering::Equal => return Some(text),
+            }
+        }
+        None
+    }
+}
+
+/// Encodes `(key, text)` pairs as [`StringTable`] data. Later duplicates of
+/// a key are dropped.
+#[cfg(feature = "alloc")]
+pub fn build_string_table(pairs: &[(&str, &str)]) -> Vec<u8> {
+    let mut sorted: Vec<(&str, &str)> = Vec::with_capacity(pairs.len());
+    for &(key, text) in pairs {
+        if !sorted.iter().any(|(k, _)| *k == key) {
+            sorted.push((key, text));
+        }
+    }
+    sorted.sort_unstable_by_key(|&(key, _)| key);
+
+    let mut out = alloc::vec![0u8; STRING_TABLE_HEADER_SIZE + sorted.len() * 8];
+    write_u32(&mut out, 0, sorted.len() as u32);
+    for (i, (key, text)) in sorted.iter().enumerate() {
+        for (j, s) in [key, text].into_iter().enumerate() {
+            let offset = out.len() as u32;
+            out.extend_from_slice(s.as_bytes());
+            out.push(0);
+            write_u32(&mut out, STRING_TABLE_HEADER_SIZE + i * 8 + j * 4, offset);
+        }
+    }
+    out
+}
+
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone)]
+struct PendingResource {
+    id: String,
+    locale: String,
+    kind: ResourceKind,
+    format: u16,
+    width: u32,
+    height: u32,
+    data: Vec<u8>,
+}
+
+/// Assembles a `.plres` file. Adding the same `(id, locale)` twice replaces
+/// the earlier resource.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, Default)]
+pub struct PlresBuilder {
+    resources: Vec<PendingResource>,
+}
+
+#[cfg(feature = "alloc")]
+impl PlresBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, id: &str, locale: &str, kind: ResourceKind, format: u16, data: Vec<u8>) {
+        self.add_image(id, locale, kind, format, data, 0, 0);
+    }
+
+    /// Like [`PlresBuilder::add`] with pixel dimensions for images.
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_image(
+        &mut self,
+        id: &str,
+        locale: &str,
+        kind: ResourceKind,
+        format: u16,
+        data: Vec<u8>,
+        width: u32,
+        height: u32,
+    ) {
+        self.resources.retain(|r| r.id != id || r.locale != locale);
+        self.resources.push(PendingResource {
+            id: String::from(id),
+            locale: String::from(locale),
+            kind,
+            format,
+            width,
+            height,
+            data,
+        });
+    }
+
+    pub fn add_strings(&mut self, id: &str, locale: &str, pairs: &[(&str, &str)]) {
+        self.add(id, locale, ResourceKind::Strings, FORMAT_STRINGS, build_string_table(pairs));
+    }
+
+    /// Lays out the bundle. With `compress`, every resource except string
+    /// tables is stored as an LZ4 block when that makes it smaller.
+    pub fn build(&self, compress: Option<Compressor>) -> Vec<u8> {
+        let mut resources: Vec<&PendingResource> = self.resources.iter().collect();
+        resources.sort_unstable_by(|a, b| (&a.id, &a.locale).cmp(&(&b.id, &b.locale)));
+
+        let mut strings = Vec::from([0u8]);
+        let mut intern = |s: &str| {
+            if s.is_empty() {
+                return 0;
+            }
+            let offset = strings.len() as u32;
+            strings.extend_from_slice(s.as_bytes());
+            strings.push(0);
+            offset
+        };
+        let names: Vec<(u32, u32)> = resources.iter().map(|r| (intern(&r.id), intern(&r.locale))).collect();
+
+        let stored: Vec<(u32, Vec<u8>)> = resources
+            .iter()
+            .map(|r| match compress {
+                Some(compress) if r.kind != ResourceKind::Strings => {
+                    let compressed = compress(&r.data);
+                    if compressed.len() < r.data.len() {
+                        (FLAG_LZ4, compressed)
+                    } else {
+                        (0, r.data.clone())
+                    }
+                }
+                _ => (0, r.data.clone()),
+            })
+            .collect();
+
+        let entry_table_offset = PLRES_HEADER_SIZE as u64;
+        let string_table_offset = entry_table_offset + (resources.len() * RESOURCE_ENTRY_SIZE) as u64;
+        let mut next_offset = string_table_offset + strings.len() as u64;
+
+        let mut entries = Vec::with_capacity(resources.len());
+        for ((resource, (id, locale)), (flags, data)) in resources.iter().zip(&names).zip(&stored) {
+            let offset = align_up(next_offset, RESOURCE_ALIGN);
+            next_offset = offset + data.len() as u64;
+            entries.push(PlresEntry {
+                id: *id,
+                locale: *locale,
+                kind: resource.kind as u16,
+                format: resource.format,
+                flags: *flags,
+                width: resource.width,
+                height: resource.height,
+                crc32: crc32(&resource.data),
+                reserved: 0,
+                offset,
+                stored_size: data.len() as u64,
+                size: resource.data.len() as u64,
+            });
+        }
+
+        let header = PlresHeader {
+            magic: PLRES_MAGIC,
+            version: PLRES_VERSION,
+            reserved: 0,
+            entry_count: entries.len() as u32,
+            reserved2: 0,
+            entry_table_offset,
+            string_table_offset,
+            string_table_size: strings.len() as u64,
+            file_size: next_offset,
+        };
+
+        let mut out = alloc::vec![0u8; next_offset as usize];
+        header.write(&mut out).unwrap();
+        for (i, entry) in entries.iter().enumerate() {
+            entry.write(&mut out[entry_table_offset as usize + i * RESOURCE_ENTRY_SIZE..]);
+        }
+        out[string_table_offset as usize..][..strings.len()].copy_from_slice(&strings);
+        for (entry, (_, data)) in entries.iter().zip(&stored) {
+            out[entry.offset as usize..][..data.len()].copy_from_slice(data);
+        }
+        out
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+
+    /// A one-resource file whose entry is then rewritten as LZ4 data of
+    /// `size` bytes.
+    fn lz4_file(size: u64) -> Vec<u8> {
+        let mut builder = PlresBuilder::new();
+        builder.add("blob", "", ResourceKind::Blob, 0, alloc::vec![0x40, b'a', b'b', b'c', b'd']);
+        let mut data = builder.build(None);
+        let entry = PlresFile::parse(&data).unwrap().header.entry_table_offset as usize;
+        write_u32(&mut data, entry + offset_of!(PlresEntry, flags), FLAG_LZ4);
+        write_u64(&mut data, entry + offset_of!(PlresEntry, size), size);
+        data
+    }
+
+    #[test]
+    fn to_vec_decompresses() {
+        let mut data = lz4_file(4);
+        let entry = PlresFile::parse(&data).unwrap().header.entry_table_offset as usize;
+        write_u32(&mut data, entry + offset_of!(PlresEntry, crc32), crc32(b"abcd"));
+        let file = PlresFile::parse(&data).unwrap();
+        assert_eq!(file.get("blob", "").unwrap().to_vec(), Ok(b"abcd".to_vec()));
+    }
+
+    #[test]
+    fn lz4_size_bounded_by_stored_size() {
+        let limit = lz4::max_decompressed_size(5);
+        let data = lz4_file(limit);
+        let file = PlresFile::parse(&data).unwrap();
+        assert_eq!(file.get("blob", "").unwrap().to_vec(), Err(PlresError::BadData));
+
+        assert_eq!(PlresFile::parse(&lz4_file(limit + 1)).map(|_| ()), Err(PlresError::BadData));
+        assert_eq!(PlresFile::parse(&lz4_file(u64::MAX)).map(|_| ()), Err(PlresError::BadData));
+    }
+}
@@ -0,0 +1,474 @@
+use core::fmt;
+use core::mem::{offset_of, size_of};
+
+#[cfg(feature = "alloc")]
+use alloc::{collections::BTreeMap, string::String, vec::Vec};
+
+use crate::bytes::{self, read_cstr, read_u16, read_u32, read_u64, write_u16, write_u32, write_u64};
+#[cfg(feature = "alloc")]
+use crate::plam::align_up;
+
+pub const PLSTAT_MAGIC: [u8; 4] = *b"PLST";
+pub const PLSTAT_VERSION_MAJOR: u16 = 1;
+pub const PLSTAT_VERSION: u16 = PLSTAT_VERSION_MAJOR << 8;
+
+/// Member data is aligned to this many bytes inside the archive.
+pub const MEMBER_ALIGN: u64 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlstatError {
+    TooShort,
+    BadMagic([u8; 4]),
+    UnsupportedVersion(u16),
+    SizeMismatch { header: u64, actual: u64 },
+    OutOfBounds(&'static str),
+    BadString,
+    BadSymbolIndex,
+}
+
+impl fmt::Display for PlstatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlstatError::TooShort => write!(f, "buffer is shorter than the PLSTAT header"),
+            PlstatError::BadMagic(m) => write!(f, "bad magic {:02x?}, expected \"PLST\"", m),
+            PlstatError::UnsupportedVersion(v) => {
+                write!(f, "unsupported version {}.{} (expected {}.x)", v >> 8, v & 0xFF, PLSTAT_VERSION_MAJOR)
+            }
+            PlstatError::SizeMismatch { header, actual } => {
+                write!(f, "header describes {} bytes but the file is {} bytes", header, actual)
+            }
+            PlstatError::OutOfBounds(what) => write!(f, "{} out of bounds", what),
+            PlstatError::BadString => write!(f, "string table entry is not NUL-terminated UTF-8"),
+            PlstatError::BadSymbolIndex => write!(f, "symbol index is unsorted or names a missing member"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for PlstatError {}
+
+/// Header of a `.plstat` static archive: a list of object members plus an
+/// index from every global symbol they define to the defining member, so a
+/// linker only pulls in the members it needs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct PlstatHeader {
+    pub magic: [u8; 4],
+    pub version: u16,
+    pub reserved: u16,
+    pub member_count: u32,
+    pub symbol_count: u32,
+    pub member_table_offset: u64,
+    pub symbol_table_offset: u64,
+    pub string_table_offset: u64,
+    pub string_table_size: u64,
+    pub file_size: u64,
+}
+
+pub const PLSTAT_HEADER_SIZE: usize = size_of::<PlstatHeader>();
+
+const _: () = assert!(PLSTAT_HEADER_SIZE == 0x38);
+
+impl PlstatHeader {
+    pub fn parse(buf: &[u8]) -> Result<Self, PlstatError> {
+        if buf.len() < PLSTAT_HEADER_SIZE {
+            return Err(PlstatError::TooShort);
+        }
+
+        let magic: [u8; 4] = buf[0x00..0x04].try_into().unwrap();
+        if magic != PLSTAT_MAGIC {
+            return Err(PlstatError::BadMagic(magic));
+        }
+
+        let header = PlstatHeader {
+            magic,
+            version: read_u16(buf, offset_of!(PlstatHeader, version)),
+            reserved: read_u16(buf, offset_of!(PlstatHeader, reserved)),
+            member_count: read_u32(buf, offset_of!(PlstatHeader, member_count)),
+            symbol_count: read_u32(buf, offset_of!(PlstatHeader, symbol_count)),
+            member_table_offset: read_u64(buf, offset_of!(PlstatHeader, member_table_offset)),
+            symbol_table_offset: read_u64(buf, offset_of!(PlstatHeader, symbol_table_offset)),
+            string_table_offset: read_u64(buf, offset_of!(PlstatHeader, string_table_offset)),
+            string_table_size: read_u64(buf, offset_of!(PlstatHeader, string_table_size)),
+            file_size: read_u64(buf, offset_of!(PlstatHeader, file_size)),
+        };
+
+        if header.version >> 8 != PLSTAT_VERSION_MAJOR {
+            return Err(PlstatError::UnsupportedVersion(header.version));
+        }
+
+        Ok(header)
+    }
+
+    pub fn write(&self, buf: &mut [u8]) -> Result<(), PlstatError> {
+        if buf.len() < PLSTAT_HEADER_SIZE {
+            return Err(PlstatError::TooShort);
+        }
+
+        buf[0x00..0x04].copy_from_slice(&self.magic);
+        write_u16(buf, offset_of!(PlstatHeader, version), self.version);
+        write_u16(buf, offset_of!(PlstatHeader, reserved), self.reserved);
+        write_u32(buf, offset_of!(PlstatHeader, member_count), self.member_count);
+        write_u32(buf, offset_of!(PlstatHeader, symbol_count), self.symbol_count);
+        write_u64(buf, offset_of!(PlstatHeader, member_table_offset), self.member_table_offset);
+        write_u64(buf, offset_of!(PlstatHeader, symbol_table_offset), self.symbol_table_offset);
+        write_u64(buf, offset_of!(PlstatHeader, string_table_offset), self.string_table_offset);
+        write_u64(buf, offset_of!(PlstatHeader, string_table_size), self.string_table_size);
+        write_u64(buf, offset_of!(PlstatHeader, file_size), self.file_size);
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct PlstatMember {
+    pub name: u32,
+    pub reserved: u32,
+    pub mtime: u64,
+    pub offset: u64,
+    pub size: u64,
+}
+
+pub const MEMBER_ENTRY_SIZE: usize = size_of::<PlstatMember>();
+
+const _: () = assert!(MEMBER_ENTRY_SIZE == 32);
+
+impl PlstatMember {
+    pub fn parse(entry: &[u8]) -> Self {
+        PlstatMember {
+            name: read_u32(entry, offset_of!(PlstatMember, name)),
+            reserved: read_u32(entry, offset_of!(PlstatMember, reserved)),
+            mtime: read_u64(entry, offset_of!(PlstatMember, mtime)),
+            offset: read_u64(entry, offset_of!(PlstatMember, offset)),
+            size: read_u64(entry, offset_of!(PlstatMember, size)),
+        }
+    }
+
+    pub fn write(&self, entry: &mut [u8]) {
+        write_u32(entry, offset_of!(PlstatMember, name), self.name);
+        write_u32(entry, offset_of!(PlstatMember, reserved), self.reserved);
+        write_u64(entry, offset_of!(PlstatMember, mtime), self.mtime);
+        write_u64(entry, offset_of!(PlstatMember, offset), self.offset);
+        write_u64(entry, offset_of!(PlstatMember, size), self.size);
+    }
+}
+
+/// Symbol index entry; the index is sorted by symbol name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct PlstatSymbol {
+    pub name: u32,
+    pub member: u32,
+}
+
+pub const SYMBOL_ENTRY_SIZE: usize = size_of::<PlstatSymbol>();
+
+const _: () = assert!(SYMBOL_ENTRY_SIZE == 8);
+
+impl PlstatSymbol {
+    pub fn parse(entry: &[u8]) -> Self {
+        PlstatSymbol {
+            name: read_u32(entry, offset_of!(PlstatSymbol, name)),
+            member: read_u32(entry, offset_of!(PlstatSymbol, member)),
+        }
+    }
+
+    pub fn write(&self, entry: &mut [u8]) {
+        write_u32(entry, offset_of!(PlstatSymbol, name), self.name);
+        write_u32(entry, offset_of!(PlstatSymbol, member), self.member);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Member<'a> {
+    pub name: &'a str,
+    pub mtime: u64,
+    pub data: &'a [u8],
+}
+
+/// A parsed `.plstat` file. Every table, string and member is checked by
+/// [`PlstatFile::parse`], so the accessors below cannot fail.
+#[derive(Debug, Clone, Copy)]
+pub struct PlstatFile<'a> {
+    pub header: PlstatHeader,
+    data: &'a [u8],
+}
+
+impl<'a> PlstatFile<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self, PlstatError> {
+        let header = PlstatHeader::parse(data)?;
+        if header.file_size != data.len() as u64 {
+            return Err(PlstatError::SizeMismatch {
+                header: header.file_size,
+                actual: data.len() as u64,
+            });
+        }
+
+        let file = PlstatFile { header, data };
+        file.table(header.member_table_offset, header.member_count as u64, MEMBER_ENTRY_SIZE, "member table")?;
+        file.table(header.symbol_table_offset, header.symbol_count as u64, SYMBOL_ENTRY_SIZE, "symbol index")?;
+        file.table(header.string_table_offset, header.string_table_size, 1, "string table")?;
+
+        for entry in file.member_entries() {
+            file.string(entry.name)?;
+            file.table(entry.offset, entry.size, 1, "member data")?;
+        }
+        let mut previous = None;
+        for entry in file.symbol_entries() {
+            let name = file.string(entry.name)?;
+            if entry.member >= header.member_count || previous.is_some_and(|p| p >= name) {
+                return Err(PlstatError::BadSymbolIndex);
+            }
+            previous = Some(name);
+        }
+
+        Ok(file)
+    }
+
+    fn table(&self, offset: u64, count: u64, entry_size: usize, what: &'static str) -> Result<&'a [u8], PlstatError> {
+        bytes::table(self.data, offset, count, entry_size, PLSTAT_HEADER_SIZE as u64, u64::MAX)
+            .ok_or(PlstatError::OutOfBounds(what))
+    }
+
+    pub fn string(&self, offset: u32) -> Result<&'a str, PlstatError> {
+        let strings = self.table(self.header.string_table_offset, self.header.string_table_size, 1, "string table")?;
+        read_cstr(strings, offset as usize).ok_or(PlstatError::BadString)
+    }
+
+    pub fn member_entries(&self) -> impl Iterator<Item = PlstatMember> + 'a {
+        let header = self.header;
+        self.table(header.member_table_offset, header.member_count as u64, MEMBER_ENTRY_SIZE, "member table")
+            .unwrap_or_default()
+            .chunks_exact(MEMBER_ENTRY_SIZE)
+            .map(PlstatMember::parse)
+    }
+
+    pub fn symbol_entries(&self) -> impl Iterator<Item = PlstatSymbol> + 'a {
+        let header = self.header;
+        self.table(header.symbol_table_offset, header.symbol_count as u64, SYMBOL_ENTRY_SIZE, "symbol index")
+            .unwrap_or_default()
+            .chunks_exact(SYMBOL_ENTRY_SIZE)
+            .map(PlstatSymbol::parse)
+    }
+
+    fn to_member(self, entry: PlstatMember) -> Member<'a> {
+        Member {
+            name: self.string(entry.name).unwrap_or_default(),
+            mtime: entry.mtime,
+            data: self.table(entry.offset, entry.size, 1, "member data").unwrap_or_default(),
+        }
+    }
+
+    pub fn members(&self) -> impl Iterator<Item = Member<'a>> + 'a {
+        let file = *self;
+        self.member_entries().map(move |entry| file.to_member(entry))
+    }
+
+    pub fn member(&self, index: u32) -> Option<Member<'a>> {
+        self.member_entries().nth(index as usize).map(|entry| self.to_member(entry))
+    }
+
+    /// `(symbol, member index)` pairs in name order.
+    pub fn symbols(&self) -> impl Iterator<Item = (&'a str, u32)> + 'a {
+        let file = *self;
+        self.symbol_entries()
+            .map(move |entry| (file.string(entry.name).unwrap_or_default(), entry.member))
+    }
+
+    /// Index of the member defining `symbol`, by binary search of the index.
+    pub fn find_symbol(&self, symbol: &str) -> Option<u32> {
+        let (mut low, mut high) = (0usize, self.header.symbol_count as usize);
+        while low < high {
+            let mid = (low + high) / 2;
+            let offset = self.header.symbol_table_offset as usize + mid * SYMBOL_ENTRY_SIZE;
+            let entry = PlstatSymbol::parse(&self.data[offset..]);
+            match self.string(entry.name).ok()?.cmp(symbol) {
+                core::cmp::Ordering::Less => low = mid + 1,
+                core::cmp::Ordering::Greater => high = mid,
+                core::cmp::Ordering::Equal => return Some(entry.member),
+            }
+        }
+        None
+    }
+}
+
+/// Assembles a `.plstat` file. When several members define the same symbol
+/// the index points at the first one, as with `ar`.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, Default)]
+pub struct PlstatBuilder {
+    members: Vec<(String, u64, Vec<u8>)>,
+    /// Member index of each symbol, in name order.
+    symbols: BTreeMap<String, u32>,
+}
+
+#[cfg(feature = "alloc")]
+impl PlstatBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a member defining `symbols` and returns its index.
+    pub fn add_member<'s>(
+        &mut self,
+        name: &str,
+        mtime: u64,
+        data: Vec<u8>,
+        symbols: impl IntoIterator<Item = &'s str>,
+    ) -> u32 {
+        let index = self.members.len() as u32;
+        self.members.push((String::from(name), mtime, data));
+        for symbol in symbols {
+            if !self.symbols.contains_key(symbol) {
+                self.symbols.insert(String::from(symbol), index);
+            }
+        }
+        index
+    }
+
+    pub fn member_count(&self) -> usize {
+        self.members.len()
+    }
+
+    pub fn build(&self) -> Vec<u8> {
+        let mut strings = Vec::from([0u8]);
+        let mut intern = |s: &str| {
+            let offset = strings.len() as u32;
+            strings.extend_from_slice(s.as_bytes());
+            strings.push(0);
+            offset
+        };
+
+        let symbols: Vec<PlstatSymbol> = self
+            .symbols
+            .iter()
+            .map(|(name, member)| PlstatSymbol {
+                name: intern(name),
+                member: *member,
+            })
+            .collect();
+        let names: Vec<u32> = self.members.iter().map(|(name, _, _)| intern(name)).collect();
+
+        let member_table_offset = PLSTAT_HEADER_SIZE as u64;
+        let symbol_table_offset = member_table_offset + (self.members.len() * MEMBER_ENTRY_SIZE) as u64;
+        let string_table_offset = symbol_table_offset + (symbols.len() * SYMBOL_ENTRY_SIZE) as u64;
+        let mut next_offset = string_table_offset + strings.len() as u64;
+
+        let mut members = Vec::with_capacity(self.members.len());
+        for ((_, mtime, data), name) in self.members.iter().zip(names) {
+            let offset = align_up(next_offset, MEMBER_ALIGN);
+            next_offset = offset + data.len() as u64;
+            members.push(PlstatMember {
+                name,
+                reserved: 0,
+                mtime: *mtime,
+                offset,
+                size: data.len() as u64,
+            });
+        }
+
+        let header = PlstatHeader {
+            magic: PLSTAT_MAGIC,
+            version: PLSTAT_VERSION,
+            reserved: 0,
+            member_count: members.len() as u32,
+            symbol_count: symbols.len() as u32,
+            member_table_offset,
+            symbol_table_offset,
+            string_table_offset,
+            string_table_size: strings.len() as u64,
+            file_size: next_offset,
+        };
+
+        let mut out = alloc::vec![0u8; next_offset as usize];
+        header.write(&mut out).unwrap();
+        for (i, member) in members.iter().enumerate() {
+            member.write(&mut out[member_table_offset as usize + i * MEMBER_ENTRY_SIZE..]);
+        }
+        for (i, symbol) in symbols.iter().enumerate() {
+            symbol.write(&mut out[symbol_table_offset as usize + i * SYMBOL_ENTRY_SIZE..]);
+        }
+        out[string_table_offset as usize..][..strings.len()].copy_from_slice(&strings);
+        for (member, (_, _, data)) in members.iter().zip(&self.members) {
+            out[member.offset as usize..][..data.len()].copy_from_slice(data);
+        }
+        out
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+
+    fn sample() -> Vec<u8> {
+        let mut builder = PlstatBuilder::new();
+        builder.add_member("str.pom", 100, alloc::vec![1, 2, 3], ["strlen", "strcpy", "memcpy"]);
+        builder.add_member("mem.pom", 200, alloc::vec![4; 9], ["memset", "memcpy"]);
+        builder.add_member("empty.pom", 300, Vec::new(), []);
+        builder.build()
+    }
+
+    #[test]
+    fn build_parse_round_trip() {
+        let data = sample();
+        let file = PlstatFile::parse(&data).unwrap();
+        assert_eq!(file.header.file_size, data.len() as u64);
+
+        let members: Vec<_> = file.members().collect();
+        assert_eq!(
+            members,
+            [
+                Member { name: "str.pom", mtime: 100, data: &[1, 2, 3] },
+                Member { name: "mem.pom", mtime: 200, data: &[4; 9] },
+                Member { name: "empty.pom", mtime: 300, data: &[] },
+            ]
+        );
+        for entry in file.member_entries() {
+            assert_eq!(entry.offset % MEMBER_ALIGN, 0);
+        }
+        assert_eq!(file.member(1), Some(members[1]));
+        assert_eq!(file.member(3), None);
+
+        let symbols: Vec<_> = file.symbols().collect();
+        assert_eq!(symbols, [("memcpy", 0), ("memset", 1), ("strcpy", 0), ("strlen", 0)]);
+    }
+
+    #[test]
+    fn find_symbol_takes_first_definition() {
+        let data = sample();
+        let file = PlstatFile::parse(&data).unwrap();
+        assert_eq!(file.find_symbol("strlen"), Some(0));
+        assert_eq!(file.find_symbol("memset"), Some(1));
+        // Both members define memcpy; the index keeps the first.
+        assert_eq!(file.find_symbol("memcpy"), Some(0));
+        assert_eq!(file.find_symbol("memmove"), None);
+        assert_eq!(file.find_symbol(""), None);
+
+        let empty = PlstatBuilder::new().build();
+        assert_eq!(PlstatFile::parse(&empty).unwrap().find_symbol("strlen"), None);
+    }
+
+    #[test]
+    fn parse_rejects_bad_index() {
+        let data = sample();
+        let header = PlstatFile::parse(&data).unwrap().header;
+        let symbol = |i: usize| header.symbol_table_offset as usize + i * SYMBOL_ENTRY_SIZE;
+
+        let mut bad_member = data.clone();
+        write_u32(&mut bad_member, symbol(0) + offset_of!(PlstatSymbol, member), header.member_count);
+        assert_eq!(PlstatFile::parse(&bad_member).map(|_| ()), Err(PlstatError::BadSymbolIndex));
+
+        // Swapping two names breaks the order find_symbol relies on.
+        let mut unsorted = data.clone();
+        let first = read_u32(&data, symbol(0) + offset_of!(PlstatSymbol, name));
+        let second = read_u32(&data, symbol(1) + offset_of!(PlstatSymbol, name));
+        write_u32(&mut unsorted, symbol(0) + offset_of!(PlstatSymbol, name), second);
+        write_u32(&mut unsorted, symbol(1) + offset_of!(PlstatSymbol, name), first);
+        assert_eq!(PlstatFile::parse(&unsorted).map(|_| ()), Err(PlstatError::BadSymbolIndex));
+
+        assert_eq!(
+            PlstatFile::parse(&data[..data.len() - 1]).map(|_| ()),
+            Err(PlstatError::SizeMismatch { header: data.len() as u64, actual: data.len() as u64 - 1 })
+        );
+    }
+}
@@ -0,0 +1,1764 @@
+use core::fmt;
+use core::mem::{offset_of, size_of};
+
+#[cfg(feature = "alloc")]
+use alloc::{string::String, vec::Vec};
+
+use crate::bytes::{self, read_cstr, read_u16, read_u32, read_u64, write_u16, write_u32, write_u64};
+#[cfg(feature = "alloc")]
+use crate::elf::{self, Elf, ElfError};
+#[cfg(feature = "alloc")]
+use crate::plam::align_up;
+use crate::plam::{arch_name, CPU_AARCH64, CPU_RISCV64, CPU_X86_64};
+
+pub const POM_MAGIC: [u8; 4] = *b"POM\0";
+pub const POM_VERSION_MAJOR: u16 = 1;
+pub const POM_VERSION: u16 = POM_VERSION_MAJOR << 8;
+
+/// Section data is aligned to this many bytes inside the object.
+pub const SECTION_DATA_ALIGN: u64 = 16;
+
+/// `section` of a symbol that is referenced but not defined here.
+pub const SECTION_UNDEF: u32 = u32::MAX;
+/// `section` of a symbol whose value is an absolute address.
+pub const SECTION_ABS: u32 = u32::MAX - 1;
+/// `section` of a common symbol: `size` bytes aligned to `value`, allocated
+/// by the linker unless another object defines the symbol.
+pub const SECTION_COMMON: u32 = u32::MAX - 2;
+
+/// `symbol` of a relocation against nothing: S is 0 and A the whole value.
+pub const NO_SYMBOL: u32 = u32::MAX;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PomError {
+    TooShort,
+    BadMagic([u8; 4]),
+    UnsupportedVersion(u16),
+    UnsupportedArch(u16),
+    SizeMismatch { header: u64, actual: u64 },
+    OutOfBounds(&'static str),
+    BadString,
+    BadSectionKind(u16),
+    BadSymbol(u32),
+    BadReloc(u32),
+    RelocOverflow(RelocKind),
+    RelocMisaligned(RelocKind),
+}
+
+impl fmt::Display for PomError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PomError::TooShort => write!(f, "buffer is shorter than the POM header"),
+            PomError::BadMagic(m) => write!(f, "bad magic {:02x?}, expected \"POM\\0\"", m),
+            PomError::UnsupportedVersion(v) => {
+                write!(f, "unsupported version {}.{} (expected {}.x)", v >> 8, v & 0xFF, POM_VERSION_MAJOR)
+            }
+            PomError::UnsupportedArch(cpu_id) => write!(f, "unsupported cpu_id 0x{:04x}", cpu_id),
+            PomError::SizeMismatch { header, actual } => {
+                write!(f, "header describes {} bytes but the file is {} bytes", header, actual)
+            }
+            PomError::OutOfBounds(what) => write!(f, "{} out of bounds", what),
+            PomError::BadString => write!(f, "string table entry is not NUL-terminated UTF-8"),
+            PomError::BadSectionKind(kind) => write!(f, "unknown section kind {}", kind),
+            PomError::BadSymbol(index) => write!(f, "symbol {} is malformed", index),
+            PomError::BadReloc(index) => write!(f, "relocation {} is malformed", index),
+            PomError::RelocOverflow(kind) => write!(f, "{} relocation overflows its field", kind.name()),
+            PomError::RelocMisaligned(kind) => write!(f, "{} relocation target is misaligned", kind.name()),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for PomError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u16)]
+pub enum SectionKind {
+    Text = 0,
+    ReadOnly = 1,
+    Data = 2,
+    /// Zero-initialized; occupies no space in the file.
+    Bss = 3,
+}
+
+impl SectionKind {
+    pub fn from_u16(value: u16) -> Option<Self> {
+        match value {
+            0 => Some(SectionKind::Text),
+            1 => Some(SectionKind::ReadOnly),
+            2 => Some(SectionKind::Data),
+            3 => Some(SectionKind::Bss),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SectionKind::Text => "text",
+            SectionKind::ReadOnly => "rodata",
+            SectionKind::Data => "data",
+            SectionKind::Bss => "bss",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum SymbolBinding {
+    Local = 0,
+    Global = 1,
+    Weak = 2,
+}
+
+impl SymbolBinding {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(SymbolBinding::Local),
+            1 => Some(SymbolBinding::Global),
+            2 => Some(SymbolBinding::Weak),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SymbolBinding::Local => "local",
+            SymbolBinding::Global => "global",
+            SymbolBinding::Weak => "weak",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum SymbolKind {
+    NoType = 0,
+    Func = 1,
+    Object = 2,
+    /// Names the start of a section; relocations against section contents
+    /// use it.
+    Section = 3,
+}
+
+impl SymbolKind {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(SymbolKind::NoType),
+            1 => Some(SymbolKind::Func),
+            2 => Some(SymbolKind::Object),
+            3 => Some(SymbolKind::Section),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SymbolKind::NoType => "notype",
+            SymbolKind::Func => "func",
+            SymbolKind::Object => "object",
+            SymbolKind::Section => "section",
+        }
+    }
+}
+
+/// What a relocation computes and which field it patches. S is the symbol
+/// address (or its GOT slot for the `Got` kinds), A the addend and P the
+/// address of the patched place. Kinds below 0x100 are plain data and valid
+/// for every architecture, including prum64; the rest patch instruction
+/// fields of one architecture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum RelocKind {
+    /// S + A, 64-bit.
+    Abs64 = 0x01,
+    /// S + A, 32-bit zero-extended.
+    Abs32 = 0x02,
+    /// S + A, 32-bit sign-extended.
+    Abs32S = 0x03,
+    /// S + A - P, 64-bit.
+    Pc64 = 0x04,
+    /// S + A - P, 32-bit signed.
+    Pc32 = 0x05,
+    /// Adds S + A to the 32-bit value in place.
+    Add32 = 0x06,
+    /// Subtracts S + A from the 32-bit value in place.
+    Sub32 = 0x07,
+    Add64 = 0x08,
+    Sub64 = 0x09,
+
+    /// Page(S + A) - Page(P) into the immediate of ADRP.
+    Aarch64AdrPage21 = 0x100,
+    /// S + A - P into the immediate of ADR.
+    Aarch64AdrLo21 = 0x101,
+    /// Low 12 bits of S + A into the immediate of ADD.
+    Aarch64AddLo12 = 0x102,
+    /// Low 12 bits of S + A, scaled by the access size, into LDR/STR.
+    Aarch64Ldst8Lo12 = 0x103,
+    Aarch64Ldst16Lo12 = 0x104,
+    Aarch64Ldst32Lo12 = 0x105,
+    Aarch64Ldst64Lo12 = 0x106,
+    Aarch64Ldst128Lo12 = 0x107,
+    /// S + A - P into B or BL.
+    Aarch64Branch26 = 0x108,
+    /// S + A - P into B.cond, CBZ or CBNZ.
+    Aarch64CondBr19 = 0x109,
+    /// S + A - P into TBZ or TBNZ.
+    Aarch64TstBr14 = 0x10A,
+    /// Page(G) - Page(P) into ADRP, G being the GOT slot of the symbol.
+    Aarch64GotPage21 = 0x10B,
+    /// Low 12 bits of G into a 64-bit LDR.
+    Aarch64GotLo12 = 0x10C,
+
+    /// S + A - P into a conditional branch.
+    RiscvBranch = 0x200,
+    /// S + A - P into JAL.
+    RiscvJal = 0x201,
+    /// S + A - P into an AUIPC + JALR pair.
+    RiscvCall = 0x202,
+    /// High 20 bits of S + A - P into AUIPC.
+    RiscvPcrelHi20 = 0x203,
+    /// Low 12 bits into an I-type instruction. The symbol labels the AUIPC
+    /// and the value is that of the `RiscvPcrelHi20` or `RiscvGotHi20`
+    /// relocation there.
+    RiscvPcrelLo12I = 0x204,
+    /// Like `RiscvPcrelLo12I` for an S-type (store) instruction.
+    RiscvPcrelLo12S = 0x205,
+    /// High 20 bits of S + A into LUI.
+    RiscvHi20 = 0x206,
+    /// Low 12 bits of S + A into an I-type instruction.
+    RiscvLo12I = 0x207,
+    /// Low 12 bits of S + A into an S-type instruction.
+    RiscvLo12S = 0x208,
+    /// High 20 bits of G - P into AUIPC, G being the GOT slot of the symbol.
+    RiscvGotHi20 = 0x209,
+
+    /// G + A - P, 32-bit signed, G being the GOT slot of the symbol.
+    X86GotPc32 = 0x300,
+}
+
+impl RelocKind {
+    pub const ALL: [RelocKind; 33] = [
+        RelocKind::Abs64,
+        RelocKind::Abs32,
+        RelocKind::Abs32S,
+        RelocKind::Pc64,
+        RelocKind::Pc32,
+        RelocKind::Add32,
+        RelocKind::Sub32,
+        RelocKind::Add64,
+        RelocKind::Sub64,
+        RelocKind::Aarch64AdrPage21,
+        RelocKind::Aarch64AdrLo21,
+        RelocKind::Aarch64AddLo12,
+        RelocKind::Aarch64Ldst8Lo12,
+        RelocKind::Aarch64Ldst16Lo12,
+        RelocKind::Aarch64Ldst32Lo12,
+        RelocKind::Aarch64Ldst64Lo12,
+        RelocKind::Aarch64Ldst128Lo12,
+        RelocKind::Aarch64Branch26,
+        RelocKind::Aarch64CondBr19,
+        RelocKind::Aarch64TstBr14,
+        RelocKind::Aarch64GotPage21,
+        RelocKind::Aarch64GotLo12,
+        RelocKind::RiscvBranch,
+        RelocKind::RiscvJal,
+        RelocKind::RiscvCall,
+        RelocKind::RiscvPcrelHi20,
+        RelocKind::RiscvPcrelLo12I,
+        RelocKind::RiscvPcrelLo12S,
+        RelocKind::RiscvHi20,
+        RelocKind::RiscvLo12I,
+        RelocKind::RiscvLo12S,
+        RelocKind::RiscvGotHi20,
+        RelocKind::X86GotPc32,
+    ];
+
+    pub fn from_u32(value: u32) -> Option<Self> {
+        Self::ALL.into_iter().find(|k| *k as u32 == value)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RelocKind::Abs64 => "ABS64",
+            RelocKind::Abs32 => "ABS32",
+            RelocKind::Abs32S => "ABS32S",
+            RelocKind::Pc64 => "PC64",
+            RelocKind::Pc32 => "PC32",
+            RelocKind::Add32 => "ADD32",
+            RelocKind::Sub32 => "SUB32",
+            RelocKind::Add64 => "ADD64",
+            RelocKind::Sub64 => "SUB64",
+            RelocKind::Aarch64AdrPage21 => "AARCH64_ADR_PAGE21",
+            RelocKind::Aarch64AdrLo21 => "AARCH64_ADR_LO21",
+            RelocKind::Aarch64AddLo12 => "AARCH64_ADD_LO12",
+            RelocKind::Aarch64Ldst8Lo12 => "AARCH64_LDST8_LO12",
+            RelocKind::Aarch64Ldst16Lo12 => "AARCH64_LDST16_LO12",
+            RelocKind::Aarch64Ldst32Lo12 => "AARCH64_LDST32_LO12",
+            RelocKind::Aarch64Ldst64Lo12 => "AARCH64_LDST64_LO12",
+            RelocKind::Aarch64Ldst128Lo12 => "AARCH64_LDST128_LO12",
+            RelocKind::Aarch64Branch26 => "AARCH64_BRANCH26",
+            RelocKind::Aarch64CondBr19 => "AARCH64_CONDBR19",
+            RelocKind::Aarch64TstBr14 => "AARCH64_TSTBR14",
+            RelocKind::Aarch64GotPage21 => "AARCH64_GOT_PAGE21",
+            RelocKind::Aarch64GotLo12 => "AARCH64_GOT_LO12",
+            RelocKind::RiscvBranch => "RISCV_BRANCH",
+            RelocKind::RiscvJal => "RISCV_JAL",
+            RelocKind::RiscvCall => "RISCV_CALL",
+            RelocKind::RiscvPcrelHi20 => "RISCV_PCREL_HI20",
+            RelocKind::RiscvPcrelLo12I => "RISCV_PCREL_LO12_I",
+            RelocKind::RiscvPcrelLo12S => "RISCV_PCREL_LO12_S",
+            RelocKind::RiscvHi20 => "RISCV_HI20",
+            RelocKind::RiscvLo12I => "RISCV_LO12_I",
+            RelocKind::RiscvLo12S => "RISCV_LO12_S",
+            RelocKind::RiscvGotHi20 => "RISCV_GOT_HI20",
+            RelocKind::X86GotPc32 => "X86_GOTPC32",
+        }
+    }
+
+    /// The architecture this kind belongs to, `None` for data relocations.
+    pub fn cpu_id(self) -> Option<u16> {
+        match self as u32 >> 8 {
+            0 => None,
+            1 => Some(CPU_AARCH64),
+            2 => Some(CPU_RISCV64),
+            _ => Some(CPU_X86_64),
+        }
+    }
+
+    pub fn valid_for(self, cpu_id: u16) -> bool {
+        self.cpu_id().is_none_or(|id| id == cpu_id)
+    }
+
+    /// Bytes patched at the relocation offset.
+    pub fn size(self) -> usize {
+        match self {
+            RelocKind::Abs64 | RelocKind::Pc64 | RelocKind::Add64 | RelocKind::Sub64 | RelocKind::RiscvCall => 8,
+            _ => 4,
+        }
+    }
+
+    /// Whether S is the address of a GOT slot holding the symbol address.
+    pub fn uses_got(self) -> bool {
+        matches!(
+            self,
+            RelocKind::Aarch64GotPage21 | RelocKind::Aarch64GotLo12 | RelocKind::RiscvGotHi20 | RelocKind::X86GotPc32
+        )
+    }
+
+    /// Whether the relocation is meaningless against [`NO_SYMBOL`]: GOT
+    /// relocations need a symbol to give a slot, and `RiscvPcrelLo12*` name
+    /// the label of their high-part relocation.
+    pub fn needs_symbol(self) -> bool {
+        self.uses_got() || matches!(self, RelocKind::RiscvPcrelLo12I | RelocKind::RiscvPcrelLo12S)
+    }
+
+    /// The value [`RelocKind::apply`] writes. For `RiscvPcrelLo12*` pass the
+    /// value of the matching high-part relocation instead.
+    pub fn value(self, s: u64, a: i64, p: u64) -> i64 {
+        let target = s.wrapping_add(a as u64);
+        match self {
+            RelocKind::Abs64
+            | RelocKind::Abs32
+            | RelocKind::Abs32S
+            | RelocKind::Add32
+            | RelocKind::Sub32
+            | RelocKind::Add64
+            | RelocKind::Sub64
+            | RelocKind::Aarch64AddLo12
+            | RelocKind::Aarch64Ldst8Lo12
+            | RelocKind::Aarch64Ldst16Lo12
+            | RelocKind::Aarch64Ldst32Lo12
+            | RelocKind::Aarch64Ldst64Lo12
+            | RelocKind::Aarch64Ldst128Lo12
+            | RelocKind::Aarch64GotLo12
+            | RelocKind::RiscvHi20
+            | RelocKind::RiscvLo12I
+            | RelocKind::RiscvLo12S => target as i64,
+            RelocKind::Aarch64AdrPage21 | RelocKind::Aarch64GotPage21 => {
+                (target & !0xFFF).wrapping_sub(p & !0xFFF) as i64
+            }
+            _ => target.wrapping_sub(p) as i64,
+        }
+    }
+
+    /// Encodes `value` into `place`, which starts at the relocation offset.
+    pub fn apply(self, place: &mut [u8], value: i64) -> Result<(), PomError> {
+        if place.len() < self.size() {
+            return Err(PomError::OutOfBounds("relocation target"));
+        }
+        let overflow = Err(PomError::RelocOverflow(self));
+        let misaligned = Err(PomError::RelocMisaligned(self));
+        let insn = read_u32(place, 0);
+
+        match self {
+            RelocKind::Abs64 | RelocKind::Pc64 => write_u64(place, 0, value as u64),
+            RelocKind::Add64 => write_u64(place, 0, read_u64(place, 0).wrapping_add(value as u64)),
+            RelocKind::Sub64 => write_u64(place, 0, read_u64(place, 0).wrapping_sub(value as u64)),
+            RelocKind::Add32 => write_u32(place, 0, insn.wrapping_add(value as u32)),
+            RelocKind::Sub32 => write_u32(place, 0, insn.wrapping_sub(value as u32)),
+            RelocKind::Abs32 => {
+                if !(0..=u32::MAX as i64).contains(&value) {
+                    return overflow;
+                }
+                write_u32(place, 0, value as u32);
+            }
+            RelocKind::Abs32S | RelocKind::Pc32 | RelocKind::X86GotPc32 => {
+                if !fits_signed(value, 32) {
+                    return overflow;
+                }
+                write_u32(place, 0, value as u32);
+            }
+
+            RelocKind::Aarch64AdrPage21 | RelocKind::Aarch64GotPage21 | RelocKind::Aarch64AdrLo21 => {
+                let imm = if self == RelocKind::Aarch64AdrLo21 { value } else { value >> 12 };
+                if !fits_signed(imm, 21) {
+                    return overflow;
+                }
+                let imm = imm as u32;
+                let immlo = (imm & 0x3) << 29;
+                let immhi = ((imm >> 2) & 0x7FFFF) << 5;
+                write_u32(place, 0, (insn & 0x9F00001F) | immlo | immhi);
+            }
+            RelocKind::Aarch64AddLo12
+            | RelocKind::Aarch64Ldst8Lo12
+            | RelocKind::Aarch64Ldst16Lo12
+            | RelocKind::Aarch64Ldst32Lo12
+            | RelocKind::Aarch64Ldst64Lo12
+            | RelocKind::Aarch64Ldst128Lo12
+            | RelocKind::Aarch64GotLo12 => {
+                let shift = match self {
+                    RelocKind::Aarch64Ldst16Lo12 => 1,
+                    RelocKind::Aarch64Ldst32Lo12 => 2,
+                    RelocKind::Aarch64Ldst64Lo12 | RelocKind::Aarch64GotLo12 => 3,
+                    RelocKind::Aarch64Ldst128Lo12 => 4,
+                    _ => 0,
+                };
+                let lo12 = value as u32 & 0xFFF;
+                if lo12 & ((1 << shift) - 1) != 0 {
+                    return misaligned;
+                }
+                write_u32(place, 0, (insn & !(0xFFF << 10)) | ((lo12 >> shift) << 10));
+            }
+            RelocKind::Aarch64Branch26 | RelocKind::Aarch64CondBr19 | RelocKind::Aarch64TstBr14 => {
+                let (bits, shift) = match self {
+                    RelocKind::Aarch64Branch26 => (26, 0),
+                    RelocKind::Aarch64CondBr19 => (19, 5),
+                    _ => (14, 5),
+                };
+                if value & 0x3 != 0 {
+                    return misaligned;
+                }
+                if !fits_signed(value, bits + 2) {
+                    return overflow;
+                }
+                let mask = ((1u32 << bits) - 1) << shift;
+                let imm = ((value >> 2) as u32) << shift & mask;
+                write_u32(place, 0, (insn & !mask) | imm);
+            }
+
+            RelocKind::RiscvBranch => {
+                if value & 0x1 != 0 {
+                    return misaligned;
+                }
+                if !fits_signed(value, 13) {
+                    return overflow;
+                }
+                let imm = value as u32;
+                let encoded = ((imm >> 12) & 0x1) << 31
+                    | ((imm >> 5) & 0x3F) << 25
+                    | ((imm >> 1) & 0xF) << 8
+                    | ((imm >> 11) & 0x1) << 7;
+                write_u32(place, 0, (insn & 0x01FFF07F) | encoded);
+            }
+            RelocKind::RiscvJal => {
+                if value & 0x1 != 0 {
+                    return misaligned;
+                }
+                if !fits_signed(value, 21) {
+                    return overflow;
+                }
+                let imm = value as u32;
+                let encoded = ((imm >> 20) & 0x1) << 31
+                    | ((imm >> 1) & 0x3FF) << 21
+                    | ((imm >> 11) & 0x1) << 20
+                    | ((imm >> 12) & 0xFF) << 12;
+                write_u32(place, 0, (insn & 0xFFF) | encoded);
+            }
+            RelocKind::RiscvPcrelHi20 | RelocKind::RiscvHi20 | RelocKind::RiscvGotHi20 => {
+                let hi = riscv_hi20(value).ok_or(PomError::RelocOverflow(self))?;
+                write_u32(place, 0, (insn & 0xFFF) | hi);
+            }
+            RelocKind::RiscvPcrelLo12I | RelocKind::RiscvLo12I => {
+                write_u32(place, 0, (insn & 0xFFFFF) | (value as u32 & 0xFFF) << 20);
+            }
+            RelocKind::RiscvPcrelLo12S | RelocKind::RiscvLo12S => {
+                let imm = value as u32 & 0xFFF;
+                write_u32(place, 0, (insn & 0x01FFF07F) | (imm >> 5) << 25 | (imm & 0x1F) << 7);
+            }
+            RelocKind::RiscvCall => {
+                let hi = riscv_hi20(value).ok_or(PomError::RelocOverflow(self))?;
+                write_u32(place, 0, (insn & 0xFFF) | hi);
+                let jalr = read_u32(place, 4);
+                write_u32(place, 4, (jalr & 0xFFFFF) | (value as u32 & 0xFFF) << 20);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn fits_signed(value: i64, bits: u32) -> bool {
+    let limit = 1i64 << (bits - 1);
+    (-limit..limit).contains(&value)
+}
+
+/// The U-type immediate for `value`, rounded so that adding the sign-extended
+/// low 12 bits gives `value` back.
+fn riscv_hi20(value: i64) -> Option<u32> {
+    let hi = value.checked_add(0x800)? >> 12;
+    fits_signed(hi, 20).then_some((hi as u32) << 12)
+}
+
+/// Header of a `.pom` relocatable object, the input of the PlumOS linker:
+/// sections with their contents, a symbol table and relocations against it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct PomHeader {
+    pub magic: [u8; 4],
+    pub version: u16,
+    pub cpu_id: u16,
+    pub section_count: u32,
+    pub symbol_count: u32,
+    pub reloc_count: u32,
+    pub reserved: u32,
+    pub section_table_offset: u64,
+    pub symbol_table_offset: u64,
+    pub reloc_table_offset: u64,
+    pub string_table_offset: u64,
+    pub string_table_size: u64,
+    pub file_size: u64,
+}
+
+pub const POM_HEADER_SIZE: usize = size_of::<PomHeader>();
+
+const _: () = assert!(POM_HEADER_SIZE == 0x48);
+
+impl PomHeader {
+    pub fn parse(buf: &[u8]) -> Result<Self, PomError> {
+        if buf.len() < POM_HEADER_SIZE {
+            return Err(PomError::TooShort);
+        }
+
+        let magic: [u8; 4] = buf[0x00..0x04].try_into().unwrap();
+        if magic != POM_MAGIC {
+            return Err(PomError::BadMagic(magic));
+        }
+
+        let header = PomHeader {
+            magic,
+            version: read_u16(buf, offset_of!(PomHeader, version)),
+            cpu_id: read_u16(buf, offset_of!(PomHeader, cpu_id)),
+            section_count: read_u32(buf, offset_of!(PomHeader, section_count)),
+            symbol_count: read_u32(buf, offset_of!(PomHeader, symbol_count)),
+            reloc_count: read_u32(buf, offset_of!(PomHeader, reloc_count)),
+            reserved: read_u32(buf, offset_of!(PomHeader, reserved)),
+            section_table_offset: read_u64(buf, offset_of!(PomHeader, section_table_offset)),
+            symbol_table_offset: read_u64(buf, offset_of!(PomHeader, symbol_table_offset)),
+            reloc_table_offset: read_u64(buf, offset_of!(PomHeader, reloc_table_offset)),
+            string_table_offset: read_u64(buf, offset_of!(PomHeader, string_table_offset)),
+            string_table_size: read_u64(buf, offset_of!(PomHeader, string_table_size)),
+            file_size: read_u64(buf, offset_of!(PomHeader, file_size)),
+        };
+
+        if header.version >> 8 != POM_VERSION_MAJOR {
+            return Err(PomError::UnsupportedVersion(header.version));
+        }
+        if arch_name(header.cpu_id).is_none() {
+            return Err(PomError::UnsupportedArch(header.cpu_id));
+        }
+
+        Ok(header)
+    }
+
+    pub fn write(&self, buf: &mut [u8]) -> Result<(), PomError> {
+        if buf.len() < POM_HEADER_SIZE {
+            return Err(PomError::TooShort);
+        }
+
+        buf[0x00..0x04].copy_from_slice(&self.magic);
+        write_u16(buf, offset_of!(PomHeader, version), self.version);
+        write_u16(buf, offset_of!(PomHeader, cpu_id), self.cpu_id);
+        write_u32(buf, offset_of!(PomHeader, section_count), self.section_count);
+        write_u32(buf, offset_of!(PomHeader, symbol_count), self.symbol_count);
+        write_u32(buf, offset_of!(PomHeader, reloc_count), self.reloc_count);
+        write_u32(buf, offset_of!(PomHeader, reserved), self.reserved);
+        write_u64(buf, offset_of!(PomHeader, section_table_offset), self.section_table_offset);
+        write_u64(buf, offset_of!(PomHeader, symbol_table_offset), self.symbol_table_offset);
+        write_u64(buf, offset_of!(PomHeader, reloc_table_offset), self.reloc_table_offset);
+        write_u64(buf, offset_of!(PomHeader, string_table_offset), self.string_table_offset);
+        write_u64(buf, offset_of!(PomHeader, string_table_size), self.string_table_size);
+        write_u64(buf, offset_of!(PomHeader, file_size), self.file_size);
+        Ok(())
+    }
+}
+
+/// Section table entry. `offset` is zero for [`SectionKind::Bss`], whose
+/// `size` bytes are not stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct PomSectionEntry {
+    pub name: u32,
+    pub kind: u16,
+    pub reserved: u16,
+    pub align: u64,
+    pub offset: u64,
+    pub size: u64,
+}
+
+pub const SECTION_ENTRY_SIZE: usize = size_of::<PomSectionEntry>();
+
+const _: () = assert!(SECTION_ENTRY_SIZE == 32);
+
+impl PomSectionEntry {
+    pub fn parse(entry: &[u8]) -> Self {
+        PomSectionEntry {
+            name: read_u32(entry, offset_of!(PomSectionEntry, name)),
+            kind: read_u16(entry, offset_of!(PomSectionEntry, kind)),
+            reserved: read_u16(entry, offset_of!(PomSectionEntry, reserved)),
+            align: read_u64(entry, offset_of!(PomSectionEntry, align)),
+            offset: read_u64(entry, offset_of!(PomSectionEntry, offset)),
+            size: read_u64(entry, offset_of!(PomSectionEntry, size)),
+        }
+    }
+
+    pub fn write(&self, entry: &mut [u8]) {
+        write_u32(entry, offset_of!(PomSectionEntry, name), self.name);
+        write_u16(entry, offset_of!(PomSectionEntry, kind), self.kind);
+        write_u16(entry, offset_of!(PomSectionEntry, reserved), self.reserved);
+        write_u64(entry, offset_of!(PomSectionEntry, align), self.align);
+        write_u64(entry, offset_of!(PomSectionEntry, offset), self.offset);
+        write_u64(entry, offset_of!(PomSectionEntry, size), self.size);
+    }
+}
+
+/// Symbol table entry. `value` is an offset into `section`, an address for
+/// [`SECTION_ABS`] and the alignment for [`SECTION_COMMON`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct PomSymbolEntry {
+    pub name: u32,
+    pub section: u32,
+    pub binding: u8,
+    pub kind: u8,
+    pub reserved: u16,
+    pub reserved2: u32,
+    pub value: u64,
+    pub size: u64,
+}
+
+pub const SYMBOL_ENTRY_SIZE: usize = size_of::<PomSymbolEntry>();
+
+const _: () = assert!(SYMBOL_ENTRY_SIZE == 32);
+
+impl PomSymbolEntry {
+    pub fn parse(entry: &[u8]) -> Self {
+        PomSymbolEntry {
+            name: read_u32(entry, offset_of!(PomSymbolEntry, name)),
+            section: read_u32(entry, offset_of!(PomSymbolEntry, section)),
+            binding: entry[offset_of!(PomSymbolEntry, binding)],
+            kind: entry[offset_of!(PomSymbolEntry, kind)],
+            reserved: read_u16(entry, offset_of!(PomSymbolEntry, reserved)),
+            reserved2: read_u32(entry, offset_of!(PomSymbolEntry, reserved2)),
+            value: read_u64(entry, offset_of!(PomSymbolEntry, value)),
+            size: read_u64(entry, offset_of!(PomSymbolEntry, size)),
+        }
+    }
+
+    pub fn write(&self, entry: &mut [u8]) {
+        write_u32(entry, offset_of!(PomSymbolEntry, name), self.name);
+        write_u32(entry, offset_of!(PomSymbolEntry, section), self.section);
+        entry[offset_of!(PomSymbolEntry, binding)] = self.binding;
+        entry[offset_of!(PomSymbolEntry, kind)] = self.kind;
+        write_u16(entry, offset_of!(PomSymbolEntry, reserved), self.reserved);
+        write_u32(entry, offset_of!(PomSymbolEntry, reserved2), self.reserved2);
+        write_u64(entry, offset_of!(PomSymbolEntry, value), self.value);
+        write_u64(entry, offset_of!(PomSymbolEntry, size), self.size);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct PomRelocEntry {
+    pub section: u32,
+    pub symbol: u32,
+    pub offset: u64,
+    pub kind: u32,
+    pub reserved: u32,
+    pub addend: i64,
+}
+
+pub const RELOC_ENTRY_SIZE: usize = size_of::<PomRelocEntry>();
+
+const _: () = assert!(RELOC_ENTRY_SIZE == 32);
+
+impl PomRelocEntry {
+    pub fn parse(entry: &[u8]) -> Self {
+        PomRelocEntry {
+            section: read_u32(entry, offset_of!(PomRelocEntry, section)),
+            symbol: read_u32(entry, offset_of!(PomRelocEntry, symbol)),
+            offset: read_u64(entry, offset_of!(PomRelocEntry, offset)),
+            kind: read_u32(entry, offset_of!(PomRelocEntry, kind)),
+            reserved: read_u32(entry, offset_of!(PomRelocEntry, reserved)),
+            addend: read_u64(entry, offset_of!(PomRelocEntry, addend)) as i64,
+        }
+    }
+
+    pub fn write(&self, entry: &mut [u8]) {
+        write_u32(entry, offset_of!(PomRelocEntry, section), self.section);
+        write_u32(entry, offset_of!(PomRelocEntry, symbol), self.symbol);
+        write_u64(entry, offset_of!(PomRelocEntry, offset), self.offset);
+        write_u32(entry, offset_of!(PomRelocEntry, kind), self.kind);
+        write_u32(entry, offset_of!(PomRelocEntry, reserved), self.reserved);
+        write_u64(entry, offset_of!(PomRelocEntry, addend), self.addend as u64);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Section<'a> {
+    pub name: &'a str,
+    pub kind: SectionKind,
+    pub align: u64,
+    pub size: u64,
+    /// Empty for [`SectionKind::Bss`].
+    pub data: &'a [u8],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Symbol<'a> {
+    pub name: &'a str,
+    pub section: u32,
+    pub binding: SymbolBinding,
+    pub kind: SymbolKind,
+    pub value: u64,
+    pub size: u64,
+}
+
+impl Symbol<'_> {
+    pub fn is_defined(&self) -> bool {
+        self.section != SECTION_UNDEF && self.section != SECTION_COMMON
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reloc {
+    pub section: u32,
+    pub offset: u64,
+    pub kind: RelocKind,
+    pub symbol: u32,
+    pub addend: i64,
+}
+
+/// A parsed `.pom` file. Every table, string, section index, symbol index
+/// and relocation is checked by [`PomFile::parse`], so the accessors below
+/// cannot fail.
+#[derive(Debug, Clone, Copy)]
+pub struct PomFile<'a> {
+    pub header: PomHeader,
+    data: &'a [u8],
+}
+
+impl<'a> PomFile<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self, PomError> {
+        let header = PomHeader::parse(data)?;
+        if header.file_size != data.len() as u64 {
+            return Err(PomError::SizeMismatch {
+                header: header.file_size,
+                actual: data.len() as u64,
+            });
+        }
+
+        let file = PomFile { header, data };
+        file.table(header.section_table_offset, header.section_count as u64, SECTION_ENTRY_SIZE, "section table")?;
+        file.table(header.symbol_table_offset, header.symbol_count as u64, SYMBOL_ENTRY_SIZE, "symbol table")?;
+        file.table(header.reloc_table_offset, header.reloc_count as u64, RELOC_ENTRY_SIZE, "relocation table")?;
+        file.table(header.string_table_offset, header.string_table_size, 1, "string table")?;
+
+        for entry in file.section_entries() {
+            file.string(entry.name)?;
+            let kind = SectionKind::from_u16(entry.kind).ok_or(PomError::BadSectionKind(entry.kind))?;
+            if !entry.align.is_power_of_two() {
+                return Err(PomError::OutOfBounds("section alignment"));
+            }
+            if kind != SectionKind::Bss {
+                file.table(entry.offset, entry.size, 1, "section data")?;
+            }
+        }
+
+        let section_count = header.section_count;
+        for (index, entry) in file.symbol_entries().enumerate() {
+            file.string(entry.name)?;
+            let in_section = entry.section < section_count
+                || matches!(entry.section, SECTION_UNDEF | SECTION_ABS | SECTION_COMMON);
+            if !in_section
+                || SymbolBinding::from_u8(entry.binding).is_none()
+                || SymbolKind::from_u8(entry.kind).is_none()
+            {
+                return Err(PomError::BadSymbol(index as u32));
+            }
+        }
+
+        for (index, entry) in file.reloc_entries().enumerate() {
+            let bad = PomError::BadReloc(index as u32);
+            let kind = RelocKind::from_u32(entry.kind).ok_or(bad)?;
+            if !kind.valid_for(header.cpu_id) {
+                return Err(bad);
+            }
+            let has_symbol = entry.symbol != NO_SYMBOL;
+            if (has_symbol && entry.symbol >= header.symbol_count) || (!has_symbol && kind.needs_symbol()) {
+                return Err(bad);
+            }
+            let section = file.section_entry(entry.section).ok_or(bad)?;
+            let end = entry.offset.checked_add(kind.size() as u64).ok_or(bad)?;
+            if section.kind == SectionKind::Bss as u16 || end > section.size {
+                return Err(bad);
+            }
+        }
+
+        Ok(file)
+    }
+
+    fn table(&self, offset: u64, count: u64, entry_size: usize, what: &'static str) -> Result<&'a [u8], PomError> {
+        bytes::table(self.data, offset, count, entry_size, POM_HEADER_SIZE as u64, u64::MAX)
+            .ok_or(PomError::OutOfBounds(what))
+    }
+
+    pub fn string(&self, offset: u32) -> Result<&'a str, PomError> {
+        let strings = self.table(self.header.string_table_offset, self.header.string_table_size, 1, "string table")?;
+        read_cstr(strings, offset as usize).ok_or(PomError::BadString)
+    }
+
+    pub fn cpu_id(&self) -> u16 {
+        self.header.cpu_id
+    }
+
+    pub fn section_entries(&self) -> impl Iterator<Item = PomSectionEntry> + 'a {
+        let header = self.header;
+        self.table(header.section_table_offset, header.section_count as u64, SECTION_ENTRY_SIZE, "section table")
+            .unwrap_or_default()
+            .chunks_exact(SECTION_ENTRY_SIZE)
+            .map(PomSectionEntry::parse)
+    }
+
+    fn section_entry(&self, index: u32) -> Option<PomSectionEntry> {
+        if index >= self.header.section_count {
+            return None;
+        }
+        let offset = self.header.section_table_offset as usize + index as usize * SECTION_ENTRY_SIZE;
+        Some(PomSectionEntry::parse(&self.data[offset..]))
+    }
+
+    pub fn symbol_entries(&self) -> impl Iterator<Item = PomSymbolEntry> + 'a {
+        let header = self.header;
+        self.table(header.symbol_table_offset, header.symbol_count as u64, SYMBOL_ENTRY_SIZE, "symbol table")
+            .unwrap_or_default()
+            .chunks_exact(SYMBOL_ENTRY_SIZE)
+            .map(PomSymbolEntry::parse)
+    }
+
+    pub fn reloc_entries(&self) -> impl Iterator<Item = PomRelocEntry> + 'a {
+        let header = self.header;
+        self.table(header.reloc_table_offset, header.reloc_count as u64, RELOC_ENTRY_SIZE, "relocation table")
+            .unwrap_or_default()
+            .chunks_exact(RELOC_ENTRY_SIZE)
+            .map(PomRelocEntry::parse)
+    }
+
+    fn to_section(self, entry: PomSectionEntry) -> Section<'a> {
+        let kind = SectionKind::from_u16(entry.kind).unwrap_or(SectionKind::Bss);
+        Section {
+            name: self.string(entry.name).unwrap_or_default(),
+            kind,
+            align: entry.align,
+            size: entry.size,
+            data: match kind {
+                SectionKind::Bss => &[],
+                _ => self.table(entry.offset, entry.size, 1, "section data").unwrap_or_default(),
+            },
+        }
+    }
+
+    fn to_symbol(self, entry: PomSymbolEntry) -> Symbol<'a> {
+        Symbol {
+            name: self.string(entry.name).unwrap_or_default(),
+            section: entry.section,
+            binding: SymbolBinding::from_u8(entry.binding).unwrap_or(SymbolBinding::Local),
+            kind: SymbolKind::from_u8(entry.kind).unwrap_or(SymbolKind::NoType),
+            value: entry.value,
+            size: entry.size,
+        }
+    }
+
+    pub fn sections(&self) -> impl Iterator<Item = Section<'a>> + 'a {
+        let file = *self;
+        self.section_entries().map(move |entry| file.to_section(entry))
+    }
+
+    pub fn section(&self, index: u32) -> Option<Section<'a>> {
+        self.section_entry(index).map(|entry| self.to_section(entry))
+    }
+
+    pub fn symbols(&self) -> impl Iterator<Item = Symbol<'a>> + 'a {
+        let file = *self;
+        self.symbol_entries().map(move |entry| file.to_symbol(entry))
+    }
+
+    pub fn symbol(&self, index: u32) -> Option<Symbol<'a>> {
+        if index >= self.header.symbol_count {
+            return None;
+        }
+        let offset = self.header.symbol_table_offset as usize + index as usize * SYMBOL_ENTRY_SIZE;
+        Some(self.to_symbol(PomSymbolEntry::parse(&self.data[offset..])))
+    }
+
+    pub fn relocs(&self) -> impl Iterator<Item = Reloc> + 'a {
+        self.reloc_entries().map(|entry| Reloc {
+            section: entry.section,
+            offset: entry.offset,
+            kind: RelocKind::from_u32(entry.kind).unwrap_or(RelocKind::Abs64),
+            symbol: entry.symbol,
+            addend: entry.addend,
+        })
+    }
+
+    /// Global and weak symbols defined here, as indexed by `.plstat`.
+    pub fn exported_symbols(&self) -> impl Iterator<Item = Symbol<'a>> + 'a {
+        self.symbols()
+            .filter(|sym| sym.binding != SymbolBinding::Local && sym.section != SECTION_UNDEF)
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone)]
+struct PendingSection {
+    name: String,
+    kind: SectionKind,
+    align: u64,
+    size: u64,
+    data: Vec<u8>,
+}
+
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone)]
+struct PendingSymbol {
+    name: String,
+    section: u32,
+    binding: SymbolBinding,
+    kind: SymbolKind,
+    value: u64,
+    size: u64,
+}
+
+/// Assembles a `.pom` file. Indices returned by the `add_*` methods are the
+/// ones relocations and symbols refer to.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone)]
+pub struct PomBuilder {
+    pub cpu_id: u16,
+    sections: Vec<PendingSection>,
+    symbols: Vec<PendingSymbol>,
+    relocs: Vec<Reloc>,
+}
+
+#[cfg(feature = "alloc")]
+impl PomBuilder {
+    pub fn new(cpu_id: u16) -> Self {
+        PomBuilder {
+            cpu_id,
+            sections: Vec::new(),
+            symbols: Vec::new(),
+            relocs: Vec::new(),
+        }
+    }
+
+    pub fn add_section(&mut self, name: &str, kind: SectionKind, align: u64, data: Vec<u8>) -> u32 {
+        self.sections.push(PendingSection {
+            name: String::from(name),
+            kind,
+            align: align.max(1),
+            size: data.len() as u64,
+            data,
+        });
+        (self.sections.len() - 1) as u32
+    }
+
+    pub fn add_bss(&mut self, name: &str, align: u64, size: u64) -> u32 {
+        self.sections.push(PendingSection {
+            name: String::from(name),
+            kind: SectionKind::Bss,
+            align: align.max(1),
+            size,
+            data: Vec::new(),
+        });
+        (self.sections.len() - 1) as u32
+    }
+
+    pub fn add_symbol(
+        &mut self,
+        name: &str,
+        section: u32,
+        binding: SymbolBinding,
+        kind: SymbolKind,
+        value: u64,
+        size: u64,
+    ) -> u32 {
+        self.symbols.push(PendingSymbol {
+            name: String::from(name),
+            section,
+            binding,
+            kind,
+            value,
+            size,
+        });
+        (self.symbols.len() - 1) as u32
+    }
+
+    pub fn add_reloc(&mut self, section: u32, offset: u64, kind: RelocKind, symbol: u32, addend: i64) {
+        self.relocs.push(Reloc {
+            section,
+            offset,
+            kind,
+            symbol,
+            addend,
+        });
+    }
+
+    pub fn section_count(&self) -> usize {
+        self.sections.len()
+    }
+
+    pub fn symbol_count(&self) -> usize {
+        self.symbols.len()
+    }
+
+    pub fn reloc_count(&self) -> usize {
+        self.relocs.len()
+    }
+
+    pub fn build(&self) -> Vec<u8> {
+        let mut strings = Vec::from([0u8]);
+        let mut intern = |s: &str| {
+            if s.is_empty() {
+                return 0;
+            }
+            let offset = strings.len() as u32;
+            strings.extend_from_slice(s.as_bytes());
+            strings.push(0);
+            offset
+        };
+        let section_names: Vec<u32> = self.sections.iter().map(|s| intern(&s.name)).collect();
+        let symbol_names: Vec<u32> = self.symbols.iter().map(|s| intern(&s.name)).collect();
+
+        let section_table_offset = POM_HEADER_SIZE as u64;
+        let symbol_table_offset = section_table_offset + (self.sections.len() * SECTION_ENTRY_SIZE) as u64;
+        let reloc_table_offset = symbol_table_offset + (self.symbols.len() * SYMBOL_ENTRY_SIZE) as u64;
+        let string_table_offset = reloc_table_offset + (self.relocs.len() * RELOC_ENTRY_SIZE) as u64;
+        let mut next_offset = string_table_offset + strings.len() as u64;
+
+        let mut section_entries = Vec::with_capacity(self.sections.len());
+        for (section, &name) in self.sections.iter().zip(&section_names) {
+            let offset = match section.kind {
+                SectionKind::Bss => 0,
+                _ => {
+                    let offset = align_up(next_offset, SECTION_DATA_ALIGN);
+                    next_offset = offset + section.size;
+                    offset
+                }
+            };
+            section_entries.push(PomSectionEntry {
+                name,
+                kind: section.kind as u16,
+                reserved: 0,
+                align: section.align,
+                offset,
+                size: section.size,
+            });
+        }
+
+        let header = PomHeader {
+            magic: POM_MAGIC,
+            version: POM_VERSION,
+            cpu_id: self.cpu_id,
+            section_count: self.sections.len() as u32,
+            symbol_count: self.symbols.len() as u32,
+            reloc_count: self.relocs.len() as u32,
+            reserved: 0,
+            section_table_offset,
+            symbol_table_offset,
+            reloc_table_offset,
+            string_table_offset,
+            string_table_size: strings.len() as u64,
+            file_size: next_offset,
+        };
+
+        let mut out = alloc::vec![0u8; next_offset as usize];
+        header.write(&mut out).unwrap();
+        for (i, entry) in section_entries.iter().enumerate() {
+            entry.write(&mut out[section_table_offset as usize + i * SECTION_ENTRY_SIZE..]);
+        }
+        for (i, (symbol, &name)) in self.symbols.iter().zip(&symbol_names).enumerate() {
+            PomSymbolEntry {
+                name,
+                section: symbol.section,
+                binding: symbol.binding as u8,
+                kind: symbol.kind as u8,
+                reserved: 0,
+                reserved2: 0,
+                value: symbol.value,
+                size: symbol.size,
+            }
+            .write(&mut out[symbol_table_offset as usize + i * SYMBOL_ENTRY_SIZE..]);
+        }
+        for (i, reloc) in self.relocs.iter().enumerate() {
+            PomRelocEntry {
+                section: reloc.section,
+                symbol: reloc.symbol,
+                offset: reloc.offset,
+                kind: reloc.kind as u32,
+                reserved: 0,
+                addend: reloc.addend,
+            }
+            .write(&mut out[reloc_table_offset as usize + i * RELOC_ENTRY_SIZE..]);
+        }
+        out[string_table_offset as usize..][..strings.len()].copy_from_slice(&strings);
+        for (section, entry) in self.sections.iter().zip(&section_entries) {
+            out[entry.offset as usize..][..section.data.len()].copy_from_slice(&section.data);
+        }
+        out
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl From<PomFile<'_>> for PomBuilder {
+    /// Rebuilding a parsed object reproduces it byte for byte when it was
+    /// written by [`PomBuilder::build`].
+    fn from(file: PomFile<'_>) -> Self {
+        let mut builder = PomBuilder::new(file.cpu_id());
+        for section in file.sections() {
+            match section.kind {
+                SectionKind::Bss => builder.add_bss(section.name, section.align, section.size),
+                kind => builder.add_section(section.name, kind, section.align, section.data.to_vec()),
+            };
+        }
+        for sym in file.symbols() {
+            builder.add_symbol(sym.name, sym.section, sym.binding, sym.kind, sym.value, sym.size);
+        }
+        builder.relocs.extend(file.relocs());
+        builder
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConvertError {
+    Elf(ElfError),
+    NotRelocatable(u16),
+    UnsupportedMachine(u16),
+    /// Thread-local storage has no PlumOS equivalent yet.
+    TlsSection(String),
+    /// Only RELA relocations are used by the supported architectures.
+    RelSection(String),
+    UnsupportedReloc { section: String, r_type: u32 },
+    /// A kept section refers to a symbol in a section that is dropped.
+    DiscardedSymbol { section: String, symbol: String },
+}
+
+#[cfg(feature = "alloc")]
+impl fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConvertError::Elf(e) => write!(f, "{}", e),
+            ConvertError::NotRelocatable(e_type) => {
+                write!(f, "not a relocatable object (e_type {}, expected ET_REL)", e_type)
+            }
+            ConvertError::UnsupportedMachine(machine) => write!(f, "unsupported e_machine {}", machine),
+            ConvertError::TlsSection(name) => write!(f, "{}: thread-local sections are not supported", name),
+            ConvertError::RelSection(name) => write!(f, "{}: only RELA relocations are supported", name),
+            ConvertError::UnsupportedReloc { section, r_type } => {
+                write!(f, "{}: unsupported relocation type {}", section, r_type)
+            }
+            ConvertError::DiscardedSymbol { section, symbol } => {
+                write!(f, "{}: relocation against `{}` in a discarded section", section, symbol)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ConvertError {}
+
+#[cfg(feature = "alloc")]
+impl From<ElfError> for ConvertError {
+    fn from(e: ElfError) -> Self {
+        ConvertError::Elf(e)
+    }
+}
+
+#[cfg(feature = "alloc")]
+const SHT_INIT_ARRAY: u32 = 14;
+#[cfg(feature = "alloc")]
+const SHT_FINI_ARRAY: u32 = 15;
+#[cfg(feature = "alloc")]
+const SHT_PREINIT_ARRAY: u32 = 16;
+#[cfg(feature = "alloc")]
+const SHF_TLS: u64 = 1 << 10;
+#[cfg(feature = "alloc")]
+const STT_FILE: u8 = 4;
+
+/// The POM relocation for an ELF one, `Ok(None)` for markers that need no
+/// work when nothing is relaxed (`NONE`, RISC-V `RELAX` and `ALIGN`).
+#[cfg(feature = "alloc")]
+fn elf_reloc_kind(e_machine: u16, r_type: u32) -> Result<Option<RelocKind>, u32> {
+    let kind = match (e_machine, r_type) {
+        (_, 0) => return Ok(None),
+        (elf::EM_X86_64, 1) => RelocKind::Abs64,
+        (elf::EM_X86_64, 2 | 4) => RelocKind::Pc32,
+        (elf::EM_X86_64, 9 | 41 | 42) => RelocKind::X86GotPc32,
+        (elf::EM_X86_64, 10) => RelocKind::Abs32,
+        (elf::EM_X86_64, 11) => RelocKind::Abs32S,
+        (elf::EM_X86_64, 24) => RelocKind::Pc64,
+        (elf::EM_AARCH64, 257) => RelocKind::Abs64,
+        (elf::EM_AARCH64, 258) => RelocKind::Abs32,
+        (elf::EM_AARCH64, 260) => RelocKind::Pc64,
+        (elf::EM_AARCH64, 261) => RelocKind::Pc32,
+        (elf::EM_AARCH64, 274) => RelocKind::Aarch64AdrLo21,
+        (elf::EM_AARCH64, 275) => RelocKind::Aarch64AdrPage21,
+        (elf::EM_AARCH64, 277) => RelocKind::Aarch64AddLo12,
+        (elf::EM_AARCH64, 278) => RelocKind::Aarch64Ldst8Lo12,
+        (elf::EM_AARCH64, 279) => RelocKind::Aarch64TstBr14,
+        (elf::EM_AARCH64, 280) => RelocKind::Aarch64CondBr19,
+        (elf::EM_AARCH64, 282 | 283) => RelocKind::Aarch64Branch26,
+        (elf::EM_AARCH64, 284) => RelocKind::Aarch64Ldst16Lo12,
+        (elf::EM_AARCH64, 285) => RelocKind::Aarch64Ldst32Lo12,
+        (elf::EM_AARCH64, 286) => RelocKind::Aarch64Ldst64Lo12,
+        (elf::EM_AARCH64, 299) => RelocKind::Aarch64Ldst128Lo12,
+        (elf::EM_AARCH64, 311) => RelocKind::Aarch64GotPage21,
+        (elf::EM_AARCH64, 312) => RelocKind::Aarch64GotLo12,
+        (elf::EM_RISCV, 43 | 51) => return Ok(None),
+        (elf::EM_RISCV, 1) => RelocKind::Abs32,
+        (elf::EM_RISCV, 2) => RelocKind::Abs64,
+        (elf::EM_RISCV, 16) => RelocKind::RiscvBranch,
+        (elf::EM_RISCV, 17) => RelocKind::RiscvJal,
+        (elf::EM_RISCV, 18 | 19) => RelocKind::RiscvCall,
+        (elf::EM_RISCV, 20) => RelocKind::RiscvGotHi20,
+        (elf::EM_RISCV, 23) => RelocKind::RiscvPcrelHi20,
+        (elf::EM_RISCV, 24) => RelocKind::RiscvPcrelLo12I,
+        (elf::EM_RISCV, 25) => RelocKind::RiscvPcrelLo12S,
+        (elf::EM_RISCV, 26) => RelocKind::RiscvHi20,
+        (elf::EM_RISCV, 27) => RelocKind::RiscvLo12I,
+        (elf::EM_RISCV, 28) => RelocKind::RiscvLo12S,
+        (elf::EM_RISCV, 35) => RelocKind::Add32,
+        (elf::EM_RISCV, 36) => RelocKind::Add64,
+        (elf::EM_RISCV, 39) => RelocKind::Sub32,
+        (elf::EM_RISCV, 40) => RelocKind::Sub64,
+        (elf::EM_RISCV, 57) => RelocKind::Pc32,
+        _ => return Err(r_type),
+    };
+    Ok(Some(kind))
+}
+
+/// Converts an ELF relocatable object (`ET_REL`). Allocated sections are
+/// kept; debug info, notes and `.eh_frame` unwind tables are dropped, and
+/// so are the relocations and local symbols that only they used.
+#[cfg(feature = "alloc")]
+pub fn from_elf(elf: &Elf) -> Result<PomBuilder, ConvertError> {
+    if elf.e_type != elf::ET_REL {
+        return Err(ConvertError::NotRelocatable(elf.e_type));
+    }
+    let cpu_id = match elf.e_machine {
+        elf::EM_X86_64 => CPU_X86_64,
+        elf::EM_AARCH64 => CPU_AARCH64,
+        elf::EM_RISCV => CPU_RISCV64,
+        machine => return Err(ConvertError::UnsupportedMachine(machine)),
+    };
+    let mut builder = PomBuilder::new(cpu_id);
+
+    // ELF section index -> POM section index.
+    let mut section_map: Vec<Option<u32>> = Vec::with_capacity(elf.section_count());
+    for sh in elf.section_headers() {
+        let name = elf.section_name(&sh);
+        let kept_type = matches!(
+            sh.sh_type,
+            elf::SHT_PROGBITS | elf::SHT_NOBITS | SHT_INIT_ARRAY | SHT_FINI_ARRAY | SHT_PREINIT_ARRAY
+        );
+        if sh.sh_flags & elf::SHF_ALLOC == 0 || !kept_type || name.starts_with(".eh_frame") {
+            section_map.push(None);
+            continue;
+        }
+        if sh.sh_flags & SHF_TLS != 0 {
+            return Err(ConvertError::TlsSection(String::from(name)));
+        }
+        let index = if sh.sh_type == elf::SHT_NOBITS {
+            builder.add_bss(name, sh.sh_addralign, sh.sh_size)
+        } else {
+            let kind = if sh.sh_flags & elf::SHF_EXECINSTR != 0 {
+                SectionKind::Text
+            } else if sh.sh_flags & elf::SHF_WRITE != 0 {
+                SectionKind::Data
+            } else {
+                SectionKind::ReadOnly
+            };
+            builder.add_section(name, kind, sh.sh_addralign, elf.section_data(&sh)?.to_vec())
+        };
+        section_map.push(Some(index));
+    }
+
+    // ELF symbol index -> POM symbol index, `None` for dropped symbols.
+    let mut symbol_map: Vec<Option<u32>> = Vec::new();
+    let symtab = elf.section_headers().find(|sh| sh.sh_type == elf::SHT_SYMTAB);
+    let mut symbol_names = Vec::new();
+    if let Some(symtab) = &symtab {
+        for (index, sym) in elf.symbols(symtab)?.enumerate() {
+            symbol_names.push(sym.name);
+            if index == 0 || sym.sym_type() == STT_FILE {
+                symbol_map.push(None);
+                continue;
+            }
+            let section = match sym.st_shndx {
+                elf::SHN_UNDEF => Some(SECTION_UNDEF),
+                elf::SHN_ABS => Some(SECTION_ABS),
+                elf::SHN_COMMON => Some(SECTION_COMMON),
+                shndx => section_map.get(shndx as usize).copied().flatten(),
+            };
+            let Some(section) = section else {
+                symbol_map.push(None);
+                continue;
+            };
+            let binding = match sym.bind() {
+                elf::STB_GLOBAL => SymbolBinding::Global,
+                elf::STB_WEAK => SymbolBinding::Weak,
+                _ => SymbolBinding::Local,
+            };
+            let (kind, name) = match sym.sym_type() {
+                elf::STT_FUNC => (SymbolKind::Func, sym.name),
+                elf::STT_OBJECT => (SymbolKind::Object, sym.name),
+                // Section symbols take the section's name; SHN_ABS and
+                // SHN_COMMON have no section to name them after.
+                elf::STT_SECTION => (
+                    SymbolKind::Section,
+                    builder.sections.get(section as usize).map_or(sym.name, |s| s.name.as_str()),
+                ),
+                _ => (SymbolKind::NoType, sym.name),
+            };
+            let name = String::from(name);
+            symbol_map.push(Some(builder.add_symbol(&name, section, binding, kind, sym.st_value, sym.st_size)));
+        }
+    }
+
+    for sh in elf.section_headers() {
+        if sh.sh_type != elf::SHT_RELA && sh.sh_type != elf::SHT_REL {
+            continue;
+        }
+        let Some(target) = section_map.get(sh.sh_info as usize).copied().flatten() else {
+            continue;
+        };
+        let section_name = String::from(elf.section_name(&sh));
+        if sh.sh_type == elf::SHT_REL {
+            return Err(ConvertError::RelSection(section_name));
+        }
+        for rela in elf.relas(&sh)? {
+            let kind = elf_reloc_kind(elf.e_machine, rela.r_type).map_err(|r_type| ConvertError::UnsupportedReloc {
+                section: section_name.clone(),
+                r_type,
+            })?;
+            let Some(kind) = kind else {
+                continue;
+            };
+            let symbol = match rela.r_sym {
+                0 => NO_SYMBOL,
+                index => symbol_map.get(index as usize).copied().flatten().ok_or_else(|| {
+                    ConvertError::DiscardedSymbol {
+                        section: section_name.clone(),
+                        symbol: String::from(symbol_names.get(index as usize).copied().unwrap_or("")),
+                    }
+                })?,
+            };
+            builder.add_reloc(target, rela.r_offset, kind, symbol, rela.r_addend);
+        }
+    }
+
+    Ok(builder)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "alloc")]
+    use alloc::vec;
+    use crate::plam::CPU_PRUM64;
+
+    /// Applies `kind` to `insn` (and `next`, the following word) and returns
+    /// the patched words.
+    fn patch(kind: RelocKind, insn: u32, next: u32, value: i64) -> Result<(u32, u32), PomError> {
+        let mut place = [0u8; 8];
+        write_u32(&mut place, 0, insn);
+        write_u32(&mut place, 4, next);
+        kind.apply(&mut place, value)?;
+        Ok((read_u32(&place, 0), read_u32(&place, 4)))
+    }
+
+    fn patch32(kind: RelocKind, insn: u32, value: i64) -> Result<u32, PomError> {
+        patch(kind, insn, 0, value).map(|(insn, _)| insn)
+    }
+
+    #[test]
+    fn data_relocs_apply_on_every_arch() {
+        for kind in [RelocKind::Abs64, RelocKind::Abs32, RelocKind::Pc32, RelocKind::Add64, RelocKind::Sub32] {
+            assert!(kind.valid_for(CPU_PRUM64) && kind.valid_for(CPU_X86_64));
+        }
+        assert!(!RelocKind::Aarch64Branch26.valid_for(CPU_PRUM64));
+
+        let mut place = [0xAAu8; 8];
+        RelocKind::Abs64.apply(&mut place, 0x1122_3344_5566_7788).unwrap();
+        assert_eq!(read_u64(&place, 0), 0x1122_3344_5566_7788);
+
+        assert_eq!(patch32(RelocKind::Abs32, 0, 0xFFFF_FFFF), Ok(0xFFFF_FFFF));
+        assert_eq!(patch32(RelocKind::Abs32, 0, 1 << 32), Err(PomError::RelocOverflow(RelocKind::Abs32)));
+        assert_eq!(patch32(RelocKind::Abs32, 0, -1), Err(PomError::RelocOverflow(RelocKind::Abs32)));
+        assert_eq!(patch32(RelocKind::Abs32S, 0, -2), Ok(0xFFFF_FFFE));
+        assert_eq!(patch32(RelocKind::Abs32S, 0, 1 << 31), Err(PomError::RelocOverflow(RelocKind::Abs32S)));
+        assert_eq!(patch32(RelocKind::Add32, 100, 23), Ok(123));
+        assert_eq!(patch32(RelocKind::Sub32, 100, 23), Ok(77));
+        assert_eq!(patch(RelocKind::Add64, 0xFFFF_FFFF, 0, 1), Ok((0, 1)));
+        assert_eq!(patch(RelocKind::Sub64, 0, 1, 1), Ok((0xFFFF_FFFF, 0)));
+
+        assert_eq!(RelocKind::Pc32.value(0x1000, -4, 0x2000), -0x1004);
+        assert_eq!(patch32(RelocKind::Pc32, 0, -0x1004), Ok(-0x1004i32 as u32));
+        assert_eq!(RelocKind::Abs32.apply(&mut [0u8; 3], 0), Err(PomError::OutOfBounds("relocation target")));
+    }
+
+    #[test]
+    fn x86_64_relocs() {
+        // call rel32 to 0x1000 from a call at 0x2000 (field at 0x2001).
+        let value = RelocKind::Pc32.value(0x1000, -4, 0x2001);
+        assert_eq!(patch32(RelocKind::Pc32, 0, value), Ok(0xFFFF_EFFB));
+        assert_eq!(patch32(RelocKind::X86GotPc32, 0, 0x7FFF_FFFF), Ok(0x7FFF_FFFF));
+        assert_eq!(
+            patch32(RelocKind::X86GotPc32, 0, 0x8000_0000),
+            Err(PomError::RelocOverflow(RelocKind::X86GotPc32))
+        );
+        assert!(RelocKind::X86GotPc32.uses_got() && !RelocKind::X86GotPc32.valid_for(CPU_AARCH64));
+    }
+
+    #[test]
+    fn aarch64_relocs() {
+        // adrp x0, . + 0x1000
+        let value = RelocKind::Aarch64AdrPage21.value(0x1_1234, 0, 0x0_0FFC);
+        assert_eq!(value, 0x1_1000);
+        assert_eq!(patch32(RelocKind::Aarch64AdrPage21, 0x9000_0000, 0x1000), Ok(0xB000_0000));
+        assert_eq!(patch32(RelocKind::Aarch64AdrPage21, 0x9000_0000, -0x1000), Ok(0xF0FF_FFE0));
+        assert_eq!(
+            patch32(RelocKind::Aarch64AdrPage21, 0x9000_0000, 1 << 32),
+            Err(PomError::RelocOverflow(RelocKind::Aarch64AdrPage21))
+        );
+        // add x0, x0, #0x123
+        assert_eq!(patch32(RelocKind::Aarch64AddLo12, 0x9100_0000, 0x4_5123), Ok(0x9104_8C00));
+        // ldr x1, [x0, #0x18]
+        assert_eq!(patch32(RelocKind::Aarch64Ldst64Lo12, 0xF940_0001, 0x1018), Ok(0xF940_0C01));
+        assert_eq!(
+            patch32(RelocKind::Aarch64Ldst64Lo12, 0xF940_0001, 0x101C),
+            Err(PomError::RelocMisaligned(RelocKind::Aarch64Ldst64Lo12))
+        );
+        // bl . + 8, b.eq . - 4, tbz w0, #0, . + 0x20
+        assert_eq!(patch32(RelocKind::Aarch64Branch26, 0x9400_0000, 8), Ok(0x9400_0002));
+        assert_eq!(patch32(RelocKind::Aarch64CondBr19, 0x5400_0000, -4), Ok(0x54FF_FFE0));
+        assert_eq!(patch32(RelocKind::Aarch64TstBr14, 0x3600_0000, 0x20), Ok(0x3600_0100));
+        assert_eq!(
+            patch32(RelocKind::Aarch64Branch26, 0x9400_0000, 1 << 27),
+            Err(PomError::RelocOverflow(RelocKind::Aarch64Branch26))
+        );
+        assert_eq!(
+            patch32(RelocKind::Aarch64Branch26, 0x9400_0000, 6),
+            Err(PomError::RelocMisaligned(RelocKind::Aarch64Branch26))
+        );
+    }
+
+    #[test]
+    fn riscv64_relocs() {
+        // jal ra, . + 8 and beq zero, zero, . + 16
+        assert_eq!(patch32(RelocKind::RiscvJal, 0x0000_00EF, 8), Ok(0x0080_00EF));
+        assert_eq!(patch32(RelocKind::RiscvBranch, 0x0000_0063, 16), Ok(0x0000_0863));
+        assert_eq!(
+            patch32(RelocKind::RiscvBranch, 0x0000_0063, 0x1000),
+            Err(PomError::RelocOverflow(RelocKind::RiscvBranch))
+        );
+        assert_eq!(
+            patch32(RelocKind::RiscvJal, 0x0000_00EF, 3),
+            Err(PomError::RelocMisaligned(RelocKind::RiscvJal))
+        );
+
+        // auipc a0, %pcrel_hi; addi a0, a0, %pcrel_lo rounds the high part
+        // up when the low part is negative.
+        assert_eq!(patch32(RelocKind::RiscvPcrelHi20, 0x0000_0517, 0x1234_5FFF), Ok(0x1234_6517));
+        assert_eq!(patch32(RelocKind::RiscvPcrelLo12I, 0x0005_0513, 0x1234_5FFF), Ok(0xFFF5_0513));
+        assert_eq!(
+            patch32(RelocKind::RiscvHi20, 0x0000_0537, 0x7FFF_F800),
+            Err(PomError::RelocOverflow(RelocKind::RiscvHi20))
+        );
+        // sd a0, 40(sp)
+        assert_eq!(patch32(RelocKind::RiscvLo12S, 0x00A1_3023, 40), Ok(0x02A1_3423));
+        // auipc ra, 0; jalr ra, 0(ra) -> call . + 0x1010
+        assert_eq!(patch(RelocKind::RiscvCall, 0x0000_0097, 0x0000_80E7, 0x1010), Ok((0x0000_1097, 0x0100_80E7)));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn builder_round_trip() {
+        let mut builder = PomBuilder::new(CPU_RISCV64);
+        let text = builder.add_section(".text", SectionKind::Text, 4, vec![0x13, 0, 0, 0, 0x67, 0x80, 0, 0]);
+        let rodata = builder.add_section(".rodata", SectionKind::ReadOnly, 8, vec![1, 2, 3, 4]);
+        let bss = builder.add_bss(".bss", 16, 0x100);
+        let start = builder.add_symbol("_start", text, SymbolBinding::Global, SymbolKind::Func, 0, 8);
+        builder.add_symbol("table", rodata, SymbolBinding::Local, SymbolKind::Object, 0, 3);
+        builder.add_symbol("buffer", bss, SymbolBinding::Weak, SymbolKind::Object, 0x10, 0x80);
+        let puts = builder.add_symbol("puts", SECTION_UNDEF, SymbolBinding::Global, SymbolKind::NoType, 0, 0);
+        builder.add_reloc(text, 0, RelocKind::RiscvCall, puts, 0);
+        builder.add_reloc(rodata, 0, RelocKind::Abs32, start, -8);
+        let data = builder.build();
+
+        let file = PomFile::parse(&data).unwrap();
+        assert_eq!(file.cpu_id(), CPU_RISCV64);
+        let sections: Vec<_> = file.sections().map(|s| (s.name, s.kind, s.align, s.size, s.data)).collect();
+        assert_eq!(
+            sections,
+            [
+                (".text", SectionKind::Text, 4, 8, &[0x13, 0, 0, 0, 0x67, 0x80, 0, 0][..]),
+                (".rodata", SectionKind::ReadOnly, 8, 4, &[1, 2, 3, 4][..]),
+                (".bss", SectionKind::Bss, 16, 0x100, &[][..]),
+            ]
+        );
+        let symbols: Vec<_> = file.symbols().map(|s| (s.name, s.section, s.binding, s.value, s.size)).collect();
+        assert_eq!(
+            symbols,
+            [
+                ("_start", text, SymbolBinding::Global, 0, 8),
+                ("table", rodata, SymbolBinding::Local, 0, 3),
+                ("buffer", bss, SymbolBinding::Weak, 0x10, 0x80),
+                ("puts", SECTION_UNDEF, SymbolBinding::Global, 0, 0),
+            ]
+        );
+        let exported: Vec<_> = file.exported_symbols().map(|s| s.name).collect();
+        assert_eq!(exported, ["_start", "buffer"]);
+        let relocs: Vec<_> = file.relocs().collect();
+        assert_eq!(
+            relocs,
+            [
+                Reloc { section: text, offset: 0, kind: RelocKind::RiscvCall, symbol: puts, addend: 0 },
+                Reloc { section: rodata, offset: 0, kind: RelocKind::Abs32, symbol: start, addend: -8 },
+            ]
+        );
+
+        assert_eq!(PomBuilder::from(file).build(), data);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn parse_rejects_reloc_without_symbol() {
+        let object = |cpu_id: u16, kind: RelocKind| {
+            let mut builder = PomBuilder::new(cpu_id);
+            let text = builder.add_section(".text", SectionKind::Text, 4, vec![0; 8]);
+            builder.add_reloc(text, 0, kind, NO_SYMBOL, 0x10);
+            builder.build()
+        };
+        for (cpu_id, kind) in [
+            (CPU_RISCV64, RelocKind::RiscvPcrelLo12I),
+            (CPU_RISCV64, RelocKind::RiscvPcrelLo12S),
+            (CPU_RISCV64, RelocKind::RiscvGotHi20),
+            (CPU_X86_64, RelocKind::X86GotPc32),
+        ] {
+            assert!(kind.needs_symbol());
+            assert_eq!(PomFile::parse(&object(cpu_id, kind)).map(|_| ()), Err(PomError::BadReloc(0)));
+        }
+        assert!(PomFile::parse(&object(CPU_RISCV64, RelocKind::RiscvPcrelHi20)).is_ok());
+        assert!(PomFile::parse(&object(CPU_X86_64, RelocKind::Abs64)).is_ok());
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn parse_rejects_truncated() {
+        let data = PomBuilder::new(CPU_X86_64).build();
+        assert!(PomFile::parse(&data).is_ok());
+        assert_eq!(PomFile::parse(&data[..POM_HEADER_SIZE - 1]).map(|_| ()), Err(PomError::TooShort));
+        let mut bad = data.clone();
+        bad[0] = b'X';
+        assert_eq!(PomFile::parse(&bad).map(|_| ()), Err(PomError::BadMagic(*b"XOM\0")));
+    }
+
+    /// A small x86_64 `ET_REL` as a C compiler would emit it: `main` calls
+    /// the undefined `foo`, and `.data` holds the address of the local
+    /// `counter` in `.bss`.
+    #[cfg(feature = "alloc")]
+    fn x86_64_object() -> Vec<u8> {
+        const SHSTRTAB: &[u8] = b"\0.text\0.data\0.bss\0.comment\0.rela.text\0.rela.data\0.symtab\0.strtab\0.shstrtab\0";
+        const STRTAB: &[u8] = b"\0t.c\0counter\0main\0foo\0";
+        let name = |table: &[u8], name: &str| {
+            let mut needle = vec![0u8];
+            needle.extend_from_slice(name.as_bytes());
+            needle.push(0);
+            table.windows(needle.len()).position(|w| w == needle).unwrap() as u32 + 1
+        };
+
+        let mut data = vec![0u8; 64];
+        let append = |data: &mut Vec<u8>, bytes: &[u8]| {
+            data.resize(align_up(data.len() as u64, 8) as usize, 0);
+            data.extend_from_slice(bytes);
+            (data.len() - bytes.len()) as u64
+        };
+
+        let text = append(&mut data, &[0x55, 0xE8, 0, 0, 0, 0, 0x5D, 0xC3]);
+        let data_section = append(&mut data, &[0; 8]);
+        let comment = append(&mut data, b"GCC\0");
+
+        // (name, info, shndx, value, size)
+        let symbols = [
+            (0, 0x00, 0, 0, 0),
+            (name(STRTAB, "t.c"), STT_FILE, 0xFFF1, 0, 0),
+            (0, elf::STT_SECTION, 2, 0, 0),
+            (name(STRTAB, "counter"), elf::STT_OBJECT, 3, 0, 4),
+            (name(STRTAB, "main"), 0x10 | elf::STT_FUNC, 1, 0, 8),
+            (name(STRTAB, "foo"), 0x10, 0, 0, 0),
+        ];
+        let mut symtab = Vec::new();
+        for (name, info, shndx, value, size) in symbols {
+            let mut entry = [0u8; 24];
+            write_u32(&mut entry, 0, name);
+            entry[4] = info;
+            write_u16(&mut entry, 6, shndx);
+            write_u64(&mut entry, 8, value);
+            write_u64(&mut entry, 16, size);
+            symtab.extend_from_slice(&entry);
+        }
+        let rela = |offset: u64, symbol: u64, r_type: u64, addend: i64| {
+            let mut entry = [0u8; 24];
+            write_u64(&mut entry, 0, offset);
+            write_u64(&mut entry, 8, symbol << 32 | r_type);
+            write_u64(&mut entry, 16, addend as u64);
+            entry
+        };
+        let rela_text = rela(2, 5, 4, -4);
+        let rela_data = rela(0, 3, 1, 0);
+
+        let symtab_offset = append(&mut data, &symtab);
+        let strtab = append(&mut data, STRTAB);
+        let rela_text_offset = append(&mut data, &rela_text);
+        let rela_data_offset = append(&mut data, &rela_data);
+        let shstrtab = append(&mut data, SHSTRTAB);
+
+        // (name, type, flags, offset, size, link, info, align, entsize)
+        let sections = [
+            (0, 0, 0, 0, 0, 0, 0, 0, 0),
+            (name(SHSTRTAB, ".text"), elf::SHT_PROGBITS, 0x6, text, 8, 0, 0, 16, 0),
+            (name(SHSTRTAB, ".data"), elf::SHT_PROGBITS, 0x3, data_section, 8, 0, 0, 8, 0),
+            (name(SHSTRTAB, ".bss"), elf::SHT_NOBITS, 0x3, data_section + 8, 4, 0, 0, 4, 0),
+            (name(SHSTRTAB, ".comment"), elf::SHT_PROGBITS, 0x30, comment, 4, 0, 0, 1, 1),
+            (name(SHSTRTAB, ".rela.text"), elf::SHT_RELA, 0x40, rela_text_offset, 24, 7, 1, 8, 24),
+            (name(SHSTRTAB, ".rela.data"), elf::SHT_RELA, 0x40, rela_data_offset, 24, 7, 2, 8, 24),
+            (name(SHSTRTAB, ".symtab"), elf::SHT_SYMTAB, 0, symtab_offset, symtab.len() as u64, 8, 4, 8, 24),
+            (name(SHSTRTAB, ".strtab"), elf::SHT_STRTAB, 0, strtab, STRTAB.len() as u64, 0, 0, 1, 0),
+            (name(SHSTRTAB, ".shstrtab"), elf::SHT_STRTAB, 0, shstrtab, SHSTRTAB.len() as u64, 0, 0, 1, 0),
+        ];
+        let mut headers = Vec::new();
+        for (name, sh_type, flags, offset, size, link, info, align, entsize) in sections {
+            let mut header = [0u8; 64];
+            write_u32(&mut header, 0x00, name);
+            write_u32(&mut header, 0x04, sh_type);
+            write_u64(&mut header, 0x08, flags);
+            write_u64(&mut header, 0x18, offset);
+            write_u64(&mut header, 0x20, size);
+            write_u32(&mut header, 0x28, link);
+            write_u32(&mut header, 0x2C, info);
+            write_u64(&mut header, 0x30, align);
+            write_u64(&mut header, 0x38, entsize);
+            headers.extend_from_slice(&header);
+        }
+        let shoff = append(&mut data, &headers);
+
+        data[0..4].copy_from_slice(&elf::ELF_MAGIC);
+        data[4..7].copy_from_slice(&[2, 1, 1]);
+        write_u16(&mut data, 0x10, elf::ET_REL);
+        write_u16(&mut data, 0x12, elf::EM_X86_64);
+        write_u32(&mut data, 0x14, 1);
+        write_u64(&mut data, 0x28, shoff);
+        write_u16(&mut data, 0x34, 64);
+        write_u16(&mut data, 0x3A, 64);
+        write_u16(&mut data, 0x3C, sections.len() as u16);
+        write_u16(&mut data, 0x3E, sections.len() as u16 - 1);
+        data
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn from_elf_relocatable() {
+        let object = x86_64_object();
+        let elf = Elf::parse(&object).unwrap();
+        let data = from_elf(&elf).unwrap().build();
+        let file = PomFile::parse(&data).unwrap();
+
+        assert_eq!(file.cpu_id(), CPU_X86_64);
+        let sections: Vec<_> = file.sections().map(|s| (s.name, s.kind, s.align, s.size)).collect();
+        assert_eq!(
+            sections,
+            [
+                (".text", SectionKind::Text, 16, 8),
+                (".data", SectionKind::Data, 8, 8),
+                (".bss", SectionKind::Bss, 4, 4),
+            ]
+        );
+        assert_eq!(file.section(0).unwrap().data, [0x55, 0xE8, 0, 0, 0, 0, 0x5D, 0xC3]);
+
+        let symbols: Vec<_> = file.symbols().map(|s| (s.name, s.section, s.binding, s.kind)).collect();
+        assert_eq!(
+            symbols,
+            [
+                (".data", 1, SymbolBinding::Local, SymbolKind::Section),
+                ("counter", 2, SymbolBinding::Local, SymbolKind::Object),
+                ("main", 0, SymbolBinding::Global, SymbolKind::Func),
+                ("foo", SECTION_UNDEF, SymbolBinding::Global, SymbolKind::NoType),
+            ]
+        );
+        let relocs: Vec<_> = file.relocs().collect();
+        assert_eq!(
+            relocs,
+            [
+                Reloc { section: 0, offset: 2, kind: RelocKind::Pc32, symbol: 3, addend: -4 },
+                Reloc { section: 1, offset: 0, kind: RelocKind::Abs64, symbol: 1, addend: 0 },
+            ]
+        );
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn from_elf_section_symbol_outside_sections() {
+        for (shndx, section) in [(elf::SHN_ABS, SECTION_ABS), (elf::SHN_COMMON, SECTION_COMMON)] {
+            let mut object = x86_64_object();
+            let elf = Elf::parse(&object).unwrap();
+            let symtab = elf.section_headers().find(|sh| sh.sh_type == elf::SHT_SYMTAB).unwrap();
+            // Symbol 2 is the STT_SECTION symbol for .data.
+            write_u16(&mut object, symtab.sh_offset as usize + 2 * 24 + 6, shndx);
+
+            let elf = Elf::parse(&object).unwrap();
+            let data = from_elf(&elf).unwrap().build();
+            let file = PomFile::parse(&data).unwrap();
+            let first = file.symbols().next().unwrap();
+            assert_eq!((first.name, first.section, first.kind), ("", section, SymbolKind::Section));
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn from_elf_rejects_executables() {
+        let mut object = x86_64_object();
+        write_u16(&mut object, 0x10, elf::ET_EXEC);
+        let elf = Elf::parse(&object).unwrap();
+        assert!(matches!(from_elf(&elf), Err(ConvertError::NotRelocatable(elf::ET_EXEC))));
+    }
+}
@@ -0,0 +1,51 @@
+use core::fmt;
+
+/// `major.minor.patch` packed into a u32 as `major << 16 | minor << 8 | patch`
+/// wherever a format stores a version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Version {
+    pub major: u16,
+    pub minor: u8,
+    pub patch: u8,
+}
+
+impl Version {
+    pub const fn new(major: u16, minor: u8, patch: u8) -> Self {
+        Version { major, minor, patch }
+    }
+
+    pub const fn from_u32(value: u32) -> Self {
+        Version {
+            major: (value >> 16) as u16,
+            minor: (value >> 8) as u8,
+            patch: value as u8,
+        }
+    }
+
+    pub const fn to_u32(self) -> u32 {
+        (self.major as u32) << 16 | (self.minor as u32) << 8 | self.patch as u32
+    }
+
+    /// Parses `major[.minor[.patch]]`.
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut parts = text.trim().split('.');
+        let major = parts.next()?.parse().ok()?;
+        let minor = parts.next().map_or(Some(0), |p| p.parse().ok())?;
+        let patch = parts.next().map_or(Some(0), |p| p.parse().ok())?;
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(Version::new(major, minor, patch))
+    }
+
+    /// Same major version and at least as new as `required`.
+    pub fn satisfies(self, required: Version) -> bool {
+        self.major == required.major && self >= required
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
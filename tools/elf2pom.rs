@@ -0,0 +1,56 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::exit;
+
+use plum_formats::elf::Elf;
+use plum_formats::plam::arch_name;
+use plum_formats::pom::{self, PomFile};
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 2 || args.len() > 3 || args[1].starts_with('-') {
+        print_usage();
+        exit(1);
+    }
+
+    let input = &args[1];
+    let output = match args.get(2) {
+        Some(output) => output.clone(),
+        None => Path::new(input).with_extension("pom").to_string_lossy().into_owned(),
+    };
+
+    let raw = fs::read(input).unwrap_or_else(|e| {
+        eprintln!("❌ Failed to read {}: {}", input, e);
+        exit(1);
+    });
+    let elf = Elf::parse(&raw).unwrap_or_else(|e| {
+        eprintln!("❌ {}: {}", input, e);
+        exit(1);
+    });
+    let object = pom::from_elf(&elf).unwrap_or_else(|e| {
+        eprintln!("❌ {}: {}", input, e);
+        exit(1);
+    });
+
+    let image = object.build();
+    if let Err(e) = fs::write(&output, &image) {
+        eprintln!("❌ Failed to write {}: {}", output, e);
+        exit(1);
+    }
+
+    let pom = PomFile::parse(&image).expect("converter produced an invalid object");
+    println!("✅ Converted {} → {}", input, output);
+    println!("   - Arch: {}", arch_name(pom.cpu_id()).unwrap_or("unknown"));
+    println!("   - Sections: {}", pom.header.section_count);
+    println!("   - Symbols: {}", pom.header.symbol_count);
+    println!("   - Relocations: {}", pom.header.reloc_count);
+}
+
+fn print_usage() {
+    eprintln!("Usage: elf2pom <input.o> [output.pom]");
+    eprintln!();
+    eprintln!("Converts an ELF relocatable object (x86_64, aarch64 or riscv64) to a");
+    eprintln!(".pom object. Allocated sections, their symbols and relocations are kept;");
+    eprintln!("debug info and .eh_frame unwind tables are dropped.");
+}
@@ -0,0 +1,155 @@
+use std::env;
+use std::fs;
+use std::process::exit;
+
+use plum_formats::fat::{cpu_from_name, select, FatBuilder, FatFile};
+use plum_formats::plam::arch_name;
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+
+    if args.len() == 3 && args[1] == "--list" {
+        list(&args[2]);
+        return;
+    }
+
+    let mut output = None;
+    let mut extract = None;
+    let mut inputs = Vec::new();
+
+    let mut iter = args[1..].iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-o" => match iter.next() {
+                Some(path) => output = Some(path.clone()),
+                None => {
+                    print_usage();
+                    exit(1);
+                }
+            },
+            _ if arg.starts_with("--output=") => output = Some(arg["--output=".len()..].to_string()),
+            _ if arg.starts_with("--extract=") => {
+                let name = &arg["--extract=".len()..];
+                extract = Some(cpu_from_name(name).unwrap_or_else(|| {
+                    eprintln!("❌ Unknown architecture: {}", name);
+                    exit(1);
+                }));
+            }
+            _ if !arg.starts_with('-') => inputs.push(arg.clone()),
+            _ => {
+                print_usage();
+                exit(1);
+            }
+        }
+    }
+
+    let Some(output) = output else {
+        print_usage();
+        exit(1);
+    };
+
+    if let Some(cpu_id) = extract {
+        if inputs.len() != 1 {
+            print_usage();
+            exit(1);
+        }
+        let data = read(&inputs[0]);
+        let slice = select(&data, cpu_id).unwrap_or_else(|e| {
+            eprintln!("❌ {}: {}", inputs[0], e);
+            exit(2);
+        });
+        write(&output, slice);
+        println!(
+            "✅ Extracted {} slice of {} → {} ({} bytes)",
+            arch_name(cpu_id).unwrap_or("unknown"),
+            inputs[0],
+            output,
+            slice.len()
+        );
+        return;
+    }
+
+    if inputs.is_empty() {
+        print_usage();
+        exit(1);
+    }
+
+    let mut builder = FatBuilder::new();
+    for input in &inputs {
+        let data = read(input);
+        // A universal input contributes all of its slices.
+        let slices: Vec<Vec<u8>> = match FatFile::parse(&data) {
+            Ok(fat) => fat.slices().map(|slice| slice.data.to_vec()).collect(),
+            Err(_) => vec![data],
+        };
+        for slice in slices {
+            if let Err(e) = builder.add_slice(slice) {
+                eprintln!("❌ {}: {}", input, e);
+                exit(2);
+            }
+        }
+    }
+
+    let image = builder.build();
+    write(&output, &image);
+
+    let fat = FatFile::parse(&image).expect("builder produced an invalid container");
+    println!("✅ Created {} ({} bytes)", output, image.len());
+    println!("   - Format: {}", String::from_utf8_lossy(&fat.header.format).trim_end_matches('\0'));
+    println!("   - Slices: {}", fat.header.slice_count);
+    for entry in fat.slice_entries() {
+        println!(
+            "     {:<10} offset=0x{:x} size={}",
+            arch_name(entry.cpu_id).unwrap_or("unknown"),
+            entry.offset,
+            entry.size
+        );
+    }
+}
+
+fn list(path: &str) {
+    let data = read(path);
+    let fat = FatFile::parse(&data).unwrap_or_else(|e| {
+        eprintln!("❌ {}: {}", path, e);
+        exit(2);
+    });
+    for entry in fat.slice_entries() {
+        println!(
+            "{:<10} 0x{:04X} offset=0x{:x} size={}",
+            arch_name(entry.cpu_id).unwrap_or("unknown"),
+            entry.cpu_id,
+            entry.offset,
+            entry.size
+        );
+    }
+}
+
+fn read(path: &str) -> Vec<u8> {
+    fs::read(path).unwrap_or_else(|e| {
+        eprintln!("❌ Failed to read {}: {}", path, e);
+        exit(1);
+    })
+}
+
+fn write(path: &str, data: &[u8]) {
+    if let Err(e) = fs::write(path, data) {
+        eprintln!("❌ Failed to write {}: {}", path, e);
+        exit(1);
+    }
+}
+
+fn print_usage() {
+    eprintln!("Usage: mkfat -o <output> <image>...");
+    eprintln!("       mkfat --extract=<arch> -o <output> <universal image>");
+    eprintln!("       mkfat --list <universal image>");
+    eprintln!();
+    eprintln!("Combines .plam or .plm images built for different architectures into one");
+    eprintln!("universal file that loaders and ppm resolve to the slice for the running");
+    eprintln!("CPU. Inputs that are universal themselves contribute all their slices.");
+    eprintln!();
+    eprintln!("Architectures: x86_64, aarch64, riscv64, prum64");
+    eprintln!();
+    eprintln!("Examples:");
+    eprintln!("  mkfat -o kernel.plam kernel-x86_64.plam kernel-aarch64.plam kernel-riscv64.plam");
+    eprintln!("  mkfat --extract=aarch64 -o kernel-aarch64.plam kernel.plam");
+}
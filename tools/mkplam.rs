@@ -3,135 +3,664 @@ use std::fs::File;
 use std::io::{Read, Write};
 use std::process::exit;
 
+use ed25519_dalek::SigningKey;
+use plum_formats::elf::{
+    dynamic_reloc, DynamicReloc, Elf, ELF_MAGIC, EM_AARCH64, EM_RISCV, EM_X86_64, ET_DYN, ET_EXEC, PF_R, PF_W, PF_X,
+    PT_LOAD,
+};
+use plum_formats::lz4::Compressor;
+use plum_formats::plconf::{Config, PLUM_CONFIG_SCHEMA};
+use plum_formats::plam::{
+    arch_name, PlamBuilder, PlamHeader, SectionKind, Subsystem, CONFIG_FLAGS, CPU_AARCH64, CPU_PRUM64,
+    CPU_RISCV64, CPU_X86_64, FLAG_ASLR, FLAG_RELOCATABLE, MAX_SECTIONS, PAGE_SIZE, PERM_R, PERM_W, PERM_X,
+};
+
+struct Section {
+    kind: SectionKind,
+    perms: u32,
+    vaddr: u64,
+    mem_size: u64,
+    data: Vec<u8>,
+}
+
+enum Entry {
+    Address(u64),
+    Symbol(String),
+}
+
+/// The parts of `plum.config` that mkplam understands.
+#[derive(Default)]
+struct PlumConfig {
+    system: SystemConfig,
+    plam: PlamConfig,
+}
+
+#[derive(Default)]
+struct SystemConfig {
+    arch: Option<String>,
+}
+
+#[derive(Default)]
+struct PlamConfig {
+    subsystem: Option<String>,
+    flags: Option<Vec<String>>,
+}
+
+struct ElfImage {
+    image_base: u64,
+    entry_offset: u64,
+    sections: Vec<Section>,
+    relocations: Option<Vec<u64>>,
+}
+
 fn main() {
     let args: Vec<String> = env::args().collect();
 
-    if args.len() < 4 {
+    if args.len() < 3 {
         print_usage();
         exit(1);
     }
 
-    let mut input_path = None;
-    let mut output_path = None;
+    let mut positional = Vec::new();
+    let mut section_inputs: Vec<(SectionKind, String)> = Vec::new();
+    let mut bss_size = 0u64;
     let mut cpu_id = None;
+    let mut sign_key_path = None;
+    let mut compress = false;
+    let mut image_base_arg = None;
+    let mut entry = None;
+    let mut align_arg = None;
+    let mut subsystem_arg = None;
+    let mut flags_arg = None;
+    let mut config_path = None;
 
-    let mut i = 1;
-    while i < args.len() {
-        match args[i].as_str() {
-            "--arch=aarch64" => cpu_id = Some(0xAA64u16),
-            "--arch=x86_64" => cpu_id = Some(0x8664u16),
-            "--arch=riscv64" => cpu_id = Some(0x00F3u16),
-            "--arch=prum64" => cpu_id = Some(0x7072u16),
-            _ if input_path.is_none() => input_path = Some(args[i].clone()),
-            _ if output_path.is_none() => output_path = Some(args[i].clone()),
-            _ => {
-                eprintln!("❌ Unknown argument: {}", args[i]);
+    let mut iter = args[1..].iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            _ if arg.starts_with("--arch=") => {
+                cpu_id = Some(cpu_from_name(&arg["--arch=".len()..]).unwrap_or_else(|| {
+                    eprintln!("❌ Unknown architecture: {}", arg);
+                    exit(1);
+                }))
+            }
+            _ if arg.starts_with("--image-base=") => {
+                image_base_arg = Some(parse_size(&arg["--image-base=".len()..]).unwrap_or_else(|| {
+                    eprintln!("❌ Invalid image base: {}", arg);
+                    exit(1);
+                }))
+            }
+            _ if arg.starts_with("--entry=") => {
+                let value = &arg["--entry=".len()..];
+                entry = Some(match parse_size(value) {
+                    Some(address) => Entry::Address(address),
+                    None => Entry::Symbol(value.to_string()),
+                })
+            }
+            _ if arg.starts_with("--align=") => {
+                align_arg = Some(
+                    parse_size(&arg["--align=".len()..])
+                        .filter(|a| a.is_power_of_two() && *a >= PAGE_SIZE)
+                        .unwrap_or_else(|| {
+                            eprintln!("❌ --align must be a power of two of at least {}: {}", PAGE_SIZE, arg);
+                            exit(1);
+                        }),
+                )
+            }
+            _ if arg.starts_with("--subsystem=") => subsystem_arg = Some(arg["--subsystem=".len()..].to_string()),
+            _ if arg.starts_with("--flags=") => {
+                flags_arg = Some(
+                    arg["--flags=".len()..]
+                        .split(',')
+                        .map(|s| s.trim().to_string())
+                        .filter(|s| !s.is_empty())
+                        .collect::<Vec<_>>(),
+                )
+            }
+            _ if arg.starts_with("--config=") => config_path = Some(arg["--config=".len()..].to_string()),
+            _ if arg.starts_with("--text=") => {
+                section_inputs.push((SectionKind::Text, arg["--text=".len()..].to_string()))
+            }
+            _ if arg.starts_with("--rodata=") => {
+                section_inputs.push((SectionKind::Rodata, arg["--rodata=".len()..].to_string()))
+            }
+            _ if arg.starts_with("--data=") => {
+                section_inputs.push((SectionKind::Data, arg["--data=".len()..].to_string()))
+            }
+            "--compress" | "--compress=lz4" => compress = true,
+            "--sign" => {
+                sign_key_path = Some(iter.next().cloned().unwrap_or_else(|| {
+                    eprintln!("❌ --sign requires a key file");
+                    exit(1);
+                }))
+            }
+            _ if arg.starts_with("--sign=") => sign_key_path = Some(arg["--sign=".len()..].to_string()),
+            _ if arg.starts_with("--bss=") => {
+                bss_size = parse_size(&arg["--bss=".len()..]).unwrap_or_else(|| {
+                    eprintln!("❌ Invalid .bss size: {}", arg);
+                    exit(1);
+                })
+            }
+            _ if arg.starts_with("--") => {
+                eprintln!("❌ Unknown argument: {}", arg);
                 print_usage();
                 exit(1);
             }
+            _ => positional.push(arg.clone()),
         }
-        i += 1;
     }
 
-    let input_path = input_path.expect("Missing input file");
-    let output_path = output_path.expect("Missing output file");
-    let cpu_id = cpu_id.expect("Missing architecture");
-
-    if !std::path::Path::new(&input_path).exists() {
-        eprintln!("❌ Input file not found: {}", input_path);
-        exit(1);
-    }
+    let config = config_path.as_deref().map(load_config).unwrap_or_default();
 
-    let mut raw_data = Vec::new();
-    File::open(&input_path)
-        .and_then(|mut f| f.read_to_end(&mut raw_data))
-        .unwrap_or_else(|e| {
-            eprintln!("❌ Failed to read {}: {}", input_path, e);
+    let cpu_id = cpu_id
+        .or_else(|| {
+            config.system.arch.as_deref().map(|arch| {
+                cpu_from_name(arch).unwrap_or_else(|| {
+                    eprintln!("❌ Unknown architecture in plum.config: {}", arch);
+                    exit(1);
+                })
+            })
+        })
+        .unwrap_or_else(|| {
+            eprintln!("❌ Missing architecture");
+            print_usage();
             exit(1);
         });
 
-    if raw_data.len() > 1024 * 1024 * 1024 { 
-        eprintln!("❌ Input file too large (max 1GB)");
-        exit(1);
-    }
+    let subsystem = match subsystem_arg.or(config.plam.subsystem) {
+        Some(name) => Subsystem::from_name(&name).unwrap_or_else(|| {
+            eprintln!("❌ Unknown subsystem: {}", name);
+            exit(1);
+        }),
+        None => Subsystem::NativeKernel,
+    };
+
+    let requested_flags = flags_arg.or(config.plam.flags).map(|names| {
+        names.iter().fold(0u64, |flags, name| {
+            let bit = CONFIG_FLAGS
+                .iter()
+                .find(|(flag, _)| flag == name)
+                .map(|(_, bit)| *bit)
+                .unwrap_or_else(|| {
+                    eprintln!("❌ Unknown PLAM flag: {}", name);
+                    exit(1);
+                });
+            flags | bit
+        })
+    });
 
-    if raw_data.len() % 8 != 0 {
-        println!("⚠️  Input file size not 8-byte aligned, adding padding");
-        let padding = 8 - (raw_data.len() % 8);
-        raw_data.extend(vec![0u8; padding]);
+    let flat = section_inputs.is_empty() && bss_size == 0;
+    let expected_positional = if flat { 2 } else { 1 };
+    if positional.len() != expected_positional {
+        print_usage();
+        exit(1);
     }
+    let output_path = positional.pop().unwrap();
 
-    let code_size = raw_data.len() as u64;
-    let (image_base, architecture_name) = match cpu_id {
-        0xAA64 => (0x4008_0000, "AArch64"),   
-        0x8664 => (0x100_000, "x86_64"),      
-        0x00F3 => (0x8000_0000, "RISC-V 64"), 
-        0x7072 => (0x8000_0000, "prum64"),    
+    let default_base = match cpu_id {
+        CPU_AARCH64 => 0x4008_0000u64,
+        CPU_X86_64 => 0x100_000u64,
+        CPU_RISCV64 | CPU_PRUM64 => 0x8000_0000u64,
         _ => {
             eprintln!("❌ Unknown CPU architecture: 0x{:04X}", cpu_id);
             exit(1);
         }
     };
+    let architecture_name = arch_name(cpu_id).unwrap();
+
+    let align = align_arg.unwrap_or(PAGE_SIZE);
+    let mut image_base = image_base_arg.unwrap_or(default_base);
+
+    let mut entry_offset: u64 = 0;
+    let mut sections = Vec::new();
+    let mut relocations = None;
+    let mut from_elf = false;
 
-    let entry_offset: u64 = 0;
-    let mut header = Vec::with_capacity(4096);
+    if flat {
+        let input_path = positional.pop().unwrap();
+        let raw_data = read_input(&input_path);
 
-    header.extend_from_slice(b"PLAM");
-    header.extend_from_slice(&((3 << 8) | 0u16).to_le_bytes()); 
-    header.extend_from_slice(&[0u8; 6]); 
-    
-    let flags = 0u64; 
-    header.extend_from_slice(&flags.to_le_bytes());
+        if raw_data.starts_with(&ELF_MAGIC) {
+            if align_arg.is_some() {
+                eprintln!("❌ --align does not apply to ELF input, its section layout comes from the linker");
+                exit(1);
+            }
+            let elf = load_elf(&raw_data, cpu_id, image_base, entry.as_ref()).unwrap_or_else(|e| {
+                eprintln!("❌ Invalid ELF {}: {}", input_path, e);
+                exit(1);
+            });
+            if elf.relocations.is_none() && image_base != elf.image_base && image_base_arg.is_some() {
+                eprintln!(
+                    "❌ {} is linked at 0x{:x} and is not position-independent, it cannot be moved to 0x{:x}",
+                    input_path, elf.image_base, image_base
+                );
+                exit(1);
+            }
+            image_base = elf.image_base;
+            entry_offset = elf.entry_offset;
+            sections = elf.sections;
+            relocations = elf.relocations;
+            from_elf = true;
+        } else {
+            println!("⚠️  Raw input mapped as a single RWX section, use --text/--rodata/--data/--bss for per-section permissions");
+            sections.push(Section {
+                kind: SectionKind::Text,
+                perms: PERM_R | PERM_W | PERM_X,
+                vaddr: image_base,
+                mem_size: raw_data.len() as u64,
+                data: raw_data,
+            });
+        }
+    } else {
+        let mut next_vaddr = image_base;
+        for (kind, path) in &section_inputs {
+            let data = read_input(path);
+            let section = Section {
+                kind: *kind,
+                perms: kind.default_perms(),
+                vaddr: next_vaddr,
+                mem_size: data.len() as u64,
+                data,
+            };
+            next_vaddr = section
+                .vaddr
+                .checked_add(section.mem_size)
+                .and_then(|end| end.checked_next_multiple_of(align))
+                .unwrap_or_else(|| {
+                    eprintln!("❌ {} at 0x{:x} does not fit in the address space", path, section.vaddr);
+                    exit(1);
+                });
+            sections.push(section);
+        }
 
-    let file_size = 4096 + code_size;
-    header.extend_from_slice(&file_size.to_le_bytes());
+        if bss_size > 0 {
+            sections.push(Section {
+                kind: SectionKind::Bss,
+                perms: SectionKind::Bss.default_perms(),
+                vaddr: next_vaddr,
+                mem_size: bss_size,
+                data: Vec::new(),
+            });
+        }
+    }
 
-    header.extend_from_slice(&[0u8; 32]);
+    if !image_base.is_multiple_of(align) {
+        eprintln!("❌ Image base 0x{:x} is not aligned to 0x{:x}", image_base, align);
+        exit(1);
+    }
 
-    header.extend_from_slice(&image_base.to_le_bytes());
+    if let Some(section) = sections.iter().find(|s| s.vaddr.checked_add(s.mem_size).is_none()) {
+        eprintln!(
+            "❌ {} of {} bytes at 0x{:x} does not fit in the address space",
+            section.kind.name(),
+            section.mem_size,
+            section.vaddr
+        );
+        exit(1);
+    }
 
-    header.extend_from_slice(&entry_offset.to_le_bytes());
+    if sections.len() > MAX_SECTIONS {
+        eprintln!("❌ Too many sections ({} > {})", sections.len(), MAX_SECTIONS);
+        exit(1);
+    }
 
-    while header.len() < 0x18 {
-        header.push(0);
+    if let Some(entry) = &entry {
+        if !from_elf {
+            entry_offset = match entry {
+                Entry::Address(address) if *address >= image_base => address - image_base,
+                Entry::Address(address) => {
+                    eprintln!("❌ Entry point 0x{:x} is below the image base 0x{:x}", address, image_base);
+                    exit(1);
+                }
+                Entry::Symbol(name) => {
+                    eprintln!("❌ Entry symbol {} needs ELF input", name);
+                    exit(1);
+                }
+            };
+        }
+        let entry_address = image_base + entry_offset;
+        let in_text = sections.iter().any(|s| {
+            s.perms & PERM_X != 0 && entry_address >= s.vaddr && entry_address < s.vaddr + s.mem_size
+        });
+        if !in_text {
+            eprintln!("❌ Entry point 0x{:x} is outside every executable section", entry_address);
+            exit(1);
+        }
     }
-    header.extend_from_slice(&cpu_id.to_le_bytes());
 
-    while header.len() < 4096 {
-        header.push(0);
+    let mut flags = 0;
+    match requested_flags {
+        None if relocations.is_some() => flags |= FLAG_RELOCATABLE,
+        None => {}
+        Some(requested) => {
+            if requested & FLAG_RELOCATABLE != 0 && relocations.is_none() {
+                eprintln!("❌ The pie flag needs a position-independent (ET_DYN) ELF input");
+                exit(1);
+            }
+            if requested & FLAG_ASLR != 0 && requested & FLAG_RELOCATABLE == 0 {
+                eprintln!("❌ The aslr flag needs pie");
+                exit(1);
+            }
+            if requested & FLAG_RELOCATABLE == 0 {
+                relocations = None;
+            }
+            flags = requested;
+        }
     }
 
+    let signing_key = sign_key_path.as_deref().map(load_signing_key);
+
+    let mut builder = PlamBuilder::new(cpu_id, image_base, entry_offset);
+    builder.subsystem = subsystem;
+    builder.flags = flags;
+    builder.relocations = relocations;
+    for section in sections {
+        builder.add_section(section.kind, section.perms, section.vaddr, section.data, section.mem_size);
+    }
+
+    let compressor: Option<Compressor> = compress.then_some(lz4_flex::block::compress);
+    let image = builder.build(compressor, signing_key.as_ref()).unwrap_or_else(|e| {
+        eprintln!("❌ Failed to encode header: {}", e);
+        exit(1);
+    });
+    let plam_header = PlamHeader::parse(&image).expect("builder produced an invalid header");
+
     let mut out_file = File::create(&output_path).unwrap_or_else(|e| {
         eprintln!("❌ Failed to create {}: {}", output_path, e);
         exit(1);
     });
 
-    if let Err(e) = out_file.write_all(&header) {
-        eprintln!("❌ Failed to write header: {}", e);
-        exit(1);
-    }
-    
-    if let Err(e) = out_file.write_all(&raw_data) {
-        eprintln!("❌ Failed to write data: {}", e);
+    if let Err(e) = out_file.write_all(&image) {
+        eprintln!("❌ Failed to write image: {}", e);
         exit(1);
     }
 
-    println!("✅ Created {} ({} bytes)", output_path, 4096 + raw_data.len());
+    println!("✅ Created {} ({} bytes)", output_path, image.len());
     println!("   - Architecture: {}", architecture_name);
+    println!("   - Subsystem: {}", subsystem.name());
     println!("   - Image base: 0x{:x}", image_base);
     println!("   - Entry offset: 0x{:x}", entry_offset);
-    println!("   - Code size: {} bytes", code_size);
-    println!("   - Total size: {} bytes", file_size);
-    
+    println!("   - Sections: {}", plam_header.section_count);
+    for section in plam_header.sections(&image).expect("builder produced an invalid section table") {
+        println!(
+            "     {:<8} {} vaddr=0x{:x} offset=0x{:x} file={} mem={}",
+            section.name(),
+            perms_str(section.perms),
+            section.vaddr,
+            section.file_offset,
+            section.file_size,
+            section.mem_size
+        );
+    }
+    if let Some(relocations) = &builder.relocations {
+        println!("   - Relocatable: {} relocations", relocations.len());
+    }
+    if flags & FLAG_ASLR != 0 {
+        println!("   - ASLR: enabled");
+    }
+    if compress {
+        println!(
+            "   - Payload: {} bytes, LZ4-compressed to {} bytes",
+            plam_header.payload_size, plam_header.compressed_size
+        );
+    }
+    if let Some(key) = &signing_key {
+        println!("   - Signed by: {}", hex::encode(key.verifying_key().to_bytes()));
+    }
+    println!("   - Total size: {} bytes", plam_header.file_size);
+
     if let Ok(metadata) = std::fs::metadata(&output_path) {
         println!("   - File size on disk: {} bytes", metadata.len());
     }
 }
 
+fn read_input(path: &str) -> Vec<u8> {
+    if !std::path::Path::new(path).exists() {
+        eprintln!("❌ Input file not found: {}", path);
+        exit(1);
+    }
+
+    let mut raw_data = Vec::new();
+    File::open(path)
+        .and_then(|mut f| f.read_to_end(&mut raw_data))
+        .unwrap_or_else(|e| {
+            eprintln!("❌ Failed to read {}: {}", path, e);
+            exit(1);
+        });
+
+    if raw_data.len() > 1024 * 1024 * 1024 {
+        eprintln!("❌ Input file too large (max 1GB): {}", path);
+        exit(1);
+    }
+
+    raw_data
+}
+
+fn load_signing_key(path: &str) -> SigningKey {
+    let key_hex = std::fs::read_to_string(path).unwrap_or_else(|e| {
+        eprintln!("❌ Failed to read signing key {}: {}", path, e);
+        exit(1);
+    });
+    let key_bytes: [u8; 32] = hex::decode(key_hex.trim())
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .unwrap_or_else(|| {
+            eprintln!("❌ {} is not a hex-encoded Ed25519 signing key", path);
+            exit(1);
+        });
+    SigningKey::from_bytes(&key_bytes)
+}
+
+/// Loads the PT_LOAD segments of `data`. A position-independent executable
+/// (ET_DYN) is rebased to `base` with its R_*_RELATIVE relocations applied,
+/// and the offsets they patch are returned for the loader. `entry` overrides
+/// `e_entry` with a link-time address or a symbol name.
+fn load_elf(data: &[u8], cpu_id: u16, base: u64, entry: Option<&Entry>) -> Result<ElfImage, String> {
+    let elf = Elf::parse(data).map_err(|e| e.to_string())?;
+    if elf.e_type != ET_EXEC && elf.e_type != ET_DYN {
+        return Err(format!("unsupported ELF type {}", elf.e_type));
+    }
+
+    let expected_machine = match cpu_id {
+        CPU_X86_64 => EM_X86_64,
+        CPU_AARCH64 => EM_AARCH64,
+        CPU_RISCV64 => EM_RISCV,
+        _ => return Err(format!("no ELF machine is defined for cpu_id 0x{:04X}", cpu_id)),
+    };
+    if elf.e_machine != expected_machine {
+        return Err(format!(
+            "e_machine {} does not match --arch (expected {})",
+            elf.e_machine, expected_machine
+        ));
+    }
+
+    let e_entry = match entry {
+        None => elf.e_entry,
+        Some(Entry::Address(address)) => *address,
+        Some(Entry::Symbol(name)) => elf
+            .find_symbol(name)
+            .map(|sym| sym.st_value)
+            .ok_or_else(|| format!("entry symbol {} not found", name))?,
+    };
+
+    let mut sections = Vec::new();
+    for (i, ph) in elf.program_headers().enumerate() {
+        if ph.p_type != PT_LOAD {
+            continue;
+        }
+        let segment = elf
+            .segment_data(&ph)
+            .map_err(|_| format!("PT_LOAD segment {} out of bounds", i))?;
+        if ph.p_memsz < ph.p_filesz {
+            return Err(format!("PT_LOAD segment {} has p_memsz < p_filesz", i));
+        }
+        if ph.p_vaddr.checked_add(ph.p_memsz).is_none() {
+            return Err(format!("PT_LOAD segment {} wraps the address space", i));
+        }
+
+        let kind = if ph.p_flags & PF_X != 0 {
+            SectionKind::Text
+        } else if ph.p_flags & PF_W != 0 {
+            if ph.p_filesz == 0 {
+                SectionKind::Bss
+            } else {
+                SectionKind::Data
+            }
+        } else {
+            SectionKind::Rodata
+        };
+
+        let mut perms = 0;
+        if ph.p_flags & PF_R != 0 {
+            perms |= PERM_R;
+        }
+        if ph.p_flags & PF_W != 0 {
+            perms |= PERM_W;
+        }
+        if ph.p_flags & PF_X != 0 {
+            perms |= PERM_X;
+        }
+
+        sections.push(Section {
+            kind,
+            perms,
+            vaddr: ph.p_vaddr,
+            mem_size: ph.p_memsz,
+            data: segment.to_vec(),
+        });
+    }
+
+    if sections.is_empty() {
+        return Err("no PT_LOAD segments".into());
+    }
+
+    sections.sort_by_key(|s| s.vaddr);
+    let link_base = sections[0].vaddr & !(PAGE_SIZE - 1);
+    let image_end = sections.iter().map(|s| s.vaddr + s.mem_size).max().unwrap();
+    if e_entry < link_base || e_entry >= image_end {
+        return Err(format!("entry point 0x{:x} is outside the loaded image", e_entry));
+    }
+
+    if elf.e_type != ET_DYN {
+        return Ok(ElfImage {
+            image_base: link_base,
+            entry_offset: e_entry - link_base,
+            sections,
+            relocations: None,
+        });
+    }
+
+    let delta = base.wrapping_sub(link_base);
+    for section in &mut sections {
+        section.vaddr = section.vaddr.wrapping_add(delta);
+    }
+
+    let mut relocations = Vec::new();
+    for rela in elf.dynamic_relas().map_err(|e| e.to_string())? {
+        match dynamic_reloc(elf.e_machine, rela.r_type) {
+            Some(DynamicReloc::None) => continue,
+            Some(DynamicReloc::Relative) => {}
+            _ => return Err(format!("unsupported relocation type {} at 0x{:x}", rela.r_type, rela.r_offset)),
+        }
+        let target = rela.r_offset.wrapping_add(delta);
+        let (section, at) = sections
+            .iter_mut()
+            .find_map(|s| {
+                let at = target.checked_sub(s.vaddr)?;
+                (at.checked_add(8)? <= s.data.len() as u64).then_some((s, at as usize))
+            })
+            .ok_or_else(|| format!("relocation at 0x{:x} is outside the file-backed image", rela.r_offset))?;
+        section.data[at..at + 8].copy_from_slice(&(rela.r_addend as u64).wrapping_add(delta).to_le_bytes());
+        relocations.push(rela.r_offset.wrapping_sub(link_base));
+    }
+    relocations.sort_unstable();
+
+    Ok(ElfImage {
+        image_base: base,
+        entry_offset: e_entry - link_base,
+        sections,
+        relocations: Some(relocations),
+    })
+}
+
+fn cpu_from_name(name: &str) -> Option<u16> {
+    match name {
+        "aarch64" => Some(CPU_AARCH64),
+        "x86_64" => Some(CPU_X86_64),
+        "riscv64" => Some(CPU_RISCV64),
+        "prum64" => Some(CPU_PRUM64),
+        _ => None,
+    }
+}
+
+fn load_config(path: &str) -> PlumConfig {
+    let text = std::fs::read_to_string(path).unwrap_or_else(|e| {
+        eprintln!("❌ Failed to read {}: {}", path, e);
+        exit(1);
+    });
+    let config = Config::load(path, &text, &mut |include: &str| std::fs::read_to_string(include).ok())
+        .unwrap_or_else(|e| {
+            eprintln!("❌ {}", e);
+            exit(1);
+        });
+    if let Err(errors) = PLUM_CONFIG_SCHEMA.validate(&config) {
+        for e in &errors {
+            eprintln!("❌ {}", e);
+        }
+        exit(1);
+    }
+
+    let string = |section: &str, key: &str| config.get(section, key).and_then(|v| v.as_str()).map(str::to_string);
+    PlumConfig {
+        system: SystemConfig {
+            arch: string("system", "arch"),
+        },
+        plam: PlamConfig {
+            subsystem: string("plam", "subsystem"),
+            flags: config.get("plam", "flags").and_then(|v| v.as_list()).map(|flags| {
+                flags.iter().filter_map(|flag| flag.as_str().map(str::to_string)).collect()
+            }),
+        },
+    }
+}
+
+fn parse_size(s: &str) -> Option<u64> {
+    match s.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+fn perms_str(perms: u32) -> String {
+    format!(
+        "{}{}{}",
+        if perms & PERM_R != 0 { 'R' } else { '-' },
+        if perms & PERM_W != 0 { 'W' } else { '-' },
+        if perms & PERM_X != 0 { 'X' } else { '-' }
+    )
+}
+
 fn print_usage() {
-    eprintln!("Usage: mkplam <input.raw> <output.plam> --arch=aarch64|--arch=x86_64|--arch=riscv64|--arch=prum64");
+    eprintln!("Usage: mkplam <input.raw|input.elf> <output.plam> --arch=aarch64|--arch=x86_64|--arch=riscv64|--arch=prum64");
+    eprintln!("       mkplam [--text=<file>] [--rodata=<file>] [--data=<file>] [--bss=<size>] <output.plam> --arch=...");
+    eprintln!();
+    eprintln!("Options:");
+    eprintln!("  --image-base=<addr>        load address (default depends on --arch)");
+    eprintln!("  --entry=<addr|symbol>      entry point, a symbol name needs ELF input");
+    eprintln!("  --align=<bytes>            image base and section alignment (default 4096, not for ELF input)");
+    eprintln!("  --subsystem=<name>         native_kernel, driver, console_app, gui_app, wasm or firmware");
+    eprintln!("  --flags=<flag,...>         pie, aslr (replaces the automatic pie for ET_DYN input)");
+    eprintln!("  --config=<plum.config>     take --arch, --subsystem and --flags from [system] and [plam]");
+    eprintln!("  --compress                 store the payload as an LZ4 block");
+    eprintln!("  --sign <signing-key.hex>   embed an Ed25519 signature (key from ppm keygen)");
+    eprintln!();
+    eprintln!("PIE (ET_DYN) ELF input is rebased to --image-base (or the default) and marked");
+    eprintln!("relocatable, with its R_*_RELATIVE relocations kept for the loader.");
     eprintln!();
     eprintln!("Examples:");
     eprintln!("  mkplam kernel.bin kernel.plam --arch=x86_64");
+    eprintln!("  mkplam kernel.elf kernel.plam --arch=riscv64");
+    eprintln!("  mkplam kernel.elf kernel.plam --arch=x86_64 --sign keys/signing-key.hex");
     eprintln!("  mkplam bootloader.bin bootloader.plam --arch=aarch64");
-}
\ No newline at end of file
+    eprintln!("  mkplam driver.elf driver.plam --config=plum.config --subsystem=driver --entry=driver_main");
+    eprintln!("  mkplam --text=kernel.text --rodata=kernel.rodata --data=kernel.data --bss=0x4000 kernel.plam --arch=riscv64");
+}
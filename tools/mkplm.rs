@@ -0,0 +1,640 @@
+use std::env;
+use std::fs;
+use std::process::exit;
+
+use ed25519_dalek::SigningKey;
+use plum_formats::elf::{
+    dynamic_reloc, DynamicReloc, Elf, EM_AARCH64, EM_RISCV, EM_X86_64, ET_DYN, ET_EXEC, PF_R, PF_W, PF_X, PT_LOAD,
+    SHF_ALLOC, SHN_ABS, SHT_DYNSYM, SHT_RELA, STB_GLOBAL, STB_WEAK, STT_FUNC, STT_OBJECT,
+};
+use plum_formats::plam::{
+    arch_name, CPU_AARCH64, CPU_PRUM64, CPU_RISCV64, CPU_X86_64, PAGE_SIZE, PERM_R, PERM_W, PERM_X,
+};
+use plum_formats::plkmod::{ModuleKind, PlkmodBuilder, PlkmodFile, KERNEL_ABI};
+use plum_formats::plib::{PlibBuilder, PlibFile, EXPORT_FUNC, EXPORT_OBJECT};
+use plum_formats::plm::{Abi, PlmBuilder, PlmFile, DEFAULT_IMAGE_BASE, DEFAULT_STACK_SIZE, FLAG_PIE};
+use plum_formats::version::Version;
+
+struct Segment {
+    perms: u32,
+    vaddr: u64,
+    mem_size: u64,
+    data: Vec<u8>,
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+
+    let mut positional = Vec::new();
+    let mut cpu_id = None;
+    let mut abi = Abi::Native;
+    let mut stack_size = DEFAULT_STACK_SIZE;
+    let mut entry = None;
+    let mut image_base = None;
+    let mut library = (String::new(), Version::default());
+    let mut plib = None;
+    let mut plkmod = None;
+    let mut kind = ModuleKind::Module;
+    let mut class = String::new();
+    let mut description = String::new();
+    let mut kernel_abi = KERNEL_ABI;
+    let mut depends = Vec::new();
+    let mut exit_symbol = None;
+    let mut sign_key_path = None;
+
+    for arg in &args[1..] {
+        match arg.as_str() {
+            _ if arg.starts_with("--arch=") => {
+                cpu_id = Some(match &arg["--arch=".len()..] {
+                    "aarch64" => CPU_AARCH64,
+                    "x86_64" => CPU_X86_64,
+                    "riscv64" => CPU_RISCV64,
+                    "prum64" => CPU_PRUM64,
+                    _ => {
+                        eprintln!("❌ Unknown architecture: {}", arg);
+                        exit(1);
+                    }
+                })
+            }
+            _ if arg.starts_with("--abi=") => {
+                abi = Abi::from_name(&arg["--abi=".len()..]).unwrap_or_else(|| {
+                    eprintln!("❌ Unknown ABI: {}", arg);
+                    exit(1);
+                })
+            }
+            _ if arg.starts_with("--stack-size=") => {
+                stack_size = parse_size(&arg["--stack-size=".len()..]).unwrap_or_else(|| {
+                    eprintln!("❌ Invalid stack size: {}", arg);
+                    exit(1);
+                })
+            }
+            _ if arg.starts_with("--entry=") => entry = Some(arg["--entry=".len()..].to_string()),
+            _ if arg.starts_with("--image-base=") => {
+                image_base = Some(
+                    parse_size(&arg["--image-base=".len()..])
+                        .filter(|base| base.is_multiple_of(PAGE_SIZE))
+                        .unwrap_or_else(|| {
+                            eprintln!("❌ Invalid or misaligned image base: {}", arg);
+                            exit(1);
+                        }),
+                )
+            }
+            _ if arg.starts_with("--library=") => {
+                let value = &arg["--library=".len()..];
+                library = match value.split_once('@') {
+                    Some((name, version)) => (
+                        name.to_string(),
+                        Version::parse(version).unwrap_or_else(|| {
+                            eprintln!("❌ Invalid version in {}", arg);
+                            exit(1);
+                        }),
+                    ),
+                    None => (value.to_string(), Version::default()),
+                };
+            }
+            _ if arg.starts_with("--plib=") => plib = Some(parse_named_version(arg, "--plib=")),
+            _ if arg.starts_with("--plkmod=") => plkmod = Some(parse_named_version(arg, "--plkmod=")),
+            _ if arg.starts_with("--kind=") => {
+                kind = ModuleKind::from_name(&arg["--kind=".len()..]).unwrap_or_else(|| {
+                    eprintln!("❌ Unknown module kind: {}", arg);
+                    exit(1);
+                })
+            }
+            _ if arg.starts_with("--class=") => class = arg["--class=".len()..].to_string(),
+            _ if arg.starts_with("--description=") => description = arg["--description=".len()..].to_string(),
+            _ if arg.starts_with("--kernel-abi=") => {
+                kernel_abi = Version::parse(&arg["--kernel-abi=".len()..]).unwrap_or_else(|| {
+                    eprintln!("❌ Invalid version in {}", arg);
+                    exit(1);
+                })
+            }
+            _ if arg.starts_with("--depends=") => {
+                let value = &arg["--depends=".len()..];
+                depends.push(match value.split_once('@') {
+                    Some(_) => parse_named_version(arg, "--depends="),
+                    None => (value.to_string(), Version::default()),
+                });
+            }
+            _ if arg.starts_with("--exit=") => exit_symbol = Some(arg["--exit=".len()..].to_string()),
+            _ if arg.starts_with("--sign=") => sign_key_path = Some(arg["--sign=".len()..].to_string()),
+            _ if arg.starts_with("--") => {
+                eprintln!("❌ Unknown argument: {}", arg);
+                print_usage();
+                exit(1);
+            }
+            _ => positional.push(arg.clone()),
+        }
+    }
+
+    if positional.len() != 2 {
+        print_usage();
+        exit(1);
+    }
+    let (input_path, output_path) = (&positional[0], &positional[1]);
+
+    let raw = fs::read(input_path).unwrap_or_else(|e| {
+        eprintln!("❌ Failed to read {}: {}", input_path, e);
+        exit(1);
+    });
+    let elf = Elf::parse(&raw).unwrap_or_else(|e| {
+        eprintln!("❌ {}: {}", input_path, e);
+        exit(1);
+    });
+
+    let elf_cpu = match elf.e_machine {
+        EM_X86_64 => CPU_X86_64,
+        EM_AARCH64 => CPU_AARCH64,
+        EM_RISCV => CPU_RISCV64,
+        machine => {
+            eprintln!("❌ {}: unsupported e_machine {}", input_path, machine);
+            exit(1);
+        }
+    };
+    if cpu_id.is_some_and(|id| id != elf_cpu) {
+        eprintln!("❌ {} is built for {}, not --arch", input_path, arch_name(elf_cpu).unwrap());
+        exit(1);
+    }
+
+    if plib.is_some() && plkmod.is_some() {
+        eprintln!("❌ --plib and --plkmod are mutually exclusive");
+        exit(1);
+    }
+    if plkmod.is_none() && (exit_symbol.is_some() || sign_key_path.is_some() || !depends.is_empty()) {
+        eprintln!("❌ --exit, --sign and --depends only apply to --plkmod");
+        exit(1);
+    }
+    if plkmod.is_some() && entry.is_none() {
+        eprintln!("❌ A kernel module needs --entry=<init function>");
+        exit(1);
+    }
+    if (plib.is_some() || plkmod.is_some()) && elf.e_type != ET_DYN {
+        eprintln!("❌ {}: a .plib or .plkmod needs a shared object (ET_DYN)", input_path);
+        exit(1);
+    }
+
+    let shared = plib.is_some() || plkmod.is_some();
+    let mut plm = convert(&elf, elf_cpu, image_base, entry.as_deref(), &library, shared).unwrap_or_else(|e| {
+        eprintln!("❌ {}: {}", input_path, e);
+        exit(1);
+    });
+    plm.abi = abi;
+    plm.stack_size = stack_size;
+
+    let image = match (&plib, &plkmod) {
+        (Some((name, version)), _) => {
+            let mut builder = PlibBuilder::new(name, *version, plm);
+            for (symbol, kind, value) in exports(&elf) {
+                builder.add_export(symbol, kind, value - link_base(&elf));
+            }
+            builder.build()
+        }
+        (_, Some((name, version))) => {
+            let init_offset = plm.entry_offset;
+            let mut builder = PlkmodBuilder::new(name, *version, plm, init_offset);
+            builder.kind = kind;
+            builder.class = class;
+            builder.description = description;
+            builder.kernel_abi = kernel_abi;
+            builder.exit_offset = exit_symbol.map(|value| {
+                image_offset(&elf, &value).unwrap_or_else(|e| {
+                    eprintln!("❌ {}: exit: {}", input_path, e);
+                    exit(1);
+                })
+            });
+            for (dependency, min_version) in &depends {
+                builder.add_dependency(dependency, *min_version);
+            }
+            builder.build(sign_key_path.as_deref().map(load_signing_key).as_ref())
+        }
+        _ => plm.build(),
+    };
+
+    if let Err(e) = fs::write(output_path, &image) {
+        eprintln!("❌ Failed to write {}: {}", output_path, e);
+        exit(1);
+    }
+
+    let library_file = plib.as_ref().map(|_| {
+        PlibFile::parse(&image).unwrap_or_else(|e| {
+            eprintln!("❌ Produced an invalid PLIB: {}", e);
+            exit(1);
+        })
+    });
+    let module_file = plkmod.as_ref().map(|_| {
+        PlkmodFile::parse(&image).unwrap_or_else(|e| {
+            eprintln!("❌ Produced an invalid PLKMOD: {}", e);
+            exit(1);
+        })
+    });
+    let file = match (&library_file, &module_file) {
+        (Some(library_file), _) => library_file.image,
+        (_, Some(module_file)) => module_file.image,
+        _ => PlmFile::parse(&image).unwrap_or_else(|e| {
+            eprintln!("❌ Produced an invalid PLM: {}", e);
+            exit(1);
+        }),
+    };
+    println!("✅ Created {} ({} bytes)", output_path, image.len());
+    if let Some(library_file) = &library_file {
+        println!("   - Library: {} {}", library_file.name().unwrap(), library_file.lib_version());
+    }
+    if let Some(module_file) = &module_file {
+        println!(
+            "   - Module: {} {} ({}, kernel ABI {})",
+            module_file.name(),
+            module_file.mod_version(),
+            module_file.header.module_kind().name(),
+            module_file.kernel_abi()
+        );
+        for dependency in module_file.dependencies() {
+            println!("     depends on {} >= {}", dependency.name, dependency.min_version);
+        }
+        if let Ok(block) = module_file.signature() {
+            println!("   - Signed by: {}", hex::encode(block.public_key));
+        }
+    }
+    println!("   - Architecture: {}", arch_name(elf_cpu).unwrap());
+    println!("   - ABI: {}", abi.name());
+    println!("   - Image base: 0x{:x}{}", file.header.image_base, if file.header.is_pie() { " (PIE)" } else { "" });
+    println!("   - Entry offset: 0x{:x}", file.header.entry_offset);
+    println!("   - Stack size: {} bytes", file.header.stack_size);
+    println!("   - Segments: {}", file.header.segment_count);
+    for segment in file.segments() {
+        println!(
+            "     {} vaddr=0x{:x} offset=0x{:x} file={} mem={}",
+            perms_str(segment.perms),
+            segment.vaddr,
+            segment.file_offset,
+            segment.file_size,
+            segment.mem_size
+        );
+    }
+    println!("   - Relocations: {}", file.header.reloc_count);
+    println!("   - Imports: {}", file.header.import_count);
+    for import in file.imports() {
+        let from = if import.library.is_empty() { "*" } else { import.library };
+        println!("     {} from {} >= {} at +0x{:x}", import.symbol, from, import.min_version, import.slot);
+    }
+    if let Some(library_file) = &library_file {
+        println!("   - Exports: {}", library_file.header.export_count);
+        for export in library_file.exports() {
+            let kind = if export.kind == EXPORT_FUNC { "func" } else { "object" };
+            println!("     {} {} at +0x{:x}", kind, export.name, export.value);
+        }
+    }
+}
+
+/// Defined global and weak functions and objects of the dynamic symbol table,
+/// as link-time addresses.
+fn exports<'a>(elf: &Elf<'a>) -> Vec<(&'a str, u32, u64)> {
+    let mut exports: Vec<_> = elf
+        .section_headers()
+        .filter(|sh| sh.sh_type == SHT_DYNSYM)
+        .filter_map(|sh| elf.symbols(&sh).ok())
+        .flatten()
+        .filter(|sym| sym.is_defined() && sym.st_shndx != SHN_ABS)
+        .filter(|sym| sym.bind() == STB_GLOBAL || sym.bind() == STB_WEAK)
+        .filter_map(|sym| match sym.sym_type() {
+            STT_FUNC => Some((sym.name, EXPORT_FUNC, sym.st_value)),
+            STT_OBJECT => Some((sym.name, EXPORT_OBJECT, sym.st_value)),
+            _ => None,
+        })
+        .collect();
+    exports.sort_unstable();
+    exports.dedup_by_key(|export| export.0);
+    exports
+}
+
+/// Turns the PT_LOAD segments of `elf` into PLM segments. Dynamic relocations
+/// against the image become the PLM relocation table, relocations against
+/// undefined symbols become imports from `library`. A `shared` object may
+/// have no entry point.
+fn convert(
+    elf: &Elf,
+    cpu_id: u16,
+    image_base: Option<u64>,
+    entry: Option<&str>,
+    library: &(String, Version),
+    shared: bool,
+) -> Result<PlmBuilder, String> {
+    if elf.e_type != ET_EXEC && elf.e_type != ET_DYN {
+        return Err(format!("unsupported ELF type {}", elf.e_type));
+    }
+    let pie = elf.e_type == ET_DYN;
+
+    let mut segments: Vec<Segment> = elf
+        .program_headers()
+        .filter(|ph| ph.p_type == PT_LOAD)
+        .map(|ph| {
+            let data = elf.segment_data(&ph).map_err(|e| e.to_string())?;
+            if ph.p_memsz < ph.p_filesz {
+                return Err(format!("PT_LOAD segment at 0x{:x} has p_memsz < p_filesz", ph.p_vaddr));
+            }
+            let mut perms = 0;
+            if ph.p_flags & PF_R != 0 {
+                perms |= PERM_R;
+            }
+            if ph.p_flags & PF_W != 0 {
+                perms |= PERM_W;
+            }
+            if ph.p_flags & PF_X != 0 {
+                perms |= PERM_X;
+            }
+            Ok(Segment {
+                perms,
+                vaddr: ph.p_vaddr,
+                mem_size: ph.p_memsz,
+                data: data.to_vec(),
+            })
+        })
+        .collect::<Result<_, String>>()?;
+    if segments.is_empty() {
+        return Err("no PT_LOAD segments".into());
+    }
+    segments.sort_by_key(|s| s.vaddr);
+    let mut image_end = 0;
+    for segment in &segments {
+        if segment.vaddr < image_end {
+            return Err(format!("PT_LOAD segment at 0x{:x} overlaps the segment before it", segment.vaddr));
+        }
+        image_end = segment.vaddr.checked_add(segment.mem_size).ok_or_else(|| {
+            format!("PT_LOAD segment at 0x{:x} extends past the end of the address space", segment.vaddr)
+        })?;
+    }
+
+    let link_base = segments[0].vaddr & !(PAGE_SIZE - 1);
+    let base = match (pie, image_base) {
+        (true, Some(base)) => base,
+        (true, None) => DEFAULT_IMAGE_BASE,
+        (false, Some(base)) if base != link_base => {
+            return Err(format!("not position-independent, linked at 0x{:x}", link_base));
+        }
+        (false, _) => link_base,
+    };
+    if base.checked_add(image_end - link_base).is_none() {
+        return Err(format!("image of {} bytes does not fit at 0x{:x}", image_end - link_base, base));
+    }
+    let delta = base.wrapping_sub(link_base);
+
+    let e_entry = match entry {
+        None if shared && elf.e_entry == 0 => link_base,
+        None => elf.e_entry,
+        Some(value) => resolve_address(elf, value)?,
+    };
+    if e_entry < link_base || e_entry >= image_end {
+        return Err(format!("entry point 0x{:x} is outside the loaded image", e_entry));
+    }
+
+    for segment in &mut segments {
+        segment.vaddr = segment.vaddr.wrapping_add(delta);
+    }
+
+    let mut plm = PlmBuilder::new(cpu_id, base, e_entry - link_base);
+    let mut relocations = Vec::new();
+
+    for rela_section in elf.section_headers().filter(|sh| sh.sh_type == SHT_RELA && sh.sh_flags & SHF_ALLOC != 0) {
+        let symtab = elf.section_header(rela_section.sh_link as usize);
+        for rela in elf.relas(&rela_section).map_err(|e| e.to_string())? {
+            let kind = dynamic_reloc(elf.e_machine, rela.r_type)
+                .ok_or_else(|| format!("unsupported relocation type {} at 0x{:x}", rela.r_type, rela.r_offset))?;
+            let value = match kind {
+                DynamicReloc::None => continue,
+                DynamicReloc::Relative => Some((rela.r_addend as u64).wrapping_add(delta)),
+                DynamicReloc::Symbol => {
+                    let symtab = symtab.ok_or("relocation section has no symbol table")?;
+                    let sym = elf.symbol(&symtab, rela.r_sym).map_err(|e| e.to_string())?;
+                    if sym.is_defined() {
+                        Some(sym.st_value.wrapping_add(rela.r_addend as u64).wrapping_add(delta))
+                    } else {
+                        plm.add_import(&library.0, sym.name, library.1, rela.r_offset - link_base, rela.r_addend);
+                        None
+                    }
+                }
+            };
+
+            let target = rela.r_offset.wrapping_add(delta);
+            let segment = segments
+                .iter_mut()
+                .find(|s| {
+                    target >= s.vaddr && target.checked_add(8).is_some_and(|end| end <= s.vaddr + s.data.len() as u64)
+                })
+                .ok_or_else(|| format!("relocation at 0x{:x} is outside the file-backed image", rela.r_offset))?;
+            let at = (target - segment.vaddr) as usize;
+            segment.data[at..at + 8].copy_from_slice(&value.unwrap_or(0).to_le_bytes());
+            if value.is_some() && pie {
+                relocations.push(rela.r_offset - link_base);
+            }
+        }
+    }
+
+    relocations.sort_unstable();
+    for offset in relocations {
+        plm.add_relocation(offset);
+    }
+    if pie {
+        plm.flags |= FLAG_PIE;
+    }
+    for segment in segments {
+        plm.add_segment(segment.perms, segment.vaddr, segment.data, segment.mem_size);
+    }
+
+    Ok(plm)
+}
+
+fn link_base(elf: &Elf) -> u64 {
+    elf.program_headers()
+        .filter(|ph| ph.p_type == PT_LOAD)
+        .map(|ph| ph.p_vaddr)
+        .min()
+        .unwrap_or(0)
+        & !(PAGE_SIZE - 1)
+}
+
+/// Link-time address of `value`, either a number or a defined symbol.
+fn resolve_address(elf: &Elf, value: &str) -> Result<u64, String> {
+    match parse_size(value) {
+        Some(address) => Ok(address),
+        None => elf
+            .find_symbol(value)
+            .map(|sym| sym.st_value)
+            .ok_or_else(|| format!("symbol {} not found", value)),
+    }
+}
+
+/// Offset of `value` from the image base, checked against the PT_LOAD extent.
+fn image_offset(elf: &Elf, value: &str) -> Result<u64, String> {
+    let address = resolve_address(elf, value)?;
+    let image_end = elf
+        .program_headers()
+        .filter(|ph| ph.p_type == PT_LOAD)
+        .map(|ph| ph.p_vaddr.saturating_add(ph.p_memsz))
+        .max()
+        .unwrap_or(0);
+    if address < link_base(elf) || address >= image_end {
+        return Err(format!("0x{:x} is outside the loaded image", address));
+    }
+    Ok(address - link_base(elf))
+}
+
+/// Parses the `<name>@<version>` value of `--<option>=`.
+fn parse_named_version(arg: &str, option: &str) -> (String, Version) {
+    match arg[option.len()..].split_once('@') {
+        Some((name, version)) if !name.is_empty() => (
+            name.to_string(),
+            Version::parse(version).unwrap_or_else(|| {
+                eprintln!("❌ Invalid version in {}", arg);
+                exit(1);
+            }),
+        ),
+        _ => {
+            eprintln!("❌ Expected {}<name>@<version>: {}", option, arg);
+            exit(1);
+        }
+    }
+}
+
+fn load_signing_key(path: &str) -> SigningKey {
+    let key_hex = fs::read_to_string(path).unwrap_or_else(|e| {
+        eprintln!("❌ Failed to read signing key {}: {}", path, e);
+        exit(1);
+    });
+    let key_bytes: [u8; 32] = hex::decode(key_hex.trim())
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .unwrap_or_else(|| {
+            eprintln!("❌ {} is not a hex-encoded Ed25519 signing key", path);
+            exit(1);
+        });
+    SigningKey::from_bytes(&key_bytes)
+}
+
+fn parse_size(s: &str) -> Option<u64> {
+    match s.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+fn perms_str(perms: u32) -> String {
+    format!(
+        "{}{}{}",
+        if perms & PERM_R != 0 { 'R' } else { '-' },
+        if perms & PERM_W != 0 { 'W' } else { '-' },
+        if perms & PERM_X != 0 { 'X' } else { '-' }
+    )
+}
+
+fn print_usage() {
+    eprintln!("Usage: mkplm <input.elf> <output.plm|.plib|.plkmod> [options]");
+    eprintln!();
+    eprintln!("Options:");
+    eprintln!("  --arch=<arch>              fail unless the ELF is built for aarch64, x86_64 or riscv64");
+    eprintln!("  --abi=<abi>                native, posix, win32, darwin, android or linux (default native)");
+    eprintln!("  --stack-size=<bytes>       main thread stack size (default {})", DEFAULT_STACK_SIZE);
+    eprintln!("  --entry=<addr|symbol>      entry point instead of e_entry");
+    eprintln!("  --image-base=<addr>        preferred load address of a PIE (default 0x{:x})", DEFAULT_IMAGE_BASE);
+    eprintln!("  --library=<name>[@<ver>]   library that undefined symbols are imported from");
+    eprintln!("  --plib=<name>@<ver>        write a .plib exporting the shared object's dynamic symbols");
+    eprintln!("  --plkmod=<name>@<ver>      write a kernel module; --entry names its init function");
+    eprintln!("  --kind=<kind>              module, driver or service (default module)");
+    eprintln!("  --class=<class>            driver class matching plum.config [drivers] (usb, nvme, ...)");
+    eprintln!("  --description=<text>       one-line module description");
+    eprintln!("  --kernel-abi=<ver>         kernel ABI the module needs (default {})", KERNEL_ABI);
+    eprintln!("  --depends=<name>[@<ver>]   module that must be loaded first (repeatable)");
+    eprintln!("  --exit=<addr|symbol>       exit function of an unloadable module");
+    eprintln!("  --sign=<signing-key.hex>   embed an Ed25519 signature in the module");
+    eprintln!();
+    eprintln!("Examples:");
+    eprintln!("  mkplm hello.elf hello.plm --abi=posix");
+    eprintln!("  mkplm psh.elf psh.plm --library=libplum@1.0 --stack-size=0x40000");
+    eprintln!("  mkplm libplum.so libplum.plib --plib=libplum@1.2.0");
+    eprintln!("  mkplm nvme.so nvme.pkd --plkmod=nvme@0.3 --kind=driver --class=nvme --entry=nvme_init \\");
+    eprintln!("        --exit=nvme_exit --depends=pci@1.0 --sign=keys/signing-key.hex");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plum_formats::elf::ELF_MAGIC;
+
+    /// An x86_64 `ET_EXEC` with one PT_LOAD per `(p_flags, p_vaddr, data,
+    /// p_memsz)`, each segment's data following the program headers.
+    fn executable(segments: &[(u32, u64, &[u8], u64)], entry: u64) -> Vec<u8> {
+        let phoff = 64;
+        let mut data = vec![0u8; phoff + segments.len() * 56];
+        data[0..4].copy_from_slice(&ELF_MAGIC);
+        data[4..7].copy_from_slice(&[2, 1, 1]);
+        data[0x10..0x12].copy_from_slice(&ET_EXEC.to_le_bytes());
+        data[0x12..0x14].copy_from_slice(&EM_X86_64.to_le_bytes());
+        data[0x18..0x20].copy_from_slice(&entry.to_le_bytes());
+        data[0x20..0x28].copy_from_slice(&(phoff as u64).to_le_bytes());
+        data[0x36..0x38].copy_from_slice(&56u16.to_le_bytes());
+        data[0x38..0x3A].copy_from_slice(&(segments.len() as u16).to_le_bytes());
+
+        for (i, (flags, vaddr, contents, mem_size)) in segments.iter().enumerate() {
+            let offset = data.len() as u64;
+            data.extend_from_slice(contents);
+            let ph = &mut data[phoff + i * 56..][..56];
+            ph[0x00..0x04].copy_from_slice(&PT_LOAD.to_le_bytes());
+            ph[0x04..0x08].copy_from_slice(&flags.to_le_bytes());
+            ph[0x08..0x10].copy_from_slice(&offset.to_le_bytes());
+            ph[0x10..0x18].copy_from_slice(&vaddr.to_le_bytes());
+            ph[0x20..0x28].copy_from_slice(&(contents.len() as u64).to_le_bytes());
+            ph[0x28..0x30].copy_from_slice(&mem_size.to_le_bytes());
+        }
+        data
+    }
+
+    fn convert_exec(data: &[u8]) -> Result<PlmBuilder, String> {
+        let elf = Elf::parse(data).unwrap();
+        convert(&elf, CPU_X86_64, None, None, &(String::new(), Version::default()), false)
+    }
+
+    #[test]
+    fn convert_round_trip() {
+        let text = [0x90, 0x90, 0xC3];
+        let data = [1, 2, 3, 4, 5, 6, 7, 8];
+        let elf = executable(&[(PF_R | PF_X, 0x40_0000, &text, 3), (PF_R | PF_W, 0x40_1000, &data, 0x20)], 0x40_0001);
+        let image = convert_exec(&elf).unwrap().build();
+
+        let file = PlmFile::parse(&image).unwrap();
+        assert_eq!((file.header.image_base, file.header.entry_offset), (0x40_0000, 1));
+        assert!(!file.header.is_pie());
+        let segments: Vec<_> = file.segments().map(|s| (s.perms, s.vaddr, s.file_size, s.mem_size)).collect();
+        assert_eq!(segments, [(PERM_R | PERM_X, 0x40_0000, 3, 3), (PERM_R | PERM_W, 0x40_1000, 8, 0x20)]);
+
+        let mut memory = vec![0xAA; file.image_size() as usize];
+        file.load(&mut memory).unwrap();
+        assert_eq!(memory[..3], text);
+        assert_eq!(memory[0x1000..0x1008], data);
+        assert!(memory[0x1008..].iter().all(|b| *b == 0));
+    }
+
+    #[test]
+    fn convert_rejects_truncated_segment() {
+        let mut elf = executable(&[(PF_R | PF_X, 0x40_0000, &[0xC3; 16], 16)], 0x40_0000);
+        elf.truncate(elf.len() - 1);
+        assert_eq!(convert_exec(&elf).err().unwrap(), "segment data out of bounds");
+    }
+
+    #[test]
+    fn convert_rejects_overlapping_segments() {
+        let segments: [(u32, u64, &[u8], u64); 2] =
+            [(PF_R | PF_X, 0x40_0000, &[0xC3; 16], 0x20), (PF_R | PF_W, 0x40_0010, &[0; 8], 8)];
+        let elf = executable(&segments, 0x40_0000);
+        assert_eq!(convert_exec(&elf).err().unwrap(), "PT_LOAD segment at 0x400010 overlaps the segment before it");
+    }
+
+    #[test]
+    fn convert_rejects_segment_past_address_space() {
+        let top = u64::MAX - PAGE_SIZE + 1;
+        let elf = executable(&[(PF_R | PF_X, top, &[0xC3], PAGE_SIZE)], top);
+        assert_eq!(
+            convert_exec(&elf).err().unwrap(),
+            format!("PT_LOAD segment at 0x{:x} extends past the end of the address space", top)
+        );
+
+        let elf = executable(&[(PF_R | PF_X, 0x40_0000, &[0xC3; 4], 3)], 0x40_0000);
+        assert_eq!(convert_exec(&elf).err().unwrap(), "PT_LOAD segment at 0x400000 has p_memsz < p_filesz");
+    }
+}
@@ -0,0 +1,284 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::exit;
+
+use plum_formats::lz4::Compressor;
+use plum_formats::plconf::{Config, Field, Schema, SectionSchema, Type};
+use plum_formats::plres::{
+    format_name, PlresBuilder, PlresFile, ResourceKind, FORMAT_BMP, FORMAT_OTF, FORMAT_PNG, FORMAT_RAW, FORMAT_TTF,
+};
+
+/// Fields shared by every `[[image]]`, `[[font]]`, `[[strings]]` and
+/// `[[blob]]` section of a manifest. Paths are relative to the manifest.
+const RESOURCE_FIELDS: &[Field] = &[
+    Field::required("id", Type::Str),
+    Field::required("path", Type::Str),
+    Field::new("locale", Type::Str),
+];
+
+const MANIFEST_SCHEMA: Schema = Schema {
+    sections: &[
+        SectionSchema { name: "image", repeated: true, required: false, fields: RESOURCE_FIELDS },
+        SectionSchema { name: "font", repeated: true, required: false, fields: RESOURCE_FIELDS },
+        SectionSchema { name: "strings", repeated: true, required: false, fields: RESOURCE_FIELDS },
+        SectionSchema { name: "blob", repeated: true, required: false, fields: RESOURCE_FIELDS },
+    ],
+};
+
+struct Input {
+    kind: ResourceKind,
+    id: String,
+    locale: String,
+    path: PathBuf,
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+
+    if args.len() == 3 && args[1] == "--list" {
+        list(&args[2]);
+        return;
+    }
+
+    let mut output = None;
+    let mut inputs = Vec::new();
+    let mut compress = false;
+
+    for arg in &args[1..] {
+        match arg.as_str() {
+            "--compress" | "--compress=lz4" => compress = true,
+            _ if arg.starts_with("--manifest=") => inputs.extend(load_manifest(&arg["--manifest=".len()..])),
+            _ if arg.starts_with("--") => {
+                let Some((kind, value)) = arg[2..].split_once('=') else {
+                    eprintln!("❌ Unknown argument: {}", arg);
+                    print_usage();
+                    exit(1);
+                };
+                let kind = ResourceKind::from_name(kind).unwrap_or_else(|| {
+                    eprintln!("❌ Unknown argument: {}", arg);
+                    print_usage();
+                    exit(1);
+                });
+                inputs.push(parse_input(kind, value).unwrap_or_else(|| {
+                    eprintln!("❌ Expected <id>[@<locale>]:<path> in {}", arg);
+                    exit(1);
+                }));
+            }
+            _ if output.is_none() => output = Some(arg.clone()),
+            _ => {
+                print_usage();
+                exit(1);
+            }
+        }
+    }
+
+    let Some(output) = output else {
+        print_usage();
+        exit(1);
+    };
+    if inputs.is_empty() {
+        eprintln!("❌ No resources given");
+        exit(1);
+    }
+
+    let mut builder = PlresBuilder::new();
+    for input in &inputs {
+        add_resource(&mut builder, input);
+    }
+
+    let compressor: Option<Compressor> = compress.then_some(lz4_flex::block::compress);
+    let bundle = builder.build(compressor);
+    fs::write(&output, &bundle).unwrap_or_else(|e| {
+        eprintln!("❌ Failed to write {}: {}", output, e);
+        exit(1);
+    });
+
+    let file = PlresFile::parse(&bundle).expect("builder produced an invalid bundle");
+    println!("✅ Created {}", output);
+    println!("   - Resources: {}", file.header.entry_count);
+    println!("   - Size: {} bytes", bundle.len());
+}
+
+/// Parses `<id>[@<locale>]:<path>`.
+fn parse_input(kind: ResourceKind, value: &str) -> Option<Input> {
+    let (name, path) = value.split_once(':')?;
+    let (id, locale) = name.split_once('@').unwrap_or((name, ""));
+    if id.is_empty() || path.is_empty() {
+        return None;
+    }
+    Some(Input {
+        kind,
+        id: id.to_string(),
+        locale: locale.to_string(),
+        path: PathBuf::from(path),
+    })
+}
+
+fn load_manifest(path: &str) -> Vec<Input> {
+    let text = fs::read_to_string(path).unwrap_or_else(|e| {
+        eprintln!("❌ Failed to read {}: {}", path, e);
+        exit(1);
+    });
+    let config = Config::load(path, &text, &mut |include: &str| fs::read_to_string(include).ok())
+        .unwrap_or_else(|e| {
+            eprintln!("❌ {}", e);
+            exit(1);
+        });
+    if let Err(errors) = MANIFEST_SCHEMA.validate(&config) {
+        for e in &errors {
+            eprintln!("❌ {}", e);
+        }
+        exit(1);
+    }
+
+    let base = Path::new(path).parent().unwrap_or(Path::new(""));
+    let mut inputs = Vec::new();
+    for section in &config.sections {
+        let Some(kind) = ResourceKind::from_name(&section.name) else {
+            continue;
+        };
+        let field = |key: &str| section.get(key).and_then(|v| v.as_str()).unwrap_or_default().to_string();
+        inputs.push(Input {
+            kind,
+            id: field("id"),
+            locale: field("locale"),
+            path: base.join(field("path")),
+        });
+    }
+    inputs
+}
+
+fn add_resource(builder: &mut PlresBuilder, input: &Input) {
+    let data = fs::read(&input.path).unwrap_or_else(|e| {
+        eprintln!("❌ Failed to read {}: {}", input.path.display(), e);
+        exit(1);
+    });
+
+    match input.kind {
+        ResourceKind::Image => {
+            let (format, width, height) = image_info(&data).unwrap_or_else(|| {
+                eprintln!("❌ {} is not a BMP or PNG image", input.path.display());
+                exit(1);
+            });
+            builder.add_image(&input.id, &input.locale, input.kind, format, data, width, height);
+        }
+        ResourceKind::Font => {
+            let format = font_format(&data).unwrap_or_else(|| {
+                eprintln!("❌ {} is not a TrueType or OpenType font", input.path.display());
+                exit(1);
+            });
+            builder.add(&input.id, &input.locale, input.kind, format, data);
+        }
+        ResourceKind::Strings => add_strings(builder, input, &data),
+        ResourceKind::Blob => builder.add(&input.id, &input.locale, input.kind, FORMAT_RAW, data),
+    }
+}
+
+/// A strings file is a `.plconf` whose top-level keys are the text without
+/// a locale and whose `[<locale>]` sections hold the translations.
+fn add_strings(builder: &mut PlresBuilder, input: &Input, data: &[u8]) {
+    let name = input.path.display().to_string();
+    let text = String::from_utf8_lossy(data);
+    let config = Config::parse(&name, &text).unwrap_or_else(|e| {
+        eprintln!("❌ {}", e);
+        exit(1);
+    });
+
+    for section in &config.sections {
+        if section.repeated {
+            eprintln!("❌ {}: [[{}]] is not a locale section", name, section.name);
+            exit(1);
+        }
+        let mut pairs = Vec::new();
+        for entry in &section.entries {
+            let Some(text) = entry.value.as_str() else {
+                eprintln!(
+                    "❌ {}:{}:{}: {} must be a string",
+                    name, entry.location.line, entry.location.column, entry.key
+                );
+                exit(1);
+            };
+            pairs.push((entry.key.as_str(), text));
+        }
+        // Without a locale on the command line the sections decide it.
+        let locale = if section.name.is_empty() { &input.locale } else { &section.name };
+        builder.add_strings(&input.id, locale, &pairs);
+    }
+}
+
+fn image_info(data: &[u8]) -> Option<(u16, u32, u32)> {
+    if data.starts_with(b"BM") && data.len() >= 26 {
+        let width = i32::from_le_bytes(data[18..22].try_into().unwrap());
+        let height = i32::from_le_bytes(data[22..26].try_into().unwrap());
+        Some((FORMAT_BMP, width.unsigned_abs(), height.unsigned_abs()))
+    } else if data.starts_with(b"\x89PNG\r\n\x1a\n") && data.len() >= 24 {
+        let width = u32::from_be_bytes(data[16..20].try_into().unwrap());
+        let height = u32::from_be_bytes(data[20..24].try_into().unwrap());
+        Some((FORMAT_PNG, width, height))
+    } else {
+        None
+    }
+}
+
+fn font_format(data: &[u8]) -> Option<u16> {
+    match data.get(..4)? {
+        b"\x00\x01\x00\x00" | b"true" => Some(FORMAT_TTF),
+        b"OTTO" => Some(FORMAT_OTF),
+        _ => None,
+    }
+}
+
+fn list(path: &str) {
+    let data = fs::read(path).unwrap_or_else(|e| {
+        eprintln!("❌ Failed to read {}: {}", path, e);
+        exit(1);
+    });
+    let file = PlresFile::parse(&data).unwrap_or_else(|e| {
+        eprintln!("❌ {}: {}", path, e);
+        exit(2);
+    });
+
+    println!("{:<24} {:<8} {:<8} {:<8} {:>10} {:>10}  details", "id", "locale", "kind", "format", "size", "stored");
+    let mut status = 0;
+    for resource in file.resources() {
+        let details = match resource.kind {
+            ResourceKind::Image => format!("{}x{}", resource.width, resource.height),
+            ResourceKind::Strings => file
+                .strings(resource.id, resource.locale)
+                .map(|table| format!("{} strings", table.len()))
+                .unwrap_or_default(),
+            _ => String::new(),
+        };
+        println!(
+            "{:<24} {:<8} {:<8} {:<8} {:>10} {:>10}  {}",
+            resource.id,
+            if resource.locale.is_empty() { "-" } else { resource.locale },
+            resource.kind.name(),
+            format_name(resource.format).unwrap_or("?"),
+            resource.size,
+            resource.stored.len(),
+            details
+        );
+        if let Err(e) = resource.to_vec() {
+            eprintln!("❌ {}: {}", resource.id, e);
+            status = 2;
+        }
+    }
+    exit(status);
+}
+
+fn print_usage() {
+    eprintln!("Usage: mkplres <output.plres> [--manifest=<resources.plconf>] [resources...] [--compress]");
+    eprintln!("       mkplres --list <file.plres>");
+    eprintln!();
+    eprintln!("Resources:");
+    eprintln!("  --image=<id>[@<locale>]:<path>    BMP or PNG image");
+    eprintln!("  --font=<id>[@<locale>]:<path>     TrueType or OpenType font");
+    eprintln!("  --strings=<id>[@<locale>]:<path>  string table, a .plconf with one section per locale");
+    eprintln!("  --blob=<id>[@<locale>]:<path>     any other file");
+    eprintln!();
+    eprintln!("A manifest lists the same resources as [[image]], [[font]], [[strings]] and");
+    eprintln!("[[blob]] sections with id, path and optional locale keys.");
+    eprintln!("--compress stores resources as LZ4 blocks where that makes them smaller.");
+}
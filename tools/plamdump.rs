@@ -0,0 +1,305 @@
+use std::env;
+use std::fs;
+use std::process::exit;
+
+use plum_formats::plam::{
+    arch_name, validate, verify_signature, PlamError, PlamHeader, PlamSection, Subsystem, FLAG_ASLR, HEADER_SIZE,
+    PAGE_SIZE, PERM_R, PERM_W, PERM_X,
+};
+
+const MAX_PAYLOAD_SIZE: u64 = 1024 * 1024 * 1024;
+
+enum DumpError {
+    Io(String),
+    Format(PlamError),
+    UnknownCpu(u16),
+    Misaligned(String),
+    BadSectionTable(String),
+    BadEntry(u64),
+}
+
+impl DumpError {
+    fn code(&self) -> i32 {
+        match self {
+            DumpError::Io(_) => 1,
+            DumpError::Format(PlamError::BadMagic(_)) => 2,
+            DumpError::Format(PlamError::UnsupportedVersion(_)) => 3,
+            DumpError::Format(PlamError::TooShort) => 4,
+            DumpError::Format(PlamError::BadSectionTable) => 7,
+            DumpError::Format(PlamError::NotSigned | PlamError::BadSignatureBlock) => 9,
+            DumpError::Format(PlamError::UntrustedKey) => 10,
+            DumpError::Format(PlamError::BadSignature) => 11,
+            DumpError::Format(PlamError::BadPayload) => 12,
+            DumpError::Format(PlamError::PayloadChecksum) => 13,
+            DumpError::Format(PlamError::SizeMismatch { .. }) => 4,
+            DumpError::Format(PlamError::PayloadHash) => 13,
+            DumpError::Format(PlamError::HeaderChecksum) => 14,
+            DumpError::Format(PlamError::NotRelocatable | PlamError::BadRelocation) => 15,
+            DumpError::UnknownCpu(_) => 5,
+            DumpError::Misaligned(_) => 6,
+            DumpError::BadSectionTable(_) => 7,
+            DumpError::BadEntry(_) => 8,
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            DumpError::Io(e) => e.clone(),
+            DumpError::Format(e) => e.to_string(),
+            DumpError::UnknownCpu(id) => format!("unknown cpu_id 0x{:04X}", id),
+            DumpError::Misaligned(what) => format!("misaligned {}", what),
+            DumpError::BadSectionTable(what) => format!("bad section table: {}", what),
+            DumpError::BadEntry(entry) => format!("entry offset 0x{:x} is outside every executable section", entry),
+        }
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+
+    let mut quiet = false;
+    let mut path = None;
+    let mut trusted_keys = Vec::new();
+
+    let mut iter = args[1..].iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-q" | "--quiet" => quiet = true,
+            "--trusted" => match iter.next() {
+                Some(key_path) => trusted_keys.push(load_public_key(key_path)),
+                None => {
+                    print_usage();
+                    exit(1);
+                }
+            },
+            _ if path.is_none() && !arg.starts_with('-') => path = Some(arg.clone()),
+            _ => {
+                print_usage();
+                exit(1);
+            }
+        }
+    }
+
+    let path = path.unwrap_or_else(|| {
+        print_usage();
+        exit(1);
+    });
+
+    match dump(&path, quiet, &trusted_keys) {
+        Ok(()) => {
+            if !quiet {
+                println!("✅ {} is a valid PLAM image", path);
+            }
+        }
+        Err(e) => {
+            eprintln!("❌ {}: {}", path, e.message());
+            exit(e.code());
+        }
+    }
+}
+
+fn dump(path: &str, quiet: bool, trusted_keys: &[[u8; 32]]) -> Result<(), DumpError> {
+    let image = fs::read(path).map_err(|e| DumpError::Io(format!("failed to read: {}", e)))?;
+
+    let header = PlamHeader::parse(&image).map_err(DumpError::Format)?;
+    let architecture_name = arch_name(header.cpu_id);
+
+    if !quiet {
+        println!("PLAM header:");
+        println!("   - Magic: PLAM");
+        println!("   - Version: {}.{}", header.version >> 8, header.version & 0xFF);
+        println!("   - Flags: 0x{:016x}", header.flags);
+        match Subsystem::from_u16(header.subsystem) {
+            Some(subsystem) => println!("   - Subsystem: {}", subsystem.name()),
+            None => println!("   - Subsystem: unknown ({})", header.subsystem),
+        }
+        println!("   - File size: {} bytes", header.file_size);
+        println!(
+            "   - Architecture: {} (cpu_id 0x{:04X})",
+            architecture_name.unwrap_or("unknown"),
+            header.cpu_id
+        );
+        println!("   - Image base: 0x{:x}", header.image_base);
+        println!("   - Entry offset: 0x{:x}", header.entry_offset);
+        println!("   - Sections: {} (table at 0x{:x})", header.section_count, header.section_table_offset);
+        if header.is_compressed() {
+            println!(
+                "   - Payload: {} bytes, LZ4-compressed to {} bytes",
+                header.payload_size, header.compressed_size
+            );
+        } else {
+            println!("   - Payload: {} bytes", header.payload_size);
+        }
+        println!("   - Payload CRC32: 0x{:08x}", header.payload_crc32);
+        println!("   - Payload SHA256: {}", hex::encode(header.payload_sha256));
+        println!("   - Header CRC32: 0x{:08x}", header.header_crc32);
+        if header.is_relocatable() {
+            println!("   - Relocatable: {} relocations at 0x{:x}", header.reloc_count, header.reloc_offset);
+        }
+        if header.flags & FLAG_ASLR != 0 {
+            println!("   - ASLR: enabled");
+        }
+        if let Ok(block) = header.signature(&image) {
+            println!("   - Signed by: {}", hex::encode(block.public_key));
+        }
+    }
+
+    validate(&image).map_err(DumpError::Format)?;
+    if architecture_name.is_none() {
+        return Err(DumpError::UnknownCpu(header.cpu_id));
+    }
+    if !header.image_base.is_multiple_of(PAGE_SIZE) {
+        return Err(DumpError::Misaligned(format!("image base 0x{:x}", header.image_base)));
+    }
+    if header.payload_size > MAX_PAYLOAD_SIZE {
+        return Err(DumpError::Format(PlamError::BadPayload));
+    }
+
+    let mut inflated = image[..HEADER_SIZE].to_vec();
+    inflated.resize(header.image_size() as usize, 0);
+    header
+        .load_payload(&image, &mut inflated[HEADER_SIZE..])
+        .map_err(DumpError::Format)?;
+
+    let sections: Vec<PlamSection> = header
+        .sections(&image)
+        .map_err(DumpError::Format)?
+        .collect();
+
+    if !quiet && !sections.is_empty() {
+        println!();
+        println!("  {:<3} {:<8} {:<4} {:<4} {:>18} {:>10} {:>10} {:>10}", "#", "Name", "Kind", "Perm", "VAddr", "Offset", "FileSz", "MemSz");
+        for (i, section) in sections.iter().enumerate() {
+            println!(
+                "  {:<3} {:<8} {:<4} {:<4} {:>#18x} {:>#10x} {:>10} {:>10}",
+                i,
+                section.name(),
+                section.kind,
+                perms_str(section.perms),
+                section.vaddr,
+                section.file_offset,
+                section.file_size,
+                section.mem_size
+            );
+        }
+        println!();
+    }
+
+    let entry = header
+        .image_base
+        .checked_add(header.entry_offset)
+        .ok_or(DumpError::BadEntry(header.entry_offset))?;
+    let mut entry_found = false;
+    let mut ends = Vec::with_capacity(sections.len());
+    for (i, section) in sections.iter().enumerate() {
+        if section.section_kind().is_none() {
+            return Err(DumpError::BadSectionTable(format!("section {} has unknown kind {}", i, section.kind)));
+        }
+        if section.perms & !(PERM_R | PERM_W | PERM_X) != 0 {
+            return Err(DumpError::BadSectionTable(format!("section {} has unknown permission bits", i)));
+        }
+        if section.file_size > section.mem_size {
+            return Err(DumpError::BadSectionTable(format!("section {} has file size larger than memory size", i)));
+        }
+        if section.vaddr < header.image_base {
+            return Err(DumpError::BadSectionTable(format!("section {} is below the image base", i)));
+        }
+        let end = section.vaddr.checked_add(section.mem_size).ok_or_else(|| {
+            DumpError::BadSectionTable(format!("section {} extends past the end of the address space", i))
+        })?;
+        if section.file_size > 0 {
+            let file_end = section.file_offset.checked_add(section.file_size);
+            if section.file_offset < HEADER_SIZE as u64
+                || file_end.is_none_or(|file_end| file_end > header.image_size())
+            {
+                return Err(DumpError::BadSectionTable(format!("section {} data lies outside the file", i)));
+            }
+            if section.file_offset % PAGE_SIZE != section.vaddr % PAGE_SIZE {
+                return Err(DumpError::Misaligned(format!(
+                    "section {} (offset 0x{:x} vs vaddr 0x{:x})",
+                    i, section.file_offset, section.vaddr
+                )));
+            }
+        }
+
+        if section.perms & PERM_X != 0 && entry >= section.vaddr && entry < end {
+            entry_found = true;
+        }
+        ends.push(end);
+    }
+
+    for (i, (a, a_end)) in sections.iter().zip(&ends).enumerate() {
+        for (b, b_end) in sections[i + 1..].iter().zip(&ends[i + 1..]) {
+            if a.vaddr < *b_end && b.vaddr < *a_end {
+                return Err(DumpError::BadSectionTable(format!("sections {} and {} overlap", a.name(), b.name())));
+            }
+        }
+    }
+
+    if !entry_found {
+        return Err(DumpError::BadEntry(header.entry_offset));
+    }
+
+    if header.is_relocatable() {
+        for offset in header.relocations(&inflated).map_err(DumpError::Format)? {
+            let target = header.image_base.wrapping_add(offset);
+            let in_section = sections
+                .iter()
+                .any(|s| target >= s.vaddr && target.saturating_add(8) <= s.vaddr + s.file_size);
+            if !in_section {
+                return Err(DumpError::Format(PlamError::BadRelocation));
+            }
+        }
+    }
+
+    if header.is_signed() {
+        header.signature(&image).map_err(DumpError::Format)?;
+    }
+    if !trusted_keys.is_empty() {
+        verify_signature(&image, trusted_keys).map_err(DumpError::Format)?;
+    }
+
+    Ok(())
+}
+
+fn load_public_key(path: &str) -> [u8; 32] {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|key_hex| hex::decode(key_hex.trim()).ok())
+        .and_then(|bytes| bytes.try_into().ok())
+        .unwrap_or_else(|| {
+            eprintln!("❌ {} is not a hex-encoded Ed25519 public key", path);
+            exit(1);
+        })
+}
+
+fn perms_str(perms: u32) -> String {
+    format!(
+        "{}{}{}",
+        if perms & PERM_R != 0 { 'R' } else { '-' },
+        if perms & PERM_W != 0 { 'W' } else { '-' },
+        if perms & PERM_X != 0 { 'X' } else { '-' }
+    )
+}
+
+fn print_usage() {
+    eprintln!("Usage: plamdump [-q|--quiet] [--trusted <key.pubhex>]... <image.plam>");
+    eprintln!();
+    eprintln!("Exit codes:");
+    eprintln!("  0  valid image");
+    eprintln!("  1  usage or I/O error");
+    eprintln!("  2  bad magic");
+    eprintln!("  3  unsupported version");
+    eprintln!("  4  file size mismatch");
+    eprintln!("  5  unknown cpu_id");
+    eprintln!("  6  misaligned image base or section");
+    eprintln!("  7  malformed section table");
+    eprintln!("  8  entry point outside executable sections");
+    eprintln!("  9  missing or malformed signature block");
+    eprintln!("  10 signed by a key that is not trusted");
+    eprintln!("  11 signature does not match the image");
+    eprintln!("  12 payload is truncated or cannot be decompressed");
+    eprintln!("  13 payload checksum mismatch");
+    eprintln!("  14 header checksum mismatch");
+    eprintln!("  15 relocation outside the image");
+}
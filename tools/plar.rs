@@ -0,0 +1,297 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::exit;
+use std::time::UNIX_EPOCH;
+
+use plum_formats::elf::{Elf, ET_REL, SHT_SYMTAB, STB_GLOBAL, STB_WEAK, STT_SECTION};
+use plum_formats::plstat::{PlstatBuilder, PlstatFile};
+use plum_formats::pom::{self, PomFile, POM_MAGIC};
+
+const AR_MAGIC: &[u8] = b"!<arch>\n";
+const AR_HEADER_SIZE: usize = 60;
+
+struct ArMember<'a> {
+    name: String,
+    mtime: u64,
+    data: &'a [u8],
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+
+    let mut positional = Vec::new();
+    let mut show_symbols = false;
+    let mut to_pom = false;
+    let mut output_dir = String::from(".");
+
+    for arg in &args[1..] {
+        match arg.as_str() {
+            "--symbols" => show_symbols = true,
+            "--pom" => to_pom = true,
+            _ if arg.starts_with("--output-dir=") => output_dir = arg["--output-dir=".len()..].to_string(),
+            _ if arg.starts_with("--") => {
+                eprintln!("❌ Unknown argument: {}", arg);
+                print_usage();
+                exit(1);
+            }
+            _ => positional.push(arg.clone()),
+        }
+    }
+
+    if positional.len() < 2 {
+        print_usage();
+        exit(1);
+    }
+    let (command, archive_path, inputs) = (&positional[0], &positional[1], &positional[2..]);
+
+    match command.as_str() {
+        "create" | "append" => {
+            if inputs.is_empty() {
+                eprintln!("❌ No input files given");
+                exit(1);
+            }
+            let mut builder = PlstatBuilder::new();
+            if command == "append" {
+                let raw = read_file(archive_path);
+                let archive = parse_archive(archive_path, &raw);
+                let mut member_symbols = vec![Vec::new(); archive.header.member_count as usize];
+                for (symbol, index) in archive.symbols() {
+                    if let Some(symbols) = member_symbols.get_mut(index as usize) {
+                        symbols.push(symbol);
+                    }
+                }
+                for (member, symbols) in archive.members().zip(member_symbols) {
+                    builder.add_member(member.name, member.mtime, member.data.to_vec(), symbols);
+                }
+            }
+            let existing = builder.member_count();
+            for input in inputs {
+                add_input(&mut builder, input, to_pom);
+            }
+
+            let image = builder.build();
+            if let Err(e) = fs::write(archive_path, &image) {
+                eprintln!("❌ Failed to write {}: {}", archive_path, e);
+                exit(1);
+            }
+            let archive = parse_archive(archive_path, &image);
+            println!(
+                "✅ {} {} ({} members, {} added, {} symbols)",
+                if command == "create" { "Created" } else { "Updated" },
+                archive_path,
+                archive.header.member_count,
+                archive.header.member_count as usize - existing,
+                archive.header.symbol_count
+            );
+        }
+        "list" => {
+            let raw = read_file(archive_path);
+            let archive = parse_archive(archive_path, &raw);
+            for (index, member) in archive.members().enumerate() {
+                println!("{:>10}  {}", member.data.len(), member.name);
+                if show_symbols {
+                    for (symbol, _) in archive.symbols().filter(|&(_, i)| i as usize == index) {
+                        println!("            {}", symbol);
+                    }
+                }
+            }
+        }
+        "extract" => {
+            let raw = read_file(archive_path);
+            let archive = parse_archive(archive_path, &raw);
+            for wanted in inputs {
+                if !archive.members().any(|m| m.name == wanted) {
+                    eprintln!("❌ {}: no member named {}", archive_path, wanted);
+                    exit(1);
+                }
+            }
+            for member in archive.members() {
+                if !inputs.is_empty() && !inputs.iter().any(|w| w == member.name) {
+                    continue;
+                }
+                if member.name.is_empty() || member.name.contains(['/', '\\']) || member.name == ".." {
+                    eprintln!("❌ Refusing to extract member with unsafe name {:?}", member.name);
+                    exit(1);
+                }
+                let path = Path::new(&output_dir).join(member.name);
+                if let Err(e) = fs::write(&path, member.data) {
+                    eprintln!("❌ Failed to write {}: {}", path.display(), e);
+                    exit(1);
+                }
+                println!("✅ Extracted {}", path.display());
+            }
+        }
+        _ => {
+            eprintln!("❌ Unknown command: {}", command);
+            print_usage();
+            exit(1);
+        }
+    }
+}
+
+fn read_file(path: &str) -> Vec<u8> {
+    fs::read(path).unwrap_or_else(|e| {
+        eprintln!("❌ Failed to read {}: {}", path, e);
+        exit(1);
+    })
+}
+
+fn parse_archive<'a>(path: &str, raw: &'a [u8]) -> PlstatFile<'a> {
+    PlstatFile::parse(raw).unwrap_or_else(|e| {
+        eprintln!("❌ {}: {}", path, e);
+        exit(1);
+    })
+}
+
+/// Adds an object file, or every object member of an `ar` archive.
+fn add_input(builder: &mut PlstatBuilder, input: &str, to_pom: bool) {
+    let raw = read_file(input);
+
+    if raw.starts_with(AR_MAGIC) {
+        let members = read_ar(&raw).unwrap_or_else(|e| {
+            eprintln!("❌ {}: {}", input, e);
+            exit(1);
+        });
+        for member in members {
+            if let Err(e) = add_object(builder, &member.name, member.mtime, member.data, to_pom) {
+                println!("⚠️  Skipping {}({}): {}", input, member.name, e);
+            }
+        }
+        return;
+    }
+
+    let mtime = fs::metadata(input)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_secs());
+    let name = Path::new(input).file_name().unwrap().to_string_lossy();
+    if let Err(e) = add_object(builder, &name, mtime, &raw, to_pom) {
+        eprintln!("❌ {}: {}", input, e);
+        exit(1);
+    }
+}
+
+/// Adds one `.pom` or ELF relocatable object, converting ELF to `.pom`
+/// first when asked to.
+fn add_object(builder: &mut PlstatBuilder, name: &str, mtime: u64, data: &[u8], to_pom: bool) -> Result<(), String> {
+    if to_pom && !data.starts_with(&POM_MAGIC) {
+        let elf = Elf::parse(data).map_err(|e| e.to_string())?;
+        let object = pom::from_elf(&elf).map_err(|e| e.to_string())?.build();
+        let stem = name.strip_suffix(".o").unwrap_or(name);
+        let symbols = object_symbols(&object)?;
+        builder.add_member(&format!("{}.pom", stem), mtime, object.clone(), symbols);
+        return Ok(());
+    }
+    let symbols = object_symbols(data)?;
+    builder.add_member(name, mtime, data.to_vec(), symbols);
+    Ok(())
+}
+
+/// Global and weak symbols defined by a relocatable object.
+fn object_symbols(data: &[u8]) -> Result<Vec<&str>, String> {
+    if data.starts_with(&POM_MAGIC) {
+        let object = PomFile::parse(data).map_err(|e| e.to_string())?;
+        return Ok(object
+            .exported_symbols()
+            .map(|sym| sym.name)
+            .filter(|name| !name.is_empty())
+            .collect());
+    }
+
+    let elf = Elf::parse(data).map_err(|e| e.to_string())?;
+    if elf.e_type != ET_REL {
+        return Err("not a relocatable object".into());
+    }
+
+    let mut symbols = Vec::new();
+    for symtab in elf.section_headers().filter(|sh| sh.sh_type == SHT_SYMTAB) {
+        for sym in elf.symbols(&symtab).map_err(|e| e.to_string())? {
+            if sym.is_defined()
+                && (sym.bind() == STB_GLOBAL || sym.bind() == STB_WEAK)
+                && sym.sym_type() != STT_SECTION
+                && !sym.name.is_empty()
+            {
+                symbols.push(sym.name);
+            }
+        }
+    }
+    Ok(symbols)
+}
+
+/// Members of a System V / GNU or BSD `ar` archive, without the symbol and
+/// long-name tables.
+fn read_ar(data: &[u8]) -> Result<Vec<ArMember<'_>>, String> {
+    let mut members = Vec::new();
+    let mut long_names: &[u8] = &[];
+    let mut offset = AR_MAGIC.len();
+
+    while offset < data.len() {
+        let header = data
+            .get(offset..offset + AR_HEADER_SIZE)
+            .ok_or("truncated member header")?;
+        if &header[58..60] != b"`\n" {
+            return Err(format!("bad member header at offset {}", offset));
+        }
+        let field = |range: std::ops::Range<usize>| String::from_utf8_lossy(&header[range]).trim_end().to_string();
+        let raw_name = field(0..16);
+        let mtime = field(16..28).parse().unwrap_or(0);
+        let size: usize = field(48..58)
+            .parse()
+            .map_err(|_| format!("bad member size at offset {}", offset))?;
+
+        let start = offset + AR_HEADER_SIZE;
+        let mut content = data.get(start..start + size).ok_or("truncated member data")?;
+        offset = start + size + size % 2;
+
+        let name = match raw_name.as_str() {
+            "/" | "/SYM64/" | "__.SYMDEF" | "__.SYMDEF SORTED" => continue,
+            "//" => {
+                long_names = content;
+                continue;
+            }
+            _ if raw_name.starts_with("#1/") => {
+                let len: usize = raw_name[3..].parse().map_err(|_| format!("bad BSD name {}", raw_name))?;
+                let name = content.get(..len).ok_or("truncated BSD name")?;
+                content = &content[len..];
+                let name = String::from_utf8_lossy(name).trim_end_matches('\0').to_string();
+                if name.starts_with("__.SYMDEF") {
+                    continue;
+                }
+                name
+            }
+            _ if raw_name.starts_with('/') => {
+                let at: usize = raw_name[1..].parse().map_err(|_| format!("bad long name {}", raw_name))?;
+                let tail = long_names.get(at..).ok_or("long name outside the name table")?;
+                let end = tail.iter().position(|&b| b == b'\n').unwrap_or(tail.len());
+                String::from_utf8_lossy(&tail[..end]).trim_end_matches('/').to_string()
+            }
+            _ => raw_name.trim_end_matches('/').to_string(),
+        };
+
+        members.push(ArMember { name, mtime, data: content });
+    }
+
+    Ok(members)
+}
+
+fn print_usage() {
+    eprintln!("Usage: plar <command> <archive.plstat> [files...] [options]");
+    eprintln!();
+    eprintln!("Commands:");
+    eprintln!("  create <archive> <files...>    create an archive from .pom or ELF objects and ar archives (.a)");
+    eprintln!("  append <archive> <files...>    add members to an existing archive");
+    eprintln!("  list <archive>                 list members");
+    eprintln!("  extract <archive> [members]    write members (all by default) to files");
+    eprintln!();
+    eprintln!("Options:");
+    eprintln!("  --symbols                      list the symbols each member defines");
+    eprintln!("  --pom                          convert ELF objects to .pom as they are added");
+    eprintln!("  --output-dir=<dir>             directory for extracted members (default .)");
+    eprintln!();
+    eprintln!("Examples:");
+    eprintln!("  plar create libplum.plstat start.o syscalls.o");
+    eprintln!("  plar create --pom libcore.plstat libcore.a");
+    eprintln!("  plar list libplum.plstat --symbols");
+}
@@ -0,0 +1,132 @@
+use std::env;
+use std::fs;
+use std::process::exit;
+
+use plum_formats::plconf::{Config, Schema, PLUM_CONFIG_SCHEMA, PPM_CONFIG_SCHEMA};
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+
+    let mut positional = Vec::new();
+    let mut schema = None;
+    let mut overrides = Vec::new();
+
+    for arg in &args[1..] {
+        match arg.as_str() {
+            _ if arg.starts_with("--schema=") => {
+                schema = Some(schema_by_name(&arg["--schema=".len()..]).unwrap_or_else(|| {
+                    eprintln!("❌ Unknown schema: {}", arg);
+                    exit(1);
+                }))
+            }
+            _ if arg.starts_with("--set=") => overrides.push(arg["--set=".len()..].to_string()),
+            _ if arg.starts_with("--") => {
+                eprintln!("❌ Unknown argument: {}", arg);
+                print_usage();
+                exit(1);
+            }
+            _ => positional.push(arg.clone()),
+        }
+    }
+
+    if positional.len() < 2 {
+        print_usage();
+        exit(1);
+    }
+    let (command, path) = (&positional[0], &positional[1]);
+
+    let text = fs::read_to_string(path).unwrap_or_else(|e| {
+        eprintln!("❌ Failed to read {}: {}", path, e);
+        exit(1);
+    });
+    let mut config = Config::load(path, &text, &mut |include: &str| fs::read_to_string(include).ok())
+        .unwrap_or_else(|e| {
+            eprintln!("❌ {}", e);
+            exit(1);
+        });
+    for value in &overrides {
+        if let Err(e) = config.apply_override(value) {
+            eprintln!("❌ {}", e);
+            exit(1);
+        }
+    }
+    if let Some(schema) = schema {
+        if let Err(errors) = schema.validate(&config) {
+            for e in &errors {
+                eprintln!("❌ {}", e);
+            }
+            exit(2);
+        }
+    }
+
+    match (command.as_str(), positional.get(2)) {
+        ("check", None) => {
+            println!("✅ {} is valid", path);
+            println!("   - Files: {}", config.files.join(", "));
+            println!("   - Sections: {}", config.sections.len());
+        }
+        ("get", Some(key)) => {
+            let (section, key) = key.rsplit_once('.').unwrap_or(("", key));
+            match config.get(section, key) {
+                Some(value) => match value.as_str() {
+                    Some(s) => println!("{}", s),
+                    None => println!("{}", value),
+                },
+                None => {
+                    eprintln!("❌ {} is not set", positional[2]);
+                    exit(3);
+                }
+            }
+        }
+        ("dump", None) => {
+            for section in &config.sections {
+                match (section.name.as_str(), section.repeated) {
+                    ("", _) => {}
+                    (name, true) => println!("[[{}]]", name),
+                    (name, false) => println!("[{}]", name),
+                }
+                for entry in &section.entries {
+                    println!(
+                        "{} = {}    # {}:{}",
+                        entry.key,
+                        entry.value,
+                        config.file_name(entry.location),
+                        entry.location.line
+                    );
+                }
+                println!();
+            }
+        }
+        _ => {
+            print_usage();
+            exit(1);
+        }
+    }
+}
+
+fn schema_by_name(name: &str) -> Option<Schema> {
+    match name {
+        "plum" => Some(PLUM_CONFIG_SCHEMA),
+        "ppm" => Some(PPM_CONFIG_SCHEMA),
+        _ => None,
+    }
+}
+
+fn print_usage() {
+    eprintln!("Usage: plconf <command> <file.plconf> [options]");
+    eprintln!();
+    eprintln!("Commands:");
+    eprintln!("  check                      parse the file and its includes");
+    eprintln!("  get <section.key>          print one value");
+    eprintln!("  dump                       print the merged configuration and where each value came from");
+    eprintln!();
+    eprintln!("Options:");
+    eprintln!("  --schema=<plum|ppm>        validate against the kernel or ppm schema");
+    eprintln!("  --set=<section.key=value>  override a value (repeatable)");
+    eprintln!();
+    eprintln!("Exit codes:");
+    eprintln!("  0  ok");
+    eprintln!("  1  usage, I/O or syntax error");
+    eprintln!("  2  schema validation failed");
+    eprintln!("  3  key not set");
+}
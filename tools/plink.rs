@@ -0,0 +1,1939 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::process::exit;
+
+use ed25519_dalek::SigningKey;
+use plum_formats::elf::Elf;
+use plum_formats::lz4::Compressor;
+use plum_formats::plam::{
+    arch_name, PlamBuilder, Subsystem, CPU_AARCH64, CPU_PRUM64, CPU_RISCV64, CPU_X86_64, FLAG_ASLR,
+    PAGE_SIZE, PERM_R, PERM_W, PERM_X,
+};
+use plum_formats::plconf::{Config, Field, Schema, SectionSchema, Type};
+use plum_formats::plib::{PlibBuilder, PlibFile, EXPORT_FUNC, EXPORT_OBJECT, PLIB_MAGIC};
+use plum_formats::plm::{Abi, PlmBuilder, DEFAULT_IMAGE_BASE, DEFAULT_STACK_SIZE, FLAG_PIE};
+use plum_formats::plstat::{PlstatFile, PLSTAT_MAGIC};
+use plum_formats::pom::{
+    self, PomError, PomFile, Reloc, RelocKind, SectionKind, SymbolBinding, SymbolKind, NO_SYMBOL, POM_MAGIC,
+    SECTION_ABS, SECTION_COMMON, SECTION_UNDEF,
+};
+use plum_formats::version::Version;
+
+const ELF_MAGIC: &[u8; 4] = b"\x7fELF";
+const PLT_STUB_SIZE: u64 = 16;
+const GOT_ENTRY_SIZE: u64 = 8;
+
+/// The memory layout description given with `--script=`.
+const SCRIPT_SCHEMA: Schema = Schema {
+    sections: &[
+        SectionSchema {
+            name: "",
+            repeated: false,
+            required: false,
+            fields: &[
+                Field::new("entry", Type::Str),
+                Field::new("base", Type::Size),
+                Field::new("align", Type::Size),
+            ],
+        },
+        SectionSchema {
+            name: "segment",
+            repeated: true,
+            required: true,
+            fields: &[
+                Field::required("name", Type::Str),
+                Field::required("perms", Type::OneOf(&["r", "rw", "rx", "rwx"])),
+                Field::new("sections", Type::List(&Type::Str)),
+                Field::new("address", Type::Size),
+                Field::new("align", Type::Size),
+            ],
+        },
+        SectionSchema {
+            name: "symbol",
+            repeated: true,
+            required: false,
+            fields: &[Field::required("name", Type::Str), Field::required("value", Type::Size)],
+        },
+    ],
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Plm,
+    Plib,
+    Plam,
+}
+
+impl Format {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "plm" => Some(Format::Plm),
+            "plib" => Some(Format::Plib),
+            "plam" => Some(Format::Plam),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Format::Plm => "plm",
+            Format::Plib => "plib",
+            Format::Plam => "plam",
+        }
+    }
+}
+
+struct Object {
+    name: String,
+    cpu_id: u16,
+    sections: Vec<InputSection>,
+    symbols: Vec<InputSymbol>,
+    relocs: Vec<Reloc>,
+}
+
+struct InputSection {
+    name: String,
+    kind: SectionKind,
+    align: u64,
+    size: u64,
+    /// Empty for [`SectionKind::Bss`].
+    data: Vec<u8>,
+    /// Where layout put the section.
+    segment: usize,
+    address: u64,
+}
+
+impl InputSection {
+    fn new(name: &str, kind: SectionKind, align: u64, size: u64, data: Vec<u8>) -> Self {
+        InputSection {
+            name: name.to_string(),
+            kind,
+            align,
+            size,
+            data,
+            segment: 0,
+            address: 0,
+        }
+    }
+}
+
+struct InputSymbol {
+    name: String,
+    section: u32,
+    binding: SymbolBinding,
+    kind: SymbolKind,
+    /// The alignment for COMMON symbols.
+    value: u64,
+    size: u64,
+}
+
+/// One `[[segment]]` of the layout and, once laid out, its place in memory.
+/// Input sections go to the first segment with a matching pattern, or by
+/// kind to the first segment whose permissions suit them.
+struct Segment {
+    name: String,
+    perms: u32,
+    patterns: Vec<String>,
+    address: Option<u64>,
+    align: u64,
+    sections: Vec<(usize, usize)>,
+    vaddr: u64,
+    file_size: u64,
+    mem_size: u64,
+}
+
+impl Segment {
+    fn new(name: &str, perms: u32) -> Self {
+        Segment {
+            name: name.to_string(),
+            perms,
+            patterns: Vec::new(),
+            address: None,
+            align: PAGE_SIZE,
+            sections: Vec::new(),
+            vaddr: 0,
+            file_size: 0,
+            mem_size: 0,
+        }
+    }
+}
+
+struct Script {
+    entry: Option<String>,
+    base: Option<u64>,
+    segments: Vec<Segment>,
+    symbols: Vec<(String, u64)>,
+}
+
+impl Default for Script {
+    /// Code, read-only data and writable data in that order, sections
+    /// placed by kind.
+    fn default() -> Self {
+        Script {
+            entry: None,
+            base: None,
+            segments: vec![
+                Segment::new("text", PERM_R | PERM_X),
+                Segment::new("rodata", PERM_R),
+                Segment::new("data", PERM_R | PERM_W),
+            ],
+            symbols: Vec::new(),
+        }
+    }
+}
+
+enum Definition {
+    /// A symbol of an object, in a section or absolute.
+    Object { object: usize, symbol: usize },
+    /// A COMMON symbol waiting for its space in bss.
+    Common { object: usize, symbol: usize },
+    /// A `[[symbol]]` of the layout.
+    Absolute(u64),
+    Linker(LinkerSymbol),
+    Import(usize),
+}
+
+struct Global {
+    definition: Definition,
+    weak: bool,
+}
+
+/// Symbols the linker defines when no object does.
+#[derive(Debug, Clone, Copy)]
+enum LinkerSymbol {
+    SegmentStart(usize),
+    SegmentEnd(usize),
+    BssStart,
+    BssEnd,
+    End,
+    GlobalOffsetTable,
+    GlobalPointer,
+}
+
+struct Reference {
+    object: usize,
+    weak: bool,
+}
+
+struct Import {
+    library: String,
+    version: Version,
+    symbol: String,
+    /// Exported as data, so it cannot be called through a PLT stub.
+    object: bool,
+}
+
+/// What a relocation refers to, keyed so that GOT and PLT entries are
+/// shared by every reference to the same symbol.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum SymbolRef {
+    None,
+    Local(usize, usize),
+    Global(String),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Target {
+    Address { value: u64, relocatable: bool },
+    Import(usize),
+}
+
+/// The laid-out segments with every relocation applied.
+struct Image {
+    segments: Vec<Vec<u8>>,
+    /// Offsets from the image base of the 64-bit addresses to rebase.
+    rebase: Vec<u64>,
+    /// (import, offset from the image base, addend) of every import slot.
+    import_slots: Vec<(usize, u64, i64)>,
+}
+
+struct Linker {
+    cpu_id: u16,
+    objects: Vec<Object>,
+    globals: BTreeMap<String, Global>,
+    references: BTreeMap<String, Reference>,
+    imports: Vec<Import>,
+    segments: Vec<Segment>,
+    got: Vec<SymbolRef>,
+    got_index: HashMap<SymbolRef, usize>,
+    plt: Vec<SymbolRef>,
+    plt_index: HashMap<SymbolRef, usize>,
+    /// Sections of the object holding what the linker creates itself.
+    got_section: Option<(usize, usize)>,
+    plt_section: Option<(usize, usize)>,
+    bss: Option<(u64, u64)>,
+    end: u64,
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+
+    let mut inputs = Vec::new();
+    let mut output = None;
+    let mut format = None;
+    let mut cpu_id = None;
+    let mut script_path = None;
+    let mut entry = None;
+    let mut image_base = None;
+    let mut plib = None;
+    let mut library = None;
+    let mut pie = false;
+    let mut abi = None;
+    let mut stack_size = None;
+    let mut subsystem = None;
+    let mut aslr = false;
+    let mut compress = false;
+    let mut sign_key_path = None;
+    let mut map_path = None;
+
+    let mut iter = args[1..].iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-o" => {
+                output = Some(iter.next().cloned().unwrap_or_else(|| {
+                    eprintln!("❌ -o requires an output file");
+                    exit(1);
+                }))
+            }
+            _ if arg.starts_with("--output=") => output = Some(arg["--output=".len()..].to_string()),
+            _ if arg.starts_with("--format=") => {
+                format = Some(Format::from_name(&arg["--format=".len()..]).unwrap_or_else(|| {
+                    eprintln!("❌ Unknown output format: {}", arg);
+                    exit(1);
+                }))
+            }
+            _ if arg.starts_with("--arch=") => {
+                cpu_id = Some(cpu_from_name(&arg["--arch=".len()..]).unwrap_or_else(|| {
+                    eprintln!("❌ Unknown architecture: {}", arg);
+                    exit(1);
+                }))
+            }
+            _ if arg.starts_with("--script=") => script_path = Some(arg["--script=".len()..].to_string()),
+            _ if arg.starts_with("--entry=") => entry = Some(arg["--entry=".len()..].to_string()),
+            _ if arg.starts_with("--image-base=") => {
+                image_base = Some(
+                    parse_size(&arg["--image-base=".len()..])
+                        .filter(|base| base.is_multiple_of(PAGE_SIZE))
+                        .unwrap_or_else(|| {
+                            eprintln!("❌ Invalid or misaligned image base: {}", arg);
+                            exit(1);
+                        }),
+                )
+            }
+            _ if arg.starts_with("--plib=") => plib = Some(parse_named_version(arg, "--plib=")),
+            _ if arg.starts_with("--library=") => {
+                let value = &arg["--library=".len()..];
+                library = Some(match value.split_once('@') {
+                    Some(_) => parse_named_version(arg, "--library="),
+                    None => (value.to_string(), Version::default()),
+                });
+            }
+            "--pie" => pie = true,
+            _ if arg.starts_with("--abi=") => {
+                abi = Some(Abi::from_name(&arg["--abi=".len()..]).unwrap_or_else(|| {
+                    eprintln!("❌ Unknown ABI: {}", arg);
+                    exit(1);
+                }))
+            }
+            _ if arg.starts_with("--stack-size=") => {
+                stack_size = Some(parse_size(&arg["--stack-size=".len()..]).unwrap_or_else(|| {
+                    eprintln!("❌ Invalid stack size: {}", arg);
+                    exit(1);
+                }))
+            }
+            _ if arg.starts_with("--subsystem=") => {
+                subsystem = Some(Subsystem::from_name(&arg["--subsystem=".len()..]).unwrap_or_else(|| {
+                    eprintln!("❌ Unknown subsystem: {}", arg);
+                    exit(1);
+                }))
+            }
+            "--aslr" => aslr = true,
+            "--compress" | "--compress=lz4" => compress = true,
+            _ if arg.starts_with("--sign=") => sign_key_path = Some(arg["--sign=".len()..].to_string()),
+            _ if arg.starts_with("--map=") => map_path = Some(arg["--map=".len()..].to_string()),
+            _ if arg.starts_with("-") => {
+                eprintln!("❌ Unknown argument: {}", arg);
+                print_usage();
+                exit(1);
+            }
+            _ => inputs.push(arg.clone()),
+        }
+    }
+
+    let Some(output) = output else {
+        print_usage();
+        exit(1);
+    };
+    if inputs.is_empty() {
+        eprintln!("❌ No input files given");
+        exit(1);
+    }
+
+    let format = format
+        .or_else(|| plib.as_ref().map(|_| Format::Plib))
+        .or_else(|| output.rsplit_once('.').and_then(|(_, ext)| Format::from_name(ext)))
+        .unwrap_or_else(|| {
+            eprintln!("❌ Cannot tell the output format from {}, use --format=plm|plib|plam", output);
+            exit(1);
+        });
+    if format == Format::Plib && plib.is_none() {
+        eprintln!("❌ A .plib needs --plib=<name>@<version>");
+        exit(1);
+    }
+    if format != Format::Plam && (subsystem.is_some() || aslr || compress || sign_key_path.is_some()) {
+        eprintln!("❌ --subsystem, --aslr, --compress and --sign only apply to .plam output");
+        exit(1);
+    }
+    if format == Format::Plam && (abi.is_some() || stack_size.is_some()) {
+        eprintln!("❌ --abi and --stack-size do not apply to .plam output");
+        exit(1);
+    }
+    if aslr && !pie {
+        eprintln!("❌ --aslr needs --pie");
+        exit(1);
+    }
+    let pie = pie || format == Format::Plib;
+
+    let script = script_path.as_deref().map(load_script).unwrap_or_default();
+
+    // Objects are linked in command-line order; archive members are only
+    // pulled in for symbols nothing else defines.
+    let mut objects = Vec::new();
+    let mut archives = Vec::new();
+    let mut libraries = Vec::new();
+    for path in &inputs {
+        let data = fs::read(path).unwrap_or_else(|e| {
+            eprintln!("❌ Failed to read {}: {}", path, e);
+            exit(1);
+        });
+        if data.starts_with(&PLSTAT_MAGIC) {
+            if let Err(e) = PlstatFile::parse(&data) {
+                eprintln!("❌ {}: {}", path, e);
+                exit(1);
+            }
+            archives.push((path.clone(), data));
+        } else if data.starts_with(&PLIB_MAGIC) {
+            if let Err(e) = PlibFile::parse(&data) {
+                eprintln!("❌ {}: {}", path, e);
+                exit(1);
+            }
+            libraries.push((path.clone(), data));
+        } else if data.starts_with(&POM_MAGIC) || data.starts_with(ELF_MAGIC) {
+            objects.push(load_object(path, &data).unwrap_or_else(|e| {
+                eprintln!("❌ {}: {}", path, e);
+                exit(1);
+            }));
+        } else {
+            eprintln!("❌ {}: not a .pom, ELF object, .plstat or .plib", path);
+            exit(1);
+        }
+    }
+    if format == Format::Plam && (library.is_some() || !libraries.is_empty()) {
+        eprintln!("❌ A .plam cannot import from libraries");
+        exit(1);
+    }
+
+    let Some(first) = objects.first() else {
+        eprintln!("❌ No objects to link, archives only supply missing symbols");
+        exit(1);
+    };
+    let cpu_id = cpu_id.unwrap_or(first.cpu_id);
+    let architecture_name = arch_name(cpu_id).unwrap_or("?");
+
+    let mut linker = Linker::new(cpu_id, script.segments);
+
+    let result = objects
+        .into_iter()
+        .try_for_each(|object| linker.add_object(object))
+        .and_then(|_| linker.pull_archive_members(&archives))
+        .and_then(|_| {
+            linker.provide_symbols(&script.symbols);
+            linker.import_from_libraries(&libraries)
+        });
+    if let Err(e) = result {
+        eprintln!("❌ {}", e);
+        exit(1);
+    }
+    if let Some((name, version)) = &library {
+        linker.import_undefined(name, *version);
+    }
+
+    let undefined = linker.undefined(false);
+    if !undefined.is_empty() {
+        for name in &undefined {
+            let reference = &linker.references[name];
+            eprintln!("❌ Undefined symbol {} (referenced by {})", name, linker.objects[reference.object].name);
+        }
+        exit(1);
+    }
+
+    let base = image_base.or(script.base).unwrap_or_else(|| default_base(format, cpu_id));
+    let result = linker.create_sections().and_then(|_| linker.layout(base));
+    if let Err(e) = result {
+        eprintln!("❌ {}", e);
+        exit(1);
+    }
+
+    let entry_offset = match entry.or(script.entry) {
+        Some(entry) => linker.entry_offset(&entry, base),
+        None if format == Format::Plib => Ok(0),
+        None => linker.entry_offset("_start", base),
+    }
+    .unwrap_or_else(|e| {
+        eprintln!("❌ {}", e);
+        exit(1);
+    });
+
+    let image = linker.emit(base, pie).unwrap_or_else(|e| {
+        eprintln!("❌ {}", e);
+        exit(1);
+    });
+    let rebase_count = image.rebase.len();
+    let import_count = image.import_slots.len();
+
+    let mut export_count = 0;
+    let output_image = match format {
+        Format::Plm | Format::Plib => {
+            let mut plm = linker.plm(image, base, entry_offset, pie);
+            plm.abi = abi.unwrap_or(Abi::Native);
+            plm.stack_size = stack_size.unwrap_or(DEFAULT_STACK_SIZE);
+            match &plib {
+                Some((name, version)) => {
+                    let mut builder = PlibBuilder::new(name, *version, plm);
+                    for (symbol, kind, value) in linker.exports() {
+                        builder.add_export(symbol, kind, value - base);
+                        export_count += 1;
+                    }
+                    builder.build()
+                }
+                None => plm.build(),
+            }
+        }
+        Format::Plam => {
+            let mut plam = linker.plam(image, base, entry_offset, pie);
+            plam.subsystem = subsystem.unwrap_or(Subsystem::NativeKernel);
+            if aslr {
+                plam.flags |= FLAG_ASLR;
+            }
+            let compressor: Option<Compressor> = compress.then_some(lz4_flex::block::compress);
+            let signing_key = sign_key_path.as_deref().map(load_signing_key);
+            plam.build(compressor, signing_key.as_ref()).unwrap_or_else(|e| {
+                eprintln!("❌ Failed to encode header: {}", e);
+                exit(1);
+            })
+        }
+    };
+
+    if let Err(e) = fs::write(&output, &output_image) {
+        eprintln!("❌ Failed to write {}: {}", output, e);
+        exit(1);
+    }
+    if let Some(map_path) = &map_path {
+        if let Err(e) = fs::write(map_path, linker.map()) {
+            eprintln!("❌ Failed to write {}: {}", map_path, e);
+            exit(1);
+        }
+    }
+
+    println!("✅ Created {} ({} bytes)", output, output_image.len());
+    println!("   - Format: {}", format.name());
+    println!("   - Architecture: {}", architecture_name);
+    println!("   - Objects: {}", linker.objects.len() - 1);
+    println!("   - Image base: 0x{:x}{}", base, if pie { " (PIE)" } else { "" });
+    println!("   - Entry offset: 0x{:x}", entry_offset);
+    println!("   - Segments:");
+    for segment in linker.segments.iter().filter(|segment| segment.mem_size > 0) {
+        println!(
+            "     {:<8} {} vaddr=0x{:x} file={} mem={}",
+            segment.name,
+            perms_str(segment.perms),
+            segment.vaddr,
+            segment.file_size,
+            segment.mem_size
+        );
+    }
+    if pie {
+        println!("   - Relocations: {}", rebase_count);
+    }
+    if import_count > 0 {
+        println!("   - Imports: {} ({} GOT, {} PLT)", import_count, linker.got.len(), linker.plt.len());
+    }
+    if format == Format::Plib {
+        println!("   - Exports: {}", export_count);
+    }
+    if let Some(map_path) = &map_path {
+        println!("   - Map: {}", map_path);
+    }
+}
+
+/// Reads a `.pom` or an ELF relocatable object, converting the latter.
+fn load_object(name: &str, data: &[u8]) -> Result<Object, String> {
+    let converted;
+    let data = if data.starts_with(ELF_MAGIC) {
+        let elf = Elf::parse(data).map_err(|e| e.to_string())?;
+        converted = pom::from_elf(&elf).map_err(|e| e.to_string())?.build();
+        &converted[..]
+    } else {
+        data
+    };
+    let file = PomFile::parse(data).map_err(|e| e.to_string())?;
+
+    Ok(Object {
+        name: name.to_string(),
+        cpu_id: file.cpu_id(),
+        sections: file
+            .sections()
+            .map(|section| InputSection::new(section.name, section.kind, section.align, section.size, section.data.to_vec()))
+            .collect(),
+        symbols: file
+            .symbols()
+            .map(|symbol| InputSymbol {
+                name: symbol.name.to_string(),
+                section: symbol.section,
+                binding: symbol.binding,
+                kind: symbol.kind,
+                value: symbol.value,
+                size: symbol.size,
+            })
+            .collect(),
+        relocs: file.relocs().collect(),
+    })
+}
+
+fn load_script(path: &str) -> Script {
+    let text = fs::read_to_string(path).unwrap_or_else(|e| {
+        eprintln!("❌ Failed to read {}: {}", path, e);
+        exit(1);
+    });
+    let config = Config::load(path, &text, &mut |include: &str| fs::read_to_string(include).ok())
+        .unwrap_or_else(|e| {
+            eprintln!("❌ {}", e);
+            exit(1);
+        });
+    if let Err(errors) = SCRIPT_SCHEMA.validate(&config) {
+        for e in &errors {
+            eprintln!("❌ {}", e);
+        }
+        exit(1);
+    }
+
+    let alignment = |value: Option<u64>, what: &str| {
+        let align = value.unwrap_or(PAGE_SIZE);
+        if !align.is_power_of_two() || align < PAGE_SIZE {
+            eprintln!("❌ {}: {} must be a power of two of at least {}", path, what, PAGE_SIZE);
+            exit(1);
+        }
+        align
+    };
+    let default_align = alignment(config.get("", "align").and_then(|v| v.as_size()), "align");
+
+    let mut script = Script {
+        entry: config.get("", "entry").and_then(|v| v.as_str()).map(str::to_string),
+        base: config.get("", "base").and_then(|v| v.as_size()),
+        segments: Vec::new(),
+        symbols: Vec::new(),
+    };
+    if script.base.is_some_and(|base| !base.is_multiple_of(PAGE_SIZE)) {
+        eprintln!("❌ {}: base must be page-aligned", path);
+        exit(1);
+    }
+
+    for section in config.sections("segment") {
+        let name = section.get("name").and_then(|v| v.as_str()).unwrap_or_default();
+        let perms = section.get("perms").and_then(|v| v.as_str()).unwrap_or_default();
+        let mut segment = Segment::new(name, parse_perms(perms));
+        segment.patterns = section
+            .get("sections")
+            .and_then(|v| v.as_list())
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|pattern| pattern.as_str().map(str::to_string))
+            .collect();
+        segment.align = alignment(section.get("align").and_then(|v| v.as_size()), &format!("segment {} align", name));
+        segment.address = section.get("address").and_then(|v| v.as_size());
+        if segment.address.is_some_and(|address| !address.is_multiple_of(PAGE_SIZE)) {
+            eprintln!("❌ {}: segment {} address must be page-aligned", path, name);
+            exit(1);
+        }
+        if script.segments.iter().any(|other| other.name == segment.name) {
+            eprintln!("❌ {}: segment {} is declared twice", path, name);
+            exit(1);
+        }
+        if section.get("align").is_none() {
+            segment.align = default_align;
+        }
+        script.segments.push(segment);
+    }
+
+    for section in config.sections("symbol") {
+        let name = section.get("name").and_then(|v| v.as_str()).unwrap_or_default();
+        let value = section.get("value").and_then(|v| v.as_size()).unwrap_or_default();
+        script.symbols.push((name.to_string(), value));
+    }
+    script
+}
+
+
+impl Linker {
+    fn new(cpu_id: u16, segments: Vec<Segment>) -> Self {
+        Linker {
+            cpu_id,
+            objects: Vec::new(),
+            globals: BTreeMap::new(),
+            references: BTreeMap::new(),
+            imports: Vec::new(),
+            segments,
+            got: Vec::new(),
+            got_index: HashMap::new(),
+            plt: Vec::new(),
+            plt_index: HashMap::new(),
+            got_section: None,
+            plt_section: None,
+            bss: None,
+            end: 0,
+        }
+    }
+
+    /// Adds `object` and its global definitions. A strong definition beats
+    /// a COMMON one, which beats a weak one; two strong ones are an error.
+    fn add_object(&mut self, object: Object) -> Result<(), String> {
+        if object.cpu_id != self.cpu_id {
+            return Err(format!(
+                "{} is built for {}, not {}",
+                object.name,
+                arch_name(object.cpu_id).unwrap_or("?"),
+                arch_name(self.cpu_id).unwrap_or("?")
+            ));
+        }
+
+        let index = self.objects.len();
+        for (i, symbol) in object.symbols.iter().enumerate() {
+            if symbol.binding == SymbolBinding::Local || symbol.name.is_empty() {
+                continue;
+            }
+            let weak = symbol.binding == SymbolBinding::Weak;
+            let (definition, strength) = match symbol.section {
+                SECTION_UNDEF => {
+                    let reference = self
+                        .references
+                        .entry(symbol.name.clone())
+                        .or_insert(Reference { object: index, weak });
+                    reference.weak &= weak;
+                    continue;
+                }
+                SECTION_COMMON => (Definition::Common { object: index, symbol: i }, 1),
+                _ => (Definition::Object { object: index, symbol: i }, if weak { 0 } else { 2 }),
+            };
+
+            let replace = match self.globals.get(&symbol.name) {
+                None => true,
+                Some(existing) => match (&existing.definition, strength) {
+                    (Definition::Object { object: other, .. }, 2) if !existing.weak => {
+                        return Err(format!(
+                            "duplicate symbol {}: defined in {} and {}",
+                            symbol.name, self.objects[*other].name, object.name
+                        ));
+                    }
+                    // Of two COMMON symbols the larger one wins.
+                    (Definition::Common { object: other, symbol: other_symbol }, 1) => {
+                        self.objects[*other].symbols[*other_symbol].size < symbol.size
+                    }
+                    (Definition::Common { .. }, strength) => strength > 1,
+                    (_, strength) => existing.weak && strength > 0,
+                },
+            };
+            if replace {
+                self.globals.insert(symbol.name.clone(), Global { definition, weak });
+            }
+        }
+        self.objects.push(object);
+        Ok(())
+    }
+
+    /// Referenced symbols nothing defines yet, optionally including those
+    /// only referenced weakly.
+    fn undefined(&self, include_weak: bool) -> Vec<String> {
+        self.references
+            .iter()
+            .filter(|(name, reference)| (include_weak || !reference.weak) && !self.globals.contains_key(*name))
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    /// Pulls in the archive members that define undefined symbols, over
+    /// and over until no archive has anything more to offer, so members may
+    /// depend on each other across archives in any order.
+    fn pull_archive_members(&mut self, archives: &[(String, Vec<u8>)]) -> Result<(), String> {
+        let mut loaded = HashSet::new();
+        loop {
+            let mut pulled = false;
+            for (index, (path, data)) in archives.iter().enumerate() {
+                let archive = PlstatFile::parse(data).map_err(|e| format!("{}: {}", path, e))?;
+                for name in self.undefined(false) {
+                    if self.globals.contains_key(&name) {
+                        continue;
+                    }
+                    let Some(member_index) = archive.find_symbol(&name) else {
+                        continue;
+                    };
+                    if !loaded.insert((index, member_index)) {
+                        continue;
+                    }
+                    let member = archive
+                        .member(member_index)
+                        .ok_or_else(|| format!("{}: bad member index {}", path, member_index))?;
+                    let member_name = format!("{}({})", path, member.name);
+                    let object = load_object(&member_name, member.data).map_err(|e| format!("{}: {}", member_name, e))?;
+                    self.add_object(object)?;
+                    pulled = true;
+                }
+            }
+            if !pulled {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Defines the `[[symbol]]`s of the layout and the linker symbols,
+    /// unless an object already does.
+    fn provide_symbols(&mut self, symbols: &[(String, u64)]) {
+        let mut provided: Vec<(String, Definition)> = symbols
+            .iter()
+            .map(|(name, value)| (name.clone(), Definition::Absolute(*value)))
+            .collect();
+        for (index, segment) in self.segments.iter().enumerate() {
+            provided.push((format!("__{}_start", segment.name), Definition::Linker(LinkerSymbol::SegmentStart(index))));
+            provided.push((format!("__{}_end", segment.name), Definition::Linker(LinkerSymbol::SegmentEnd(index))));
+        }
+        provided.extend([
+            ("__bss_start".to_string(), Definition::Linker(LinkerSymbol::BssStart)),
+            ("__bss_end".to_string(), Definition::Linker(LinkerSymbol::BssEnd)),
+            ("_end".to_string(), Definition::Linker(LinkerSymbol::End)),
+            ("_GLOBAL_OFFSET_TABLE_".to_string(), Definition::Linker(LinkerSymbol::GlobalOffsetTable)),
+        ]);
+        if self.cpu_id == CPU_RISCV64 {
+            provided.push(("__global_pointer$".to_string(), Definition::Linker(LinkerSymbol::GlobalPointer)));
+        }
+
+        for (name, definition) in provided {
+            self.globals.entry(name).or_insert(Global { definition, weak: false });
+        }
+    }
+
+    /// Turns references to symbols the `.plib` inputs export into imports.
+    fn import_from_libraries(&mut self, libraries: &[(String, Vec<u8>)]) -> Result<(), String> {
+        for (path, data) in libraries {
+            let library = PlibFile::parse(data).map_err(|e| format!("{}: {}", path, e))?;
+            if library.image.header.cpu_id != self.cpu_id {
+                return Err(format!("{} is built for {}", path, arch_name(library.image.header.cpu_id).unwrap_or("?")));
+            }
+            let name = library.name().map_err(|e| format!("{}: {}", path, e))?;
+            for symbol in self.undefined(true) {
+                if let Some(export) = library.lookup(&symbol) {
+                    self.add_import(name, library.lib_version(), &symbol, export.kind == EXPORT_OBJECT);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Imports every symbol still undefined from `library`.
+    fn import_undefined(&mut self, library: &str, version: Version) {
+        for symbol in self.undefined(true) {
+            self.add_import(library, version, &symbol, false);
+        }
+    }
+
+    fn add_import(&mut self, library: &str, version: Version, symbol: &str, object: bool) {
+        let index = self.imports.len();
+        self.imports.push(Import {
+            library: library.to_string(),
+            version,
+            symbol: symbol.to_string(),
+            object,
+        });
+        let global = Global {
+            definition: Definition::Import(index),
+            weak: false,
+        };
+        self.globals.insert(symbol.to_string(), global);
+    }
+
+    fn symbol_ref(&self, object: usize, symbol: u32) -> SymbolRef {
+        if symbol == NO_SYMBOL {
+            return SymbolRef::None;
+        }
+        let sym = &self.objects[object].symbols[symbol as usize];
+        if sym.binding == SymbolBinding::Local && sym.section != SECTION_UNDEF {
+            SymbolRef::Local(object, symbol as usize)
+        } else {
+            SymbolRef::Global(sym.name.clone())
+        }
+    }
+
+    fn import_of(&self, symbol: &SymbolRef) -> Option<usize> {
+        match symbol {
+            SymbolRef::Global(name) => match self.globals.get(name)?.definition {
+                Definition::Import(index) => Some(index),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Gives COMMON symbols their space, finds the GOT and PLT entries the
+    /// relocations need and adds an object holding all three.
+    fn create_sections(&mut self) -> Result<(), String> {
+        let index = self.objects.len();
+        let mut object = Object {
+            name: "<linker>".to_string(),
+            cpu_id: self.cpu_id,
+            sections: Vec::new(),
+            symbols: Vec::new(),
+            relocs: Vec::new(),
+        };
+
+        for (name, global) in self.globals.iter_mut() {
+            let Definition::Common { object: other, symbol } = global.definition else {
+                continue;
+            };
+            let common = &self.objects[other].symbols[symbol];
+            object.sections.push(InputSection::new("COMMON", SectionKind::Bss, common.value.max(1), common.size, Vec::new()));
+            object.symbols.push(InputSymbol {
+                name: name.clone(),
+                section: object.sections.len() as u32 - 1,
+                binding: SymbolBinding::Global,
+                kind: SymbolKind::Object,
+                value: 0,
+                size: common.size,
+            });
+            global.definition = Definition::Object {
+                object: index,
+                symbol: object.symbols.len() - 1,
+            };
+        }
+
+        let mut got = Vec::new();
+        let mut plt = Vec::new();
+        for (o, input) in self.objects.iter().enumerate() {
+            for reloc in &input.relocs {
+                let symbol = self.symbol_ref(o, reloc.symbol);
+                if reloc.kind.uses_got() {
+                    got.push(symbol);
+                    continue;
+                }
+                let Some(import) = self.import_of(&symbol) else {
+                    continue;
+                };
+                let import = &self.imports[import];
+                if reloc.kind == RelocKind::Abs64 {
+                    continue;
+                }
+                if !is_call(reloc.kind) {
+                    return Err(format!(
+                        "{}: {} against {} from {} needs a GOT access, rebuild it as position-independent code",
+                        input.name,
+                        reloc.kind.name(),
+                        import.symbol,
+                        import.library_name()
+                    ));
+                }
+                if import.object {
+                    return Err(format!("{}: {} calls {}, which is data", input.name, reloc.kind.name(), import.symbol));
+                }
+                if self.cpu_id == CPU_PRUM64 {
+                    return Err(format!("{}: prum64 has no call stubs, {} must be called through a pointer", input.name, import.symbol));
+                }
+                got.push(symbol.clone());
+                plt.push(symbol);
+            }
+        }
+        for symbol in got {
+            if !self.got_index.contains_key(&symbol) {
+                self.got_index.insert(symbol.clone(), self.got.len());
+                self.got.push(symbol);
+            }
+        }
+        for symbol in plt {
+            if !self.plt_index.contains_key(&symbol) {
+                self.plt_index.insert(symbol.clone(), self.plt.len());
+                self.plt.push(symbol);
+            }
+        }
+
+        if !self.got.is_empty() || self.references.contains_key("_GLOBAL_OFFSET_TABLE_") {
+            let size = self.got.len() as u64 * GOT_ENTRY_SIZE;
+            object.sections.push(InputSection::new(".got", SectionKind::Data, 8, size, vec![0; size as usize]));
+            self.got_section = Some((index, object.sections.len() - 1));
+        }
+        if !self.plt.is_empty() {
+            let size = self.plt.len() as u64 * PLT_STUB_SIZE;
+            object.sections.push(InputSection::new(".plt", SectionKind::Text, 16, size, vec![0; size as usize]));
+            self.plt_section = Some((index, object.sections.len() - 1));
+        }
+        self.objects.push(object);
+        Ok(())
+    }
+
+    /// The segment a section no pattern claims goes to.
+    fn default_segment(&self, kind: SectionKind) -> Option<usize> {
+        let find = |matches: fn(u32) -> bool| self.segments.iter().position(|segment| matches(segment.perms));
+        match kind {
+            SectionKind::Text => find(|perms| perms & PERM_X != 0),
+            SectionKind::ReadOnly => {
+                find(|perms| perms & (PERM_W | PERM_X) == 0).or_else(|| find(|perms| perms & PERM_W == 0))
+            }
+            SectionKind::Data | SectionKind::Bss => find(|perms| perms & PERM_W != 0),
+        }
+    }
+
+    /// Assigns every section to a segment and an address. Segments follow
+    /// each other from `base` unless they have an address of their own and
+    /// never share a page; within a segment bss comes last so that it needs
+    /// no space in the file.
+    fn layout(&mut self, base: u64) -> Result<(), String> {
+        let mut assigned: Vec<Vec<bool>> = self.objects.iter().map(|o| vec![false; o.sections.len()]).collect();
+        for segment in &mut self.segments {
+            for pattern in &segment.patterns {
+                for (o, object) in self.objects.iter().enumerate() {
+                    for (i, section) in object.sections.iter().enumerate() {
+                        if !assigned[o][i] && glob_match(pattern, &section.name) {
+                            assigned[o][i] = true;
+                            segment.sections.push((o, i));
+                        }
+                    }
+                }
+            }
+        }
+        for (o, object) in self.objects.iter().enumerate() {
+            for (i, section) in object.sections.iter().enumerate() {
+                if assigned[o][i] {
+                    continue;
+                }
+                let s = self.default_segment(section.kind).ok_or_else(|| {
+                    format!("{}: no segment can hold {} section {}", object.name, section.kind.name(), section.name)
+                })?;
+                self.segments[s].sections.push((o, i));
+            }
+        }
+
+        let overflow = |segment: &Segment| format!("segment {}: layout overflows the address space", segment.name);
+        let mut cursor = base;
+        for (s, segment) in self.segments.iter_mut().enumerate() {
+            let objects = &self.objects;
+            segment.sections.sort_by_key(|&(o, i)| objects[o].sections[i].kind == SectionKind::Bss);
+
+            let start = match segment.address {
+                Some(address) if address < cursor => {
+                    return Err(format!(
+                        "segment {} at 0x{:x} overlaps the segments before it, which end at 0x{:x}",
+                        segment.name, address, cursor
+                    ));
+                }
+                Some(address) => address,
+                None => cursor.checked_next_multiple_of(segment.align).ok_or_else(|| overflow(segment))?,
+            };
+            let mut address = start;
+            let mut file_end = start;
+            for &(o, i) in &segment.sections {
+                let section = &mut self.objects[o].sections[i];
+                address = address
+                    .checked_next_multiple_of(section.align.max(1))
+                    .filter(|start| start.checked_add(section.size).is_some())
+                    .ok_or_else(|| overflow(segment))?;
+                section.segment = s;
+                section.address = address;
+                if section.kind == SectionKind::Bss {
+                    let (bss_start, _) = self.bss.unwrap_or((address, address));
+                    self.bss = Some((bss_start.min(address), address + section.size));
+                }
+                address += section.size;
+                if section.kind != SectionKind::Bss {
+                    file_end = address;
+                }
+            }
+            segment.vaddr = start;
+            segment.file_size = file_end - start;
+            segment.mem_size = address - start;
+            cursor = address.checked_next_multiple_of(PAGE_SIZE).ok_or_else(|| overflow(segment))?;
+            self.end = self.end.max(address);
+        }
+        Ok(())
+    }
+
+    fn section_address(&self, (object, section): (usize, usize)) -> u64 {
+        self.objects[object].sections[section].address
+    }
+
+    fn linker_symbol_value(&self, symbol: LinkerSymbol) -> u64 {
+        match symbol {
+            LinkerSymbol::SegmentStart(index) => self.segments[index].vaddr,
+            LinkerSymbol::SegmentEnd(index) => self.segments[index].vaddr + self.segments[index].mem_size,
+            LinkerSymbol::BssStart => self.bss.map_or(self.end, |(start, _)| start),
+            LinkerSymbol::BssEnd => self.bss.map_or(self.end, |(_, end)| end),
+            LinkerSymbol::End => self.end,
+            LinkerSymbol::GlobalOffsetTable => self.got_section.map_or(0, |got| self.section_address(got)),
+            // Nothing is relaxed to gp-relative accesses, so any address in
+            // the writable data does.
+            LinkerSymbol::GlobalPointer => self
+                .segments
+                .iter()
+                .find(|segment| segment.perms & PERM_W != 0)
+                .map_or(0, |segment| segment.vaddr + 0x800),
+        }
+    }
+
+    fn symbol_target(&self, object: usize, symbol: usize) -> Target {
+        let sym = &self.objects[object].symbols[symbol];
+        if sym.section == SECTION_ABS {
+            return Target::Address {
+                value: sym.value,
+                relocatable: false,
+            };
+        }
+        Target::Address {
+            value: self.section_address((object, sym.section as usize)) + sym.value,
+            relocatable: true,
+        }
+    }
+
+    /// Where `symbol` ended up. Weak references to undefined symbols are 0.
+    fn target(&self, symbol: &SymbolRef) -> Target {
+        let undefined = Target::Address {
+            value: 0,
+            relocatable: false,
+        };
+        match symbol {
+            SymbolRef::None => undefined,
+            SymbolRef::Local(object, symbol) => self.symbol_target(*object, *symbol),
+            SymbolRef::Global(name) => match self.globals.get(name).map(|global| &global.definition) {
+                None => undefined,
+                Some(Definition::Object { object, symbol }) => self.symbol_target(*object, *symbol),
+                Some(Definition::Common { .. }) => unreachable!("COMMON symbols are allocated before layout"),
+                Some(Definition::Absolute(value)) => Target::Address {
+                    value: *value,
+                    relocatable: false,
+                },
+                Some(Definition::Linker(symbol)) => Target::Address {
+                    value: self.linker_symbol_value(*symbol),
+                    relocatable: true,
+                },
+                Some(Definition::Import(index)) => Target::Import(*index),
+            },
+        }
+    }
+
+    fn got_address(&self, symbol: &SymbolRef) -> u64 {
+        let got = self.got_section.expect("GOT entries without a .got");
+        self.section_address(got) + self.got_index[symbol] as u64 * GOT_ENTRY_SIZE
+    }
+
+    fn plt_address(&self, symbol: &SymbolRef) -> u64 {
+        let plt = self.plt_section.expect("PLT entries without a .plt");
+        self.section_address(plt) + self.plt_index[symbol] as u64 * PLT_STUB_SIZE
+    }
+
+    fn entry_offset(&self, entry: &str, base: u64) -> Result<u64, String> {
+        let address = match parse_size(entry) {
+            Some(address) => address,
+            None => match self.globals.get(entry).map(|_| self.target(&SymbolRef::Global(entry.to_string()))) {
+                Some(Target::Address { value, .. }) => value,
+                Some(Target::Import(_)) => return Err(format!("Entry symbol {} is imported", entry)),
+                None => return Err(format!("Entry symbol {} is not defined, use --entry=", entry)),
+            },
+        };
+        let executable = self.segments.iter().any(|segment| {
+            segment.perms & PERM_X != 0 && address >= segment.vaddr && address < segment.vaddr + segment.mem_size
+        });
+        if !executable || address < base {
+            return Err(format!("Entry point 0x{:x} is outside every executable segment", address));
+        }
+        Ok(address - base)
+    }
+
+    /// Copies the sections into their segments, fills the GOT and PLT and
+    /// applies every relocation. With `pie`, absolute addresses inside the
+    /// image are recorded for the loader to rebase.
+    fn emit(&self, base: u64, pie: bool) -> Result<Image, String> {
+        let mut image = Image {
+            segments: self.segments.iter().map(|segment| vec![0u8; segment.file_size as usize]).collect(),
+            rebase: Vec::new(),
+            import_slots: Vec::new(),
+        };
+        for object in &self.objects {
+            for section in object.sections.iter().filter(|section| section.kind != SectionKind::Bss) {
+                let start = (section.address - self.segments[section.segment].vaddr) as usize;
+                image.segments[section.segment][start..][..section.data.len()].copy_from_slice(&section.data);
+            }
+        }
+
+        for (index, symbol) in self.got.iter().enumerate() {
+            let slot = self.got_address(symbol);
+            match self.target(symbol) {
+                Target::Address { value, relocatable } => {
+                    self.place(&mut image, slot)?[..8].copy_from_slice(&value.to_le_bytes());
+                    if pie && relocatable {
+                        image.rebase.push(slot - base);
+                    }
+                }
+                Target::Import(import) => image.import_slots.push((import, slot - base, 0)),
+            }
+            debug_assert_eq!(self.got_index[symbol], index);
+        }
+        for symbol in &self.plt {
+            let place = self.plt_address(symbol);
+            let slot = self.got_address(symbol);
+            write_plt_stub(self.cpu_id, self.place(&mut image, place)?, place, slot)
+                .map_err(|e| format!("PLT stub for {:?}: {}", symbol, e))?;
+        }
+
+        for (o, object) in self.objects.iter().enumerate() {
+            // A RISC-V PCREL_LO12 points at the AUIPC whose PCREL_HI20 or
+            // GOT_HI20 it completes and takes that relocation's value.
+            let mut hi20 = HashMap::new();
+            for reloc in &object.relocs {
+                if matches!(reloc.kind, RelocKind::RiscvPcrelHi20 | RelocKind::RiscvGotHi20) {
+                    let place = object.sections[reloc.section as usize].address + reloc.offset;
+                    let value = self.reloc_value(o, reloc, place, base, pie, &mut Image::empty())?;
+                    hi20.insert((reloc.section, reloc.offset), value);
+                }
+            }
+
+            for reloc in &object.relocs {
+                let section = &object.sections[reloc.section as usize];
+                let place = section.address + reloc.offset;
+                let value = match reloc.kind {
+                    RelocKind::RiscvPcrelLo12I | RelocKind::RiscvPcrelLo12S => {
+                        let label = object.symbols.get(reloc.symbol as usize).ok_or_else(|| {
+                            format!("{}: needs the label of its PCREL_HI20 relocation", self.describe(o, reloc))
+                        })?;
+                        let key = (label.section, label.value.wrapping_add(reloc.addend as u64));
+                        hi20.get(&key).copied().flatten().ok_or_else(|| {
+                            format!("{}: no PCREL_HI20 relocation at {}", self.describe(o, reloc), label.name)
+                        })?
+                    }
+                    _ => match self.reloc_value(o, reloc, place, base, pie, &mut image)? {
+                        Some(value) => value,
+                        None => continue,
+                    },
+                };
+                let target = self.place(&mut image, place).map_err(|e| format!("{}: {}", self.describe(o, reloc), e))?;
+                reloc.kind.apply(target, value).map_err(|e| format!("{}: {}", self.describe(o, reloc), e))?;
+            }
+        }
+
+        image.rebase.sort_unstable();
+        Ok(image)
+    }
+
+    /// The value to encode for `reloc` at `place`, or `None` when it became
+    /// an import slot the loader fills.
+    fn reloc_value(
+        &self,
+        object: usize,
+        reloc: &Reloc,
+        place: u64,
+        base: u64,
+        pie: bool,
+        image: &mut Image,
+    ) -> Result<Option<i64>, String> {
+        let symbol = self.symbol_ref(object, reloc.symbol);
+        let (value, relocatable) = if reloc.kind.uses_got() {
+            (self.got_address(&symbol), false)
+        } else {
+            match self.target(&symbol) {
+                Target::Address { value, relocatable } => (value, relocatable),
+                Target::Import(import) if reloc.kind == RelocKind::Abs64 => {
+                    image.import_slots.push((import, place - base, reloc.addend));
+                    return Ok(None);
+                }
+                Target::Import(_) => (self.plt_address(&symbol), false),
+            }
+        };
+
+        if pie && relocatable {
+            match reloc.kind {
+                RelocKind::Abs64 => image.rebase.push(place - base),
+                RelocKind::Abs32 | RelocKind::Abs32S | RelocKind::RiscvHi20 | RelocKind::RiscvLo12I | RelocKind::RiscvLo12S => {
+                    return Err(format!(
+                        "{}: an absolute address cannot be rebased, rebuild as position-independent code",
+                        self.describe(object, reloc)
+                    ));
+                }
+                _ => {}
+            }
+        }
+        Ok(Some(reloc.kind.value(value, reloc.addend, place)))
+    }
+
+    /// The bytes of the image from `address` to the end of its segment's
+    /// file data. Zero-fill has no bytes in the image to write to.
+    fn place<'a>(&self, image: &'a mut Image, address: u64) -> Result<&'a mut [u8], String> {
+        let index = self
+            .segments
+            .iter()
+            .position(|segment| address >= segment.vaddr && address - segment.vaddr < segment.file_size)
+            .ok_or_else(|| format!("0x{:x} is outside the file data of every segment", address))?;
+        Ok(&mut image.segments[index][(address - self.segments[index].vaddr) as usize..])
+    }
+
+    fn describe(&self, object: usize, reloc: &Reloc) -> String {
+        let input = &self.objects[object];
+        let symbol = match reloc.symbol {
+            NO_SYMBOL => "",
+            index => &input.symbols[index as usize].name,
+        };
+        format!(
+            "{}: {} at {}+0x{:x} against {}",
+            input.name,
+            reloc.kind.name(),
+            input.sections[reloc.section as usize].name,
+            reloc.offset,
+            symbol
+        )
+    }
+
+    /// A `.plm` of the non-empty segments of `image`, with its imports and,
+    /// for `pie`, its rebase list.
+    fn plm(&self, image: Image, base: u64, entry_offset: u64, pie: bool) -> PlmBuilder {
+        let mut plm = PlmBuilder::new(self.cpu_id, base, entry_offset);
+        for (segment, data) in self.segments.iter().zip(image.segments).filter(|(segment, _)| segment.mem_size > 0) {
+            plm.add_segment(segment.perms, segment.vaddr, data, segment.mem_size);
+        }
+        for (import, slot, addend) in &image.import_slots {
+            let import = &self.imports[*import];
+            plm.add_import(&import.library, &import.symbol, import.version, *slot, *addend);
+        }
+        for offset in image.rebase {
+            plm.add_relocation(offset);
+        }
+        if pie {
+            plm.flags |= FLAG_PIE;
+        }
+        plm
+    }
+
+    /// A `.plam` with one section per non-empty segment of `image`, its kind
+    /// taken from the permissions.
+    fn plam(&self, image: Image, base: u64, entry_offset: u64, pie: bool) -> PlamBuilder {
+        let mut plam = PlamBuilder::new(self.cpu_id, base, entry_offset);
+        if pie {
+            plam.relocations = Some(image.rebase);
+        }
+        for (segment, data) in self.segments.iter().zip(image.segments).filter(|(segment, _)| segment.mem_size > 0) {
+            let kind = if segment.perms & PERM_X != 0 {
+                plum_formats::plam::SectionKind::Text
+            } else if segment.perms & PERM_W == 0 {
+                plum_formats::plam::SectionKind::Rodata
+            } else if data.is_empty() {
+                plum_formats::plam::SectionKind::Bss
+            } else {
+                plum_formats::plam::SectionKind::Data
+            };
+            plam.add_section(kind, segment.perms, segment.vaddr, data, segment.mem_size);
+        }
+        plam
+    }
+
+    /// Defined global functions and objects, as addresses.
+    fn exports(&self) -> Vec<(&str, u32, u64)> {
+        self.globals
+            .iter()
+            .filter_map(|(name, global)| {
+                let Definition::Object { object, symbol } = global.definition else {
+                    return None;
+                };
+                let kind = match self.objects[object].symbols[symbol].kind {
+                    SymbolKind::Func => EXPORT_FUNC,
+                    SymbolKind::Object => EXPORT_OBJECT,
+                    _ => return None,
+                };
+                match self.symbol_target(object, symbol) {
+                    Target::Address { value, relocatable: true } => Some((name.as_str(), kind, value)),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+
+    /// A text link map: every segment, the sections in it and the symbols
+    /// they define, then the imports.
+    fn map(&self) -> String {
+        let mut out = String::new();
+        for segment in &self.segments {
+            let _ = writeln!(
+                out,
+                "{:<16} {} 0x{:016x} 0x{:x} (file 0x{:x})",
+                segment.name,
+                perms_str(segment.perms),
+                segment.vaddr,
+                segment.mem_size,
+                segment.file_size
+            );
+            for &(o, i) in &segment.sections {
+                let object = &self.objects[o];
+                let section = &object.sections[i];
+                let _ = writeln!(out, "  0x{:016x} {:>#10x} {:<24} {}", section.address, section.size, section.name, object.name);
+                let mut symbols: Vec<&InputSymbol> = object
+                    .symbols
+                    .iter()
+                    .filter(|symbol| symbol.section as usize == i && symbol.kind != SymbolKind::Section)
+                    .filter(|symbol| !symbol.name.is_empty())
+                    .collect();
+                symbols.sort_by_key(|symbol| symbol.value);
+                for symbol in symbols {
+                    let _ = writeln!(out, "      0x{:016x} {}", section.address + symbol.value, symbol.name);
+                }
+            }
+        }
+        let linker_symbols = self.globals.iter().filter_map(|(name, global)| match global.definition {
+            Definition::Absolute(value) => Some((name, value)),
+            Definition::Linker(symbol) => Some((name, self.linker_symbol_value(symbol))),
+            _ => None,
+        });
+        let _ = writeln!(out, "\nLinker symbols:");
+        for (name, value) in linker_symbols {
+            let _ = writeln!(out, "  0x{:016x} {}", value, name);
+        }
+        if !self.imports.is_empty() {
+            let _ = writeln!(out, "\nImports:");
+            for import in &self.imports {
+                let _ = writeln!(out, "  {} from {} >= {}", import.symbol, import.library_name(), import.version);
+            }
+        }
+        out
+    }
+}
+
+impl Import {
+    fn library_name(&self) -> &str {
+        if self.library.is_empty() {
+            "*"
+        } else {
+            &self.library
+        }
+    }
+}
+
+impl Image {
+    fn empty() -> Self {
+        Image {
+            segments: Vec::new(),
+            rebase: Vec::new(),
+            import_slots: Vec::new(),
+        }
+    }
+}
+
+/// Relocations that jump to their target, and can go through a PLT stub
+/// when it is imported.
+fn is_call(kind: RelocKind) -> bool {
+    matches!(
+        kind,
+        RelocKind::Pc32 | RelocKind::Aarch64Branch26 | RelocKind::RiscvCall | RelocKind::RiscvJal
+    )
+}
+
+/// Writes the PLT stub at `place` that jumps to the address in the GOT
+/// slot at `slot`.
+fn write_plt_stub(cpu_id: u16, stub: &mut [u8], place: u64, slot: u64) -> Result<(), PomError> {
+    let write_words = |stub: &mut [u8], words: [u32; 4]| {
+        for (i, word) in words.iter().enumerate() {
+            stub[i * 4..][..4].copy_from_slice(&word.to_le_bytes());
+        }
+    };
+    match cpu_id {
+        CPU_X86_64 => {
+            // jmp *slot(%rip), padded with int3
+            stub[..6].copy_from_slice(&[0xFF, 0x25, 0, 0, 0, 0]);
+            stub[6..PLT_STUB_SIZE as usize].fill(0xCC);
+            RelocKind::Pc32.apply(&mut stub[2..], RelocKind::Pc32.value(slot, -4, place + 2))
+        }
+        CPU_AARCH64 => {
+            // adrp x16, slot; ldr x17, [x16, :lo12:slot]; br x17; nop
+            write_words(stub, [0x9000_0010, 0xF940_0211, 0xD61F_0220, 0xD503_201F]);
+            RelocKind::Aarch64AdrPage21.apply(stub, RelocKind::Aarch64AdrPage21.value(slot, 0, place))?;
+            RelocKind::Aarch64Ldst64Lo12.apply(&mut stub[4..], RelocKind::Aarch64Ldst64Lo12.value(slot, 0, place + 4))
+        }
+        _ => {
+            // auipc t3, %pcrel_hi(slot); ld t3, %pcrel_lo(slot)(t3); jalr t1, t3; nop
+            write_words(stub, [0x0000_0E17, 0x000E_3E03, 0x000E_0367, 0x0000_0013]);
+            let value = RelocKind::RiscvPcrelHi20.value(slot, 0, place);
+            RelocKind::RiscvPcrelHi20.apply(stub, value)?;
+            RelocKind::RiscvPcrelLo12I.apply(&mut stub[4..], value)
+        }
+    }
+}
+
+/// Matches `name` against a pattern where `*` stands for any run of
+/// characters and `?` for one.
+fn glob_match(pattern: &str, name: &str) -> bool {
+    match pattern.as_bytes().first() {
+        None => name.is_empty(),
+        Some(b'*') => (0..=name.len()).any(|i| name.is_char_boundary(i) && glob_match(&pattern[1..], &name[i..])),
+        Some(b'?') => {
+            let mut chars = name.chars();
+            chars.next().is_some() && glob_match(&pattern[1..], chars.as_str())
+        }
+        Some(_) => {
+            let mut pattern_chars = pattern.chars();
+            let mut name_chars = name.chars();
+            pattern_chars.next() == name_chars.next() && glob_match(pattern_chars.as_str(), name_chars.as_str())
+        }
+    }
+}
+
+fn default_base(format: Format, cpu_id: u16) -> u64 {
+    match (format, cpu_id) {
+        (Format::Plm, _) => DEFAULT_IMAGE_BASE,
+        (Format::Plib, _) => 0,
+        (Format::Plam, CPU_AARCH64) => 0x4008_0000,
+        (Format::Plam, CPU_X86_64) => 0x100_000,
+        (Format::Plam, _) => 0x8000_0000,
+    }
+}
+
+fn parse_perms(perms: &str) -> u32 {
+    perms.chars().fold(0, |acc, c| match c {
+        'r' => acc | PERM_R,
+        'w' => acc | PERM_W,
+        'x' => acc | PERM_X,
+        _ => acc,
+    })
+}
+
+fn cpu_from_name(name: &str) -> Option<u16> {
+    match name {
+        "aarch64" => Some(CPU_AARCH64),
+        "x86_64" => Some(CPU_X86_64),
+        "riscv64" => Some(CPU_RISCV64),
+        "prum64" => Some(CPU_PRUM64),
+        _ => None,
+    }
+}
+
+/// Parses the `<name>@<version>` value of `--<option>=`.
+fn parse_named_version(arg: &str, option: &str) -> (String, Version) {
+    match arg[option.len()..].split_once('@') {
+        Some((name, version)) if !name.is_empty() => (
+            name.to_string(),
+            Version::parse(version).unwrap_or_else(|| {
+                eprintln!("❌ Invalid version in {}", arg);
+                exit(1);
+            }),
+        ),
+        _ => {
+            eprintln!("❌ Expected {}<name>@<version>: {}", option, arg);
+            exit(1);
+        }
+    }
+}
+
+fn load_signing_key(path: &str) -> SigningKey {
+    let key_hex = fs::read_to_string(path).unwrap_or_else(|e| {
+        eprintln!("❌ Failed to read signing key {}: {}", path, e);
+        exit(1);
+    });
+    let key_bytes: [u8; 32] = hex::decode(key_hex.trim())
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .unwrap_or_else(|| {
+            eprintln!("❌ {} is not a hex-encoded Ed25519 signing key", path);
+            exit(1);
+        });
+    SigningKey::from_bytes(&key_bytes)
+}
+
+fn parse_size(s: &str) -> Option<u64> {
+    match s.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+fn perms_str(perms: u32) -> String {
+    format!(
+        "{}{}{}",
+        if perms & PERM_R != 0 { 'R' } else { '-' },
+        if perms & PERM_W != 0 { 'W' } else { '-' },
+        if perms & PERM_X != 0 { 'X' } else { '-' }
+    )
+}
+
+fn print_usage() {
+    eprintln!("Usage: plink -o <output.plm|.plib|.plam> [options] <inputs...>");
+    eprintln!();
+    eprintln!("Inputs are .pom or ELF relocatable objects, .plstat archives whose members are");
+    eprintln!("pulled in for undefined symbols, and .plib libraries to import from.");
+    eprintln!();
+    eprintln!("Options:");
+    eprintln!("  --format=<plm|plib|plam>   output format (default from the extension)");
+    eprintln!("  --arch=<arch>              fail unless the objects are built for this architecture");
+    eprintln!("  --script=<layout.plconf>   memory layout (default text, rodata and data segments)");
+    eprintln!("  --entry=<addr|symbol>      entry point (default _start, none for a .plib)");
+    eprintln!("  --image-base=<addr>        address of the first segment (default depends on the format)");
+    eprintln!("  --pie                      record absolute addresses so the loader can rebase the image");
+    eprintln!("  --library=<name>[@<ver>]   import symbols nothing defines from this library");
+    eprintln!("  --plib=<name>@<ver>        write a .plib exporting the global functions and objects");
+    eprintln!("  --abi=<abi>, --stack-size=<bytes>   as for mkplm");
+    eprintln!("  --subsystem=<name>, --aslr, --compress, --sign=<key.hex>   as for mkplam");
+    eprintln!("  --map=<file>               write a link map");
+    eprintln!();
+    eprintln!("A layout is a .plconf with optional top-level entry, base and align keys and");
+    eprintln!("one [[segment]] per segment, in address order:");
+    eprintln!("  name = \"text\"                  __text_start and __text_end mark its bounds");
+    eprintln!("  perms = \"rx\"                   r, rw, rx or rwx");
+    eprintln!("  sections = [\".text*\", \".init\"]  input sections it takes first, * and ? match anything");
+    eprintln!("  address = 0x80200000           optional fixed address");
+    eprintln!("Sections no pattern takes go to the first segment whose permissions fit their kind.");
+    eprintln!("[[symbol]] sections with name and value define absolute symbols objects do not.");
+    eprintln!();
+    eprintln!("Examples:");
+    eprintln!("  plink -o kernel.plam --script=kernel.plconf boot.pom kernel.plstat --sign=keys/signing-key.hex");
+    eprintln!("  plink -o hello.plm hello.pom libplum.plib");
+    eprintln!("  plink -o libfoo.plib --plib=libfoo@1.0 foo.pom bar.o");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plum_formats::plam::{self, SectionKind as PlamSectionKind};
+    use plum_formats::plm::PlmFile;
+    use plum_formats::plstat::PlstatBuilder;
+    use plum_formats::pom::PomBuilder;
+
+    fn pom(cpu_id: u16, build: impl FnOnce(&mut PomBuilder)) -> Vec<u8> {
+        let mut builder = PomBuilder::new(cpu_id);
+        build(&mut builder);
+        builder.build()
+    }
+
+    /// An x86_64 object defining `x` in `.data`, or as COMMON of
+    /// `common_size` bytes.
+    fn defines_x(name: &str, binding: SymbolBinding, common_size: Option<u64>) -> Object {
+        let data = pom(CPU_X86_64, |b| {
+            let data = b.add_section(".data", SectionKind::Data, 8, vec![0; 8]);
+            match common_size {
+                Some(size) => b.add_symbol("x", SECTION_COMMON, binding, SymbolKind::Object, 8, size),
+                None => b.add_symbol("x", data, binding, SymbolKind::Object, 0, 8),
+            };
+        });
+        load_object(name, &data).unwrap()
+    }
+
+    /// The object whose definition of `x` wins.
+    fn x_from(objects: Vec<Object>) -> Result<String, String> {
+        let mut linker = Linker::new(CPU_X86_64, Script::default().segments);
+        for object in objects {
+            linker.add_object(object)?;
+        }
+        match linker.globals["x"].definition {
+            Definition::Object { object, .. } | Definition::Common { object, .. } => {
+                Ok(linker.objects[object].name.clone())
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    /// Runs the link up to layout with the default segments.
+    fn link(cpu_id: u16, objects: Vec<Object>, base: u64) -> Result<Linker, String> {
+        let mut linker = Linker::new(cpu_id, Script::default().segments);
+        for object in objects {
+            linker.add_object(object)?;
+        }
+        linker.provide_symbols(&[]);
+        linker.create_sections()?;
+        linker.layout(base)?;
+        Ok(linker)
+    }
+
+    #[test]
+    fn strong_beats_common_beats_weak() {
+        let strong = || defines_x("strong.pom", SymbolBinding::Global, None);
+        let weak = || defines_x("weak.pom", SymbolBinding::Weak, None);
+        let common = |size| defines_x(&format!("common{}.pom", size), SymbolBinding::Global, Some(size));
+
+        assert_eq!(x_from(vec![weak(), strong()]).unwrap(), "strong.pom");
+        assert_eq!(x_from(vec![strong(), weak()]).unwrap(), "strong.pom");
+        assert_eq!(x_from(vec![weak(), common(4)]).unwrap(), "common4.pom");
+        assert_eq!(x_from(vec![common(4), weak()]).unwrap(), "common4.pom");
+        assert_eq!(x_from(vec![common(4), strong()]).unwrap(), "strong.pom");
+        assert_eq!(x_from(vec![strong(), common(4)]).unwrap(), "strong.pom");
+        assert_eq!(x_from(vec![common(4), common(16)]).unwrap(), "common16.pom");
+        assert_eq!(x_from(vec![common(16), common(4)]).unwrap(), "common16.pom");
+        assert_eq!(
+            x_from(vec![defines_x("a.pom", SymbolBinding::Weak, None), defines_x("b.pom", SymbolBinding::Weak, None)])
+                .unwrap(),
+            "a.pom"
+        );
+        assert_eq!(
+            x_from(vec![strong(), weak(), defines_x("again.pom", SymbolBinding::Global, None)]),
+            Err("duplicate symbol x: defined in strong.pom and again.pom".to_string())
+        );
+    }
+
+    #[test]
+    fn common_symbols_get_bss() {
+        let objects = vec![
+            defines_x("small.pom", SymbolBinding::Global, Some(4)),
+            defines_x("large.pom", SymbolBinding::Global, Some(0x40)),
+        ];
+        let linker = link(CPU_X86_64, objects, DEFAULT_IMAGE_BASE).unwrap();
+        let Definition::Object { object, symbol } = linker.globals["x"].definition else {
+            panic!("x is still COMMON");
+        };
+        let allocated = &linker.objects[object];
+        assert_eq!(allocated.name, "<linker>");
+        let section = &allocated.sections[allocated.symbols[symbol].section as usize];
+        assert_eq!((section.kind, section.size, section.align), (SectionKind::Bss, 0x40, 8));
+        assert_eq!(linker.bss, Some((section.address, section.address + 0x40)));
+    }
+
+    #[test]
+    fn archive_members_are_pulled_across_archives() {
+        // Each member defines `name` and references `needs`.
+        let member = |defines: &str, needs: Option<&str>| {
+            pom(CPU_X86_64, |b| {
+                let text = b.add_section(".text", SectionKind::Text, 16, vec![0xC3]);
+                b.add_symbol(defines, text, SymbolBinding::Global, SymbolKind::Func, 0, 1);
+                if let Some(needs) = needs {
+                    b.add_symbol(needs, SECTION_UNDEF, SymbolBinding::Global, SymbolKind::NoType, 0, 0);
+                }
+            })
+        };
+        let mut first = PlstatBuilder::new();
+        first.add_member("f.pom", 0, member("f", Some("g")), ["f"]);
+        first.add_member("h.pom", 0, member("h", None), ["h"]);
+        let mut second = PlstatBuilder::new();
+        second.add_member("g.pom", 0, member("g", Some("h")), ["g"]);
+        second.add_member("unused.pom", 0, member("unused", None), ["unused"]);
+        let archives = [("first.plstat".to_string(), first.build()), ("second.plstat".to_string(), second.build())];
+
+        let mut linker = Linker::new(CPU_X86_64, Script::default().segments);
+        linker.add_object(load_object("main.pom", &member("_start", Some("f"))).unwrap()).unwrap();
+        linker.pull_archive_members(&archives).unwrap();
+
+        // h is only needed once g, from the later archive, is in.
+        let names: Vec<_> = linker.objects.iter().map(|object| object.name.as_str()).collect();
+        assert_eq!(names, ["main.pom", "first.plstat(f.pom)", "second.plstat(g.pom)", "first.plstat(h.pom)"]);
+        assert!(linker.undefined(false).is_empty());
+    }
+
+    /// `.init.text`, `.text`, `.data` and `.bss` of 4, 0x10, 8 and 0x20 bytes.
+    fn sections_object() -> Object {
+        let data = pom(CPU_RISCV64, |b| {
+            let init = b.add_section(".init.text", SectionKind::Text, 4, vec![0x13, 0, 0, 0]);
+            b.add_section(".text", SectionKind::Text, 4, vec![0x13; 0x10]);
+            b.add_section(".data", SectionKind::Data, 8, vec![0; 8]);
+            b.add_bss(".bss", 8, 0x20);
+            b.add_symbol("_start", init, SymbolBinding::Global, SymbolKind::Func, 0, 4);
+        });
+        load_object("boot.pom", &data).unwrap()
+    }
+
+    fn script(name: &str, text: &str) -> Script {
+        let path = env::temp_dir().join(format!("plink-{}-{}.plconf", name, std::process::id()));
+        fs::write(&path, text).unwrap();
+        let script = load_script(path.to_str().unwrap());
+        let _ = fs::remove_file(&path);
+        script
+    }
+
+    fn link_script(script: Script) -> Result<Linker, String> {
+        let mut linker = Linker::new(CPU_RISCV64, script.segments);
+        linker.add_object(sections_object())?;
+        linker.provide_symbols(&script.symbols);
+        linker.create_sections()?;
+        linker.layout(script.base.unwrap_or(0x1000_0000))?;
+        Ok(linker)
+    }
+
+    #[test]
+    fn script_places_segments() {
+        let script = script(
+            "layout",
+            "[[segment]]\n\
+             name = \"boot\"\nperms = \"rx\"\nsections = [\".init*\"]\naddress = 0x80000000\n\
+             [[segment]]\n\
+             name = \"text\"\nperms = \"rx\"\nsections = [\".text\"]\n\
+             [[segment]]\n\
+             name = \"data\"\nperms = \"rw\"\naddress = 0x80200000\n\
+             [[symbol]]\n\
+             name = \"__stack_top\"\nvalue = 0x80400000\n",
+        );
+        let linker = link_script(script).unwrap();
+
+        let segments: Vec<_> = linker
+            .segments
+            .iter()
+            .map(|segment| (segment.name.as_str(), segment.vaddr, segment.file_size, segment.mem_size))
+            .collect();
+        assert_eq!(
+            segments,
+            [("boot", 0x8000_0000, 4, 4), ("text", 0x8000_1000, 0x10, 0x10), ("data", 0x8020_0000, 8, 0x28)]
+        );
+        let addresses: Vec<_> = linker.objects[0].sections.iter().map(|section| section.address).collect();
+        assert_eq!(addresses, [0x8000_0000, 0x8000_1000, 0x8020_0000, 0x8020_0008]);
+        assert_eq!(linker.bss, Some((0x8020_0008, 0x8020_0028)));
+        assert_eq!(linker.entry_offset("_start", 0x8000_0000), Ok(0));
+
+        let symbol = |name: &str| match linker.target(&SymbolRef::Global(name.to_string())) {
+            Target::Address { value, .. } => value,
+            Target::Import(_) => unreachable!(),
+        };
+        assert_eq!(symbol("__stack_top"), 0x8040_0000);
+        assert_eq!(symbol("__text_end"), 0x8000_1010);
+        assert_eq!(symbol("_end"), 0x8020_0028);
+    }
+
+    #[test]
+    fn script_segments_must_not_overlap() {
+        let script = script(
+            "overlap",
+            "[[segment]]\n\
+             name = \"text\"\nperms = \"rx\"\naddress = 0x80000000\n\
+             [[segment]]\n\
+             name = \"data\"\nperms = \"rw\"\naddress = 0x80000000\n",
+        );
+        assert_eq!(
+            link_script(script).err(),
+            Some("segment data at 0x80000000 overlaps the segments before it, which end at 0x80001000".to_string())
+        );
+    }
+
+    #[test]
+    fn layout_overflow_is_an_error() {
+        let mut data = Segment::new("data", PERM_R | PERM_W);
+        data.address = Some(u64::MAX - PAGE_SIZE + 1);
+        let script = Script {
+            segments: vec![Segment::new("text", PERM_R | PERM_X), data],
+            ..Script::default()
+        };
+        assert_eq!(link_script(script).err(), Some("segment data: layout overflows the address space".to_string()));
+
+        let near_the_top = u64::MAX - PAGE_SIZE + 1;
+        assert_eq!(
+            link(CPU_RISCV64, vec![sections_object()], near_the_top).err(),
+            Some("segment text: layout overflows the address space".to_string())
+        );
+    }
+
+    /// `_start` in `.text` loads the GOT slot of `counter`; `.data` holds
+    /// the address of `_start` and of the absolute `fixed`.
+    fn pie_object(data_reloc: RelocKind) -> Object {
+        let data = pom(CPU_X86_64, |b| {
+            let text = b.add_section(".text", SectionKind::Text, 16, vec![0x48, 0x8B, 0, 0, 0, 0, 0xC3]);
+            let data = b.add_section(".data", SectionKind::Data, 8, vec![0; 24]);
+            let start = b.add_symbol("_start", text, SymbolBinding::Global, SymbolKind::Func, 0, 7);
+            let counter = b.add_symbol("counter", data, SymbolBinding::Global, SymbolKind::Object, 16, 8);
+            let fixed = b.add_symbol("fixed", SECTION_ABS, SymbolBinding::Global, SymbolKind::NoType, 0x1234, 0);
+            b.add_reloc(text, 2, RelocKind::X86GotPc32, counter, -4);
+            b.add_reloc(data, 0, data_reloc, start, 0);
+            b.add_reloc(data, 8, RelocKind::Abs64, fixed, 0);
+        });
+        load_object("pie.pom", &data).unwrap()
+    }
+
+    #[test]
+    fn pie_records_rebased_addresses() {
+        let base = DEFAULT_IMAGE_BASE;
+        let linker = link(CPU_X86_64, vec![pie_object(RelocKind::Abs64)], base).unwrap();
+        let got = linker.section_address(linker.got_section.unwrap());
+        assert_eq!(got, base + 0x1018);
+
+        let image = linker.emit(base, true).unwrap();
+        // The address of _start and the GOT slot of counter, not the
+        // absolute fixed.
+        assert_eq!(image.rebase, [0x1000, 0x1018]);
+        let data = &image.segments[2];
+        assert_eq!(u64::from_le_bytes(data[0..8].try_into().unwrap()), base);
+        assert_eq!(u64::from_le_bytes(data[8..16].try_into().unwrap()), 0x1234);
+        assert_eq!(u64::from_le_bytes(data[0x18..0x20].try_into().unwrap()), base + 0x1010);
+        let text = &image.segments[0];
+        assert_eq!(i32::from_le_bytes(text[2..6].try_into().unwrap()), (got - 4 - (base + 2)) as i32);
+
+        assert!(linker.emit(base, false).unwrap().rebase.is_empty());
+
+        let linker = link(CPU_X86_64, vec![pie_object(RelocKind::Abs32)], 0x10_0000).unwrap();
+        let e = linker.emit(0x10_0000, true).err().unwrap();
+        assert!(e.ends_with("an absolute address cannot be rebased, rebuild as position-independent code"), "{}", e);
+        assert!(linker.emit(0x10_0000, false).is_ok());
+    }
+
+    #[test]
+    fn plt_stubs_jump_through_the_got() {
+        let (place, slot) = (0x1_0000, 0x2_3008);
+        let stub = |cpu_id| {
+            let mut stub = [0u8; PLT_STUB_SIZE as usize];
+            write_plt_stub(cpu_id, &mut stub, place, slot).unwrap();
+            stub
+        };
+        let words = |stub: [u8; 16]| -> Vec<u32> {
+            stub.chunks_exact(4).map(|word| u32::from_le_bytes(word.try_into().unwrap())).collect()
+        };
+
+        // jmp *(slot - (place + 6))(%rip)
+        let x86 = stub(CPU_X86_64);
+        assert_eq!(x86[..2], [0xFF, 0x25]);
+        assert_eq!(i32::from_le_bytes(x86[2..6].try_into().unwrap()) as i64, slot as i64 - (place as i64 + 6));
+        assert_eq!(x86[6..], [0xCC; 10]);
+
+        // adrp x16, 0x23000; ldr x17, [x16, #8]; br x17; nop
+        assert_eq!(words(stub(CPU_AARCH64)), [0xF000_0090, 0xF940_0611, 0xD61F_0220, 0xD503_201F]);
+
+        // auipc t3, 0x13; ld t3, 8(t3); jalr t1, t3; nop
+        assert_eq!(words(stub(CPU_RISCV64)), [0x0001_3E17, 0x008E_3E03, 0x000E_0367, 0x0000_0013]);
+    }
+
+    #[test]
+    fn riscv_pcrel_lo12_needs_its_label() {
+        let mut object = sections_object();
+        object.relocs.push(Reloc {
+            section: 1,
+            offset: 4,
+            kind: RelocKind::RiscvPcrelLo12I,
+            symbol: NO_SYMBOL,
+            addend: 0,
+        });
+        let linker = link(CPU_RISCV64, vec![object], 0x1000_0000).unwrap();
+        let e = linker.emit(0x1000_0000, false).err().unwrap();
+        assert_eq!(
+            e,
+            "boot.pom: RISCV_PCREL_LO12_I at .text+0x4 against : needs the label of its PCREL_HI20 relocation"
+        );
+    }
+
+    #[test]
+    fn links_into_plm_and_plam() {
+        let base = DEFAULT_IMAGE_BASE;
+        let linker = link(CPU_X86_64, vec![pie_object(RelocKind::Abs64)], base).unwrap();
+        let entry_offset = linker.entry_offset("_start", base).unwrap();
+
+        let data = linker.plm(linker.emit(base, true).unwrap(), base, entry_offset, true).build();
+        let plm = PlmFile::parse(&data).unwrap();
+        assert!(plm.header.is_pie());
+        assert_eq!((plm.header.cpu_id, plm.header.image_base, plm.header.entry_offset), (CPU_X86_64, base, 0));
+        let segments: Vec<_> = plm.segments().map(|segment| (segment.perms, segment.vaddr, segment.mem_size)).collect();
+        assert_eq!(segments, [(PERM_R | PERM_X, base, 7), (PERM_R | PERM_W, base + 0x1000, 0x20)]);
+        assert_eq!(plm.relocations().collect::<Vec<_>>(), [0x1000, 0x1018]);
+        let mut memory = vec![0u8; plm.image_size() as usize];
+        plm.load(&mut memory).unwrap();
+        assert_eq!(memory[..7], [0x48, 0x8B, 0x12, 0x10, 0, 0, 0xC3]);
+
+        let data = linker.plam(linker.emit(base, true).unwrap(), base, entry_offset, true).build(None, None).unwrap();
+        let header = plam::validate(&data).unwrap();
+        assert_eq!((header.cpu_id, header.image_base, header.entry_offset), (CPU_X86_64, base, 0));
+        let sections: Vec<_> = header
+            .sections(&data)
+            .unwrap()
+            .map(|section| (section.section_kind(), section.vaddr, section.mem_size))
+            .collect();
+        assert_eq!(
+            sections,
+            [(Some(PlamSectionKind::Text), base, 7), (Some(PlamSectionKind::Data), base + 0x1000, 0x20)]
+        );
+        assert_eq!(header.relocations(&data).unwrap().collect::<Vec<_>>(), [0x1000, 0x1018]);
+    }
+}
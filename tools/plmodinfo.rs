@@ -0,0 +1,208 @@
+use std::env;
+use std::fs;
+use std::process::exit;
+
+use plum_formats::plam::arch_name;
+use plum_formats::plkmod::{load_order, PlkmodError, PlkmodFile, KERNEL_ABI};
+use plum_formats::version::Version;
+
+const EXIT_USAGE: i32 = 1;
+const EXIT_MALFORMED: i32 = 2;
+const EXIT_KERNEL_ABI: i32 = 3;
+const EXIT_SIGNATURE: i32 = 4;
+const EXIT_DEPENDENCIES: i32 = 5;
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+
+    let mut paths = Vec::new();
+    let mut trusted_keys = Vec::new();
+    let mut kernel_abi = KERNEL_ABI;
+    let mut field = None;
+
+    let mut iter = args[1..].iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--trusted" => match iter.next() {
+                Some(key_path) => trusted_keys.push(load_public_key(key_path)),
+                None => {
+                    print_usage();
+                    exit(EXIT_USAGE);
+                }
+            },
+            _ if arg.starts_with("--kernel-abi=") => {
+                kernel_abi = Version::parse(&arg["--kernel-abi=".len()..]).unwrap_or_else(|| {
+                    eprintln!("❌ Invalid version in {}", arg);
+                    exit(EXIT_USAGE);
+                })
+            }
+            _ if arg.starts_with("--field=") => field = Some(arg["--field=".len()..].to_string()),
+            _ if !arg.starts_with('-') => paths.push(arg.clone()),
+            _ => {
+                print_usage();
+                exit(EXIT_USAGE);
+            }
+        }
+    }
+
+    if paths.is_empty() {
+        print_usage();
+        exit(EXIT_USAGE);
+    }
+
+    let raws: Vec<Vec<u8>> = paths
+        .iter()
+        .map(|path| {
+            fs::read(path).unwrap_or_else(|e| {
+                eprintln!("❌ Failed to read {}: {}", path, e);
+                exit(EXIT_USAGE);
+            })
+        })
+        .collect();
+    let modules: Vec<PlkmodFile> = paths
+        .iter()
+        .zip(&raws)
+        .map(|(path, raw)| {
+            PlkmodFile::parse(raw).unwrap_or_else(|e| {
+                eprintln!("❌ {}: {}", path, e);
+                exit(EXIT_MALFORMED);
+            })
+        })
+        .collect();
+
+    if let Some(field) = &field {
+        for (path, module) in paths.iter().zip(&modules) {
+            match field_value(module, field) {
+                Some(value) => println!("{}", value),
+                None => {
+                    eprintln!("❌ {}: unknown field {}", path, field);
+                    exit(EXIT_USAGE);
+                }
+            }
+        }
+        return;
+    }
+
+    let mut status = 0;
+    for (path, module) in paths.iter().zip(&modules) {
+        print_module(path, module);
+
+        if let Err(e) = module.check_kernel_abi(kernel_abi) {
+            eprintln!("❌ {}: {}", path, e);
+            status = status.max(EXIT_KERNEL_ABI);
+        }
+        if module.header.is_signed() || !trusted_keys.is_empty() {
+            let result = if trusted_keys.is_empty() {
+                module.signature().map(|_| ())
+            } else {
+                module.verify_signature(&trusted_keys).map(|_| ())
+            };
+            if let Err(e) = result {
+                eprintln!("❌ {}: {}", path, e);
+                status = status.max(EXIT_SIGNATURE);
+            }
+        }
+        println!();
+    }
+
+    // Dependencies can only be checked against the other modules given.
+    if modules.len() > 1 {
+        match load_order(&modules) {
+            Ok(order) => {
+                let names: Vec<&str> = order.iter().map(|&i| modules[i].name()).collect();
+                println!("Load order: {}", names.join(" → "));
+            }
+            Err(e) => {
+                eprintln!("❌ {}", e);
+                status = status.max(EXIT_DEPENDENCIES);
+            }
+        }
+    }
+
+    if status == 0 {
+        println!("✅ {} module(s) valid for kernel ABI {}", modules.len(), kernel_abi);
+    }
+    exit(status);
+}
+
+fn print_module(path: &str, module: &PlkmodFile) {
+    let image = &module.image.header;
+    println!("filename:       {}", path);
+    println!("name:           {}", module.name());
+    println!("version:        {}", module.mod_version());
+    println!("kind:           {}", module.header.module_kind().name());
+    if !module.class().is_empty() {
+        println!("class:          {}", module.class());
+    }
+    if !module.description().is_empty() {
+        println!("description:    {}", module.description());
+    }
+    println!("kernel_abi:     {}", module.kernel_abi());
+    println!("arch:           {}", arch_name(image.cpu_id).unwrap_or("unknown"));
+    println!("init:           +0x{:x}", module.header.init_offset);
+    match module.exit_offset() {
+        Some(offset) => println!("exit:           +0x{:x}", offset),
+        None => println!("exit:           none (cannot be unloaded)"),
+    }
+    let depends: Vec<String> = module
+        .dependencies()
+        .map(|d| format!("{}>={}", d.name, d.min_version))
+        .collect();
+    println!("depends:        {}", depends.join(","));
+    println!("imports:        {}", image.import_count);
+    println!("image_size:     {}", module.image.image_size());
+    match module.signature() {
+        Ok(block) => println!("signer:         {}", hex::encode(block.public_key)),
+        Err(PlkmodError::NotSigned) => println!("signer:         unsigned"),
+        Err(e) => println!("signer:         {}", e),
+    }
+}
+
+fn field_value(module: &PlkmodFile, field: &str) -> Option<String> {
+    Some(match field {
+        "name" => module.name().to_string(),
+        "version" => module.mod_version().to_string(),
+        "kind" => module.header.module_kind().name().to_string(),
+        "class" => module.class().to_string(),
+        "description" => module.description().to_string(),
+        "kernel_abi" => module.kernel_abi().to_string(),
+        "depends" => module
+            .dependencies()
+            .map(|d| d.name)
+            .collect::<Vec<_>>()
+            .join(","),
+        "signer" => module
+            .signature()
+            .map(|block| hex::encode(block.public_key))
+            .unwrap_or_default(),
+        _ => return None,
+    })
+}
+
+fn load_public_key(path: &str) -> [u8; 32] {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|key_hex| hex::decode(key_hex.trim()).ok())
+        .and_then(|bytes| bytes.try_into().ok())
+        .unwrap_or_else(|| {
+            eprintln!("❌ {} is not a hex-encoded Ed25519 public key", path);
+            exit(EXIT_USAGE);
+        })
+}
+
+fn print_usage() {
+    eprintln!("Usage: plmodinfo [--trusted <key.pubhex>]... [--kernel-abi=<ver>] [--field=<name>] <module>...");
+    eprintln!();
+    eprintln!("Prints and validates .plkmod/.pkd modules. With several modules their");
+    eprintln!("dependencies are resolved against each other and a load order printed.");
+    eprintln!();
+    eprintln!("Fields: name, version, kind, class, description, kernel_abi, depends, signer");
+    eprintln!();
+    eprintln!("Exit codes:");
+    eprintln!("  0  all modules valid");
+    eprintln!("  1  usage or I/O error");
+    eprintln!("  2  malformed module");
+    eprintln!("  3  kernel ABI not satisfied (default {})", KERNEL_ABI);
+    eprintln!("  4  missing, malformed, untrusted or bad signature");
+    eprintln!("  5  missing, outdated or cyclic dependency");
+}
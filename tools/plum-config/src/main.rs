@@ -7,10 +7,11 @@ use crossterm::{
     terminal::{enable_raw_mode, disable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
     execute,
 };
-use serde::{Serialize, Deserialize};
+use serde::Serialize;
 use dialoguer::{Confirm, Input, Select};
+use plum_formats::plconf::{Config, Value, PLUM_CONFIG_SCHEMA};
 
-#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+#[derive(Debug, Serialize, Default, Clone)]
 struct PlumConfig {
     system: SystemConfig,
     drivers: DriversConfig,
@@ -19,7 +20,7 @@ struct PlumConfig {
     plam: PlamConfig,
 }
 
-#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+#[derive(Debug, Serialize, Default, Clone)]
 struct DriversConfig {
     usb: bool,
     wifi: bool,
@@ -29,7 +30,7 @@ struct DriversConfig {
     bluetooth: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+#[derive(Debug, Serialize, Default, Clone)]
 struct CompatibilityConfig {
     posix: bool,
     win32: bool,
@@ -38,7 +39,7 @@ struct CompatibilityConfig {
     linux: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+#[derive(Debug, Serialize, Default, Clone)]
 struct BuildConfig {
     mode: String,
     optimization: String,
@@ -46,14 +47,14 @@ struct BuildConfig {
     lto: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+#[derive(Debug, Serialize, Default, Clone)]
 struct PlamConfig {
     subsystem: String,
     flags: Vec<String>,
     version: String,
 }
 
-#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+#[derive(Debug, Serialize, Default, Clone)]
 struct SystemConfig {
     arch: String,
     page_size: String,
@@ -72,48 +73,17 @@ enum InputMode {
 fn main() -> io::Result<()> {
     let config_path = "plum.config";
     let mut cfg: PlumConfig = if let Ok(data) = fs::read_to_string(&config_path) {
-        toml::from_str(&data).unwrap_or_else(|e| {
-            eprintln!("Warning: Error parsing config: {}. Using defaults.", e);
-            Default::default()
-        })
+        match load_config(config_path, &data) {
+            Ok(cfg) => cfg,
+            Err(e) => {
+                // Starting from defaults would overwrite the file on the next save.
+                eprintln!("Error: {} is not a valid configuration: {}", config_path, e);
+                std::process::exit(1);
+            }
+        }
     } else {
         println!("No existing config found. Creating default configuration.");
-        PlumConfig {
-            system: SystemConfig {
-                arch: "prum64".into(),
-                page_size: "16K".into(),
-                smp_cores: 8,
-                secure_boot: true,
-                memory_size: "1G".into(),
-                boot_delay: 3,
-            },
-            drivers: DriversConfig {
-                usb: true,
-                wifi: true,
-                gpu: false,
-                nvme: true,
-                audio: false,
-                bluetooth: false,
-            },
-            compatibility: CompatibilityConfig {
-                posix: true,
-                win32: true,
-                darwin: true,
-                android: false,
-                linux: true,
-            },
-            build: BuildConfig {
-                mode: "release".into(),
-                optimization: "speed".into(),
-                debug_info: false,
-                lto: true,
-            },
-            plam: PlamConfig {
-                subsystem: "native_kernel".into(),
-                flags: vec!["pie".into(), "aslr".into()],
-                version: "1.0.0".into(),
-            },
-        }
+        default_config()
     };
 
     enable_raw_mode()?;
@@ -185,10 +155,16 @@ fn main() -> io::Result<()> {
                         },
                         InputMode::Editing => match key.code {
                             KeyCode::Enter => {
-                                if apply_edit(&mut cfg, &input_buffer, current_edit).is_ok() {
-                                    message = Some("Value updated successfully".into());
-                                } else {
-                                    message = Some("Error: Invalid value".into());
+                                let mut edited = cfg.clone();
+                                let result = apply_edit(&mut edited, &input_buffer, current_edit)
+                                    .map_err(str::to_string)
+                                    .and_then(|()| to_plconf(&edited).map(|_| ()));
+                                match result {
+                                    Ok(()) => {
+                                        cfg = edited;
+                                        message = Some("Value updated successfully".into());
+                                    }
+                                    Err(e) => message = Some(format!("Error: {}", e)),
                                 }
                                 input_mode = InputMode::Normal;
                                 input_buffer.clear();
@@ -219,6 +195,45 @@ fn main() -> io::Result<()> {
     Ok(())
 }
 
+fn default_config() -> PlumConfig {
+    PlumConfig {
+        system: SystemConfig {
+            arch: "prum64".into(),
+            page_size: "16K".into(),
+            smp_cores: 8,
+            secure_boot: true,
+            memory_size: "1G".into(),
+            boot_delay: 3,
+        },
+        drivers: DriversConfig {
+            usb: true,
+            wifi: true,
+            gpu: false,
+            nvme: true,
+            audio: false,
+            bluetooth: false,
+        },
+        compatibility: CompatibilityConfig {
+            posix: true,
+            win32: true,
+            darwin: true,
+            android: false,
+            linux: true,
+        },
+        build: BuildConfig {
+            mode: "release".into(),
+            optimization: "speed".into(),
+            debug_info: false,
+            lto: true,
+        },
+        plam: PlamConfig {
+            subsystem: "native_kernel".into(),
+            flags: vec!["pie".into(), "aslr".into()],
+            version: "1.0.0".into(),
+        },
+    }
+}
+
 fn get_max_items(tab: usize) -> usize {
     match tab {
         0 => 6, 
@@ -368,7 +383,7 @@ fn handle_toggle(cfg: &mut PlumConfig, tab: usize, item: usize) {
 fn handle_action(cfg: &mut PlumConfig, item: usize, message: &mut Option<String>) {
     match item {
         0 => { 
-            *cfg = Default::default();
+            *cfg = default_config();
             *message = Some("Configuration reset to defaults".into());
         }
         1 => { 
@@ -658,19 +673,91 @@ fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
         .split(popup_layout[1])[1]
 }
 
+/// Reads `plum.config` with the shared `.plconf` loader and checks it
+/// against `PLUM_CONFIG_SCHEMA`. Keys left out keep their zero values.
+fn load_config(path: &str, text: &str) -> Result<PlumConfig, String> {
+    let config = Config::load(path, text, &mut |include: &str| fs::read_to_string(include).ok())
+        .map_err(|e| e.to_string())?;
+    if let Err(errors) = PLUM_CONFIG_SCHEMA.validate(&config) {
+        return Err(errors.iter().map(|e| e.to_string()).collect::<Vec<_>>().join("; "));
+    }
+
+    let string = |section: &str, key: &str| match config.get(section, key) {
+        Some(Value::Str(s)) => s.clone(),
+        Some(value) => value.to_string(),
+        None => String::new(),
+    };
+    let boolean = |section: &str, key: &str| config.get(section, key).and_then(Value::as_bool).unwrap_or(false);
+    let int = |section: &str, key: &str| config.get(section, key).and_then(Value::as_int).unwrap_or(0);
+
+    Ok(PlumConfig {
+        system: SystemConfig {
+            arch: string("system", "arch"),
+            page_size: string("system", "page_size"),
+            smp_cores: int("system", "smp_cores") as usize,
+            secure_boot: boolean("system", "secure_boot"),
+            memory_size: string("system", "memory_size"),
+            boot_delay: int("system", "boot_delay") as u32,
+        },
+        drivers: DriversConfig {
+            usb: boolean("drivers", "usb"),
+            wifi: boolean("drivers", "wifi"),
+            gpu: boolean("drivers", "gpu"),
+            nvme: boolean("drivers", "nvme"),
+            audio: boolean("drivers", "audio"),
+            bluetooth: boolean("drivers", "bluetooth"),
+        },
+        compatibility: CompatibilityConfig {
+            posix: boolean("compatibility", "posix"),
+            win32: boolean("compatibility", "win32"),
+            darwin: boolean("compatibility", "darwin"),
+            android: boolean("compatibility", "android"),
+            linux: boolean("compatibility", "linux"),
+        },
+        build: BuildConfig {
+            mode: string("build", "mode"),
+            optimization: string("build", "optimization"),
+            debug_info: boolean("build", "debug_info"),
+            lto: boolean("build", "lto"),
+        },
+        plam: PlamConfig {
+            subsystem: string("plam", "subsystem"),
+            flags: config
+                .get("plam", "flags")
+                .and_then(Value::as_list)
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|flag| flag.as_str().map(str::to_string))
+                .collect(),
+            version: string("plam", "version"),
+        },
+    })
+}
+
+/// Serializes `cfg` and checks the result against the schema `load_config`
+/// enforces, so nothing is written that could not be loaded again.
+fn to_plconf(cfg: &PlumConfig) -> Result<String, String> {
+    let text = toml::to_string_pretty(cfg).map_err(|e| format!("cannot serialize config: {}", e))?;
+    let config = Config::parse("plum.config", &text).map_err(|e| e.to_string())?;
+    if let Err(errors) = PLUM_CONFIG_SCHEMA.validate(&config) {
+        return Err(errors.iter().map(|e| e.kind.to_string()).collect::<Vec<_>>().join("; "));
+    }
+    Ok(text)
+}
+
 fn save_and_generate(cfg: &PlumConfig, path: &str) -> String {
     if !validate_config(cfg) {
         return "Error: Configuration validation failed".into();
     }
 
-    match toml::to_string_pretty(cfg) {
-        Ok(toml) => {
-            if let Err(e) = fs::write(path, &toml) {
+    match to_plconf(cfg) {
+        Ok(text) => {
+            if let Err(e) = fs::write(path, &text) {
                 return format!("Error saving config: {}", e);
             }
         }
         Err(e) => {
-            return format!("Error serializing config: {}", e);
+            return format!("Error: {}", e);
         }
     }
 
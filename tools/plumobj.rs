@@ -0,0 +1,833 @@
+use std::env;
+use std::fs;
+use std::process::exit;
+
+use serde_json::{Map, Value};
+
+use plum_formats::fat::{cpu_from_name, is_fat, select, FatFile, FAT_MAGIC};
+use plum_formats::lz4;
+use plum_formats::plam::{
+    self, arch_name, PlamHeader, PlamSignature, Subsystem, FLAG_ASLR, HEADER_SIZE, PERM_R, PERM_W, PERM_X, PLAM_MAGIC,
+};
+use plum_formats::plib::{PlibFile, EXPORT_FUNC, EXPORT_OBJECT, PLIB_MAGIC};
+use plum_formats::plkmod::{PlkmodError, PlkmodFile, PLKMOD_MAGIC};
+use plum_formats::plm::{Abi, PlmFile, PLM_MAGIC};
+use plum_formats::plres::{format_name, PlresFile, PLRES_MAGIC};
+use plum_formats::plstat::{PlstatFile, PLSTAT_MAGIC};
+use plum_formats::pom::{PomFile, POM_MAGIC, SECTION_ABS, SECTION_COMMON, SECTION_UNDEF};
+
+const EXIT_USAGE: i32 = 1;
+const EXIT_UNKNOWN: i32 = 2;
+const EXIT_MALFORMED: i32 = 3;
+const EXIT_SIGNATURE: i32 = 4;
+const EXIT_NO_SLICE: i32 = 5;
+
+/// Larger payloads are not inflated to list relocations.
+const MAX_PAYLOAD_SIZE: u64 = 1024 * 1024 * 1024;
+
+/// What a part of a dump shows, for selecting parts on the command line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum What {
+    Header,
+    Sections,
+    Symbols,
+    Relocs,
+    Dynamic,
+    Signature,
+}
+
+const ALL: &[What] = &[
+    What::Header,
+    What::Sections,
+    What::Symbols,
+    What::Relocs,
+    What::Dynamic,
+    What::Signature,
+];
+
+/// One value of a dump. Numbers stay numbers in JSON whichever way the text
+/// output prints them.
+enum Cell {
+    Int(u64),
+    Hex(u64),
+    Signed(i64),
+    Str(String),
+    Bool(bool),
+    None,
+}
+
+impl Cell {
+    fn str(value: impl ToString) -> Self {
+        Cell::Str(value.to_string())
+    }
+
+    /// [`Cell::None`] for an empty string.
+    fn name(value: &str) -> Self {
+        if value.is_empty() {
+            Cell::None
+        } else {
+            Cell::str(value)
+        }
+    }
+
+    fn text(&self) -> String {
+        match self {
+            Cell::Int(value) => value.to_string(),
+            Cell::Hex(value) => format!("0x{:x}", value),
+            Cell::Signed(value) if *value < 0 => format!("-0x{:x}", value.unsigned_abs()),
+            Cell::Signed(value) => format!("+0x{:x}", value),
+            Cell::Str(value) => value.clone(),
+            Cell::Bool(value) => if *value { "yes" } else { "no" }.to_string(),
+            Cell::None => "-".to_string(),
+        }
+    }
+
+    fn is_number(&self) -> bool {
+        matches!(self, Cell::Int(_) | Cell::Hex(_) | Cell::Signed(_))
+    }
+
+    fn json(&self) -> Value {
+        match self {
+            Cell::Int(value) | Cell::Hex(value) => Value::from(*value),
+            Cell::Signed(value) => Value::from(*value),
+            Cell::Str(value) => Value::from(value.as_str()),
+            Cell::Bool(value) => Value::from(*value),
+            Cell::None => Value::Null,
+        }
+    }
+}
+
+enum Body {
+    Fields(Vec<(&'static str, Cell)>),
+    Table {
+        columns: &'static [&'static str],
+        rows: Vec<Vec<Cell>>,
+    },
+}
+
+struct Part {
+    what: What,
+    /// The JSON key, also the title in text output.
+    key: &'static str,
+    body: Body,
+}
+
+struct Dump {
+    format: &'static str,
+    description: &'static str,
+    parts: Vec<Part>,
+    /// A signature that is malformed, does not match or is not trusted.
+    bad_signature: Option<String>,
+}
+
+impl Dump {
+    fn new(format: &'static str, description: &'static str) -> Self {
+        Dump {
+            format,
+            description,
+            parts: Vec::new(),
+            bad_signature: None,
+        }
+    }
+
+    fn fields(&mut self, what: What, key: &'static str, fields: Vec<(&'static str, Cell)>) {
+        self.parts.push(Part {
+            what,
+            key,
+            body: Body::Fields(fields),
+        });
+    }
+
+    fn table(&mut self, what: What, key: &'static str, columns: &'static [&'static str], rows: Vec<Vec<Cell>>) {
+        self.parts.push(Part {
+            what,
+            key,
+            body: Body::Table { columns, rows },
+        });
+    }
+
+    /// Adds the signature part: the signer and whether the signature
+    /// matches, and is trusted when `trusted_keys` is not empty.
+    fn signature<E: ToString>(
+        &mut self,
+        block: Result<PlamSignature, E>,
+        verify: impl FnOnce(&[[u8; 32]]) -> Result<[u8; 32], E>,
+        trusted_keys: &[[u8; 32]],
+    ) {
+        let block = match block {
+            Ok(block) => block,
+            Err(e) => {
+                self.bad_signature = Some(e.to_string());
+                self.fields(What::Signature, "signature", vec![("error", Cell::str(e.to_string()))]);
+                return;
+            }
+        };
+        let keys: &[[u8; 32]] = if trusted_keys.is_empty() {
+            core::slice::from_ref(&block.public_key)
+        } else {
+            trusted_keys
+        };
+        let result = verify(keys);
+        let mut fields = vec![
+            ("algorithm", Cell::str("ed25519")),
+            ("public_key", Cell::str(hex::encode(block.public_key))),
+            ("signature", Cell::str(hex::encode(block.signature))),
+            ("valid", Cell::Bool(result.is_ok())),
+        ];
+        if !trusted_keys.is_empty() {
+            fields.push(("trusted", Cell::Bool(trusted_keys.contains(&block.public_key))));
+        }
+        if let Err(e) = result {
+            fields.push(("error", Cell::str(e.to_string())));
+            self.bad_signature = Some(e.to_string());
+        }
+        self.fields(What::Signature, "signature", fields);
+    }
+
+    fn print(&self, path: &str, selected: &[What]) {
+        println!("{}: {} ({})", path, self.description, self.format);
+        for part in self.parts.iter().filter(|part| selected.contains(&part.what)) {
+            println!();
+            match &part.body {
+                Body::Fields(fields) => {
+                    println!("{}:", title(part.key));
+                    let width = fields.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+                    for (name, cell) in fields {
+                        println!("  {:<width$}  {}", name, cell.text(), width = width);
+                    }
+                }
+                Body::Table { columns, rows } => {
+                    println!("{} ({}):", title(part.key), rows.len());
+                    if rows.is_empty() {
+                        continue;
+                    }
+                    let texts: Vec<Vec<String>> = rows.iter().map(|row| row.iter().map(Cell::text).collect()).collect();
+                    let widths: Vec<usize> = (0..columns.len())
+                        .map(|i| texts.iter().map(|row| row[i].len()).chain([columns[i].len()]).max().unwrap())
+                        .collect();
+                    let numeric: Vec<bool> = (0..columns.len())
+                        .map(|i| rows.iter().all(|row| row[i].is_number() || matches!(row[i], Cell::None)))
+                        .collect();
+                    let line = |cells: &[String]| {
+                        let mut out = String::from(" ");
+                        for (i, cell) in cells.iter().enumerate() {
+                            if numeric[i] {
+                                out += &format!(" {:>width$}", cell, width = widths[i]);
+                            } else {
+                                out += &format!(" {:<width$}", cell, width = widths[i]);
+                            }
+                        }
+                        println!("{}", out.trim_end());
+                    };
+                    line(&columns.iter().map(|column| column.to_string()).collect::<Vec<_>>());
+                    for row in &texts {
+                        line(row);
+                    }
+                }
+            }
+        }
+        println!();
+    }
+
+    fn json(&self, path: &str, selected: &[What]) -> Value {
+        let mut object = Map::new();
+        object.insert("file".to_string(), Value::from(path));
+        object.insert("format".to_string(), Value::from(self.format));
+        for part in self.parts.iter().filter(|part| selected.contains(&part.what)) {
+            let value = match &part.body {
+                Body::Fields(fields) => {
+                    Value::Object(fields.iter().map(|(name, cell)| (name.to_string(), cell.json())).collect())
+                }
+                Body::Table { columns, rows } => Value::Array(
+                    rows.iter()
+                        .map(|row| {
+                            Value::Object(columns.iter().zip(row).map(|(name, cell)| (name.to_string(), cell.json())).collect())
+                        })
+                        .collect(),
+                ),
+            };
+            object.insert(part.key.to_string(), value);
+        }
+        Value::Object(object)
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+
+    let mut paths = Vec::new();
+    let mut selected = Vec::new();
+    let mut json = false;
+    let mut trusted_keys = Vec::new();
+    let mut arch = None;
+
+    let mut iter = args[1..].iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-h" | "--header" => selected.push(What::Header),
+            "-S" | "--sections" => selected.push(What::Sections),
+            "-s" | "--symbols" => selected.push(What::Symbols),
+            "-r" | "--relocs" => selected.push(What::Relocs),
+            "-d" | "--dynamic" => selected.push(What::Dynamic),
+            "-g" | "--signature" => selected.push(What::Signature),
+            "-a" | "--all" => selected.extend_from_slice(ALL),
+            "--json" => json = true,
+            "--trusted" => match iter.next() {
+                Some(key_path) => trusted_keys.push(load_public_key(key_path)),
+                None => {
+                    print_usage();
+                    exit(EXIT_USAGE);
+                }
+            },
+            _ if arg.starts_with("--arch=") => {
+                let name = &arg["--arch=".len()..];
+                arch = Some(cpu_from_name(name).unwrap_or_else(|| {
+                    eprintln!("❌ Unknown architecture: {}", name);
+                    exit(EXIT_USAGE);
+                }));
+            }
+            "--help" => {
+                print_usage();
+                exit(0);
+            }
+            _ if !arg.starts_with('-') => paths.push(arg.clone()),
+            _ => {
+                print_usage();
+                exit(EXIT_USAGE);
+            }
+        }
+    }
+
+    if paths.is_empty() {
+        print_usage();
+        exit(EXIT_USAGE);
+    }
+    if selected.is_empty() {
+        selected.extend_from_slice(ALL);
+    }
+
+    let mut status = 0;
+    let mut documents = Vec::new();
+    for path in &paths {
+        let data = fs::read(path).unwrap_or_else(|e| {
+            eprintln!("❌ Failed to read {}: {}", path, e);
+            exit(EXIT_USAGE);
+        });
+        let data = match arch {
+            Some(cpu_id) if is_fat(&data) => match select(&data, cpu_id) {
+                Ok(slice) => slice.to_vec(),
+                Err(e) => {
+                    eprintln!("❌ {}: {}", path, e);
+                    status = status.max(EXIT_NO_SLICE);
+                    continue;
+                }
+            },
+            _ => data,
+        };
+        let dump = match inspect(&data, &trusted_keys) {
+            Ok(dump) => dump,
+            Err((code, message)) => {
+                eprintln!("❌ {}: {}", path, message);
+                status = status.max(code);
+                continue;
+            }
+        };
+        if json {
+            documents.push(dump.json(path, &selected));
+        } else {
+            dump.print(path, &selected);
+        }
+        if let Some(e) = &dump.bad_signature {
+            eprintln!("❌ {}: {}", path, e);
+            status = status.max(EXIT_SIGNATURE);
+        }
+    }
+
+    if json {
+        let document = if paths.len() == 1 {
+            documents.pop().unwrap_or(Value::Null)
+        } else {
+            Value::Array(documents)
+        };
+        println!("{}", serde_json::to_string_pretty(&document).unwrap());
+    }
+    exit(status);
+}
+
+/// Picks the format from the magic and dumps everything it has.
+fn inspect(data: &[u8], trusted_keys: &[[u8; 32]]) -> Result<Dump, (i32, String)> {
+    let malformed = |e: &dyn ToString| (EXIT_MALFORMED, e.to_string());
+    let magic: [u8; 4] = match data.get(..4) {
+        Some(magic) => magic.try_into().unwrap(),
+        None => return Err((EXIT_UNKNOWN, "file is too short to have a magic".to_string())),
+    };
+    match magic {
+        PLAM_MAGIC => dump_plam(data, trusted_keys),
+        FAT_MAGIC => {
+            let file = FatFile::parse(data).map_err(|e| malformed(&e))?;
+            let mut dump = Dump::new("fat", "PlumOS universal image");
+            let format = match file.header.format {
+                PLAM_MAGIC => "plam",
+                _ => "plm",
+            };
+            dump.fields(
+                What::Header,
+                "header",
+                vec![
+                    ("version", Cell::str(format_version(file.header.version))),
+                    ("format", Cell::str(format)),
+                    ("slices", Cell::Int(file.header.slice_count as u64)),
+                    ("file_size", Cell::Int(file.header.file_size)),
+                ],
+            );
+            let slices = file
+                .slice_entries()
+                .enumerate()
+                .map(|(i, entry)| {
+                    let [arch, cpu_id] = arch_fields(entry.cpu_id).map(|(_, cell)| cell);
+                    vec![Cell::Int(i as u64), arch, cpu_id, Cell::Hex(entry.offset), Cell::Int(entry.size)]
+                })
+                .collect();
+            dump.table(What::Sections, "slices", &["index", "arch", "cpu_id", "offset", "size"], slices);
+            Ok(dump)
+        }
+        PLM_MAGIC => {
+            let file = PlmFile::parse(data).map_err(|e| malformed(&e))?;
+            let mut dump = Dump::new("plm", "PlumOS application");
+            add_plm(&mut dump, &file, What::Header);
+            Ok(dump)
+        }
+        PLIB_MAGIC => {
+            let file = PlibFile::parse(data).map_err(|e| malformed(&e))?;
+            let mut dump = Dump::new("plib", "PlumOS shared library");
+            dump.fields(
+                What::Header,
+                "header",
+                vec![
+                    ("version", Cell::str(format_version(file.header.version))),
+                    ("name", Cell::str(file.name().map_err(|e| malformed(&e))?)),
+                    ("lib_version", Cell::str(file.lib_version())),
+                    ("exports", Cell::Int(file.header.export_count as u64)),
+                    ("hash_buckets", Cell::Int(file.header.bucket_count as u64)),
+                    ("image_offset", Cell::Hex(file.header.image_offset)),
+                    ("image_size", Cell::Int(file.header.image_size)),
+                ],
+            );
+            let exports = file
+                .exports()
+                .map(|export| {
+                    let kind = match export.kind {
+                        EXPORT_FUNC => Cell::str("func"),
+                        EXPORT_OBJECT => Cell::str("object"),
+                        kind => Cell::Int(kind as u64),
+                    };
+                    vec![Cell::str(export.name), kind, Cell::Hex(export.value)]
+                })
+                .collect();
+            dump.table(What::Dynamic, "exports", &["name", "kind", "value"], exports);
+            add_plm(&mut dump, &file.image, What::Sections);
+            Ok(dump)
+        }
+        PLSTAT_MAGIC => dump_plstat(data),
+        PLKMOD_MAGIC => {
+            let module = PlkmodFile::parse(data).map_err(|e| malformed(&e))?;
+            let mut dump = Dump::new("plkmod", "PlumOS kernel module");
+            dump.fields(
+                What::Header,
+                "header",
+                vec![
+                    ("version", Cell::str(format_version(module.header.version))),
+                    ("name", Cell::str(module.name())),
+                    ("mod_version", Cell::str(module.mod_version())),
+                    ("kind", Cell::str(module.header.module_kind().name())),
+                    ("class", Cell::name(module.class())),
+                    ("description", Cell::name(module.description())),
+                    ("kernel_abi", Cell::str(module.kernel_abi())),
+                    ("flags", Cell::Hex(module.header.flags)),
+                    ("init_offset", Cell::Hex(module.header.init_offset)),
+                    ("exit_offset", module.exit_offset().map_or(Cell::None, Cell::Hex)),
+                    ("image_offset", Cell::Hex(module.header.image_offset)),
+                    ("image_size", Cell::Int(module.header.image_size)),
+                ],
+            );
+            let dependencies = module
+                .dependencies()
+                .map(|dependency| vec![Cell::str(dependency.name), Cell::str(dependency.min_version)])
+                .collect();
+            dump.table(What::Dynamic, "dependencies", &["name", "min_version"], dependencies);
+            add_plm(&mut dump, &module.image, What::Sections);
+            match module.signature() {
+                Err(PlkmodError::NotSigned) => {}
+                block => dump.signature(block, |keys| module.verify_signature(keys), trusted_keys),
+            }
+            Ok(dump)
+        }
+        PLRES_MAGIC => {
+            let file = PlresFile::parse(data).map_err(|e| malformed(&e))?;
+            let mut dump = Dump::new("plres", "PlumOS resource bundle");
+            dump.fields(
+                What::Header,
+                "header",
+                vec![
+                    ("version", Cell::str(format_version(file.header.version))),
+                    ("resources", Cell::Int(file.header.entry_count as u64)),
+                    ("file_size", Cell::Int(file.header.file_size)),
+                ],
+            );
+            let resources = file
+                .resources()
+                .map(|resource| {
+                    let size = |value: u32| if value == 0 { Cell::None } else { Cell::Int(value as u64) };
+                    vec![
+                        Cell::str(resource.id),
+                        Cell::name(resource.locale),
+                        Cell::str(resource.kind.name()),
+                        format_name(resource.format).map_or(Cell::Int(resource.format as u64), Cell::str),
+                        size(resource.width),
+                        size(resource.height),
+                        Cell::Int(resource.size),
+                        Cell::Int(resource.stored.len() as u64),
+                        Cell::Bool(resource.is_compressed()),
+                        Cell::str(format!("{:08x}", resource.crc32)),
+                    ]
+                })
+                .collect();
+            dump.table(
+                What::Sections,
+                "resources",
+                &["id", "locale", "kind", "format", "width", "height", "size", "stored", "lz4", "crc32"],
+                resources,
+            );
+            Ok(dump)
+        }
+        POM_MAGIC => {
+            let file = PomFile::parse(data).map_err(|e| malformed(&e))?;
+            let mut dump = Dump::new("pom", "PlumOS relocatable object");
+            add_pom(&mut dump, &file);
+            Ok(dump)
+        }
+        [0x7f, b'E', b'L', b'F'] => Err((EXIT_UNKNOWN, "an ELF file, convert it with elf2pom or use readelf".to_string())),
+        _ => Err((EXIT_UNKNOWN, format!("unknown magic {}", hex::encode(magic)))),
+    }
+}
+
+fn dump_plam(data: &[u8], trusted_keys: &[[u8; 32]]) -> Result<Dump, (i32, String)> {
+    let malformed = |e: plam::PlamError| (EXIT_MALFORMED, e.to_string());
+    let header = PlamHeader::parse(data).map_err(malformed)?;
+    let mut dump = Dump::new("plam", "PlumOS kernel image");
+    let mut fields = vec![
+        ("version", Cell::str(format_version(header.version))),
+        ("flags", Cell::Hex(header.flags)),
+        (
+            "subsystem",
+            Subsystem::from_u16(header.subsystem).map_or(Cell::Int(header.subsystem as u64), |s| Cell::str(s.name())),
+        ),
+    ];
+    fields.extend(arch_fields(header.cpu_id));
+    fields.extend([
+        ("image_base", Cell::Hex(header.image_base)),
+        ("entry_offset", Cell::Hex(header.entry_offset)),
+        ("entry", Cell::Hex(header.image_base.wrapping_add(header.entry_offset))),
+        ("file_size", Cell::Int(header.file_size)),
+        ("payload_size", Cell::Int(header.payload_size)),
+        ("compressed_size", if header.is_compressed() { Cell::Int(header.compressed_size) } else { Cell::None }),
+        ("payload_crc32", Cell::str(format!("{:08x}", header.payload_crc32))),
+        ("payload_sha256", Cell::str(hex::encode(header.payload_sha256))),
+        ("header_crc32", Cell::str(format!("{:08x}", header.header_crc32))),
+        ("relocatable", Cell::Bool(header.is_relocatable())),
+        ("aslr", Cell::Bool(header.flags & FLAG_ASLR != 0)),
+    ]);
+    dump.fields(What::Header, "header", fields);
+
+    let sections = header
+        .sections(data)
+        .map_err(malformed)?
+        .enumerate()
+        .map(|(i, section)| {
+            vec![
+                Cell::Int(i as u64),
+                Cell::str(section.name()),
+                section.section_kind().map_or(Cell::Int(section.kind as u64), |kind| Cell::str(kind.name())),
+                Cell::str(perms_str(section.perms)),
+                Cell::Hex(section.vaddr),
+                Cell::Hex(section.file_offset),
+                Cell::Int(section.file_size),
+                Cell::Int(section.mem_size),
+            ]
+        })
+        .collect();
+    dump.table(
+        What::Sections,
+        "sections",
+        &["index", "name", "kind", "perms", "vaddr", "offset", "file_size", "mem_size"],
+        sections,
+    );
+
+    if header.is_relocatable() {
+        // Check the sizes against the file before allocating for them.
+        let stored_end = (HEADER_SIZE as u64).checked_add(header.stored_payload_size());
+        let fits = stored_end.is_some_and(|end| end <= data.len() as u64)
+            && (!header.is_compressed() || header.payload_size <= lz4::max_decompressed_size(header.compressed_size));
+        if !fits || header.payload_size > MAX_PAYLOAD_SIZE {
+            return Err(malformed(plam::PlamError::BadPayload));
+        }
+        let mut inflated = data[..HEADER_SIZE].to_vec();
+        inflated.resize(header.image_size() as usize, 0);
+        header.load_payload(data, &mut inflated[HEADER_SIZE..]).map_err(malformed)?;
+        let relocations = header
+            .relocations(&inflated)
+            .map_err(malformed)?
+            .map(|offset| vec![Cell::Hex(offset), Cell::Hex(header.image_base.wrapping_add(offset))])
+            .collect();
+        dump.table(What::Relocs, "relocations", &["offset", "address"], relocations);
+    }
+
+    if header.is_signed() {
+        dump.signature(header.signature(data), |keys| plam::verify_signature(data, keys), trusted_keys);
+    }
+    Ok(dump)
+}
+
+fn dump_plstat(data: &[u8]) -> Result<Dump, (i32, String)> {
+    let file = PlstatFile::parse(data).map_err(|e| (EXIT_MALFORMED, e.to_string()))?;
+    let mut dump = Dump::new("plstat", "PlumOS static library");
+    dump.fields(
+        What::Header,
+        "header",
+        vec![
+            ("version", Cell::str(format_version(file.header.version))),
+            ("members", Cell::Int(file.header.member_count as u64)),
+            ("symbols", Cell::Int(file.header.symbol_count as u64)),
+            ("file_size", Cell::Int(file.header.file_size)),
+        ],
+    );
+    let members: Vec<_> = file.members().collect();
+    let rows = members
+        .iter()
+        .enumerate()
+        .map(|(i, member)| {
+            let format = match member.data.get(..4) {
+                Some(magic) if magic == POM_MAGIC => "pom",
+                Some(b"\x7fELF") => "elf",
+                _ => "unknown",
+            };
+            vec![
+                Cell::Int(i as u64),
+                Cell::str(member.name),
+                Cell::str(format),
+                Cell::Int(member.data.len() as u64),
+                Cell::Int(member.mtime),
+            ]
+        })
+        .collect();
+    dump.table(What::Sections, "members", &["index", "name", "format", "size", "mtime"], rows);
+    let symbols = file
+        .symbols()
+        .map(|(name, member)| {
+            let member_name = members.get(member as usize).map_or(Cell::None, |m| Cell::str(m.name));
+            vec![Cell::str(name), member_name]
+        })
+        .collect();
+    dump.table(What::Symbols, "symbols", &["name", "member"], symbols);
+    Ok(dump)
+}
+
+/// Adds the parts of a PLM image, on its own or embedded in a library or
+/// module, whose header then takes the place of the image header.
+fn add_plm(dump: &mut Dump, file: &PlmFile, header_what: What) {
+    let header = &file.header;
+    let mut fields = vec![("version", Cell::str(format_version(header.version)))];
+    fields.extend(arch_fields(header.cpu_id));
+    fields.extend([
+        ("abi", Abi::from_u16(header.abi).map_or(Cell::Int(header.abi as u64), |abi| Cell::str(abi.name()))),
+        ("flags", Cell::Hex(header.flags)),
+        ("pie", Cell::Bool(header.is_pie())),
+        ("image_base", Cell::Hex(header.image_base)),
+        ("entry_offset", Cell::Hex(header.entry_offset)),
+        ("stack_size", Cell::Int(header.stack_size)),
+        ("image_size", Cell::Int(file.image_size())),
+    ]);
+    let key = if header_what == What::Header { "header" } else { "image" };
+    dump.fields(header_what, key, fields);
+
+    let segments = file
+        .segments()
+        .enumerate()
+        .map(|(i, segment)| {
+            vec![
+                Cell::Int(i as u64),
+                Cell::str(perms_str(segment.perms)),
+                Cell::Hex(segment.vaddr),
+                Cell::Hex(segment.file_offset),
+                Cell::Int(segment.file_size),
+                Cell::Int(segment.mem_size),
+            ]
+        })
+        .collect();
+    dump.table(
+        What::Sections,
+        "segments",
+        &["index", "perms", "vaddr", "offset", "file_size", "mem_size"],
+        segments,
+    );
+
+    let imports = file
+        .imports()
+        .map(|import| {
+            vec![
+                Cell::name(import.library),
+                Cell::str(import.symbol),
+                Cell::str(import.min_version),
+                Cell::Hex(import.slot),
+                Cell::Signed(import.addend),
+            ]
+        })
+        .collect();
+    dump.table(What::Dynamic, "imports", &["library", "symbol", "min_version", "slot", "addend"], imports);
+
+    let relocations = file
+        .relocations()
+        .map(|offset| vec![Cell::Hex(offset), Cell::Hex(header.image_base.wrapping_add(offset))])
+        .collect();
+    dump.table(What::Relocs, "relocations", &["offset", "address"], relocations);
+}
+
+fn add_pom(dump: &mut Dump, file: &PomFile) {
+    let header = &file.header;
+    let mut fields = vec![("version", Cell::str(format_version(header.version)))];
+    fields.extend(arch_fields(file.cpu_id()));
+    fields.extend([
+        ("sections", Cell::Int(header.section_count as u64)),
+        ("symbols", Cell::Int(header.symbol_count as u64)),
+        ("relocations", Cell::Int(header.reloc_count as u64)),
+        ("file_size", Cell::Int(header.file_size)),
+    ]);
+    dump.fields(What::Header, "header", fields);
+
+    let sections: Vec<_> = file.sections().collect();
+    let rows = sections
+        .iter()
+        .enumerate()
+        .map(|(i, section)| {
+            vec![
+                Cell::Int(i as u64),
+                Cell::str(section.name),
+                Cell::str(section.kind.name()),
+                Cell::Int(section.align),
+                Cell::Int(section.size),
+            ]
+        })
+        .collect();
+    dump.table(What::Sections, "sections", &["index", "name", "kind", "align", "size"], rows);
+
+    let symbols: Vec<_> = file.symbols().collect();
+    let rows = symbols
+        .iter()
+        .enumerate()
+        .map(|(i, symbol)| {
+            let section = match symbol.section {
+                SECTION_UNDEF => "UNDEF",
+                SECTION_ABS => "ABS",
+                SECTION_COMMON => "COMMON",
+                index => sections[index as usize].name,
+            };
+            vec![
+                Cell::Int(i as u64),
+                Cell::str(symbol.name),
+                Cell::str(section),
+                Cell::str(symbol.binding.name()),
+                Cell::str(symbol.kind.name()),
+                Cell::Hex(symbol.value),
+                Cell::Int(symbol.size),
+            ]
+        })
+        .collect();
+    dump.table(
+        What::Symbols,
+        "symbols",
+        &["index", "name", "section", "binding", "kind", "value", "size"],
+        rows,
+    );
+
+    let rows = file
+        .relocs()
+        .map(|reloc| {
+            let symbol = symbols.get(reloc.symbol as usize).map_or(Cell::None, |s| Cell::str(s.name));
+            vec![
+                Cell::str(sections[reloc.section as usize].name),
+                Cell::Hex(reloc.offset),
+                Cell::str(reloc.kind.name()),
+                symbol,
+                Cell::Signed(reloc.addend),
+            ]
+        })
+        .collect();
+    dump.table(What::Relocs, "relocations", &["section", "offset", "kind", "symbol", "addend"], rows);
+}
+
+fn arch_fields(cpu_id: u16) -> [(&'static str, Cell); 2] {
+    [
+        ("arch", arch_name(cpu_id).map_or(Cell::None, Cell::str)),
+        ("cpu_id", Cell::Hex(cpu_id as u64)),
+    ]
+}
+
+fn format_version(version: u16) -> String {
+    format!("{}.{}", version >> 8, version & 0xFF)
+}
+
+/// `signature` becomes "Signature", `segments` "Segments".
+fn title(key: &str) -> String {
+    let mut chars = key.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn perms_str(perms: u32) -> String {
+    format!(
+        "{}{}{}",
+        if perms & PERM_R != 0 { 'R' } else { '-' },
+        if perms & PERM_W != 0 { 'W' } else { '-' },
+        if perms & PERM_X != 0 { 'X' } else { '-' }
+    )
+}
+
+fn load_public_key(path: &str) -> [u8; 32] {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|key_hex| hex::decode(key_hex.trim()).ok())
+        .and_then(|bytes| bytes.try_into().ok())
+        .unwrap_or_else(|| {
+            eprintln!("❌ {} is not a hex-encoded Ed25519 public key", path);
+            exit(EXIT_USAGE);
+        })
+}
+
+fn print_usage() {
+    eprintln!("Usage: plumobj [options] <file>...");
+    eprintln!();
+    eprintln!("Dumps .plam, .plm, .plib, .plstat, .plkmod, .plres and .pom files and universal");
+    eprintln!("images, telling them apart by their magic.");
+    eprintln!();
+    eprintln!("Options (everything when none is given):");
+    eprintln!("  -h, --header       file header");
+    eprintln!("  -S, --sections     sections, segments, archive members or resources");
+    eprintln!("  -s, --symbols      symbol table or archive index");
+    eprintln!("  -r, --relocs       relocations");
+    eprintln!("  -d, --dynamic      imports, exports and module dependencies");
+    eprintln!("  -g, --signature    signer and whether the signature matches");
+    eprintln!("  -a, --all          all of the above");
+    eprintln!("  --trusted <key.pubhex>   also require a signature by one of these keys");
+    eprintln!("  --arch=<arch>      dump the slice of a universal image built for this architecture");
+    eprintln!("  --json             print a JSON object per file, an array of them for several files");
+    eprintln!();
+    eprintln!("Exit codes:");
+    eprintln!("  0  every file dumped");
+    eprintln!("  1  usage or I/O error");
+    eprintln!("  2  unknown format");
+    eprintln!("  3  malformed file");
+    eprintln!("  4  bad or untrusted signature");
+    eprintln!("  5  universal image has no slice for --arch");
+}
@@ -1,26 +1,148 @@
+use ed25519_dalek::SigningKey;
+use plum_formats::detached::{key_id, DetachedError, DetachedSignature};
 use sha2::{Sha256, Digest};
 use std::fs;
-use std::io::Read;
+use std::process::exit;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+const EXIT_USAGE: i32 = 1;
+const EXIT_BAD_SIGNATURE: i32 = 2;
+const EXIT_UNKNOWN_KEY: i32 = 3;
+const EXIT_CORRUPTED: i32 = 4;
+
+fn main() {
     let args: Vec<String> = std::env::args().collect();
-    
-    if args.len() < 2 {
-        eprintln!("Usage: {} <file>", args[0]);
-        return Ok(());
+
+    if args.len() < 3 {
+        print_usage(&args[0]);
+        exit(EXIT_USAGE);
+    }
+
+    let file = &args[2];
+    let mut keys = Vec::new();
+    let mut sig_path = None;
+
+    let mut iter = args[3..].iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-k" | "--key" => keys.push(iter.next().cloned().unwrap_or_else(|| usage_error(&args[0]))),
+            "-s" | "--sig" | "-o" | "--output" => {
+                sig_path = Some(iter.next().cloned().unwrap_or_else(|| usage_error(&args[0])))
+            }
+            _ => usage_error(&args[0]),
+        }
     }
+    let sig_path = sig_path.unwrap_or_else(|| format!("{}.sig", file));
 
-    let mut file = fs::File::open(&args[1])?;
-    let mut data = Vec::new();
-    file.read_to_end(&mut data)?;
+    match args[1].as_str() {
+        "hash" => hash(file),
+        "sign" => {
+            if keys.len() != 1 {
+                usage_error(&args[0]);
+            }
+            sign(file, &keys[0], &sig_path);
+        }
+        "verify" => {
+            if keys.is_empty() {
+                usage_error(&args[0]);
+            }
+            verify(file, &keys, &sig_path);
+        }
+        _ => usage_error(&args[0]),
+    }
+}
+
+fn hash(file: &str) {
+    let data = read_file(file);
 
     let mut hasher = Sha256::new();
     hasher.update(&data);
     let hash = hasher.finalize();
-    
-    println!("File: {}", args[1]);
+
+    println!("File: {}", file);
     println!("SHA256: {:x}", hash);
     println!("Size: {} bytes", data.len());
-    
-    Ok(())
-}
\ No newline at end of file
+}
+
+fn sign(file: &str, key_path: &str, sig_path: &str) {
+    let data = read_file(file);
+    let key = SigningKey::from_bytes(&read_hex_key(key_path));
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let signature = DetachedSignature::sign(&data, &key, timestamp);
+
+    if let Err(e) = fs::write(sig_path, signature.to_text()) {
+        eprintln!("❌ Failed to write {}: {}", sig_path, e);
+        exit(EXIT_USAGE);
+    }
+
+    println!("✅ Signed {} → {}", file, sig_path);
+    println!("   - Key id: {}", hex::encode(signature.key_id));
+    println!("   - SHA256: {}", hex::encode(signature.file_hash));
+    println!("   - Timestamp: {}", signature.timestamp);
+}
+
+fn verify(file: &str, key_paths: &[String], sig_path: &str) {
+    let data = read_file(file);
+    let trusted_keys: Vec<[u8; 32]> = key_paths.iter().map(|p| read_hex_key(p)).collect();
+
+    let text = fs::read_to_string(sig_path).unwrap_or_else(|e| {
+        eprintln!("❌ Failed to read {}: {}", sig_path, e);
+        exit(EXIT_USAGE);
+    });
+
+    let result = DetachedSignature::parse(&text).and_then(|sig| sig.verify(&data, &trusted_keys));
+    match result {
+        Ok(public_key) => {
+            println!("✅ {}: good signature", file);
+            println!("   - Key id: {}", hex::encode(key_id(&public_key)));
+        }
+        Err(e) => {
+            eprintln!("❌ {}: {}", file, e);
+            exit(match e {
+                DetachedError::Malformed | DetachedError::BadSignature => EXIT_BAD_SIGNATURE,
+                DetachedError::UnknownKey => EXIT_UNKNOWN_KEY,
+                DetachedError::Corrupted => EXIT_CORRUPTED,
+            });
+        }
+    }
+}
+
+fn read_file(path: &str) -> Vec<u8> {
+    fs::read(path).unwrap_or_else(|e| {
+        eprintln!("❌ Failed to read {}: {}", path, e);
+        exit(EXIT_USAGE);
+    })
+}
+
+fn read_hex_key(path: &str) -> [u8; 32] {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|key_hex| hex::decode(key_hex.trim()).ok())
+        .and_then(|bytes| bytes.try_into().ok())
+        .unwrap_or_else(|| {
+            eprintln!("❌ {} is not a hex-encoded Ed25519 key", path);
+            exit(EXIT_USAGE);
+        })
+}
+
+fn usage_error(program: &str) -> ! {
+    print_usage(program);
+    exit(EXIT_USAGE);
+}
+
+fn print_usage(program: &str) {
+    eprintln!("Usage: {} hash <file>", program);
+    eprintln!("       {} sign <file> --key <signing-key.hex> [--output <file.sig>]", program);
+    eprintln!("       {} verify <file> --key <key.pubhex> [--key <key.pubhex>]... [--sig <file.sig>]", program);
+    eprintln!();
+    eprintln!("Exit codes:");
+    eprintln!("  0  good signature");
+    eprintln!("  1  usage or I/O error");
+    eprintln!("  2  bad or malformed signature");
+    eprintln!("  3  signed by an unknown key");
+    eprintln!("  4  file is corrupted");
+}
@@ -1,6 +1,7 @@
 use ppm_core::Package;
 
-pub async fn analyze_package(package: &Package) -> Result<bool, Box<dyn std::error::Error>> {
+/// Returns why the package looks suspicious, if it does.
+pub async fn analyze_package(package: &Package) -> Result<Option<String>, Box<dyn std::error::Error>> {
     
     println!("🤖 AI analysis of package {}...", package.name);
     
@@ -11,9 +12,9 @@ pub async fn analyze_package(package: &Package) -> Result<bool, Box<dyn std::err
     
     for keyword in suspicious_keywords {
         if name_lower.contains(keyword) {
-            return Ok(true);
+            return Ok(Some(format!("name contains \"{}\"", keyword)));
         }
     }
     
-    Ok(false)
+    Ok(None)
 }
\ No newline at end of file
@@ -1,10 +1,11 @@
 use ppm_core::Package;
 
-pub async fn scan_package(package: &Package) -> Result<bool, Box<dyn std::error::Error>> {
+/// Returns what was found, if anything.
+pub async fn scan_package(package: &Package) -> Result<Option<String>, Box<dyn std::error::Error>> {
     
     println!("🔍 Scanning package {} for viruses...", package.name);
     
     tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
     
-    Ok(false)
+    Ok(None)
 }
\ No newline at end of file
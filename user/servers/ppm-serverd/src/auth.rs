@@ -0,0 +1,140 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::Arc;
+use warp::http::StatusCode;
+use warp::{Filter, Rejection, Reply};
+
+/// Roles are ordered: each one may do everything the roles before it can.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Uploader,
+    Reviewer,
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Uploader => "uploader",
+            Role::Reviewer => "reviewer",
+            Role::Admin => "admin",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct UserEntry {
+    pub name: String,
+    pub role: Role,
+    /// Hex SHA-256 of the user's API token, as printed by `ppm-server new-token`.
+    pub token_sha256: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct User {
+    pub name: String,
+    pub role: Role,
+}
+
+/// Users by the SHA-256 of their token, so the config never holds a token
+/// and lookups do not compare secrets byte by byte.
+pub struct Auth {
+    users: HashMap<[u8; 32], User>,
+}
+
+impl Auth {
+    pub fn new(entries: &[UserEntry]) -> Result<Self, String> {
+        let mut users = HashMap::new();
+        for entry in entries {
+            let hash: [u8; 32] = hex::decode(entry.token_sha256.trim())
+                .ok()
+                .and_then(|bytes| bytes.try_into().ok())
+                .ok_or_else(|| format!("user {}: token_sha256 is not a hex SHA-256 digest", entry.name))?;
+            let user = User {
+                name: entry.name.clone(),
+                role: entry.role,
+            };
+            if users.insert(hash, user).is_some() {
+                return Err(format!("user {}: token is shared with another user", entry.name));
+            }
+        }
+        Ok(Auth { users })
+    }
+
+    pub fn authenticate(&self, token: &str) -> Option<User> {
+        self.users.get(&token_hash(token)).cloned()
+    }
+}
+
+pub fn token_hash(token: &str) -> [u8; 32] {
+    Sha256::digest(token.as_bytes()).into()
+}
+
+/// A fresh random token and the `token_sha256` line for `server.toml`.
+pub fn new_token() -> Result<(String, String), String> {
+    let mut bytes = [0u8; 32];
+    getrandom::fill(&mut bytes).map_err(|e| format!("failed to generate a token: {}", e))?;
+    let token = hex::encode(bytes);
+    let hash = hex::encode(token_hash(&token));
+    Ok((token, hash))
+}
+
+#[derive(Debug)]
+pub enum AuthRejection {
+    Unauthorized,
+    Forbidden(Role),
+    /// The package key is pending from another uploader.
+    NotOwner(String),
+}
+
+impl warp::reject::Reject for AuthRejection {}
+
+/// Passes on the user behind the `Authorization: Bearer <token>` header if
+/// their role is at least `role`.
+pub fn require(auth: Arc<Auth>, role: Role) -> impl Filter<Extract = (User,), Error = Rejection> + Clone {
+    warp::header::optional::<String>("authorization").and_then(move |header: Option<String>| {
+        let auth = auth.clone();
+        async move {
+            let user = header
+                .as_deref()
+                .and_then(|value| value.strip_prefix("Bearer "))
+                .and_then(|token| auth.authenticate(token.trim()))
+                .ok_or_else(|| warp::reject::custom(AuthRejection::Unauthorized))?;
+            if user.role < role {
+                return Err(warp::reject::custom(AuthRejection::Forbidden(role)));
+            }
+            Ok(user)
+        }
+    })
+}
+
+pub async fn handle_rejection(err: Rejection) -> Result<impl Reply, Infallible> {
+    // Routes with another method add MethodNotAllowed to every rejection,
+    // so it is only reported when nothing more specific went wrong.
+    let (status, error) = if let Some(rejection) = err.find::<AuthRejection>() {
+        match rejection {
+            AuthRejection::Unauthorized => (StatusCode::UNAUTHORIZED, "unauthorized".to_string()),
+            AuthRejection::Forbidden(role) => (StatusCode::FORBIDDEN, format!("requires the {} role", role.as_str())),
+            AuthRejection::NotOwner(key) => (StatusCode::CONFLICT, format!("{} is pending from another uploader", key)),
+        }
+    } else if err.is_not_found() {
+        (StatusCode::NOT_FOUND, "not found".to_string())
+    } else if let Some(e) = err.find::<warp::filters::body::BodyDeserializeError>() {
+        (StatusCode::BAD_REQUEST, e.to_string())
+    } else if let Some(e) = err.find::<warp::reject::PayloadTooLarge>() {
+        (StatusCode::PAYLOAD_TOO_LARGE, e.to_string())
+    } else if let Some(e) = err.find::<warp::reject::UnsupportedMediaType>() {
+        (StatusCode::UNSUPPORTED_MEDIA_TYPE, e.to_string())
+    } else if let Some(e) = err.find::<warp::reject::InvalidHeader>() {
+        (StatusCode::BAD_REQUEST, e.to_string())
+    } else if err.find::<warp::reject::MethodNotAllowed>().is_some() {
+        (StatusCode::METHOD_NOT_ALLOWED, "method not allowed".to_string())
+    } else {
+        (StatusCode::INTERNAL_SERVER_ERROR, "internal error".to_string())
+    };
+    let body = warp::reply::json(&serde_json::json!({ "error": error }));
+    Ok(warp::reply::with_status(body, status))
+}
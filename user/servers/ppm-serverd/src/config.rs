@@ -0,0 +1,83 @@
+use serde::Deserialize;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use crate::auth::UserEntry;
+
+pub const DEFAULT_CONFIG_PATH: &str = "/srv/ppm/server.toml";
+
+/// `server.toml`:
+///
+/// ```toml
+/// bind = "127.0.0.1:8080"
+/// allowed_origins = ["https://ppm.example.org"]
+/// signing_key = "/srv/ppm/keys/signing-key.hex"
+///
+/// [[users]]
+/// name = "alice"
+/// role = "reviewer"
+/// token_sha256 = "…"
+/// ```
+///
+/// A user's `role` is `uploader`, `reviewer` or `admin`. Nothing is gated on
+/// `admin` yet, so for now it grants exactly what `reviewer` does.
+#[derive(Deserialize)]
+pub struct ServerConfig {
+    #[serde(default = "default_bind")]
+    pub bind: SocketAddr,
+    /// Origins the admin web UI may call the API from, including the one
+    /// this server serves `/admin` on. Browsers send `Origin` with every
+    /// POST, even a same-origin one, so this must not be empty. Each entry
+    /// is `scheme://host[:port]`, with no path.
+    pub allowed_origins: Vec<String>,
+    /// Repository key that signs every index, as written by `keygen`.
+    #[serde(default = "default_signing_key")]
+    pub signing_key: PathBuf,
+    #[serde(default)]
+    pub users: Vec<UserEntry>,
+}
+
+fn default_bind() -> SocketAddr {
+    SocketAddr::from(([127, 0, 0, 1], 8080))
+}
+
+fn default_signing_key() -> PathBuf {
+    PathBuf::from("/srv/ppm/keys/signing-key.hex")
+}
+
+pub fn load_config(path: &str) -> Result<ServerConfig, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("failed to read {}: {}", path, e))?;
+    let config: ServerConfig = toml::from_str(&text).map_err(|e| format!("{}: {}", path, e))?;
+    if config.allowed_origins.is_empty() {
+        return Err(format!("{}: allowed_origins must list the origin the admin UI is served from", path));
+    }
+    if let Some(origin) = config.allowed_origins.iter().find(|origin| !is_origin(origin)) {
+        return Err(format!("{}: allowed_origins entry {:?} must look like https://host[:port]", path, origin));
+    }
+    Ok(config)
+}
+
+/// Whether `origin` is `http(s)://host[:port]`, the form `warp::cors` accepts.
+fn is_origin(origin: &str) -> bool {
+    let Some(authority) = origin.strip_prefix("https://").or_else(|| origin.strip_prefix("http://")) else {
+        return false;
+    };
+    // A bracketed IPv6 host holds colons of its own.
+    let (host, port) = match authority.strip_prefix('[') {
+        Some(rest) => match rest.split_once(']') {
+            Some((host, "")) => (host, None),
+            Some((host, port)) => match port.strip_prefix(':') {
+                Some(port) => (host, Some(port)),
+                None => return false,
+            },
+            None => return false,
+        },
+        None => match authority.split_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (authority, None),
+        },
+    };
+    let host_ok = !host.is_empty()
+        && !host.contains(|c: char| matches!(c, '/' | '?' | '#' | '@' | '[' | ']') || c.is_whitespace());
+    host_ok && port.is_none_or(|port| port.parse::<u16>().is_ok())
+}
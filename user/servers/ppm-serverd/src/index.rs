@@ -0,0 +1,166 @@
+use ed25519_dalek::SigningKey;
+use plum_formats::detached::{self, DetachedSignature};
+use ppm_core::{Channel, Package, PackageIndex};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::staging;
+
+pub const INDEX_FILE: &str = "index.json";
+pub const SIGNATURE_FILE: &str = "index.json.sig";
+
+/// The repository key every index is signed with.
+pub struct RepoKey {
+    signing_key: SigningKey,
+    public_key: [u8; 32],
+}
+
+impl RepoKey {
+    /// Loads a hex-encoded key as written to `signing-key.hex` by `keygen`.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let secret: [u8; 32] = fs::read_to_string(path)
+            .ok()
+            .and_then(|key_hex| hex::decode(key_hex.trim()).ok())
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| format!("{} is not a hex-encoded Ed25519 signing key", path.display()))?;
+        let signing_key = SigningKey::from_bytes(&secret);
+        let public_key = signing_key.verifying_key().to_bytes();
+        Ok(RepoKey {
+            signing_key,
+            public_key,
+        })
+    }
+
+    pub fn public_key(&self) -> [u8; 32] {
+        self.public_key
+    }
+
+    /// The `key_id` that `.sig` files name this key by.
+    pub fn key_id(&self) -> [u8; 8] {
+        detached::key_id(&self.public_key)
+    }
+
+    /// Full SHA-256 of the public key, for checking a client's trusted key
+    /// out of band.
+    pub fn fingerprint(&self) -> String {
+        hex::encode(Sha256::digest(self.public_key))
+    }
+}
+
+/// Where a package's archive lives next to the index, and what it hashes to.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ArchiveEntry {
+    pub filename: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+/// `index.json` as the server writes it: the `PackageIndex` clients parse,
+/// plus the archive of each package by name.
+#[derive(Serialize, Deserialize)]
+pub struct RepoIndex {
+    #[serde(flatten)]
+    pub index: PackageIndex,
+    #[serde(default)]
+    pub archives: BTreeMap<String, ArchiveEntry>,
+}
+
+#[derive(Debug)]
+pub enum IndexError {
+    Io(io::Error),
+    BadSignature(detached::DetachedError),
+    /// The index predates signing and has not been signed with `sign-index`.
+    Unsigned,
+    Malformed(serde_json::Error),
+}
+
+impl fmt::Display for IndexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IndexError::Io(e) => write!(f, "{}", e),
+            IndexError::BadSignature(e) => write!(f, "{}: {}", SIGNATURE_FILE, e),
+            IndexError::Unsigned => {
+                write!(f, "{} is missing; check the index and run `ppm-server sign-index`", SIGNATURE_FILE)
+            }
+            IndexError::Malformed(e) => write!(f, "{}: {}", INDEX_FILE, e),
+        }
+    }
+}
+
+impl RepoIndex {
+    fn empty(channel: Channel) -> Self {
+        RepoIndex {
+            index: PackageIndex {
+                packages: vec![],
+                generated: "".to_string(),
+                channel,
+            },
+            archives: BTreeMap::new(),
+        }
+    }
+
+    /// Loads the index in `dir`, checking its signature first so that an
+    /// index edited on disk is never re-signed.
+    pub fn load(dir: &Path, channel: Channel, key: &RepoKey) -> Result<Self, IndexError> {
+        let data = match fs::read(dir.join(INDEX_FILE)) {
+            Ok(data) => data,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(RepoIndex::empty(channel)),
+            Err(e) => return Err(IndexError::Io(e)),
+        };
+
+        match fs::read_to_string(dir.join(SIGNATURE_FILE)) {
+            Ok(text) => {
+                DetachedSignature::parse(&text)
+                    .and_then(|sig| sig.verify(&data, &[key.public_key]))
+                    .map_err(IndexError::BadSignature)?;
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Err(IndexError::Unsigned),
+            Err(e) => return Err(IndexError::Io(e)),
+        }
+
+        serde_json::from_slice(&data).map_err(IndexError::Malformed)
+    }
+
+    /// Signs the `index.json` in `dir` as it is, for an index written before
+    /// signing. `ppm-server sign-index` runs this once per directory, after
+    /// an admin has checked the index; until then `load` refuses it.
+    pub fn sign_existing(dir: &Path, key: &RepoKey) -> Result<(), IndexError> {
+        let signature_path = dir.join(SIGNATURE_FILE);
+        if signature_path.exists() {
+            let e = io::Error::new(io::ErrorKind::AlreadyExists, format!("{} already exists", SIGNATURE_FILE));
+            return Err(IndexError::Io(e));
+        }
+        let data = fs::read(dir.join(INDEX_FILE)).map_err(IndexError::Io)?;
+        serde_json::from_slice::<RepoIndex>(&data).map_err(IndexError::Malformed)?;
+        let signature = DetachedSignature::sign(&data, &key.signing_key, chrono::Utc::now().timestamp() as u64);
+        staging::write_atomic(&signature_path, signature.to_text().as_bytes()).map_err(IndexError::Io)
+    }
+
+    pub fn insert(&mut self, package: Package, archive: ArchiveEntry) {
+        let packages = &mut self.index.packages;
+        self.archives.insert(package.name.clone(), archive);
+        if let Some(pos) = packages.iter().position(|p| p.name == package.name) {
+            packages[pos] = package;
+        } else {
+            packages.push(package);
+        }
+    }
+
+    /// Writes `index.json`, then its detached signature `index.json.sig`.
+    /// Each file is replaced atomically; a crash between the two leaves a
+    /// pair that `load` refuses rather than re-signs.
+    pub fn write(&mut self, dir: &Path, key: &RepoKey) -> io::Result<()> {
+        let now = chrono::Utc::now();
+        self.index.generated = now.to_rfc3339();
+        let data = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        let signature = DetachedSignature::sign(data.as_bytes(), &key.signing_key, now.timestamp() as u64);
+
+        staging::write_atomic(&dir.join(INDEX_FILE), data.as_bytes())?;
+        staging::write_atomic(&dir.join(SIGNATURE_FILE), signature.to_text().as_bytes())
+    }
+}
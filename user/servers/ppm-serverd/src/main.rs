@@ -2,14 +2,27 @@ use warp::Filter;
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::Mutex;
-use ppm_core::{Package, Channel, Architecture, PackageIndex};
+use ppm_core::{Channel, Architecture};
 use serde::{Deserialize, Serialize};
 use std::fs;
+use std::path::Path;
 
 mod antivirus;
 mod ai_scanner;
+mod auth;
+mod config;
+mod index;
+mod review;
+mod staging;
+mod upload;
 
-type StagingMap = Arc<Mutex<HashMap<String, Package>>>;
+use auth::{Auth, AuthRejection, Role, User};
+use index::{ArchiveEntry, RepoIndex, RepoKey};
+use review::{Action, Decision};
+use staging::{PendingMeta, ReviewStatus};
+use upload::Upload;
+
+type StagingMap = Arc<Mutex<HashMap<String, PendingMeta>>>;
 
 #[derive(Serialize, Deserialize)]
 struct ApproveRequest {
@@ -17,45 +30,128 @@ struct ApproveRequest {
     target_channel: String,
 }
 
+#[derive(Serialize, Deserialize)]
+struct DecisionRequest {
+    package_key: String,
+    reason: String,
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let staging: StagingMap = Arc::new(Mutex::new(HashMap::new()));
+    if std::env::args().nth(1).as_deref() == Some("new-token") {
+        let (token, hash) = auth::new_token()?;
+        println!("token = {}", token);
+        println!("token_sha256 = \"{}\"", hash);
+        return Ok(());
+    }
+
+    let config_path = std::env::var("PPM_SERVER_CONFIG").unwrap_or_else(|_| config::DEFAULT_CONFIG_PATH.to_string());
+    let config = config::load_config(&config_path)?;
+    let repo_key = Arc::new(RepoKey::load(&config.signing_key)?);
+
+    // One-time migration of indexes written before signing: each directory
+    // named is signed as it stands, so check the indexes first.
+    if std::env::args().nth(1).as_deref() == Some("sign-index") {
+        for dir in std::env::args().skip(2) {
+            RepoIndex::sign_existing(Path::new(&dir), &repo_key).map_err(|e| format!("{}: {}", dir, e))?;
+            println!("🔏 Signed {}", Path::new(&dir).join(index::INDEX_FILE).display());
+        }
+        return Ok(());
+    }
+
+    let auth = Arc::new(Auth::new(&config.users)?);
+    if config.users.is_empty() {
+        eprintln!("⚠️ No users in {}; every API request will be refused", config_path);
+    }
+    println!("🔑 Signing indexes with key {}", hex::encode(repo_key.key_id()));
+
+    let pending = staging::load_pending()?;
+    println!("📦 Restored {} pending package(s) from {}", pending.len(), staging::PENDING_DIR);
+    let staging: StagingMap = Arc::new(Mutex::new(pending));
 
     let staging_clone = staging.clone();
     let upload = warp::post()
         .and(warp::path("upload"))
-        .and(warp::body::json())
+        .and(auth::require(auth.clone(), Role::Uploader))
+        .and(warp::multipart::form().max_length(upload::MAX_UPLOAD_SIZE))
         .and(with_staging(staging_clone))
         .and_then(upload_handler);
 
     let staging_clone = staging.clone();
     let list_staging = warp::get()
         .and(warp::path("staging"))
+        .and(auth::require(auth.clone(), Role::Uploader))
         .and(with_staging(staging_clone))
         .and_then(list_staging_handler);
 
     let staging_clone = staging.clone();
     let approve = warp::post()
         .and(warp::path("approve"))
+        .and(auth::require(auth.clone(), Role::Reviewer))
         .and(warp::body::json::<ApproveRequest>())
         .and(with_staging(staging_clone))
+        .and(with_repo_key(repo_key.clone()))
         .and_then(approve_handler);
 
+    let staging_clone = staging.clone();
+    let reject = warp::post()
+        .and(warp::path("reject"))
+        .and(auth::require(auth.clone(), Role::Reviewer))
+        .and(warp::body::json::<DecisionRequest>())
+        .and(with_staging(staging_clone))
+        .and_then(reject_handler);
+
+    let staging_clone = staging.clone();
+    let request_changes = warp::post()
+        .and(warp::path("request-changes"))
+        .and(auth::require(auth.clone(), Role::Reviewer))
+        .and(warp::body::json::<DecisionRequest>())
+        .and(with_staging(staging_clone))
+        .and_then(request_changes_handler);
+
+    let history = warp::get()
+        .and(warp::path!("history" / String))
+        .and(auth::require(auth.clone(), Role::Uploader))
+        .and_then(history_handler);
+
+    let key = warp::get()
+        .and(warp::path("key"))
+        .and(with_repo_key(repo_key.clone()))
+        .map(|repo_key: Arc<RepoKey>| {
+            warp::reply::json(&serde_json::json!({
+                "algorithm": plum_formats::detached::ALG_ED25519,
+                "key_id": hex::encode(repo_key.key_id()),
+                "public_key": hex::encode(repo_key.public_key()),
+                "fingerprint": repo_key.fingerprint(),
+            }))
+        });
+
     let api = warp::path("api")
-        .and(upload.or(list_staging).or(approve));
+        .and(
+            upload
+                .or(list_staging)
+                .or(approve)
+                .or(reject)
+                .or(request_changes)
+                .or(history)
+                .or(key),
+        );
 
     let web = warp::path("admin")
         .and(warp::fs::dir("/srv/ppm/web/admin"));
 
-    let routes = api.or(web).with(
-        warp::cors()
-            .allow_any_origin()
-            .allow_methods(vec!["GET", "POST"])
-            .allow_headers(vec!["content-type"]),
-    );
+    let routes = api
+        .or(web)
+        .recover(auth::handle_rejection)
+        .with(
+            warp::cors()
+                .allow_origins(config.allowed_origins.iter().map(String::as_str))
+                .allow_methods(vec!["GET", "POST"])
+                .allow_headers(vec!["content-type", "authorization"]),
+        );
 
-    println!("🚀 PPM Admin running on http:
-    warp::serve(routes).run(([0, 0, 0, 0], 8080)).await?;
+    println!("🚀 PPM Admin running on http://{}", config.bind);
+    warp::serve(routes).run(config.bind).await?;
     Ok(())
 }
 
@@ -65,26 +161,37 @@ fn with_staging(
     warp::any().map(move || staging.clone())
 }
 
+fn with_repo_key(
+    repo_key: Arc<RepoKey>,
+) -> impl Filter<Extract = (Arc<RepoKey>,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || repo_key.clone())
+}
+
 async fn upload_handler(
-    mut package: Package,
+    user: User,
+    form: warp::multipart::FormData,
     staging: StagingMap,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    if package.architecture == Architecture::Prum64 {
-        package.architecture = Architecture::current();
-    }
+    let Upload {
+        mut package,
+        archive,
+        sha256,
+    } = match upload::receive(form).await {
+        Ok(upload) => upload,
+        Err(e) => {
+            eprintln!("❌ Upload from {} refused: {}", user.name, e);
+            return Ok(warp::reply::json(&serde_json::json!({
+                "error": e.code(),
+                "reason": e.to_string()
+            })));
+        }
+    };
 
-    if let Ok(true) = antivirus::scan_package(&package).await {
-        return Ok(warp::reply::json(&serde_json::json!({
-            "error": "rejected",
-            "reason": "virus_detected"
-        })));
-    }
+    // The uploader is whoever holds the token, not whatever the body claims.
+    package.author = user.name;
 
-    if let Ok(true) = ai_scanner::analyze_package(&package).await {
-        return Ok(warp::reply::json(&serde_json::json!({
-            "error": "flagged",
-            "reason": "suspicious_content"
-        })));
+    if package.architecture == Architecture::Prum64 {
+        package.architecture = Architecture::current();
     }
 
     let key = format!(
@@ -94,31 +201,93 @@ async fn upload_handler(
         package.architecture.as_str()
     );
 
-    let meta_path = format!("/srv/ppm/staging/pending/{}.meta.json", key);
-    let meta = serde_json::json!({
-        "filename": format!("{}.plpm", key),
-        "channel": "pending",
-        "verified": false,
-        "note": "awaiting human review"
-    });
-    fs::create_dir_all("/srv/ppm/staging/pending").ok();
-    fs::write(&meta_path, serde_json::to_string_pretty(&meta).unwrap()).ok();
+    // A detection refuses the upload outright; anything else the scanners
+    // report is kept with the upload for a reviewer to weigh.
+    let mut flags = Vec::new();
+    match antivirus::scan_package(&package).await.map_err(|e| e.to_string()) {
+        Ok(Some(finding)) => {
+            let _lock = staging.lock().await;
+            record(Decision::new(&package.author, Action::Refused, &key).reason(&finding));
+            eprintln!("🚫 Refused {} from {}: {}", key, package.author, finding);
+            return Ok(warp::reply::json(&serde_json::json!({
+                "error": "rejected",
+                "reason": "virus_detected",
+                "detail": finding
+            })));
+        }
+        Ok(None) => {}
+        Err(e) => flags.push(format!("antivirus scan failed: {}", e)),
+    }
+    match ai_scanner::analyze_package(&package).await.map_err(|e| e.to_string()) {
+        Ok(Some(reason)) => flags.push(format!("suspicious content: {}", reason)),
+        Ok(None) => {}
+        Err(e) => flags.push(format!("AI analysis failed: {}", e)),
+    }
 
-    staging.lock().await.insert(key.clone(), package);
+    let meta = PendingMeta {
+        filename: format!("{}.plpm", key),
+        sha256,
+        channel: "pending".to_string(),
+        verified: false,
+        note: "awaiting human review".to_string(),
+        status: ReviewStatus::PendingReview,
+        flags,
+        package,
+    };
+    // Only queue what is on disk, so a restart never loses an accepted upload.
+    // The archive goes first: metadata without its archive is never queued.
+    let mut lock = staging.lock().await;
+    // Only the original uploader may replace a pending upload.
+    if lock.get(&key).is_some_and(|pending| pending.package.author != meta.package.author) {
+        eprintln!("🚫 Refused {} from {}: pending from another uploader", key, meta.package.author);
+        return Err(warp::reject::custom(AuthRejection::NotOwner(key)));
+    }
+    let archive_path = staging::archive_path(&meta.filename);
+    if let Err(e) = archive.keep(&archive_path) {
+        eprintln!("❌ Failed to stage {}: {}", key, e);
+        return Ok(warp::reply::json(&serde_json::json!({
+            "error": "storage_failed"
+        })));
+    }
+    if let Err(e) = staging::save_pending(&key, &meta) {
+        eprintln!("❌ Failed to stage {}: {}", key, e);
+        // The new archive replaced any earlier upload under this key.
+        fs::remove_file(&archive_path).ok();
+        staging::remove_pending(&key).ok();
+        lock.remove(&key);
+        return Ok(warp::reply::json(&serde_json::json!({
+            "error": "storage_failed"
+        })));
+    }
+    let mut uploaded = Decision::new(&meta.package.author, Action::Uploaded, &key);
+    if !meta.flags.is_empty() {
+        uploaded = uploaded.reason(&meta.flags.join("; "));
+    }
+    record(uploaded);
 
-    Ok(warp::reply::json(&serde_json::json!({
-        "status": "pending_review",
-        "key": key
-    })))
+    let reply = serde_json::json!({
+        "status": ReviewStatus::PendingReview.as_str(),
+        "key": key,
+        "sha256": meta.sha256,
+        "flags": meta.flags
+    });
+    lock.insert(key, meta);
+    drop(lock);
+
+    Ok(warp::reply::json(&reply))
 }
 
 async fn list_staging_handler(
+    user: User,
     staging: StagingMap,
 ) -> Result<impl warp::Reply, warp::Rejection> {
     let packages: Vec<serde_json::Value> = {
         let lock = staging.lock().await;
+        // Uploaders only see their own submissions.
         lock.values()
-            .map(|pkg| {
+            .filter(|meta| user.role >= Role::Reviewer || meta.package.author == user.name)
+            .map(|meta| {
+                let pkg = &meta.package;
                 serde_json::json!({
                     "key": format!(
                         "{}-{}-{}",
@@ -133,6 +302,10 @@ async fn list_staging_handler(
                     "author": pkg.author,
                     "description": pkg.description,
                     "size": pkg.size,
+                    "sha256": meta.sha256,
+                    "status": meta.status.as_str(),
+                    "note": meta.note,
+                    "flags": meta.flags,
                 })
             })
             .collect()
@@ -141,8 +314,10 @@ async fn list_staging_handler(
 }
 
 async fn approve_handler(
+    user: User,
     req: ApproveRequest,
     staging: StagingMap,
+    repo_key: Arc<RepoKey>,
 ) -> Result<impl warp::Reply, warp::Rejection> {
     let target_channel = match req.target_channel.as_str() {
         "stable" => Channel::Stable,
@@ -156,46 +331,66 @@ async fn approve_handler(
     };
 
     let mut lock = staging.lock().await;
-    if let Some(pkg) = lock.remove(&req.package_key) {
+    if let Some(meta) = lock.remove(&req.package_key) {
+        let pkg = meta.package.clone();
         let arch_dir = format!(
             "/srv/ppm/{}/bin/{}",
             target_channel.name(),
             pkg.architecture.as_str()
         );
         fs::create_dir_all(&arch_dir).ok();
+        let dir = Path::new(&arch_dir);
 
-        let index_path = format!("{}/index.json", arch_dir);
-        let mut index: PackageIndex = if fs::metadata(&index_path).is_ok() {
-            let data = fs::read_to_string(&index_path).unwrap_or_default();
-            serde_json::from_str(&data)
-                .unwrap_or_else(|_| PackageIndex {
-                    packages: vec![],
-                    generated: "".to_string(),
-                    channel: Channel::Dev,
-                })
-        } else {
-            PackageIndex {
-                packages: vec![],
-                generated: "".to_string(),
-                channel: target_channel,
+        let mut index = match RepoIndex::load(dir, target_channel, &repo_key) {
+            Ok(index) => index,
+            Err(e) => {
+                eprintln!("❌ Refusing to update the index in {}: {}", arch_dir, e);
+                lock.insert(req.package_key, meta);
+                return Ok(warp::reply::json(&serde_json::json!({
+                    "error": "index_untrusted"
+                })));
             }
         };
 
-        if let Some(pos) = index.packages.iter().position(|p| p.name == pkg.name) {
-            index.packages[pos] = pkg.clone();
-        } else {
-            index.packages.push(pkg.clone());
+        // Publish the archive before the index that points clients at it.
+        let pending_path = staging::archive_path(&meta.filename);
+        let published_path = dir.join(&meta.filename);
+        if let Err(e) = staging::move_file(&pending_path, &published_path) {
+            eprintln!("❌ Failed to move {} to {}: {}", pending_path.display(), arch_dir, e);
+            lock.insert(req.package_key, meta);
+            return Ok(warp::reply::json(&serde_json::json!({
+                "error": "storage_failed"
+            })));
         }
 
-        index.generated = chrono::Utc::now().to_rfc3339();
-        index.channel = target_channel;
-        fs::write(&index_path, serde_json::to_string_pretty(&index).unwrap()).ok();
+        index.insert(
+            pkg.clone(),
+            ArchiveEntry {
+                filename: meta.filename.clone(),
+                size: pkg.size,
+                sha256: meta.sha256.clone(),
+            },
+        );
+        index.index.channel = target_channel;
+        if let Err(e) = index.write(dir, &repo_key) {
+            eprintln!("❌ Failed to write the index in {}: {}", arch_dir, e);
+            staging::move_file(&published_path, &pending_path).ok();
+            lock.insert(req.package_key, meta);
+            return Ok(warp::reply::json(&serde_json::json!({
+                "error": "storage_failed"
+            })));
+        }
+        if let Err(e) = staging::remove_pending(&req.package_key) {
+            eprintln!("⚠️ Approved {} but could not remove its pending metadata: {}", req.package_key, e);
+        }
+        record(Decision::new(&user.name, Action::Approved, &req.package_key).channel(target_channel.name()));
 
         println!(
-            "✅ Approved {} → {}/{}",
+            "✅ Approved {} → {}/{} by {}",
             req.package_key,
             target_channel.name(),
-            pkg.architecture.as_str()
+            pkg.architecture.as_str(),
+            user.name
         );
 
         Ok(warp::reply::json(&serde_json::json!({
@@ -207,4 +402,107 @@ async fn approve_handler(
             "error": "package not found"
         })))
     }
-}
\ No newline at end of file
+}
+
+async fn reject_handler(
+    user: User,
+    req: DecisionRequest,
+    staging: StagingMap,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let reason = req.reason.trim();
+    if reason.is_empty() {
+        return Ok(warp::reply::json(&serde_json::json!({
+            "error": "reason required"
+        })));
+    }
+
+    let mut lock = staging.lock().await;
+    let Some(meta) = lock.remove(&req.package_key) else {
+        return Ok(warp::reply::json(&serde_json::json!({
+            "error": "package not found"
+        })));
+    };
+    if let Err(e) = staging::remove_pending(&req.package_key) {
+        eprintln!("❌ Failed to unstage {}: {}", req.package_key, e);
+        lock.insert(req.package_key, meta);
+        return Ok(warp::reply::json(&serde_json::json!({
+            "error": "storage_failed"
+        })));
+    }
+    fs::remove_file(staging::archive_path(&meta.filename)).ok();
+    record(Decision::new(&user.name, Action::Rejected, &req.package_key).reason(reason));
+
+    println!("🚫 Rejected {} by {}: {}", req.package_key, user.name, reason);
+
+    Ok(warp::reply::json(&serde_json::json!({
+        "status": "rejected"
+    })))
+}
+
+async fn request_changes_handler(
+    user: User,
+    req: DecisionRequest,
+    staging: StagingMap,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let reason = req.reason.trim();
+    if reason.is_empty() {
+        return Ok(warp::reply::json(&serde_json::json!({
+            "error": "reason required"
+        })));
+    }
+
+    let mut lock = staging.lock().await;
+    let Some(meta) = lock.get(&req.package_key) else {
+        return Ok(warp::reply::json(&serde_json::json!({
+            "error": "package not found"
+        })));
+    };
+    // The package stays staged so its uploader sees the reason; uploading
+    // it again under the same key puts it back up for review.
+    let mut updated = meta.clone();
+    updated.status = ReviewStatus::ChangesRequested;
+    updated.note = reason.to_string();
+    if let Err(e) = staging::save_pending(&req.package_key, &updated) {
+        eprintln!("❌ Failed to update {}: {}", req.package_key, e);
+        return Ok(warp::reply::json(&serde_json::json!({
+            "error": "storage_failed"
+        })));
+    }
+    record(Decision::new(&user.name, Action::ChangesRequested, &req.package_key).reason(reason));
+    lock.insert(req.package_key.clone(), updated);
+
+    println!("✏️ Changes requested on {} by {}: {}", req.package_key, user.name, reason);
+
+    Ok(warp::reply::json(&serde_json::json!({
+        "status": ReviewStatus::ChangesRequested.as_str()
+    })))
+}
+
+async fn history_handler(key: String, user: User) -> Result<impl warp::Reply, warp::Rejection> {
+    if !upload::valid_key_part(&key) {
+        return Err(warp::reject::not_found());
+    }
+    let entries = match review::history(&key) {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!("❌ Failed to read the history of {}: {}", key, e);
+            return Ok(warp::reply::json(&serde_json::json!({
+                "error": "storage_failed"
+            })));
+        }
+    };
+    // Uploaders may follow the review of their own packages.
+    let own = entries.iter().any(|d| d.action == Action::Uploaded && d.user == user.name);
+    if user.role < Role::Reviewer && !own {
+        return Err(warp::reject::custom(AuthRejection::Forbidden(Role::Reviewer)));
+    }
+    Ok(warp::reply::json(&entries))
+}
+
+/// Writes `decision` to the audit log and its package's history. The action
+/// it describes has already happened, so a failure is reported, not returned.
+fn record(decision: Decision) {
+    if let Err(e) = review::record(&decision) {
+        eprintln!("❌ Failed to record {:?} of {}: {}", decision.action, decision.key, e);
+    }
+}
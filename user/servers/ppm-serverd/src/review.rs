@@ -0,0 +1,87 @@
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::staging;
+
+pub const HISTORY_DIR: &str = "/srv/ppm/staging/history";
+pub const AUDIT_LOG: &str = "/srv/ppm/audit.log";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Uploaded,
+    /// Refused at upload by the antivirus scan.
+    Refused,
+    Approved,
+    Rejected,
+    ChangesRequested,
+}
+
+/// One entry of a package's history and one line of the audit log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Decision {
+    pub time: String,
+    pub user: String,
+    pub action: Action,
+    pub key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl Decision {
+    pub fn new(user: &str, action: Action, key: &str) -> Self {
+        Decision {
+            time: chrono::Utc::now().to_rfc3339(),
+            user: user.to_string(),
+            action,
+            key: key.to_string(),
+            channel: None,
+            reason: None,
+        }
+    }
+
+    pub fn channel(mut self, channel: &str) -> Self {
+        self.channel = Some(channel.to_string());
+        self
+    }
+
+    pub fn reason(mut self, reason: &str) -> Self {
+        self.reason = Some(reason.to_string());
+        self
+    }
+}
+
+fn history_path(key: &str) -> PathBuf {
+    Path::new(HISTORY_DIR).join(format!("{}.json", key))
+}
+
+/// Every decision recorded for `key`, oldest first.
+pub fn history(key: &str) -> io::Result<Vec<Decision>> {
+    match fs::read_to_string(history_path(key)) {
+        Ok(data) => serde_json::from_str(&data).map_err(io::Error::other),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Appends `decision` to the audit log, then to the history of its package.
+/// Callers hold the staging lock, which serializes history updates.
+pub fn record(decision: &Decision) -> io::Result<()> {
+    let mut line = serde_json::to_string(decision).map_err(io::Error::other)?;
+    line.push('\n');
+    if let Some(dir) = Path::new(AUDIT_LOG).parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut log = OpenOptions::new().create(true).append(true).open(AUDIT_LOG)?;
+    log.write_all(line.as_bytes())?;
+    log.sync_data()?;
+
+    let mut entries = history(&decision.key)?;
+    entries.push(decision.clone());
+    let data = serde_json::to_string_pretty(&entries).map_err(io::Error::other)?;
+    staging::write_atomic(&history_path(&decision.key), data.as_bytes())
+}
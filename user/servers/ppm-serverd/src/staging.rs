@@ -0,0 +1,140 @@
+use ppm_core::Package;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+pub const PENDING_DIR: &str = "/srv/ppm/staging/pending";
+
+const META_SUFFIX: &str = ".meta.json";
+
+/// What `{key}.meta.json` in the pending directory holds. The package is
+/// stored with its review state so the queue can be rebuilt on startup;
+/// `filename` names the archive next to it.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PendingMeta {
+    pub filename: String,
+    /// Hex SHA-256 of the archive, computed while it was received.
+    pub sha256: String,
+    pub channel: String,
+    pub verified: bool,
+    /// Shown to the uploader; the reason when changes were requested.
+    pub note: String,
+    #[serde(default)]
+    pub status: ReviewStatus,
+    /// Why the scanners flagged the upload, for reviewers.
+    #[serde(default)]
+    pub flags: Vec<String>,
+    pub package: Package,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewStatus {
+    #[default]
+    PendingReview,
+    ChangesRequested,
+}
+
+impl ReviewStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReviewStatus::PendingReview => "pending_review",
+            ReviewStatus::ChangesRequested => "changes_requested",
+        }
+    }
+}
+
+pub fn meta_path(key: &str) -> PathBuf {
+    Path::new(PENDING_DIR).join(format!("{}{}", key, META_SUFFIX))
+}
+
+/// Rebuilds the staging queue from the pending directory. Leftover temp
+/// files from an interrupted write are removed; metadata that cannot be
+/// read is skipped and reported, never deleted.
+pub fn load_pending() -> io::Result<HashMap<String, PendingMeta>> {
+    let mut staging = HashMap::new();
+    let entries = match fs::read_dir(PENDING_DIR) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(staging),
+        Err(e) => return Err(e),
+    };
+
+    for entry in entries {
+        let path = entry?.path();
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        if name.starts_with('.') && name.ends_with(".tmp") {
+            fs::remove_file(&path).ok();
+            continue;
+        }
+        let Some(key) = name.strip_suffix(META_SUFFIX) else {
+            continue;
+        };
+
+        let meta = fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|data| serde_json::from_str::<PendingMeta>(&data).map_err(|e| e.to_string()));
+        match meta {
+            Ok(meta) if archive_path(&meta.filename).is_file() => {
+                staging.insert(key.to_string(), meta);
+            }
+            Ok(meta) => eprintln!("⚠️ Skipping {}: archive {} is missing", path.display(), meta.filename),
+            Err(e) => eprintln!("⚠️ Skipping {}: {}", path.display(), e),
+        }
+    }
+    Ok(staging)
+}
+
+pub fn archive_path(filename: &str) -> PathBuf {
+    Path::new(PENDING_DIR).join(filename)
+}
+
+pub fn save_pending(key: &str, meta: &PendingMeta) -> io::Result<()> {
+    let data = serde_json::to_string_pretty(meta).map_err(io::Error::other)?;
+    write_atomic(&meta_path(key), data.as_bytes())
+}
+
+pub fn remove_pending(key: &str) -> io::Result<()> {
+    match fs::remove_file(meta_path(key)) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Renames `from` to `to` and makes the rename durable.
+pub fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    let dir = to.parent().unwrap_or(Path::new("."));
+    fs::create_dir_all(dir)?;
+    fs::rename(from, to)?;
+    fs::File::open(dir)?.sync_all()
+}
+
+/// Replaces `path` with `data` so that readers, and the file after a crash,
+/// only ever see the old or the new contents: the data goes to a hidden temp
+/// file in the same directory, is flushed to disk, then renamed over `path`.
+pub fn write_atomic(path: &Path, data: &[u8]) -> io::Result<()> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    fs::create_dir_all(dir)?;
+
+    let name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))?;
+    let tmp_path = dir.join(format!(".{}.{}.tmp", name, std::process::id()));
+
+    let result = (|| {
+        let mut file = fs::File::create(&tmp_path)?;
+        file.write_all(data)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, path)?;
+        // Make the rename itself durable.
+        fs::File::open(dir)?.sync_all()
+    })();
+    if result.is_err() {
+        fs::remove_file(&tmp_path).ok();
+    }
+    result
+}
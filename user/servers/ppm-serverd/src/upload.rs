@@ -0,0 +1,188 @@
+use bytes::Buf;
+use futures_util::TryStreamExt;
+use ppm_core::Package;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::io::AsyncWriteExt;
+use warp::multipart::{FormData, Part};
+
+use crate::staging;
+
+/// Largest request `/api/upload` accepts, archive and metadata together.
+pub const MAX_UPLOAD_SIZE: u64 = 512 * 1024 * 1024;
+
+const MAX_METADATA_SIZE: usize = 64 * 1024;
+
+/// A received archive in a hidden temp file of the pending directory,
+/// removed on drop unless it was kept. Temp files left by a crash are
+/// cleaned up by `staging::load_pending`.
+pub struct TempArchive {
+    path: Option<PathBuf>,
+}
+
+impl TempArchive {
+    fn new() -> Self {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+        let name = format!(".upload-{}-{}.tmp", std::process::id(), NEXT.fetch_add(1, Ordering::Relaxed));
+        TempArchive {
+            path: Some(Path::new(staging::PENDING_DIR).join(name)),
+        }
+    }
+
+    fn path(&self) -> &Path {
+        self.path.as_deref().expect("archive already kept")
+    }
+
+    /// Moves the archive to `path`, after which it is no longer cleaned up.
+    pub fn keep(mut self, path: &Path) -> io::Result<()> {
+        staging::move_file(self.path(), path)?;
+        self.path = None;
+        Ok(())
+    }
+}
+
+impl Drop for TempArchive {
+    fn drop(&mut self) {
+        if let Some(path) = &self.path {
+            std::fs::remove_file(path).ok();
+        }
+    }
+}
+
+pub struct Upload {
+    pub package: Package,
+    pub archive: TempArchive,
+    pub sha256: String,
+}
+
+#[derive(Debug)]
+pub enum UploadError {
+    Malformed(String),
+    MissingField(&'static str),
+    BadName(String),
+    SizeMismatch { declared: u64, actual: u64 },
+    Storage(io::Error),
+}
+
+impl UploadError {
+    /// Short code for the `error` field of the reply.
+    pub fn code(&self) -> &'static str {
+        match self {
+            UploadError::Malformed(_) | UploadError::MissingField(_) => "malformed_upload",
+            UploadError::BadName(_) => "invalid_name",
+            UploadError::SizeMismatch { .. } => "size_mismatch",
+            UploadError::Storage(_) => "storage_failed",
+        }
+    }
+}
+
+impl fmt::Display for UploadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UploadError::Malformed(e) => write!(f, "malformed upload: {}", e),
+            UploadError::MissingField(name) => write!(f, "missing form field '{}'", name),
+            UploadError::BadName(name) => write!(f, "'{}' cannot be used in a package key", name),
+            UploadError::SizeMismatch { declared, actual } => {
+                write!(f, "archive is {} bytes but package.size says {}", actual, declared)
+            }
+            UploadError::Storage(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// Reads a `multipart/form-data` upload with a `package` field holding the
+/// JSON metadata and an `archive` field holding the `.plpm` file. The
+/// archive is streamed to disk and hashed as it arrives, then checked
+/// against `package.size`.
+pub async fn receive(mut form: FormData) -> Result<Upload, UploadError> {
+    let mut package: Option<Package> = None;
+    let mut archive: Option<(TempArchive, u64, String)> = None;
+
+    while let Some(part) = form.try_next().await.map_err(|e| UploadError::Malformed(e.to_string()))? {
+        match part.name() {
+            "package" => {
+                let data = read_field(part).await?;
+                package = Some(serde_json::from_slice(&data).map_err(|e| UploadError::Malformed(e.to_string()))?);
+            }
+            "archive" if archive.is_some() => {
+                return Err(UploadError::Malformed("more than one archive".to_string()));
+            }
+            "archive" => {
+                let temp = TempArchive::new();
+                let (size, sha256) = write_field(part, temp.path()).await.map_err(UploadError::Storage)?;
+                archive = Some((temp, size, sha256));
+            }
+            _ => {}
+        }
+    }
+
+    let package = package.ok_or(UploadError::MissingField("package"))?;
+    let (archive, size, sha256) = archive.ok_or(UploadError::MissingField("archive"))?;
+
+    for part in [&package.name, &package.version] {
+        if !valid_key_part(part) {
+            return Err(UploadError::BadName(part.clone()));
+        }
+    }
+    if size != package.size {
+        return Err(UploadError::SizeMismatch {
+            declared: package.size,
+            actual: size,
+        });
+    }
+
+    Ok(Upload {
+        package,
+        archive,
+        sha256,
+    })
+}
+
+async fn read_field(part: Part) -> Result<Vec<u8>, UploadError> {
+    let mut data = Vec::new();
+    let mut stream = part.stream();
+    while let Some(mut chunk) = stream.try_next().await.map_err(|e| UploadError::Malformed(e.to_string()))? {
+        if data.len() + chunk.remaining() > MAX_METADATA_SIZE {
+            return Err(UploadError::Malformed("package metadata is too large".to_string()));
+        }
+        while chunk.has_remaining() {
+            let bytes = chunk.chunk();
+            data.extend_from_slice(bytes);
+            let len = bytes.len();
+            chunk.advance(len);
+        }
+    }
+    Ok(data)
+}
+
+async fn write_field(part: Part, path: &Path) -> io::Result<(u64, String)> {
+    tokio::fs::create_dir_all(staging::PENDING_DIR).await?;
+    let mut file = tokio::fs::File::create(path).await?;
+    let mut hasher = Sha256::new();
+    let mut size = 0u64;
+
+    let mut stream = part.stream();
+    while let Some(mut chunk) = stream.try_next().await.map_err(io::Error::other)? {
+        while chunk.has_remaining() {
+            let bytes = chunk.chunk();
+            hasher.update(bytes);
+            file.write_all(bytes).await?;
+            size += bytes.len() as u64;
+            let len = bytes.len();
+            chunk.advance(len);
+        }
+    }
+    file.sync_all().await?;
+    Ok((size, hex::encode(hasher.finalize())))
+}
+
+/// Package names and versions end up in file names, so they are limited to
+/// characters that cannot form a path.
+pub fn valid_key_part(s: &str) -> bool {
+    !s.is_empty()
+        && !s.starts_with('.')
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '+' | '-'))
+}